@@ -26,6 +26,7 @@
 #define ERR(fmt, ...) fprintf(stdout, "\033[0;31m[ERROR]\033[0m " fmt "\n", ##__VA_ARGS__)
 
 #define NUM_ATTRS (10)
+#define NUM_BATCH_EVENTS (16)
 
 static const char *TIMELINE_ATTR_KEYS[] =
 {
@@ -62,6 +63,7 @@ int main(void)
     modality_runtime *rt;
     modality_ingest_client *client;
     modality_timeline_id tid;
+    modality_timeline_id other_tid;
     modality_big_int big_int;
     modality_logical_time lt;
     modality_attr timeline_attrs[NUM_ATTRS] = {0};
@@ -96,6 +98,22 @@ int main(void)
         assert(err == MODALITY_ERROR_OK);
     }
 
+    /* Must match the Rust SDK's TimelineId::from_name for the same inputs */
+    const modality_timeline_id ns = {{
+        0x3C, 0xD8, 0x73, 0x4A, 0x17, 0x47, 0x4C, 0xFD,
+        0xA3, 0x05, 0x2E, 0x8B, 0x3A, 0xA4, 0x1C, 0x8F
+    }};
+    const uint8_t expected_named_tid[16] = {
+        0x2E, 0x2D, 0xEC, 0x68, 0x6A, 0x58, 0x50, 0xF7,
+        0xA2, 0xED, 0xC5, 0x22, 0x31, 0x73, 0x6E, 0x5B
+    };
+    modality_timeline_id named_tid;
+    err = modality_timeline_id_from_name(&ns, "sensor-board-1", &named_tid);
+    assert(err == MODALITY_ERROR_OK);
+    assert(memcmp(&named_tid, expected_named_tid, sizeof(expected_named_tid)) == 0);
+    err = modality_timeline_id_from_name(&ns, NULL, &named_tid);
+    assert(err == MODALITY_ERROR_NULL_POINTER);
+
     err = modality_timeline_id_init(&tid);
     assert(err == MODALITY_ERROR_OK);
 
@@ -164,6 +182,57 @@ int main(void)
     err = modality_ingest_client_event(client, 1, 0, event_attrs, NUM_ATTRS);
     assert(err == MODALITY_ERROR_OK);
 
+    err = modality_ingest_client_batch_event(client, 2, 0, event_attrs, NUM_ATTRS);
+    assert(err == MODALITY_ERROR_NO_ACTIVE_BATCH);
+    err = modality_ingest_client_flush_batch(client);
+    assert(err == MODALITY_ERROR_NO_ACTIVE_BATCH);
+
+    err = modality_ingest_client_begin_batch(client);
+    assert(err == MODALITY_ERROR_OK);
+    err = modality_ingest_client_begin_batch(client);
+    assert(err == MODALITY_ERROR_BATCH_ALREADY_ACTIVE);
+
+    for(i = 0; i < NUM_BATCH_EVENTS; i += 1)
+    {
+        err = modality_ingest_client_batch_event(client, (uint64_t) (2 + i), 0, event_attrs, NUM_ATTRS);
+        assert(err == MODALITY_ERROR_OK);
+    }
+
+    err = modality_ingest_client_flush_batch(client);
+    assert(err == MODALITY_ERROR_OK);
+    err = modality_ingest_client_flush_batch(client);
+    assert(err == MODALITY_ERROR_NO_ACTIVE_BATCH);
+
+    /* Batched events keep the timeline they were appended on */
+    err = modality_timeline_id_init(&other_tid);
+    assert(err == MODALITY_ERROR_OK);
+
+    err = modality_ingest_client_begin_batch(client);
+    assert(err == MODALITY_ERROR_OK);
+    err = modality_ingest_client_batch_event(client, (uint64_t) (2 + NUM_BATCH_EVENTS), 0, event_attrs, NUM_ATTRS);
+    assert(err == MODALITY_ERROR_OK);
+
+    err = modality_ingest_client_open_timeline(client, &other_tid);
+    assert(err == MODALITY_ERROR_OK);
+    err = modality_ingest_client_timeline_metadata(client, timeline_attrs, NUM_ATTRS);
+    assert(err == MODALITY_ERROR_OK);
+    err = modality_ingest_client_batch_event(client, 0, 0, event_attrs, NUM_ATTRS);
+    assert(err == MODALITY_ERROR_OK);
+
+    err = modality_ingest_client_close_timeline(client);
+    assert(err == MODALITY_ERROR_OK);
+    err = modality_ingest_client_batch_event(client, 1, 0, event_attrs, NUM_ATTRS);
+    assert(err == MODALITY_ERROR_NO_BOUND_TIMELINE);
+
+    err = modality_ingest_client_open_timeline(client, &tid);
+    assert(err == MODALITY_ERROR_OK);
+    err = modality_ingest_client_flush_batch(client);
+    assert(err == MODALITY_ERROR_OK);
+
+    /* The timeline open before the flush is still open */
+    err = modality_ingest_client_event(client, (uint64_t) (3 + NUM_BATCH_EVENTS), 0, event_attrs, NUM_ATTRS);
+    assert(err == MODALITY_ERROR_OK);
+
     err = modality_ingest_client_close_timeline(client);
     assert(err == MODALITY_ERROR_OK);
 
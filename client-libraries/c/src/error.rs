@@ -52,8 +52,19 @@ pub enum error {
     InvalidDnsName = -88,
     AuthWrongParticipant = -89,
     UnexpectedAuthResponse = -90,
+    VersionMismatch = -91,
+    Unauthorized = -92,
+    RateLimited = -93,
+    InternalServerError = -94,
+    ProtocolViolation = -95,
+    UnknownServerError = -96,
     // Reserved
     NoBoundTimeline = -100,
+    TooManyAttrs = -101,
+    StringAttrTooLong = -102,
+    NoActiveBatch = -103,
+    BatchAlreadyActive = -104,
+    ConnectionInUse = -105,
 }
 
 impl From<Error> for c_int {
@@ -73,6 +84,7 @@ impl From<IngestClientInitializationError> for Error {
             ParseIngestEndpoint(_) => Error::ParseIngestEndpoint,
             InvalidDnsName(_) => Error::InvalidDnsName,
             Io(_) => Error::Io,
+            RootCert(_) => Error::Tls,
         }
     }
 }
@@ -90,6 +102,12 @@ impl From<IngestError> for Error {
             Io(_) => Error::Io,
             IngestClientInitializationError(e) => e.into(),
             LoadConfigError(_) => Error::LoadConfigError,
+            VersionMismatch { .. } => Error::VersionMismatch,
+            Unauthorized { .. } => Error::Unauthorized,
+            RateLimited { .. } => Error::RateLimited,
+            InternalServerError { .. } => Error::InternalServerError,
+            ProtocolViolation { .. } => Error::ProtocolViolation,
+            UnknownServerError { .. } => Error::UnknownServerError,
         }
     }
 }
@@ -106,6 +124,11 @@ impl From<DynamicIngestError> for Error {
         match e {
             IngestError(e) => Error::from(e),
             NoBoundTimeline => Error::NoBoundTimeline,
+            TooManyAttrs { .. } => Error::TooManyAttrs,
+            StringAttrTooLong { .. } => Error::StringAttrTooLong,
+            ReconnectFailed(_) => Error::SocketConnection,
+            ConnectionInUse => Error::ConnectionInUse,
+            ReservedAttrKey { .. } => Error::AttrKeyNaming,
         }
     }
 }
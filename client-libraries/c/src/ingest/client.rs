@@ -42,6 +42,17 @@ impl From<&attr>
 pub struct ingest_client {
     rt: &'static Runtime,
     state: InnerState,
+    batch: Option<Vec<BatchedEvent>>,
+}
+
+struct BatchedEvent {
+    /// The timeline bound when the event was appended
+    timeline: auxon_sdk::api::TimelineId,
+    ordering: u128,
+    attrs: Vec<(
+        auxon_sdk::ingest_protocol::InternedAttrKey,
+        auxon_sdk::api::AttrVal,
+    )>,
 }
 
 enum InnerState {
@@ -87,6 +98,7 @@ pub extern "C" fn modality_ingest_client_new(
         *out = Box::into_raw(Box::new(ingest_client {
             rt: &rt.0,
             state: InnerState::Init,
+            batch: None,
         }));
         Ok(())
     })
@@ -263,15 +275,105 @@ pub extern "C" fn modality_ingest_client_event(
         }
         let c = client.as_mut().ok_or(Error::NullPointer)?;
         let state = c.state.as_authed()?;
+        let ordering = ordering_from_parts(ordering_lower, ordering_upper);
+        c.rt.block_on(state.event(ordering, attrs.iter().map(|attr| attr.into())))?;
+        Ok(())
+    })
+}
 
-        let ord_lsb = ordering_lower.to_le_bytes();
-        let ord_msb = ordering_upper.to_le_bytes();
-        let mut ord_bytes = [0_u8; 16];
-        ord_bytes[..8].copy_from_slice(&ord_lsb);
-        ord_bytes[8..16].copy_from_slice(&ord_msb);
-        let ordering = u128::from_le_bytes(ord_bytes);
+/// Start buffering events on the client.
+///
+/// Events appended with `modality_ingest_client_batch_event` are held in
+/// memory until `modality_ingest_client_flush_batch` sends them all at once.
+/// A batch that is never flushed is discarded when the client is freed.
+#[no_mangle]
+pub extern "C" fn modality_ingest_client_begin_batch(client: *mut ingest_client) -> c_int {
+    capi_result(|| unsafe {
+        let c = client.as_mut().ok_or(Error::NullPointer)?;
+        c.state.as_authed()?;
+        if c.batch.is_some() {
+            return Err(Error::BatchAlreadyActive);
+        }
+        c.batch = Some(Vec::new());
+        Ok(())
+    })
+}
 
-        c.rt.block_on(state.event(ordering, attrs.iter().map(|attr| attr.into())))?;
+/// Append an event to the current batch, on the currently open timeline.
+///
+/// The timeline is recorded with the event, so timelines may be opened and
+/// closed while a batch is active. The attributes are copied, so the caller
+/// may reuse `attrs` (and any strings it points to) as soon as this returns.
+#[no_mangle]
+pub extern "C" fn modality_ingest_client_batch_event(
+    client: *mut ingest_client,
+    ordering_lower: u64,
+    ordering_upper: u64,
+    attrs: *const attr,
+    attrs_len: usize,
+) -> c_int {
+    capi_result(|| unsafe {
+        let attrs = if attrs.is_null() || attrs_len == 0 {
+            &[]
+        } else {
+            slice::from_raw_parts(attrs, attrs_len)
+        };
+        if attrs.is_empty() {
+            return Err(Error::InvalidAttrList);
+        }
+        let c = client.as_mut().ok_or(Error::NullPointer)?;
+        let batch = c.batch.as_mut().ok_or(Error::NoActiveBatch)?;
+        let timeline = c
+            .state
+            .as_authed()?
+            .bound_timeline()
+            .ok_or(Error::NoBoundTimeline)?;
+        batch.push(BatchedEvent {
+            timeline,
+            ordering: ordering_from_parts(ordering_lower, ordering_upper),
+            attrs: attrs.iter().map(|attr| attr.into()).collect(),
+        });
+        Ok(())
+    })
+}
+
+/// Send every event in the current batch, in the order they were appended,
+/// each on the timeline it was appended on, then flush the connection and end
+/// the batch. The timeline open beforehand, if any, is open again afterwards.
+///
+/// The batch ends even if sending fails; events after the failed one are
+/// dropped.
+#[no_mangle]
+pub extern "C" fn modality_ingest_client_flush_batch(client: *mut ingest_client) -> c_int {
+    capi_result(|| unsafe {
+        let c = client.as_mut().ok_or(Error::NullPointer)?;
+        let state = c.state.as_authed()?;
+        let batch = c.batch.take().ok_or(Error::NoActiveBatch)?;
+        c.rt.block_on(async {
+            let bound = state.bound_timeline();
+            for ev in batch {
+                if state.bound_timeline() != Some(ev.timeline) {
+                    state.open_timeline(ev.timeline).await?;
+                }
+                state.event(ev.ordering, ev.attrs).await?;
+            }
+            match bound {
+                Some(id) if state.bound_timeline() != bound => state.open_timeline(id).await?,
+                Some(_) => (),
+                None => state.close_timeline(),
+            }
+            state.flush().await?;
+            Ok::<_, Error>(())
+        })?;
         Ok(())
     })
 }
+
+fn ordering_from_parts(ordering_lower: u64, ordering_upper: u64) -> u128 {
+    let ord_lsb = ordering_lower.to_le_bytes();
+    let ord_msb = ordering_upper.to_le_bytes();
+    let mut ord_bytes = [0_u8; 16];
+    ord_bytes[..8].copy_from_slice(&ord_lsb);
+    ord_bytes[8..16].copy_from_slice(&ord_msb);
+    u128::from_le_bytes(ord_bytes)
+}
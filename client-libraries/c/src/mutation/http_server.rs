@@ -109,12 +109,13 @@ pub extern "C" fn modality_mutator_http_server_run(
 
         let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
 
-        rt.0.block_on(async {
+        let res = rt.0.block_on(async {
             tokio::select! {
                 _ = tokio::signal::ctrl_c() => {
                     tracing::debug!("User signled exit");
+                    Ok(())
                 }
-                _ = auxon_sdk::mutator_server::server::serve_mutators(
+                res = auxon_sdk::mutator_server::server::serve_mutators(
                       mutators,
                       None,
                       (addr, port),
@@ -123,11 +124,18 @@ pub extern "C" fn modality_mutator_http_server_run(
                               tracing::warn!("Shutdown signal channel unexpectedly closed early");
                           });
                       },
-                ) => {},
+                ) => res,
             }
         });
 
         let _ = shutdown_tx;
-        Ok(())
+        res.map_err(|e| {
+            tracing::error!(
+                mutator = %e.mutator,
+                err = &e as &dyn std::error::Error,
+                "Not serving mutator HTTP API"
+            );
+            Error::MutatorInterfaceError
+        })
     })
 }
@@ -1,4 +1,4 @@
-use crate::{capi_result, Error, NullPtrExt};
+use crate::{capi_result, util, Error, NullPtrExt};
 use std::ffi::{c_char, c_int, CStr};
 use uuid::Uuid;
 
@@ -21,6 +21,28 @@ pub extern "C" fn modality_timeline_id_init(tid: *mut timeline_id) -> c_int {
     })
 }
 
+/// Derive a stable timeline id from a name, in the given namespace. The same
+/// inputs always give the same id.
+///
+/// The id is the v5 (SHA-1) UUID of the namespace's 16 bytes and the bytes of
+/// the NUL-terminated UTF-8 `name`, as specified by RFC 4122. This matches the
+/// Rust SDK's `TimelineId::from_name`.
+#[no_mangle]
+pub extern "C" fn modality_timeline_id_from_name(
+    namespace: *const timeline_id,
+    name: *const c_char,
+    out: *mut timeline_id,
+) -> c_int {
+    capi_result(|| unsafe {
+        let namespace = namespace.as_ref().ok_or(Error::NullPointer)?;
+        let name = util::require_owned_cstr(name)?;
+        let out = out.as_mut().ok_or(Error::NullPointer)?;
+        let tid = auxon_sdk::api::TimelineId::from_name(Uuid::from_bytes(namespace.0), &name);
+        out.0.copy_from_slice(tid.get_raw().as_bytes());
+        Ok(())
+    })
+}
+
 #[repr(C)]
 pub struct big_int([u8; 16]);
 
@@ -169,7 +191,7 @@ impl From<&attr_val> for auxon_sdk::api::AttrVal {
             match val {
                 TimelineId(tid) => {
                     let tid = &*(*tid);
-                    auxon_sdk::api::AttrVal::TimelineId(Box::new(tid.into()))
+                    auxon_sdk::api::AttrVal::timeline_id(tid.into())
                 }
                 String(s) => CStr::from_ptr(*s).to_string_lossy().to_string().into(),
                 Integer(i) => (*i).into(),
@@ -1,7 +1,6 @@
-use crate::{types, SdkError};
+use crate::SdkError;
 use auxon_sdk::{
-    api::{AttrVal, TimelineId},
-    ingest_client::IngestStatus,
+    api::TimelineId, ingest_client::IngestStatus, plugin_utils::python::py_dict_to_attr_map,
 };
 use pyo3::prelude::*;
 
@@ -35,10 +34,10 @@ impl IngestClient {
         name: &str,
         timeline_attrs: &Bound<pyo3::types::PyDict>,
     ) -> Result<(), PyErr> {
-        let attrs = py_dict_to_attr_vec(timeline_attrs)?;
+        let attrs = py_dict_to_attr_map(timeline_attrs)?;
         self.rt.block_on(
             self.client
-                .send_timeline_attrs(name, attrs.iter().map(|(k, v)| (k.as_str(), v.clone()))),
+                .send_timeline_attrs(name, attrs.iter().map(|(k, v)| (k.as_ref(), v.clone()))),
         )?;
 
         Ok(())
@@ -50,12 +49,12 @@ impl IngestClient {
         ordering: u128,
         event_attrs: &Bound<pyo3::types::PyDict>,
     ) -> Result<(), PyErr> {
-        let attrs = py_dict_to_attr_vec(event_attrs)?;
+        let attrs = py_dict_to_attr_map(event_attrs)?;
 
         self.rt.block_on(self.client.send_event(
             name,
             ordering,
-            attrs.iter().map(|(k, v)| (k.as_str(), v.clone())),
+            attrs.iter().map(|(k, v)| (k.as_ref(), v.clone())),
         ))?;
 
         Ok(())
@@ -71,13 +70,3 @@ impl IngestClient {
         Ok(status)
     }
 }
-
-fn py_dict_to_attr_vec(dict: &Bound<pyo3::types::PyDict>) -> Result<Vec<(String, AttrVal)>, PyErr> {
-    let mut attrs = Vec::with_capacity(dict.len());
-    for (k, v) in dict.iter() {
-        let k = k.extract::<String>()?;
-        let v = types::py_any_to_attr_val(v)?;
-        attrs.push((k, v));
-    }
-    Ok(attrs)
-}
@@ -1,7 +1,6 @@
 mod config;
 mod ingest;
 mod mutator;
-mod types;
 
 use auxon_sdk::{ingest_client::dynamic::DynamicIngestError, mutation_plane_client::parent_connection::CommsError};
 use pyo3::prelude::*;
@@ -18,6 +17,7 @@ fn _auxon_sdk(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<mutator::MutatorParam>()?;
     m.add_class::<mutator::MutatorHost>()?;
     m.add_class::<mutator::PyMutatorDescriptor>()?;
+    m.add_class::<auxon_sdk::plugin_utils::mutation::MutationResult>()?;
     Ok(())
 }
 
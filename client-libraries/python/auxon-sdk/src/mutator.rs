@@ -8,7 +8,7 @@ use auxon_sdk::{
         OwnedMutatorDescriptor, OwnedMutatorParamDescriptor, ValueDistributionKind,
         ValueDistributionScaling,
     },
-    plugin_utils::mutation::Mutator,
+    plugin_utils::mutation::{py_inject, Mutator},
 };
 use pyo3::{
     intern,
@@ -248,19 +248,7 @@ impl Mutator for PythonMutatorProxy {
                 .setattr(py, intern!(py, "_mutator_parameters"), params)
                 .unwrap();
 
-            if let Err(e) =
-                self.obj
-                    .call_method1(py, intern!(py, "inject"), (mutation_id, params_obj))
-            {
-                tracing::warn!(
-                    err = &e as &dyn std::error::Error,
-                    "Failed to inject mutation for Python mutator"
-                );
-
-                false
-            } else {
-                true
-            }
+            py_inject(self.obj.bind(py), mutation_id, params_obj)
         })
     }
 
@@ -37,20 +37,17 @@ fn main() {
             ]
             .into_iter()
             .collect();
-            let port = config
-                .mutation
-                .as_ref()
-                .and_then(|m| m.mutator_http_api_port)
-                .unwrap_or(8080);
-            auxon_sdk::mutator_server::server::serve_mutators(
+            let mutation = config.mutation.unwrap_or_default();
+            let port = mutation.mutator_http_api_port.unwrap_or(8080);
+            auxon_sdk::mutator_server::server::serve_mutators_with_config(
                 mutators,
-                None,
+                &mutation,
                 (Ipv4Addr::UNSPECIFIED, port),
                 async {
                     shutdown_signal.await;
                 },
             )
-            .await;
+            .await?;
             Ok(())
         },
     ));
@@ -2,7 +2,7 @@ use std::net::Ipv4Addr;
 use tokio::sync::oneshot::Sender;
 
 #[tokio::main]
-async fn main() {
+async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     tracing_subscriber::fmt::init();
     let (shutdown_tx, shutdown_rx): (Sender<_>, _) = tokio::sync::oneshot::channel();
     auxon_sdk::mutator_server::server::serve_mutators(
@@ -13,6 +13,7 @@ async fn main() {
             shutdown_rx.await.ok();
         },
     )
-    .await;
+    .await?;
     let _ = shutdown_tx.send(());
+    Ok(())
 }
@@ -0,0 +1,28 @@
+//! Build-only check that the `auxon-sdk` core types are usable from a `no_std` crate.
+//!
+//! Run with `cargo build` from this directory.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{collections::BTreeMap, string::String};
+use auxon_sdk::api::{AttrKey, AttrVal, BigInt, Nanoseconds, TimelineId, Uuid};
+
+pub fn event_attrs(timeline: [u8; 16], name: String) -> BTreeMap<AttrKey, AttrVal> {
+    let mut attrs = BTreeMap::new();
+    attrs.insert(AttrKey::new_static("event.name"), name.into());
+    attrs.insert(
+        AttrKey::new_static("event.timestamp"),
+        Nanoseconds::from(1).into(),
+    );
+    attrs.insert(
+        AttrKey::new_static("event.big"),
+        BigInt::new_attr_val(i128::MAX),
+    );
+    attrs.insert(
+        AttrKey::new_static("event.interaction.remote_timeline_id"),
+        TimelineId::from(Uuid::from_bytes(timeline)).into(),
+    );
+    attrs
+}
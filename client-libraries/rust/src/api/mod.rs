@@ -7,6 +7,8 @@ pub use types::*;
 pub mod protocol;
 
 mod serde;
+#[cfg(feature = "cbor")]
+pub use self::serde::cbor;
 
 #[cfg(any(test, feature = "test_support"))]
 pub mod proptest_strategies;
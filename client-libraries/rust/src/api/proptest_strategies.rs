@@ -14,6 +14,7 @@ pub fn attr_val() -> impl Strategy<Value = AttrVal> {
         any::<f64>().prop_map_into(),
         any::<bool>().prop_map_into(),
         nanoseconds().prop_map_into(),
+        nanoseconds().prop_map(AttrVal::Duration),
         logical_time().prop_map_into(),
     ]
 }
@@ -6,6 +6,7 @@ pub const TAG_NS: Tag = Tag::Unassigned(40000);
 pub const TAG_LOGICAL_TIME: Tag = Tag::Unassigned(40001);
 pub const TAG_TIMELINE_ID: Tag = Tag::Unassigned(40002);
 pub const TAG_EVENT_COORDINATE: Tag = Tag::Unassigned(40003);
+pub const TAG_DURATION_NS: Tag = Tag::Unassigned(40004);
 
 impl Encode for Nanoseconds {
     fn encode<W: encode::Write>(&self, e: &mut Encoder<W>) -> Result<(), encode::Error<W::Error>> {
@@ -114,6 +115,9 @@ impl Encode for AttrVal {
             AttrVal::Timestamp(ns) => {
                 ns.encode(e)?;
             }
+            AttrVal::Duration(ns) => {
+                e.tag(TAG_DURATION_NS)?.u64(ns.get_raw())?;
+            }
             AttrVal::LogicalTime(lt) => {
                 lt.encode(e)?;
             }
@@ -161,6 +165,10 @@ impl<'b> Decode<'b> for AttrVal {
                 // probe == lookahead
                 match d.probe().tag()? {
                     TAG_NS => Ok(Nanoseconds::decode(d)?.into()),
+                    TAG_DURATION_NS => {
+                        d.tag()?;
+                        Ok(AttrVal::Duration(d.u64()?.into()))
+                    }
                     TAG_LOGICAL_TIME => Ok(LogicalTime::decode(d)?.into()),
                     TAG_TIMELINE_ID => Ok(TimelineId::decode(d)?.into()),
 
@@ -233,4 +241,20 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn duration_and_timestamp_encode_differently() {
+        let latency = AttrVal::Duration(Nanoseconds::from(1_500));
+        let timestamp = AttrVal::Timestamp(Nanoseconds::from(1_500));
+
+        let latency_buf = minicbor::to_vec(&latency).unwrap();
+        let timestamp_buf = minicbor::to_vec(&timestamp).unwrap();
+        assert_ne!(latency_buf, timestamp_buf);
+
+        assert_eq!(minicbor::decode::<AttrVal>(&latency_buf).unwrap(), latency);
+        assert_eq!(
+            minicbor::decode::<AttrVal>(&timestamp_buf).unwrap(),
+            timestamp
+        );
+    }
 }
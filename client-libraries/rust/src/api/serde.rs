@@ -7,6 +7,7 @@
 //! `{"TimelineId", "..."}`.
 
 use crate::api::types::{AttrVal, EventCoordinate, LogicalTime, Nanoseconds, TimelineId};
+use alloc::{format, string::String};
 use ordered_float::OrderedFloat;
 use serde::{
     de::{value::MapAccessDeserializer, Visitor},
@@ -17,6 +18,7 @@ use serde::{
 enum TaggedAttrValSer<'a> {
     TimelineId(&'a TimelineId),
     Timestamp(&'a Nanoseconds),
+    Duration(&'a Nanoseconds),
     LogicalTime(&'a LogicalTime),
     EventCoordinate(&'a EventCoordinate),
     /// thanks json, you're a real pal
@@ -42,6 +44,7 @@ enum NonFiniteFloat {
 enum TaggedAttrVal {
     TimelineId(TimelineId),
     Timestamp(Nanoseconds),
+    Duration(Nanoseconds),
     LogicalTime(LogicalTime),
     EventCoordinate(EventCoordinate),
     BigInt(String),
@@ -75,6 +78,7 @@ impl Serialize for AttrVal {
             }
             AttrVal::Bool(b) => b.serialize(ser),
             AttrVal::Timestamp(ns) => TaggedAttrValSer::Timestamp(ns).serialize(ser),
+            AttrVal::Duration(ns) => TaggedAttrValSer::Duration(ns).serialize(ser),
             AttrVal::LogicalTime(lt) => TaggedAttrValSer::LogicalTime(lt).serialize(ser),
             AttrVal::EventCoordinate(ec) => TaggedAttrValSer::EventCoordinate(ec).serialize(ser),
         }
@@ -94,7 +98,7 @@ struct AttrValVisitor;
 impl<'de> Visitor<'de> for AttrValVisitor {
     type Value = AttrVal;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str("an encoded AttrVal")
     }
 
@@ -151,6 +155,7 @@ impl<'de> Visitor<'de> for AttrValVisitor {
         match val {
             TaggedAttrVal::TimelineId(tl_id) => Ok(tl_id.into()),
             TaggedAttrVal::Timestamp(ts) => Ok(ts.into()),
+            TaggedAttrVal::Duration(ns) => Ok(AttrVal::Duration(ns)),
             TaggedAttrVal::LogicalTime(lt) => Ok(lt.into()),
             TaggedAttrVal::EventCoordinate(ec) => Ok(ec.into()),
             TaggedAttrVal::BigInt(s) => {
@@ -174,6 +179,38 @@ impl<'de> Visitor<'de> for AttrValVisitor {
     }
 }
 
+/// Compact CBOR encoding for attribute maps.
+///
+/// This uses the same serde representation as the JSON encoding, so variants that JSON can't
+/// distinguish by value (`BigInt`, `Timestamp`, `TimelineId`, ...) keep their tags, while plain
+/// integers, floats, strings and bools are encoded as native CBOR values.
+#[cfg(feature = "cbor")]
+pub mod cbor {
+    use crate::api::{AttrKey, AttrVal};
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, thiserror::Error)]
+    pub enum CborError {
+        #[error("Error encoding attributes as CBOR")]
+        Encode(#[from] ciborium::ser::Error<std::io::Error>),
+
+        #[error("Error decoding attributes from CBOR")]
+        Decode(#[from] ciborium::de::Error<std::io::Error>),
+    }
+
+    /// Encode an attribute map as CBOR.
+    pub fn to_vec(attrs: &BTreeMap<AttrKey, AttrVal>) -> Result<Vec<u8>, CborError> {
+        let mut buf = Vec::new();
+        ciborium::ser::into_writer(attrs, &mut buf)?;
+        Ok(buf)
+    }
+
+    /// Decode an attribute map previously encoded with [`to_vec`].
+    pub fn from_slice(bytes: &[u8]) -> Result<BTreeMap<AttrKey, AttrVal>, CborError> {
+        Ok(ciborium::de::from_reader(bytes)?)
+    }
+}
+
 #[cfg(feature = "schemars")]
 impl schemars::JsonSchema for AttrVal {
     fn schema_name() -> String {
@@ -202,3 +239,66 @@ impl schemars::JsonSchema for AttrVal {
         })
     }
 }
+
+#[cfg(all(test, feature = "cbor"))]
+mod tests {
+    use super::cbor;
+    use crate::api::{
+        proptest_strategies::attr_val, AttrKey, AttrVal, BigInt, EventCoordinate, LogicalTime,
+        Nanoseconds, TimelineId,
+    };
+    use proptest::prelude::*;
+    use std::collections::BTreeMap;
+
+    fn every_variant() -> BTreeMap<AttrKey, AttrVal> {
+        let timeline_id = TimelineId::from(crate::api::Uuid::from_bytes([7; 16]));
+        BTreeMap::from([
+            ("event.timeline".into(), timeline_id.into()),
+            (
+                "event.coordinate".into(),
+                EventCoordinate {
+                    timeline_id,
+                    id: [1; 16],
+                }
+                .into(),
+            ),
+            ("event.name".into(), "a name".into()),
+            ("event.count".into(), AttrVal::Integer(-42)),
+            ("event.big".into(), BigInt::new_attr_val(i128::MIN)),
+            ("event.ratio".into(), 0.25.into()),
+            ("event.nan".into(), f64::NAN.into()),
+            ("event.flag".into(), true.into()),
+            ("event.timestamp".into(), Nanoseconds::from(1_000).into()),
+            (
+                "event.latency".into(),
+                AttrVal::Duration(Nanoseconds::from(250)),
+            ),
+            ("event.clock".into(), LogicalTime::binary(1u64, 2u64).into()),
+        ])
+    }
+
+    #[test]
+    fn cbor_round_trip_all_variants() {
+        let attrs = every_variant();
+        let bytes = cbor::to_vec(&attrs).unwrap();
+        assert_eq!(cbor::from_slice(&bytes).unwrap(), attrs);
+    }
+
+    #[test]
+    fn cbor_round_trip() {
+        proptest!(|(attrs in proptest::collection::btree_map(".*", attr_val(), 0..10))| {
+            let attrs: BTreeMap<AttrKey, AttrVal> =
+                attrs.into_iter().map(|(k, v)| (AttrKey::from(k), v)).collect();
+            let bytes = cbor::to_vec(&attrs)?;
+            prop_assert_eq!(cbor::from_slice(&bytes)?, attrs);
+        });
+    }
+
+    #[test]
+    fn cbor_is_smaller_than_json() {
+        let attrs = every_variant();
+        let cbor_len = cbor::to_vec(&attrs).unwrap().len();
+        let json_len = serde_json::to_vec(&attrs).unwrap().len();
+        assert!(cbor_len < json_len, "cbor {cbor_len} >= json {json_len}");
+    }
+}
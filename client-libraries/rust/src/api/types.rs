@@ -1,4 +1,10 @@
-use std::{borrow::Cow, cmp::Ordering, ops::Deref, str::FromStr};
+use alloc::{
+    borrow::{Cow, ToOwned},
+    boxed::Box,
+    string::String,
+    vec::Vec,
+};
+use core::{cmp::Ordering, ops::Deref, str::FromStr};
 
 use ordered_float::OrderedFloat;
 pub use uuid::Uuid;
@@ -16,6 +22,29 @@ impl AttrKey {
     pub const fn new_static(k: &'static str) -> Self {
         Self(Cow::Borrowed(k))
     }
+
+    /// A copy of this key with surrounding whitespace trimmed and letter case
+    /// adjusted according to `case`.
+    ///
+    /// Keys are never normalized implicitly; `" Foo "` and `"foo"` are distinct
+    /// keys unless the caller opts in by normalizing both.
+    pub fn normalized(&self, case: AttrKeyCase) -> AttrKey {
+        let trimmed = self.0.trim();
+        match case {
+            AttrKeyCase::Preserve => AttrKey::from(trimmed),
+            AttrKeyCase::Lowercase => AttrKey::new(trimmed.to_lowercase()),
+        }
+    }
+}
+
+/// How [`AttrKey::normalized`] treats letter case.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AttrKeyCase {
+    /// Leave the case of the key unchanged.
+    #[default]
+    Preserve,
+    /// Fold the key to lowercase.
+    Lowercase,
 }
 
 impl From<&str> for AttrKey {
@@ -45,12 +74,176 @@ impl From<AttrKey> for String {
     }
 }
 
-impl std::fmt::Display for AttrKey {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+impl core::fmt::Display for AttrKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
         write!(f, "{}", self.0)
     }
 }
 
+/// Attribute keys with a special meaning to Modality, which are set by the
+/// SDK or the backend rather than by users.
+const RESERVED_ATTR_KEYS: &[&str] = &[
+    "event.name",
+    "event.timestamp",
+    "timeline.name",
+    "timeline.run_id",
+    "timeline.time_domain",
+];
+
+/// Attribute keys reserved for the SDK's internal use
+const RESERVED_ATTR_KEY_PATTERNS: &[AttrKeyPattern] = &[
+    AttrKeyPattern::new_static("event.internal.*"),
+    AttrKeyPattern::new_static("timeline.internal.*"),
+];
+
+/// The registry of reserved attribute keys; see [`AttrKey::is_reserved`].
+/// Keys under the `event.internal.` and `timeline.internal.` prefixes are
+/// reserved as well.
+pub fn reserved_attribute_keys() -> &'static [&'static str] {
+    RESERVED_ATTR_KEYS
+}
+
+impl AttrKey {
+    /// Whether this is one of the [`reserved_attribute_keys`], or is under an
+    /// internal prefix.
+    pub fn is_reserved(&self) -> bool {
+        let k = self.0.as_ref();
+        RESERVED_ATTR_KEYS.contains(&k) || RESERVED_ATTR_KEY_PATTERNS.iter().any(|p| p.matches(k))
+    }
+}
+
+/// A glob pattern over attribute keys, e.g. `event.source.*`, for allow and
+/// deny lists.
+///
+/// `*` matches any run of characters, dots included, and `?` matches exactly
+/// one character; everything else matches itself. A pattern must match the
+/// whole key, so `event.source.*` matches `event.source.id` and
+/// `event.source.can.id`, but neither `event.source` nor `my.event.source.id`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AttrKeyPattern {
+    pattern: Cow<'static, str>,
+    matcher: PatternMatcher,
+}
+
+/// How a pattern is matched, worked out once when it's created
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum PatternMatcher {
+    /// No wildcards
+    Exact,
+    /// Wildcards only in a single trailing `*`; holds the length of the literal
+    /// prefix before it
+    Prefix(usize),
+    Glob,
+}
+
+impl PatternMatcher {
+    const fn compile(pattern: &str) -> Self {
+        let bytes = pattern.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'*' || bytes[i] == b'?' {
+                return if bytes[i] == b'*' && i == bytes.len() - 1 {
+                    PatternMatcher::Prefix(i)
+                } else {
+                    PatternMatcher::Glob
+                };
+            }
+            i += 1;
+        }
+        PatternMatcher::Exact
+    }
+}
+
+impl AttrKeyPattern {
+    pub fn new(pattern: String) -> Self {
+        let matcher = PatternMatcher::compile(&pattern);
+        Self {
+            pattern: Cow::Owned(pattern),
+            matcher,
+        }
+    }
+
+    pub const fn new_static(pattern: &'static str) -> Self {
+        Self {
+            pattern: Cow::Borrowed(pattern),
+            matcher: PatternMatcher::compile(pattern),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Whether the pattern has no wildcards, and so matches only the one key
+    pub fn is_exact(&self) -> bool {
+        self.matcher == PatternMatcher::Exact
+    }
+
+    pub fn matches(&self, key: impl AsRef<str>) -> bool {
+        let key = key.as_ref();
+        match self.matcher {
+            PatternMatcher::Exact => key == self.pattern,
+            PatternMatcher::Prefix(len) => key.starts_with(&self.pattern[..len]),
+            PatternMatcher::Glob => glob_matches(&self.pattern, key),
+        }
+    }
+}
+
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where to resume after the last '*': (pattern index, name index)
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bn)) => {
+                    backtrack = Some((bp, bn + 1));
+                    p = bp + 1;
+                    n = bn + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+impl From<&str> for AttrKeyPattern {
+    fn from(s: &str) -> Self {
+        AttrKeyPattern::new(s.to_owned())
+    }
+}
+
+impl From<String> for AttrKeyPattern {
+    fn from(s: String) -> Self {
+        AttrKeyPattern::new(s)
+    }
+}
+
+impl FromStr for AttrKeyPattern {
+    type Err = core::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.into())
+    }
+}
+
+impl core::fmt::Display for AttrKeyPattern {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+        write!(f, "{}", self.pattern)
+    }
+}
+
 ////////////
 // BigInt //
 ////////////
@@ -63,6 +256,10 @@ impl std::fmt::Display for AttrKey {
 pub struct BigInt(Box<i128>);
 
 impl BigInt {
+    /// Create an integer `AttrVal` from an `i128`.
+    ///
+    /// Values that fit in an `i64` are stored as [`AttrVal::Integer`]; only values outside
+    /// of that range are stored as [`AttrVal::BigInt`]. See also [`AttrVal::big_int`].
     pub fn new_attr_val(big_i: i128) -> AttrVal {
         // Store it as an Integer if it's small enough
         if big_i < (i64::MIN as i128) || big_i > (i64::MAX as i128) {
@@ -71,9 +268,18 @@ impl BigInt {
             AttrVal::Integer(big_i as i64)
         }
     }
+
+    /// Create an integer `AttrVal` from a `u128`, such as an unsigned 64-bit counter
+    /// widened to `u128`.
+    ///
+    /// Fails if the value is larger than `i128::MAX`, which is the largest integer an
+    /// `AttrVal` can represent.
+    pub fn try_from_u128(big_u: u128) -> Result<AttrVal, core::num::TryFromIntError> {
+        i128::try_from(big_u).map(Self::new_attr_val)
+    }
 }
-impl std::fmt::Display for BigInt {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for BigInt {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         self.0.fmt(f)
     }
 }
@@ -116,14 +322,56 @@ impl From<u64> for Nanoseconds {
     }
 }
 
-impl std::fmt::Display for Nanoseconds {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+/// The time since the Unix epoch, saturating: times before the epoch become
+/// zero, and times too far in the future for a `u64` become `u64::MAX`.
+#[cfg(feature = "std")]
+impl From<std::time::SystemTime> for Nanoseconds {
+    fn from(t: std::time::SystemTime) -> Self {
+        match t.duration_since(std::time::UNIX_EPOCH) {
+            Ok(d) => Nanoseconds(d.as_nanos().try_into().unwrap_or(u64::MAX)),
+            Err(_) => Nanoseconds(0),
+        }
+    }
+}
+
+/// Nanoseconds since the Unix epoch, as a `SystemTime`
+#[cfg(feature = "std")]
+impl From<Nanoseconds> for std::time::SystemTime {
+    fn from(ns: Nanoseconds) -> Self {
+        std::time::UNIX_EPOCH + std::time::Duration::from_nanos(ns.0)
+    }
+}
+
+/// The time since the Unix epoch, saturating like the `SystemTime` conversion.
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for Nanoseconds {
+    fn from(t: chrono::DateTime<chrono::Utc>) -> Self {
+        match t.timestamp_nanos_opt() {
+            Some(n) => Nanoseconds(n.try_into().unwrap_or(0)),
+            // Out of range for an i64 of nanoseconds, either way
+            None if t.timestamp() < 0 => Nanoseconds(0),
+            None => Nanoseconds(u64::MAX),
+        }
+    }
+}
+
+/// Nanoseconds since the Unix epoch, as a `DateTime`. Times past the range of
+/// `DateTime::from_timestamp_nanos` (in the year 2262) saturate.
+#[cfg(feature = "chrono")]
+impl From<Nanoseconds> for chrono::DateTime<chrono::Utc> {
+    fn from(ns: Nanoseconds) -> Self {
+        chrono::DateTime::from_timestamp_nanos(ns.0.try_into().unwrap_or(i64::MAX))
+    }
+}
+
+impl core::fmt::Display for Nanoseconds {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}ns", self.0)
     }
 }
 
 impl FromStr for Nanoseconds {
-    type Err = std::num::ParseIntError;
+    type Err = core::num::ParseIntError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         Ok(Nanoseconds(s.parse::<u64>()?))
     }
@@ -217,13 +465,13 @@ impl Ord for LogicalTime {
 }
 
 impl PartialOrd for LogicalTime {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl std::fmt::Display for LogicalTime {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for LogicalTime {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}:{}:{}:{}", self.0[0], self.0[1], self.0[2], self.0[3])
     }
 }
@@ -299,10 +547,48 @@ impl TimelineId {
         TimelineId(Uuid::nil())
     }
 
+    /// Allocate a new random timeline id. Requires the `std` feature; without it,
+    /// construct timeline ids from a [`Uuid`] instead.
+    #[cfg(feature = "std")]
     pub fn allocate() -> Self {
         TimelineId(Uuid::new_v4())
     }
 
+    /// Derive a stable timeline id from a `name`, in the given `namespace`. The
+    /// same inputs always give the same id, so a collector that restarts (or is
+    /// written in another language) can recover the id of a logical entity.
+    ///
+    /// The id is the v5 (SHA-1) UUID of the namespace UUID and the UTF-8 bytes
+    /// of `name`, as specified by RFC 4122. The C API's
+    /// `modality_timeline_id_from_name` computes the same thing.
+    pub fn from_name(namespace: Uuid, name: &str) -> Self {
+        TimelineId(Uuid::new_v5(&namespace, name.as_bytes()))
+    }
+
+    /// Derive a timeline id for the current thread, in the given `namespace`.
+    /// Repeated calls from the same thread of the same process return the same
+    /// id; other threads, and other runs of the program, get different ids.
+    ///
+    /// The id is a v5 UUID of the process id, the thread id, and the time of
+    /// the first call to this function in the process, which stands in for the
+    /// process start time. Requires the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn for_current_thread(namespace: Uuid) -> Self {
+        static PROCESS_EPOCH: std::sync::OnceLock<u128> = std::sync::OnceLock::new();
+        let epoch = PROCESS_EPOCH.get_or_init(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or_default()
+        });
+
+        let mut name = Vec::new();
+        name.extend_from_slice(&epoch.to_be_bytes());
+        name.extend_from_slice(&std::process::id().to_be_bytes());
+        name.extend_from_slice(format!("{:?}", std::thread::current().id()).as_bytes());
+        TimelineId(Uuid::new_v5(&namespace, &name))
+    }
+
     pub fn get_raw(&self) -> &Uuid {
         &self.0
     }
@@ -314,8 +600,8 @@ impl From<Uuid> for TimelineId {
     }
 }
 
-impl std::fmt::Display for TimelineId {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for TimelineId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         self.0.fmt(f)
     }
 }
@@ -356,8 +642,8 @@ impl EventCoordinate {
     }
 }
 
-impl std::fmt::Display for EventCoordinate {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for EventCoordinate {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{TIMELINE_ID_SIGIL}")?;
 
         // print the uuid as straight hex, for compactness
@@ -371,8 +657,8 @@ impl std::fmt::Display for EventCoordinate {
 
 pub struct EncodeHexWithoutLeadingZeroes<'a>(pub &'a [u8]);
 
-impl<'a> std::fmt::Display for EncodeHexWithoutLeadingZeroes<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl<'a> core::fmt::Display for EncodeHexWithoutLeadingZeroes<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let mut cursor = 0;
         let bytes = self.0;
         while bytes[cursor] == 0 && cursor < bytes.len() - 1 {
@@ -395,6 +681,26 @@ impl<'a> std::fmt::Display for EncodeHexWithoutLeadingZeroes<'a> {
 // AttrVal //
 /////////////
 
+/// An attribute value.
+///
+/// `AttrVal` is totally ordered. Values of different variants are ordered by
+/// variant, in the order they're declared here; values of the same variant are
+/// ordered by value:
+///
+/// * `TimelineId`s by their UUID bytes.
+/// * `EventCoordinate`s by timeline id, then event id.
+/// * `String`s lexicographically, by bytes.
+/// * `Integer`s, `BigInt`s, `Timestamp`s and `Duration`s numerically. Numbers
+///   of different variants aren't compared numerically: every `Integer` is less
+///   than every `BigInt`.
+/// * `Float`s numerically, with `-0.0 == 0.0`, and NaN equal to itself and
+///   greater than every other float.
+/// * `Bool`s with `false < true`.
+/// * `LogicalTime`s segment by segment, most significant first.
+///
+/// So sorting, and iterating over a `BTreeMap` or `BTreeSet` of values, is
+/// deterministic. New variants are only ever added at the end, so this order is
+/// stable.
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum AttrVal {
     TimelineId(Box<TimelineId>),
@@ -406,6 +712,8 @@ pub enum AttrVal {
     Bool(bool),
     Timestamp(Nanoseconds),
     LogicalTime(LogicalTime),
+    /// An elapsed time, as opposed to a point in time like [`AttrVal::Timestamp`]
+    Duration(Nanoseconds),
 }
 
 impl AttrVal {
@@ -419,49 +727,80 @@ impl AttrVal {
             AttrVal::Float(_) => AttrType::Float,
             AttrVal::Bool(_) => AttrType::Bool,
             AttrVal::Timestamp(_) => AttrType::Nanoseconds,
+            AttrVal::Duration(_) => AttrType::Duration,
             AttrVal::LogicalTime(_) => AttrType::LogicalTime,
         }
     }
 
-    pub fn as_timeline_id(self) -> std::result::Result<TimelineId, WrongAttrTypeError> {
+    /// Create an integer `AttrVal` from an `i128`.
+    ///
+    /// This never overflows: values that fit in an `i64` are stored as
+    /// [`AttrVal::Integer`], and larger ones as [`AttrVal::BigInt`]. Either way,
+    /// [`AttrVal::as_bigint`] returns the original value.
+    pub fn big_int(i: i128) -> AttrVal {
+        BigInt::new_attr_val(i)
+    }
+
+    /// Create an [`AttrVal::TimelineId`] without boxing the id by hand.
+    ///
+    /// This still allocates once per value. The id would fit inline without growing
+    /// `AttrVal`, but unboxing the variant would break code that matches on it.
+    pub fn timeline_id(timeline_id: TimelineId) -> AttrVal {
+        AttrVal::TimelineId(Box::new(timeline_id))
+    }
+
+    pub fn as_timeline_id(self) -> core::result::Result<TimelineId, WrongAttrTypeError> {
         self.try_into()
     }
 
-    pub fn as_event_coordinate(self) -> std::result::Result<EventCoordinate, WrongAttrTypeError> {
+    pub fn as_event_coordinate(self) -> core::result::Result<EventCoordinate, WrongAttrTypeError> {
         self.try_into()
     }
 
-    pub fn as_string(self) -> std::result::Result<Cow<'static, str>, WrongAttrTypeError> {
+    pub fn as_string(self) -> core::result::Result<Cow<'static, str>, WrongAttrTypeError> {
         self.try_into()
     }
 
-    pub fn as_int(self) -> std::result::Result<i64, WrongAttrTypeError> {
+    pub fn as_int(self) -> core::result::Result<i64, WrongAttrTypeError> {
         self.try_into()
     }
 
-    pub fn as_bigint(self) -> std::result::Result<i128, WrongAttrTypeError> {
+    /// Get the value of an integer attribute, whether it is stored as an
+    /// [`AttrVal::Integer`] or an [`AttrVal::BigInt`].
+    pub fn as_bigint(self) -> core::result::Result<i128, WrongAttrTypeError> {
         self.try_into()
     }
 
-    pub fn as_float(self) -> std::result::Result<f64, WrongAttrTypeError> {
+    pub fn as_float(self) -> core::result::Result<f64, WrongAttrTypeError> {
         self.try_into()
     }
 
-    pub fn as_bool(self) -> std::result::Result<bool, WrongAttrTypeError> {
+    pub fn as_bool(self) -> core::result::Result<bool, WrongAttrTypeError> {
         self.try_into()
     }
 
-    pub fn as_timestamp(self) -> std::result::Result<Nanoseconds, WrongAttrTypeError> {
+    pub fn as_timestamp(self) -> core::result::Result<Nanoseconds, WrongAttrTypeError> {
         self.try_into()
     }
 
-    pub fn as_logical_time(self) -> std::result::Result<LogicalTime, WrongAttrTypeError> {
+    pub fn as_duration(self) -> core::result::Result<Nanoseconds, WrongAttrTypeError> {
+        if let AttrVal::Duration(ns) = self {
+            Ok(ns)
+        } else {
+            Err(WrongAttrTypeError {
+                actual: self.attr_type(),
+                expected: AttrType::Duration,
+            })
+        }
+    }
+
+    pub fn as_logical_time(self) -> core::result::Result<LogicalTime, WrongAttrTypeError> {
         self.try_into()
     }
 }
 
-impl std::fmt::Display for AttrVal {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for AttrVal {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             AttrVal::String(s) => s.fmt(f),
             AttrVal::Integer(i) => i.fmt(f),
@@ -469,6 +808,7 @@ impl std::fmt::Display for AttrVal {
             AttrVal::Float(fp) => fp.fmt(f),
             AttrVal::Bool(b) => b.fmt(f),
             AttrVal::Timestamp(ns) => ns.fmt(f),
+            AttrVal::Duration(ns) => ns.fmt(f),
             AttrVal::LogicalTime(lt) => lt.fmt(f),
             AttrVal::EventCoordinate(ec) => ec.fmt(f),
             AttrVal::TimelineId(tid) => tid.fmt(f),
@@ -477,7 +817,7 @@ impl std::fmt::Display for AttrVal {
 }
 
 impl FromStr for AttrVal {
-    type Err = std::convert::Infallible;
+    type Err = core::convert::Infallible;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         // N.B. Eventually we will want  parsing that is informed by the AttrKey, that will allow
@@ -538,6 +878,15 @@ impl From<Nanoseconds> for AttrVal {
     }
 }
 
+impl From<core::time::Duration> for AttrVal {
+    /// Durations longer than `u64::MAX` nanoseconds saturate
+    fn from(d: core::time::Duration) -> AttrVal {
+        AttrVal::Duration(Nanoseconds::from(
+            u64::try_from(d.as_nanos()).unwrap_or(u64::MAX),
+        ))
+    }
+}
+
 impl From<LogicalTime> for AttrVal {
     fn from(lt: LogicalTime) -> AttrVal {
         AttrVal::LogicalTime(lt)
@@ -546,7 +895,7 @@ impl From<LogicalTime> for AttrVal {
 
 impl From<Uuid> for AttrVal {
     fn from(u: Uuid) -> AttrVal {
-        AttrVal::TimelineId(Box::new(u.into()))
+        AttrVal::timeline_id(u.into())
     }
 }
 
@@ -558,7 +907,7 @@ impl From<EventCoordinate> for AttrVal {
 
 impl From<TimelineId> for AttrVal {
     fn from(timeline_id: TimelineId) -> Self {
-        AttrVal::TimelineId(Box::new(timeline_id))
+        AttrVal::timeline_id(timeline_id)
     }
 }
 
@@ -574,10 +923,11 @@ pub enum AttrType {
     Nanoseconds,
     LogicalTime,
     Any,
+    Duration,
 }
 
-impl std::fmt::Display for AttrType {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for AttrType {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             AttrType::TimelineId => "TimelineId",
             AttrType::String => "String",
@@ -589,13 +939,14 @@ impl std::fmt::Display for AttrType {
             AttrType::LogicalTime => "LogicalTime",
             AttrType::Any => "Any",
             AttrType::EventCoordinate => "Coordinate",
+            AttrType::Duration => "Duration",
         }
         .fmt(f)
     }
 }
 
 pub mod conversion {
-    use std::convert::TryFrom;
+    use core::convert::TryFrom;
 
     use super::*;
 
@@ -648,7 +999,7 @@ pub mod conversion {
             impl TryFrom<AttrVal> for $ty {
                 type Error = WrongAttrTypeError;
 
-                fn try_from(value: AttrVal) -> std::result::Result<Self, Self::Error> {
+                fn try_from(value: AttrVal) -> core::result::Result<Self, Self::Error> {
                     if let $variant(x) = value {
                         Ok(x.into())
                     } else {
@@ -667,7 +1018,7 @@ pub mod conversion {
             impl TryFrom<AttrVal> for $ty {
                 type Error = WrongAttrTypeError;
 
-                fn try_from(value: AttrVal) -> std::result::Result<Self, Self::Error> {
+                fn try_from(value: AttrVal) -> core::result::Result<Self, Self::Error> {
                     if let $variant(x) = value {
                         Ok((*x).clone())
                     } else {
@@ -690,20 +1041,47 @@ pub mod conversion {
 
     impl_try_from_attr_val!(AttrVal::Integer, i64, AttrType::Integer);
     impl_try_from_attr_val!(AttrVal::String, Cow<'static, str>, AttrType::String);
-    impl_try_from_attr_val_deref!(AttrVal::BigInt, i128, AttrType::BigInt);
     impl_try_from_attr_val!(AttrVal::Float, f64, AttrType::Float);
     impl_try_from_attr_val!(AttrVal::Bool, bool, AttrType::Bool);
     impl_try_from_attr_val!(AttrVal::LogicalTime, LogicalTime, AttrType::LogicalTime);
     impl_try_from_attr_val!(AttrVal::Timestamp, Nanoseconds, AttrType::Nanoseconds);
+
+    // Small big integers are stored as Integers, so accept either variant here
+    impl TryFrom<AttrVal> for i128 {
+        type Error = WrongAttrTypeError;
+
+        fn try_from(value: AttrVal) -> core::result::Result<Self, Self::Error> {
+            match value {
+                AttrVal::Integer(i) => Ok(i.into()),
+                AttrVal::BigInt(bi) => Ok(*bi),
+                _ => Err(WrongAttrTypeError {
+                    actual: value.attr_type(),
+                    expected: AttrType::BigInt,
+                }),
+            }
+        }
+    }
 }
 
-#[derive(Debug, thiserror::Error, Eq, PartialEq)]
-#[error("Wrong attribute type: expected {expected:?}, found {actual:?}")]
+#[derive(Debug, Eq, PartialEq)]
 pub struct WrongAttrTypeError {
     actual: AttrType,
     expected: AttrType,
 }
 
+impl core::fmt::Display for WrongAttrTypeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "Wrong attribute type: expected {:?}, found {:?}",
+            self.expected, self.actual
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for WrongAttrTypeError {}
+
 #[cfg(feature = "pyo3")]
 impl<'py> pyo3::FromPyObject<'py> for AttrVal {
     fn extract_bound(
@@ -711,11 +1089,21 @@ impl<'py> pyo3::FromPyObject<'py> for AttrVal {
     ) -> pyo3::prelude::PyResult<Self> {
         use pyo3::prelude::*;
 
-        // Check the most common types first
+        // Check the most common types first. `bool` is a subclass of `int` in
+        // Python, so it has to come before the integer check.
+        if let Ok(b) = ob.extract::<bool>() {
+            return Ok(b.into());
+        }
+
         if let Ok(i) = ob.extract::<i64>() {
             return Ok(i.into());
         }
 
+        // Large ints would otherwise be accepted as (lossy) floats below
+        if let Ok(i) = ob.extract::<i128>() {
+            return Ok(i.into());
+        }
+
         if let Ok(f) = ob.extract::<f64>() {
             return Ok(f.into());
         }
@@ -724,10 +1112,6 @@ impl<'py> pyo3::FromPyObject<'py> for AttrVal {
             return Ok(s.into());
         }
 
-        if let Ok(b) = ob.extract::<bool>() {
-            return Ok(b.into());
-        }
-
         if let Ok(ts) = ob.extract::<std::time::SystemTime>() {
             match ts.duration_since(std::time::UNIX_EPOCH) {
                 Ok(dur) => {
@@ -753,6 +1137,13 @@ impl<'py> pyo3::FromPyObject<'py> for AttrVal {
             return Ok(tl_id.0.into());
         }
 
+        if ob.is_instance(&py_uuid_class(ob.py())?)? {
+            let hex = ob.getattr("hex")?.extract::<String>()?;
+            let uuid = Uuid::parse_str(&hex)
+                .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
+            return Ok(TimelineId(uuid).into());
+        }
+
         if let Ok(lt) = ob.extract::<LogicalTime>() {
             return Ok(lt.into());
         }
@@ -761,10 +1152,6 @@ impl<'py> pyo3::FromPyObject<'py> for AttrVal {
             return Ok(ec.into());
         }
 
-        if let Ok(i) = ob.extract::<i128>() {
-            return Ok(i.into());
-        }
-
         if let Ok(id) = ob.extract::<crate::mutation_plane::types::MutationId>() {
             return Ok(i128::from_le_bytes(Uuid::from(id).into_bytes()).into());
         }
@@ -793,15 +1180,130 @@ impl pyo3::IntoPy<pyo3::PyObject> for AttrVal {
             AttrVal::Timestamp(ns) => {
                 (std::time::UNIX_EPOCH + std::time::Duration::from_nanos(ns.get_raw())).into_py(py)
             }
+            AttrVal::Duration(ns) => std::time::Duration::from_nanos(ns.get_raw()).into_py(py),
             AttrVal::LogicalTime(lt) => lt.into_py(py),
         }
     }
 }
 
+#[cfg(feature = "pyo3")]
+pub(crate) fn py_uuid_class(
+    py: pyo3::prelude::Python<'_>,
+) -> pyo3::prelude::PyResult<pyo3::prelude::Bound<'_, pyo3::prelude::PyAny>> {
+    use pyo3::prelude::*;
+    py.import_bound("uuid")?.getattr("UUID")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn reserved_attr_keys() {
+        assert!(reserved_attribute_keys().contains(&"event.timestamp"));
+        assert!(AttrKey::from("event.name").is_reserved());
+        assert!(AttrKey::from("timeline.run_id").is_reserved());
+        assert!(AttrKey::from("event.internal.rs.kind").is_reserved());
+        assert!(AttrKey::from("timeline.name").is_reserved());
+        assert!(!AttrKey::from("event.name.suffix").is_reserved());
+        assert!(!AttrKey::from("event.internalish").is_reserved());
+    }
+
+    #[test]
+    fn timeline_id_from_name() {
+        let ns = Uuid::parse_str("3cd8734a-1747-4cfd-a305-2e8b3aa41c8f").unwrap();
+        let tid = TimelineId::from_name(ns, "sensor-board-1");
+        assert_eq!(tid, TimelineId::from_name(ns, "sensor-board-1"));
+        assert_ne!(tid, TimelineId::from_name(ns, "sensor-board-2"));
+
+        // Also checked by the C API's tests; the two must agree
+        assert_eq!(
+            tid.get_raw().to_string(),
+            "2e2dec68-6a58-50f7-a2ed-c52231736e5b"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn nanoseconds_from_system_time() {
+        use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+        let t = UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789);
+        let ns = Nanoseconds::from(t);
+        assert_eq!(ns, Nanoseconds::from(1_700_000_000_123_456_789));
+        assert_eq!(SystemTime::from(ns), t);
+
+        assert_eq!(
+            Nanoseconds::from(UNIX_EPOCH - Duration::from_secs(1)),
+            Nanoseconds::from(0)
+        );
+        assert_eq!(
+            Nanoseconds::from(UNIX_EPOCH + Duration::from_secs(u64::MAX / 1_000_000_000 + 1)),
+            Nanoseconds::from(u64::MAX)
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn nanoseconds_from_chrono() {
+        use chrono::{DateTime, TimeZone, Utc};
+
+        let t = Utc.timestamp_opt(1_700_000_000, 123_456_789).unwrap();
+        let ns = Nanoseconds::from(t);
+        assert_eq!(ns, Nanoseconds::from(1_700_000_000_123_456_789));
+        assert_eq!(DateTime::<Utc>::from(ns), t);
+
+        let before_epoch = Utc.timestamp_opt(-1, 0).unwrap();
+        assert_eq!(Nanoseconds::from(before_epoch), Nanoseconds::from(0));
+    }
+
+    #[test]
+    fn attr_key_patterns() {
+        let source = AttrKeyPattern::from("event.source.*");
+        assert!(source.matches("event.source.id"));
+        assert!(source.matches("event.source.can.id"));
+        assert!(source.matches(AttrKey::from("event.source.")));
+        assert!(!source.matches("event.source"));
+        assert!(!source.matches("my.event.source.id"));
+        assert!(!source.is_exact());
+
+        let single = AttrKeyPattern::from("timeline.?");
+        assert!(single.matches("timeline.a"));
+        assert!(single.matches("timeline.µ"));
+        assert!(!single.matches("timeline."));
+        assert!(!single.matches("timeline.ab"));
+        assert!(!single.matches("xtimeline.a"));
+
+        let exact = AttrKeyPattern::from("event.name");
+        assert!(exact.is_exact());
+        assert!(exact.matches("event.name"));
+        assert!(!exact.matches("event.name.suffix"));
+        assert!(!exact.matches("my.event.name"));
+
+        let middle = AttrKeyPattern::new_static("event.*.id");
+        assert!(middle.matches("event.source.id"));
+        assert!(middle.matches("event.a.b.id"));
+        assert!(!middle.matches("event.source.id.x"));
+        assert!(AttrKeyPattern::from("*").matches(""));
+        assert_eq!(middle.to_string(), "event.*.id");
+    }
+
+    #[test]
+    fn attr_key_normalization() {
+        let padded = AttrKey::from(" Foo ");
+        let plain = AttrKey::from("foo");
+        assert_ne!(padded, plain);
+
+        assert_eq!(
+            padded.normalized(AttrKeyCase::Preserve),
+            AttrKey::from("Foo")
+        );
+        assert_eq!(
+            padded.normalized(AttrKeyCase::Lowercase),
+            plain.normalized(AttrKeyCase::Lowercase)
+        );
+    }
+
     #[test]
     fn parse_logical_time() {
         let reference = Ok(LogicalTime::quaternary(0u64, 0u64, 0u64, 42u64));
@@ -822,6 +1324,37 @@ mod tests {
         assert_eq!(Err(()), "example.com:8080".parse::<LogicalTime>());
     }
 
+    #[test]
+    fn big_int_boundaries() {
+        let max = i64::MAX as i128;
+        assert_eq!(AttrVal::big_int(max), AttrVal::Integer(i64::MAX));
+        assert_eq!(AttrVal::big_int(max).as_int(), Ok(i64::MAX));
+        assert_eq!(AttrVal::big_int(max).as_bigint(), Ok(max));
+
+        let over = max + 1;
+        assert_eq!(AttrVal::big_int(over).attr_type(), AttrType::BigInt);
+        assert_eq!(AttrVal::big_int(over).as_bigint(), Ok(over));
+        assert!(AttrVal::big_int(over).as_int().is_err());
+
+        let under = i64::MIN as i128 - 1;
+        assert_eq!(AttrVal::big_int(under).attr_type(), AttrType::BigInt);
+        assert_eq!(AttrVal::big_int(under).as_bigint(), Ok(under));
+
+        assert_eq!(
+            BigInt::try_from_u128(over as u128),
+            Ok(AttrVal::big_int(over))
+        );
+        assert_eq!(
+            BigInt::try_from_u128(u64::MAX.into()).unwrap().as_bigint(),
+            Ok(u64::MAX.into())
+        );
+        assert_eq!(
+            BigInt::try_from_u128(i128::MAX as u128),
+            Ok(AttrVal::big_int(i128::MAX))
+        );
+        assert!(BigInt::try_from_u128(i128::MAX as u128 + 1).is_err());
+    }
+
     #[test]
     fn parse_attr_vals() {
         // Bool
@@ -886,4 +1419,112 @@ mod tests {
 
         assert_eq!(Ok(AttrVal::String("".into())), "".parse());
     }
+
+    #[test]
+    fn duration_is_distinct_from_timestamp() {
+        let latency = AttrVal::from(core::time::Duration::from_micros(3));
+        let timestamp = AttrVal::from(Nanoseconds::from(3_000));
+
+        assert_eq!(latency, AttrVal::Duration(Nanoseconds::from(3_000)));
+        assert_ne!(latency, timestamp);
+        assert_eq!(latency.attr_type(), AttrType::Duration);
+        assert_eq!(timestamp.attr_type(), AttrType::Nanoseconds);
+        assert_eq!(latency.to_string(), "3000ns");
+
+        assert_eq!(latency.clone().as_duration(), Ok(Nanoseconds::from(3_000)));
+        assert_eq!(
+            latency.clone().as_timestamp(),
+            Err(WrongAttrTypeError {
+                actual: AttrType::Duration,
+                expected: AttrType::Nanoseconds,
+            })
+        );
+        assert!(timestamp.clone().as_duration().is_err());
+
+        let latency_json = serde_json::to_string(&latency).unwrap();
+        let timestamp_json = serde_json::to_string(&timestamp).unwrap();
+        assert_eq!(latency_json, r#"{"Duration":3000}"#);
+        assert_ne!(latency_json, timestamp_json);
+        assert_eq!(
+            serde_json::from_str::<AttrVal>(&latency_json).unwrap(),
+            latency
+        );
+        assert_eq!(
+            serde_json::from_str::<AttrVal>(&timestamp_json).unwrap(),
+            timestamp
+        );
+    }
+
+    #[test]
+    fn timeline_id_constructor() {
+        let id = TimelineId::allocate();
+        assert_eq!(AttrVal::timeline_id(id), AttrVal::TimelineId(Box::new(id)));
+        assert_eq!(AttrVal::timeline_id(id), AttrVal::from(id));
+        assert_eq!(AttrVal::timeline_id(id).as_timeline_id(), Ok(id));
+    }
+
+    #[test]
+    fn timeline_id_for_current_thread() {
+        let ns = Uuid::new_v4();
+        let id = TimelineId::for_current_thread(ns);
+        assert_eq!(TimelineId::for_current_thread(ns), id);
+        assert_ne!(TimelineId::for_current_thread(Uuid::new_v4()), id);
+
+        let other = std::thread::spawn(move || {
+            (
+                TimelineId::for_current_thread(ns),
+                TimelineId::for_current_thread(ns),
+            )
+        })
+        .join()
+        .unwrap();
+        assert_eq!(other.0, other.1);
+        assert_ne!(other.0, id);
+    }
+
+    #[test]
+    fn attr_val_ordering() {
+        let tl_a = TimelineId::from(Uuid::from_u128(1));
+        let tl_b = TimelineId::from(Uuid::from_u128(2));
+        let coord = |timeline_id, n| {
+            AttrVal::EventCoordinate(Box::new(EventCoordinate {
+                timeline_id,
+                id: [n; 16],
+            }))
+        };
+
+        let expected = vec![
+            AttrVal::timeline_id(tl_a),
+            AttrVal::timeline_id(tl_b),
+            coord(tl_a, 9),
+            coord(tl_b, 0),
+            AttrVal::from("B"),
+            AttrVal::from("a"),
+            AttrVal::from("ab"),
+            AttrVal::from(-5),
+            AttrVal::from(3),
+            BigInt::new_attr_val(i128::MIN),
+            BigInt::new_attr_val(i128::MAX),
+            AttrVal::from(f64::NEG_INFINITY),
+            AttrVal::from(-0.0),
+            AttrVal::from(1.5),
+            AttrVal::from(f64::NAN),
+            AttrVal::from(false),
+            AttrVal::from(true),
+            AttrVal::Timestamp(Nanoseconds::from(1)),
+            AttrVal::Timestamp(Nanoseconds::from(2)),
+            AttrVal::LogicalTime(LogicalTime::binary(0u64, 5u64)),
+            AttrVal::LogicalTime(LogicalTime::binary(1u64, 0u64)),
+            AttrVal::Duration(Nanoseconds::from(0)),
+        ];
+
+        let mut shuffled = expected.clone();
+        shuffled.reverse();
+        shuffled.rotate_left(7);
+        shuffled.sort();
+        assert_eq!(shuffled, expected);
+
+        assert_eq!(AttrVal::from(-0.0), AttrVal::from(0.0));
+        assert_eq!(AttrVal::from(f64::NAN), AttrVal::from(f64::NAN));
+    }
 }
@@ -0,0 +1,378 @@
+//! A portable file format for captured timelines and events, independent of
+//! the live ingest protocol; e.g. for test fixtures, or attaching a session
+//! to a bug report.
+//!
+//! An archive starts with [EVENT_ARCHIVE_MAGIC] and a big-endian `u32`
+//! [EVENT_ARCHIVE_VERSION]. Then come its records, each a big-endian `u32` length
+//! followed by a CBOR [ArchiveRecord], of at most [MAX_RECORD_LEN] bytes.
+//! Attribute keys are written out in full, so an archive can be read without
+//! any other context.
+//!
+//! This isn't the same format as an ingest archive, from
+//! [crate::ingest_client::archive], which holds the protocol messages a
+//! client would have sent, to be replayed through a live connection. An event
+//! archive holds the timelines and events themselves, and is read back here.
+//! The two start with different magic bytes, so neither is mistaken for the
+//! other.
+//!
+//! ```
+//! # fn example() -> Result<(), auxon_sdk::archive::EventArchiveError> {
+//! use auxon_sdk::api::{AttrKey, AttrVal, Nanoseconds, TimelineId};
+//! use auxon_sdk::archive::{EventArchiveReader, EventArchiveWriter};
+//! use std::collections::BTreeMap;
+//!
+//! let tl = TimelineId::allocate();
+//! let attrs = BTreeMap::from([(AttrKey::from("event.name"), AttrVal::from("boot"))]);
+//!
+//! let mut writer = EventArchiveWriter::new(Vec::new())?;
+//! writer.write_event(tl, Nanoseconds::from(1_000), attrs)?;
+//! let bytes = writer.finish()?;
+//!
+//! for record in EventArchiveReader::new(bytes.as_slice())? {
+//!     println!("{:?}", record?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::api::{AttrKey, AttrVal, Nanoseconds, TimelineId};
+use minicbor::{decode, encode, Decode, Decoder, Encode, Encoder};
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+
+/// The first bytes of every archive
+pub const EVENT_ARCHIVE_MAGIC: [u8; 8] = *b"AUXONARC";
+
+/// The version of the archive format written by [EventArchiveWriter]. Readers
+/// accept archives of this version or older.
+pub const EVENT_ARCHIVE_VERSION: u32 = 1;
+
+/// The largest encoded record allowed in an archive, so that a corrupt length
+/// prefix can't make a reader allocate without bound
+pub const MAX_RECORD_LEN: u32 = 16 * 1024 * 1024;
+
+/// One entry in an archive
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArchiveRecord {
+    /// Attributes of a timeline
+    TimelineMetadata {
+        timeline: TimelineId,
+        attrs: BTreeMap<AttrKey, AttrVal>,
+    },
+
+    /// An event on a timeline
+    Event {
+        timeline: TimelineId,
+        timestamp: Nanoseconds,
+        attrs: BTreeMap<AttrKey, AttrVal>,
+    },
+}
+
+const RECORD_TIMELINE_METADATA: u8 = 0;
+const RECORD_EVENT: u8 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EventArchiveError {
+    #[error("Not an archive")]
+    BadMagic,
+
+    #[error("Unsupported archive version {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("The archive ends partway through a record")]
+    Truncated,
+
+    #[error("Archive record of {0} bytes is longer than the limit of {MAX_RECORD_LEN}")]
+    RecordTooLong(usize),
+
+    #[error("Error reading or writing archive")]
+    Io(#[from] io::Error),
+
+    #[error("Error encoding archive record")]
+    Encode(#[from] encode::Error<io::Error>),
+
+    #[error("Malformed archive record")]
+    Decode(#[from] decode::Error),
+}
+
+/// Writes an archive; see the [module docs](self).
+pub struct EventArchiveWriter<W: Write> {
+    w: W,
+}
+
+impl<W: Write> EventArchiveWriter<W> {
+    /// Start an archive, by writing its header to `w`.
+    pub fn new(mut w: W) -> Result<Self, EventArchiveError> {
+        w.write_all(&EVENT_ARCHIVE_MAGIC)?;
+        w.write_all(&EVENT_ARCHIVE_VERSION.to_be_bytes())?;
+        Ok(EventArchiveWriter { w })
+    }
+
+    pub fn write_record(&mut self, record: &ArchiveRecord) -> Result<(), EventArchiveError> {
+        let buf = minicbor::to_vec(record)?;
+        if buf.len() > MAX_RECORD_LEN as usize {
+            return Err(EventArchiveError::RecordTooLong(buf.len()));
+        }
+        self.w.write_all(&(buf.len() as u32).to_be_bytes())?;
+        self.w.write_all(&buf)?;
+        Ok(())
+    }
+
+    pub fn write_timeline_metadata(
+        &mut self,
+        timeline: TimelineId,
+        attrs: BTreeMap<AttrKey, AttrVal>,
+    ) -> Result<(), EventArchiveError> {
+        self.write_record(&ArchiveRecord::TimelineMetadata { timeline, attrs })
+    }
+
+    pub fn write_event(
+        &mut self,
+        timeline: TimelineId,
+        timestamp: Nanoseconds,
+        attrs: BTreeMap<AttrKey, AttrVal>,
+    ) -> Result<(), EventArchiveError> {
+        self.write_record(&ArchiveRecord::Event {
+            timeline,
+            timestamp,
+            attrs,
+        })
+    }
+
+    /// Flush the archive, and give back the underlying writer.
+    pub fn finish(mut self) -> Result<W, EventArchiveError> {
+        self.w.flush()?;
+        Ok(self.w)
+    }
+}
+
+/// Reads an archive, yielding its records in order; see the [module docs](self).
+pub struct EventArchiveReader<R: Read> {
+    r: R,
+    version: u32,
+    buf: Vec<u8>,
+}
+
+impl<R: Read> EventArchiveReader<R> {
+    /// Check the archive header at the start of `r`.
+    pub fn new(mut r: R) -> Result<Self, EventArchiveError> {
+        let mut header = [0u8; EVENT_ARCHIVE_MAGIC.len() + 4];
+        read_exact_or_truncated(&mut r, &mut header)?;
+        if header[..EVENT_ARCHIVE_MAGIC.len()] != EVENT_ARCHIVE_MAGIC {
+            return Err(EventArchiveError::BadMagic);
+        }
+        let version = u32::from_be_bytes(header[EVENT_ARCHIVE_MAGIC.len()..].try_into().unwrap());
+        if version == 0 || version > EVENT_ARCHIVE_VERSION {
+            return Err(EventArchiveError::UnsupportedVersion(version));
+        }
+        Ok(EventArchiveReader {
+            r,
+            version,
+            buf: Vec::new(),
+        })
+    }
+
+    /// The format version the archive was written with
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    fn read_record(&mut self) -> Result<Option<ArchiveRecord>, EventArchiveError> {
+        let mut len = [0u8; 4];
+        // A clean end of the archive is only allowed between records
+        let mut filled = 0;
+        while filled < len.len() {
+            match self.r.read(&mut len[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(EventArchiveError::Truncated),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let len = u32::from_be_bytes(len);
+        if len > MAX_RECORD_LEN {
+            return Err(EventArchiveError::RecordTooLong(len as usize));
+        }
+        self.buf.resize(len as usize, 0);
+        read_exact_or_truncated(&mut self.r, &mut self.buf)?;
+        Ok(Some(minicbor::decode(&self.buf)?))
+    }
+}
+
+impl<R: Read> Iterator for EventArchiveReader<R> {
+    type Item = Result<ArchiveRecord, EventArchiveError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+fn read_exact_or_truncated<R: Read>(r: &mut R, buf: &mut [u8]) -> Result<(), EventArchiveError> {
+    r.read_exact(buf).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => EventArchiveError::Truncated,
+        _ => e.into(),
+    })
+}
+
+impl Encode for ArchiveRecord {
+    fn encode<W: encode::Write>(&self, e: &mut Encoder<W>) -> Result<(), encode::Error<W::Error>> {
+        match self {
+            ArchiveRecord::TimelineMetadata { timeline, attrs } => {
+                e.array(3)?.u8(RECORD_TIMELINE_METADATA)?.encode(timeline)?;
+                encode_attrs(attrs, e)
+            }
+            ArchiveRecord::Event {
+                timeline,
+                timestamp,
+                attrs,
+            } => {
+                e.array(4)?
+                    .u8(RECORD_EVENT)?
+                    .encode(timeline)?
+                    .encode(timestamp)?;
+                encode_attrs(attrs, e)
+            }
+        }
+    }
+}
+
+impl<'b> Decode<'b> for ArchiveRecord {
+    fn decode(d: &mut Decoder<'b>) -> Result<Self, decode::Error> {
+        let len = d.array()?;
+        match (d.u8()?, len) {
+            (RECORD_TIMELINE_METADATA, Some(3)) => Ok(ArchiveRecord::TimelineMetadata {
+                timeline: d.decode()?,
+                attrs: decode_attrs(d)?,
+            }),
+            (RECORD_EVENT, Some(4)) => Ok(ArchiveRecord::Event {
+                timeline: d.decode()?,
+                timestamp: d.decode()?,
+                attrs: decode_attrs(d)?,
+            }),
+            _ => Err(decode::Error::Message("Unknown archive record")),
+        }
+    }
+}
+
+fn encode_attrs<W: encode::Write>(
+    attrs: &BTreeMap<AttrKey, AttrVal>,
+    e: &mut Encoder<W>,
+) -> Result<(), encode::Error<W::Error>> {
+    e.map(attrs.len() as u64)?;
+    for (k, v) in attrs {
+        e.str(k.as_ref())?.encode(v)?;
+    }
+    Ok(())
+}
+
+fn decode_attrs(d: &mut Decoder<'_>) -> Result<BTreeMap<AttrKey, AttrVal>, decode::Error> {
+    let len = d.map()?.ok_or(decode::Error::Message(
+        "Expected a definite-length attribute map",
+    ))?;
+    (0..len)
+        .map(|_| Ok((AttrKey::from(d.str()?.to_owned()), d.decode()?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_records() -> Vec<ArchiveRecord> {
+        let tl = |n| TimelineId::from(uuid::Uuid::from_u128(n));
+        let attrs = |kvs: &[(&str, AttrVal)]| {
+            kvs.iter()
+                .map(|(k, v)| (AttrKey::from(*k), v.clone()))
+                .collect()
+        };
+        vec![
+            ArchiveRecord::TimelineMetadata {
+                timeline: tl(1),
+                attrs: attrs(&[("timeline.name", "sensor".into())]),
+            },
+            ArchiveRecord::TimelineMetadata {
+                timeline: tl(2),
+                attrs: attrs(&[
+                    ("timeline.name", "actuator".into()),
+                    ("timeline.run_id", 7.into()),
+                ]),
+            },
+            ArchiveRecord::Event {
+                timeline: tl(1),
+                timestamp: 100.into(),
+                attrs: attrs(&[
+                    ("event.name", "reading".into()),
+                    ("event.value", AttrVal::Float(1.5.into())),
+                    ("event.source", AttrVal::TimelineId(Box::new(tl(2)))),
+                ]),
+            },
+            ArchiveRecord::Event {
+                timeline: tl(2),
+                timestamp: 200.into(),
+                attrs: attrs(&[]),
+            },
+        ]
+    }
+
+    fn write_archive(records: &[ArchiveRecord]) -> Vec<u8> {
+        let mut writer = EventArchiveWriter::new(Vec::new()).unwrap();
+        for record in records {
+            writer.write_record(record).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let records = sample_records();
+        let bytes = write_archive(&records);
+
+        let reader = EventArchiveReader::new(bytes.as_slice()).unwrap();
+        assert_eq!(reader.version(), EVENT_ARCHIVE_VERSION);
+        let read: Vec<ArchiveRecord> = reader.collect::<Result<_, _>>().unwrap();
+        assert_eq!(read, records);
+    }
+
+    #[test]
+    fn truncated_archive() {
+        let records = sample_records();
+        let bytes = write_archive(&records);
+        let last_record_len = minicbor::to_vec(records.last().unwrap()).unwrap().len();
+
+        // Cut off partway through the header, the last length prefix, and the last record
+        for cut in [6, bytes.len() - last_record_len - 2, bytes.len() - 1] {
+            let result = EventArchiveReader::new(&bytes[..cut])
+                .and_then(|r| r.collect::<Result<Vec<_>, _>>());
+            assert!(
+                matches!(result, Err(EventArchiveError::Truncated)),
+                "cut at {cut}: {result:?}"
+            );
+        }
+
+        assert!(matches!(
+            EventArchiveReader::new(&b"NOTANARCHIVE"[..]),
+            Err(EventArchiveError::BadMagic)
+        ));
+    }
+
+    #[test]
+    fn oversized_record() {
+        let mut bytes = write_archive(&[]);
+        bytes.extend_from_slice(&(MAX_RECORD_LEN + 1).to_be_bytes());
+        let mut reader = EventArchiveReader::new(bytes.as_slice()).unwrap();
+        assert!(matches!(
+            reader.next(),
+            Some(Err(EventArchiveError::RecordTooLong(len))) if len == MAX_RECORD_LEN as usize + 1
+        ));
+
+        // Nor is an ingest archive mistaken for an event archive
+        let mut ingest_archive = crate::ingest_client::archive::ARCHIVE_MAGIC.to_vec();
+        ingest_archive.extend_from_slice(&1u32.to_be_bytes());
+        assert!(matches!(
+            EventArchiveReader::new(ingest_archive.as_slice()),
+            Err(EventArchiveError::BadMagic)
+        ));
+    }
+}
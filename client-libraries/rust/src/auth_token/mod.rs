@@ -8,6 +8,7 @@ use std::{
     env,
     path::{Path, PathBuf},
     str::FromStr,
+    sync::{Arc, Mutex},
 };
 use thiserror::Error;
 use token_user_file::{
@@ -21,33 +22,79 @@ pub const MODALITY_AUTH_TOKEN_ENV_VAR: &str = "MODALITY_AUTH_TOKEN";
 const DEFAULT_CONTEXT_DIR: &str = "modality_cli";
 const MODALITY_CONTEXT_DIR_ENV_VAR: &str = "MODALITY_CONTEXT_DIR";
 
+/// Set by systemd for services with credentials; see `LoadCredential=` in systemd.exec(5)
+pub const CREDENTIALS_DIRECTORY_ENV_VAR: &str = "CREDENTIALS_DIRECTORY";
+
+/// The name of the systemd credential holding the auth token, in the same format as the
+/// user auth token file
+pub const AUTH_TOKEN_CREDENTIAL_NAME: &str = "modality-auth-token";
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[repr(transparent)]
 pub struct AuthToken(Vec<u8>);
 
+/// The token last loaded by [AuthToken::load_cached]
+static CACHED_AUTH_TOKEN: Mutex<Option<Arc<AuthToken>>> = Mutex::new(None);
+
 impl AuthToken {
-    /// Load an auth token meant for user-api usage
+    /// Load an auth token meant for user-api usage.
+    ///
+    /// In order of precedence, this uses the `MODALITY_AUTH_TOKEN` environment variable,
+    /// the `modality-auth-token` systemd credential (when `CREDENTIALS_DIRECTORY` is set),
+    /// and the user auth token file in the modality context dir.
     pub fn load() -> Result<Self, LoadAuthTokenError> {
         if let Ok(s) = std::env::var(MODALITY_AUTH_TOKEN_ENV_VAR) {
             return Ok(AuthTokenHexString(s).try_into()?);
         }
 
+        if let Some(credentials_dir) = env::var_os(CREDENTIALS_DIRECTORY_ENV_VAR) {
+            let credential_path = Path::new(&credentials_dir).join(AUTH_TOKEN_CREDENTIAL_NAME);
+            if credential_path.exists() {
+                return Self::load_file(&credential_path);
+            }
+        }
+
         let context_dir = Self::context_dir()?;
         let user_auth_token_path = context_dir.join(USER_AUTH_TOKEN_FILE_NAME);
         if user_auth_token_path.exists() {
-            if let Some(file_contents) = read_user_auth_token_file(&user_auth_token_path)? {
-                return Ok(file_contents.auth_token);
-            } else {
-                return Err(LoadAuthTokenError::NoTokenInFile(
-                    user_auth_token_path.to_owned(),
-                ));
-            }
+            return Self::load_file(&user_auth_token_path);
         }
 
         Err(LoadAuthTokenError::NoAuthToken)
     }
 
+    /// Like [AuthToken::load], but the token is only loaded once per process;
+    /// later calls share it, until [AuthToken::invalidate_cached] is called
+    /// (e.g. after the token is rotated). Failures aren't cached.
+    pub fn load_cached() -> Result<Arc<Self>, LoadAuthTokenError> {
+        let mut cached = CACHED_AUTH_TOKEN
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(token) = cached.as_ref() {
+            return Ok(token.clone());
+        }
+        let token = Arc::new(Self::load()?);
+        *cached = Some(token.clone());
+        Ok(token)
+    }
+
+    /// Forget the token cached by [AuthToken::load_cached], so the next call
+    /// loads it again.
+    pub fn invalidate_cached() {
+        *CACHED_AUTH_TOKEN
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+    }
+
+    fn load_file(path: &Path) -> Result<Self, LoadAuthTokenError> {
+        if let Some(file_contents) = read_user_auth_token_file(path)? {
+            Ok(file_contents.auth_token)
+        } else {
+            Err(LoadAuthTokenError::NoTokenInFile(path.to_owned()))
+        }
+    }
+
     fn context_dir() -> Result<PathBuf, LoadAuthTokenError> {
         match env::var(MODALITY_CONTEXT_DIR_ENV_VAR) {
             Ok(val) => Ok(PathBuf::from(val)),
@@ -218,4 +265,62 @@ mod tests {
             }
         });
     }
+
+    #[test]
+    #[serial_test::serial]
+    fn load_from_systemd_credential() {
+        use token_user_file::write_user_auth_token_file;
+
+        let credentials_dir = tempfile::tempdir().unwrap();
+        let context_dir = tempfile::tempdir().unwrap();
+        env::remove_var(MODALITY_AUTH_TOKEN_ENV_VAR);
+        env::set_var(CREDENTIALS_DIRECTORY_ENV_VAR, credentials_dir.path());
+        env::set_var(MODALITY_CONTEXT_DIR_ENV_VAR, context_dir.path());
+
+        write_user_auth_token_file(
+            &context_dir.path().join(USER_AUTH_TOKEN_FILE_NAME),
+            AuthToken::from(vec![1, 1]),
+        )
+        .unwrap();
+
+        // No credential file, fall back to the context dir
+        assert_eq!(AuthToken::load().unwrap(), AuthToken::from(vec![1, 1]));
+
+        // The credential takes precedence over the context dir
+        write_user_auth_token_file(
+            &credentials_dir.path().join(AUTH_TOKEN_CREDENTIAL_NAME),
+            AuthToken::from(vec![2, 2]),
+        )
+        .unwrap();
+        assert_eq!(AuthToken::load().unwrap(), AuthToken::from(vec![2, 2]));
+
+        // And the env var over both
+        env::set_var(MODALITY_AUTH_TOKEN_ENV_VAR, "0303");
+        assert_eq!(AuthToken::load().unwrap(), AuthToken::from(vec![3, 3]));
+
+        env::remove_var(MODALITY_AUTH_TOKEN_ENV_VAR);
+        env::remove_var(CREDENTIALS_DIRECTORY_ENV_VAR);
+        env::remove_var(MODALITY_CONTEXT_DIR_ENV_VAR);
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn load_cached_shares_one_token() {
+        AuthToken::invalidate_cached();
+        env::set_var(MODALITY_AUTH_TOKEN_ENV_VAR, "0101");
+
+        let first = AuthToken::load_cached().unwrap();
+        env::set_var(MODALITY_AUTH_TOKEN_ENV_VAR, "0202");
+        let second = AuthToken::load_cached().unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(*second, AuthToken::from(vec![1, 1]));
+
+        AuthToken::invalidate_cached();
+        let reloaded = AuthToken::load_cached().unwrap();
+        assert!(!Arc::ptr_eq(&first, &reloaded));
+        assert_eq!(*reloaded, AuthToken::from(vec![2, 2]));
+
+        env::remove_var(MODALITY_AUTH_TOKEN_ENV_VAR);
+        AuthToken::invalidate_cached();
+    }
 }
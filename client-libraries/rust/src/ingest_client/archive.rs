@@ -0,0 +1,308 @@
+//! Ingest archives: files of ingest protocol messages, captured without a
+//! live connection and uploaded later.
+//!
+//! An archive starts with [ARCHIVE_MAGIC] and a big-endian `u32`
+//! [ARCHIVE_VERSION], followed by the messages a client would have sent to
+//! the server, framed exactly as on the wire (a big-endian `u32` length,
+//! then a CBOR [IngestMessage]). Archives never contain auth requests.
+//!
+//! For a self-contained file of timelines and events, to be read back rather
+//! than replayed to a server, see the event archives in [crate::archive].
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use auxon_sdk::api::TimelineId;
+//! use auxon_sdk::ingest_client::{archive, IngestClient, ReadyState};
+//!
+//! // Capture
+//! let client = IngestClient::<ReadyState>::create_archive("capture.ingest").await?;
+//! let mut client = client.open_timeline(TimelineId::allocate()).await?;
+//! client.flush().await?;
+//!
+//! // Later, somewhere with connectivity
+//! # let mut live_client: IngestClient<ReadyState> = todo!();
+//! let file = tokio::fs::File::open("capture.ingest").await?;
+//! archive::upload_archive(file, &mut live_client).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{
+    IngestClient, IngestClientCommon, IngestConnection, IngestError, IngestTransport, ReadyState,
+};
+use crate::ingest_protocol::{IngestMessage, InternedAttrKey, PackedAttrKvs};
+use std::{
+    io,
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter, ReadBuf};
+
+/// The first bytes of every ingest archive
+pub const ARCHIVE_MAGIC: [u8; 8] = *b"MDLYINGA";
+
+/// The version of the archive format written by [ArchiveWriter]
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// The largest encoded message accepted by [upload_archive], so that a
+/// corrupt length prefix can't make it allocate without bound
+pub const MAX_MESSAGE_LEN: u32 = 16 * 1024 * 1024;
+
+/// The transport behind [IngestClient::create_archive]: a buffered file,
+/// which starts with the archive header. It can't be read from, since there's
+/// no server to respond.
+#[derive(Debug)]
+pub struct ArchiveWriter {
+    file: BufWriter<tokio::fs::File>,
+}
+
+impl ArchiveWriter {
+    /// Create (or truncate) the archive at `path`, and write its header.
+    pub async fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = BufWriter::new(tokio::fs::File::create(path).await?);
+        file.write_all(&ARCHIVE_MAGIC).await?;
+        file.write_all(&ARCHIVE_VERSION.to_be_bytes()).await?;
+        Ok(ArchiveWriter { file })
+    }
+}
+
+impl AsyncWrite for ArchiveWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.file).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.file).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.file).poll_shutdown(cx)
+    }
+}
+
+impl AsyncRead for ArchiveWriter {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        _buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Poll::Ready(Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "an ingest archive has no server to respond",
+        )))
+    }
+}
+
+impl IngestTransport for ArchiveWriter {}
+
+impl IngestClient<ReadyState> {
+    /// Create a client that writes everything it sends to a new archive at
+    /// `path`, instead of connecting to a server. See the [module docs](self).
+    ///
+    /// There's no server to answer requests, so only sending works; e.g.
+    /// `status` fails. Call `flush` once done, to write out buffered data.
+    pub async fn create_archive(
+        path: impl AsRef<Path>,
+    ) -> Result<IngestClient<ReadyState>, IngestError> {
+        let writer = ArchiveWriter::create(path).await?;
+        Ok(IngestClient {
+            state: ReadyState {},
+            common: IngestClientCommon::new(Duration::from_secs(1), IngestConnection::new(writer)),
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ArchiveError {
+    #[error("Not an ingest archive")]
+    BadMagic,
+
+    #[error("Unsupported ingest archive version {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("Ingest archives can't contain auth requests")]
+    UnexpectedAuthRequest,
+
+    #[error("The ingest archive ends partway through a message")]
+    Truncated,
+
+    #[error("Ingest archive message of {0} bytes is longer than the limit of {MAX_MESSAGE_LEN}")]
+    MessageTooLong(u32),
+
+    #[error("Error reading ingest archive")]
+    Io(#[from] io::Error),
+
+    #[error("Malformed message in ingest archive")]
+    Decode(#[from] minicbor::decode::Error),
+
+    #[error(transparent)]
+    Ingest(#[from] IngestError),
+}
+
+/// Send all the messages in the archive read from `reader` through `client`.
+/// Returns the number of messages sent.
+///
+/// The archive's own `Flush` messages are sent along with everything else;
+/// this doesn't add one.
+///
+/// Attr keys declared in the archive are given fresh ids on `client`'s
+/// connection, so the client can carry on being used afterwards. Like any
+/// other message, an archived `OpenTimeline` leaves the connection bound to
+/// that timeline.
+pub async fn upload_archive<R: AsyncRead + Unpin>(
+    mut reader: R,
+    client: &mut IngestClient<ReadyState>,
+) -> Result<u64, ArchiveError> {
+    let mut magic = [0u8; ARCHIVE_MAGIC.len()];
+    reader.read_exact(&mut magic).await?;
+    if magic != ARCHIVE_MAGIC {
+        return Err(ArchiveError::BadMagic);
+    }
+    let version = reader.read_u32().await?;
+    if version != ARCHIVE_VERSION {
+        return Err(ArchiveError::UnsupportedVersion(version));
+    }
+
+    let id_offset = client.common.next_id;
+    let remap = |k: InternedAttrKey| InternedAttrKey::from(u32::from(k) + id_offset);
+    let remap_attrs = |attrs: PackedAttrKvs<InternedAttrKey>| {
+        PackedAttrKvs(attrs.0.into_iter().map(|(k, v)| (remap(k), v)).collect())
+    };
+
+    let mut count = 0;
+    let mut msg_buf = Vec::new();
+    loop {
+        let mut len = [0u8; 4];
+        // A clean end of the archive is only allowed between messages
+        let mut filled = 0;
+        while filled < len.len() {
+            match reader.read(&mut len[filled..]).await {
+                Ok(0) if filled == 0 => break,
+                Ok(0) => return Err(ArchiveError::Truncated),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        if filled == 0 {
+            break;
+        }
+
+        let msg_len = u32::from_be_bytes(len);
+        if msg_len > MAX_MESSAGE_LEN {
+            return Err(ArchiveError::MessageTooLong(msg_len));
+        }
+        msg_buf.resize(msg_len as usize, 0);
+        reader
+            .read_exact(&mut msg_buf)
+            .await
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::UnexpectedEof => ArchiveError::Truncated,
+                _ => e.into(),
+            })?;
+
+        let msg = match minicbor::decode::<IngestMessage>(&msg_buf)? {
+            IngestMessage::AuthRequest { .. } => return Err(ArchiveError::UnexpectedAuthRequest),
+            IngestMessage::DeclareAttrKey { name, wire_id } => {
+                let wire_id = remap(wire_id);
+                client.common.next_id = client.common.next_id.max(u32::from(wire_id) + 1);
+                IngestMessage::DeclareAttrKey { name, wire_id }
+            }
+            IngestMessage::TimelineMetadata { attrs } => IngestMessage::TimelineMetadata {
+                attrs: remap_attrs(attrs),
+            },
+            IngestMessage::Event { be_ordering, attrs } => IngestMessage::Event {
+                be_ordering,
+                attrs: remap_attrs(attrs),
+            },
+            msg => msg,
+        };
+        client.common.send(&msg).await?;
+        count += 1;
+    }
+
+    client.common.connection.flush().await?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::AttrVal;
+    use crate::test_fixtures::RecordedTransport;
+
+    async fn captured_archive() -> Vec<u8> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("capture.ingest");
+        let client = IngestClient::create_archive(&path).await.unwrap();
+        let mut client = client
+            .open_timeline(crate::api::TimelineId::allocate())
+            .await
+            .unwrap();
+        let key = client
+            .declare_attr_key("event.name".to_string())
+            .await
+            .unwrap();
+        client
+            .event(0, [(key, AttrVal::from("boot"))])
+            .await
+            .unwrap();
+        client.flush().await.unwrap();
+        tokio::fs::read(&path).await.unwrap()
+    }
+
+    fn recording_client() -> IngestClient<ReadyState> {
+        IngestClient {
+            state: ReadyState {},
+            common: IngestClientCommon::new(
+                Duration::from_secs(1),
+                IngestConnection::new(RecordedTransport::new()),
+            ),
+        }
+    }
+
+    #[tokio::test]
+    async fn upload_complete_archive() {
+        let bytes = captured_archive().await;
+        let mut client = recording_client();
+        // OpenTimeline, DeclareAttrKey, Event and Flush
+        assert_eq!(
+            upload_archive(bytes.as_slice(), &mut client).await.unwrap(),
+            4
+        );
+    }
+
+    #[tokio::test]
+    async fn upload_truncated_archive() {
+        let bytes = captured_archive().await;
+        let header_len = ARCHIVE_MAGIC.len() + 4;
+
+        // Partway through a length prefix
+        let mut truncated = bytes[..header_len].to_vec();
+        truncated.extend_from_slice(&bytes[header_len..header_len + 2]);
+        assert!(matches!(
+            upload_archive(truncated.as_slice(), &mut recording_client()).await,
+            Err(ArchiveError::Truncated)
+        ));
+
+        // Partway through a message
+        assert!(matches!(
+            upload_archive(&bytes[..bytes.len() - 1], &mut recording_client()).await,
+            Err(ArchiveError::Truncated)
+        ));
+
+        // A length over the limit is rejected before reading the message
+        let mut oversized = bytes[..header_len].to_vec();
+        oversized.extend_from_slice(&(MAX_MESSAGE_LEN + 1).to_be_bytes());
+        assert!(matches!(
+            upload_archive(oversized.as_slice(), &mut recording_client()).await,
+            Err(ArchiveError::MessageTooLong(len)) if len == MAX_MESSAGE_LEN + 1
+        ));
+    }
+}
@@ -1,13 +1,13 @@
+use super::IngestTransport;
 use crate::api::types::{AttrKey, AttrVal, TimelineId};
-use crate::ingest_protocol::{IngestMessage, IngestResponse, InternedAttrKey, PackedAttrKvs};
-use std::{net::SocketAddr, path::PathBuf, time::Duration};
-use thiserror::Error;
-use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
-    net::{TcpSocket, TcpStream},
-    time::timeout,
+use crate::ingest_protocol::{
+    IngestMessage, IngestResponse, InternedAttrKey, PackedAttrKvs, ServerErrorCode,
+    DURATION_INGEST_PROTOCOL_VERSION, SUPPORTED_INGEST_PROTOCOL_VERSIONS,
 };
-use tokio_rustls::client::TlsStream;
+use std::{net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
+use thiserror::Error;
+use tokio::{io::AsyncWriteExt, net::TcpSocket, time::timeout};
+use tokio_rustls::rustls::{pki_types::CertificateDer, ClientConfig, RootCertStore};
 use url::Url;
 
 pub struct IngestClient<S> {
@@ -26,8 +26,12 @@ pub struct BoundTimelineState {
 #[doc(hidden)]
 pub struct IngestClientCommon {
     pub timeout: Duration,
-    connection: IngestConnection,
-    next_id: u32,
+    pub(crate) connection: IngestConnection,
+    pub(crate) next_id: u32,
+    /// The protocol version required when authenticating, if pinned
+    pub(crate) protocol_version: Option<u32>,
+    /// The protocol version the server confirmed when authenticating, if any
+    pub(crate) negotiated_protocol_version: Option<u32>,
 }
 
 impl IngestClientCommon {
@@ -37,6 +41,8 @@ impl IngestClientCommon {
             timeout,
             connection,
             next_id: 0,
+            protocol_version: None,
+            negotiated_protocol_version: None,
         }
     }
 
@@ -44,7 +50,15 @@ impl IngestClientCommon {
     #[doc(hidden)]
     pub async fn send_recv(&mut self, msg: &IngestMessage) -> Result<IngestResponse, IngestError> {
         self.connection.write_msg(msg).await?;
-        timeout(self.timeout, self.connection.read_msg()).await?
+        match timeout(self.timeout, self.connection.read_msg()).await?? {
+            IngestResponse::ErrorResponse { code, message } => {
+                Err(IngestError::from_server_error(code, message))
+            }
+            IngestResponse::UnauthenticatedResponse {} => {
+                Err(IngestError::Unauthorized { message: None })
+            }
+            resp => Ok(resp),
+        }
     }
 
     /// Send a message.
@@ -84,15 +98,34 @@ pub enum TlsMode {
     Insecure,
 }
 
-pub enum IngestConnection {
-    Tcp(TcpStream),
-    Tls(TlsStream<TcpStream>),
+/// A connection to an ingest server, or to something that stands in for one,
+/// such as an archive file. The messages it carries are framed by its
+/// [IngestTransport].
+pub struct IngestConnection {
+    transport: Box<dyn IngestTransport>,
 }
 
 impl IngestConnection {
+    /// A connection over `transport`, which must already be connected.
+    pub fn new(transport: impl IngestTransport + 'static) -> Self {
+        IngestConnection {
+            transport: Box::new(transport),
+        }
+    }
+
     pub async fn connect(
         endpoint: &Url,
         allow_insecure_tls: bool,
+    ) -> Result<IngestConnection, IngestClientInitializationError> {
+        Self::connect_with_tls_config(endpoint, allow_insecure_tls, None).await
+    }
+
+    /// Like [`IngestConnection::connect`], but `modality-ingest-tls` endpoints are verified
+    /// with `tls_config` instead of the platform roots, unless `allow_insecure_tls` is set.
+    pub async fn connect_with_tls_config(
+        endpoint: &Url,
+        allow_insecure_tls: bool,
+        tls_config: Option<Arc<ClientConfig>>,
     ) -> Result<IngestConnection, IngestClientInitializationError> {
         let endpoint = IngestEndpoint::parse_and_resolve(endpoint, allow_insecure_tls).await?;
 
@@ -124,16 +157,16 @@ impl IngestConnection {
         })?;
 
         if let Some(tls_mode) = endpoint.tls_mode {
-            let config = match tls_mode {
-                TlsMode::Insecure => crate::tls::INSECURE.clone(),
-                TlsMode::Secure => crate::tls::SECURE.clone(),
-            };
+            let config = crate::tls::client_config(
+                matches!(tls_mode, TlsMode::Insecure),
+                tls_config.as_ref(),
+            );
 
             let cx = tokio_rustls::TlsConnector::from(config);
             let stream = cx.connect(endpoint.cert_domain.try_into()?, stream).await?;
-            Ok(IngestConnection::Tls(stream))
+            Ok(IngestConnection::new(stream))
         } else {
-            Ok(IngestConnection::Tcp(stream))
+            Ok(IngestConnection::new(stream))
         }
     }
 
@@ -146,48 +179,16 @@ impl IngestConnection {
     /// socket. `msg_buf` should NOT include the length prefix; that
     /// is added by this method.
     pub async fn write_bytes(&mut self, msg_buf: &[u8]) -> Result<(), IngestError> {
-        let msg_len = msg_buf.len() as u32;
-
-        match self {
-            IngestConnection::Tcp(s) => {
-                s.write_all(&msg_len.to_be_bytes())
-                    .await
-                    .map_err(minicbor::encode::Error::Write)?;
-                s.write_all(msg_buf)
-                    .await
-                    .map_err(minicbor::encode::Error::Write)?;
-            }
-            IngestConnection::Tls(s) => {
-                // We have to use write_all here, because https://github.com/tokio-rs/tls/issues/41
-                s.write_all(&msg_len.to_be_bytes())
-                    .await
-                    .map_err(minicbor::encode::Error::Write)?;
-                s.write_all(msg_buf)
-                    .await
-                    .map_err(minicbor::encode::Error::Write)?;
-            }
-        }
-
+        self.transport
+            .write_frame(msg_buf)
+            .await
+            .map_err(minicbor::encode::Error::Write)?;
         Ok(())
     }
 
     pub async fn read_msg(&mut self) -> Result<IngestResponse, IngestError> {
-        match self {
-            IngestConnection::Tcp(s) => {
-                let msg_len = s.read_u32().await?; // yes, this is big-endian
-                let mut msg_buf = vec![0u8; msg_len as usize];
-                s.read_exact(msg_buf.as_mut_slice()).await?;
-
-                Ok(minicbor::decode::<IngestResponse>(&msg_buf)?)
-            }
-            IngestConnection::Tls(s) => {
-                let msg_len = s.read_u32().await?; // yes, this is big-endian
-                let mut msg_buf = vec![0u8; msg_len as usize];
-                s.read_exact(msg_buf.as_mut_slice()).await?;
-
-                Ok(minicbor::decode::<IngestResponse>(&msg_buf)?)
-            }
-        }
+        let msg_buf = self.transport.read_frame().await?;
+        Ok(minicbor::decode::<IngestResponse>(&msg_buf)?)
     }
 
     /// Copy data directly from `reader` to the ingest socket.
@@ -195,17 +196,21 @@ impl IngestConnection {
     where
         R: tokio::io::AsyncRead + Unpin + ?Sized,
     {
-        match self {
-            IngestConnection::Tcp(s) => tokio::io::copy(reader, s).await,
-            IngestConnection::Tls(s) => tokio::io::copy(reader, s).await,
-        }
+        tokio::io::copy(reader, &mut self.transport).await
+    }
+
+    /// Shut down the write side of the connection, flushing it first.
+    pub async fn shutdown(&mut self) -> tokio::io::Result<()> {
+        self.transport.shutdown().await
+    }
+
+    /// The remote address of a network connection, as resolved from the endpoint URL
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.transport.peer_addr()
     }
 
     pub async fn flush(&mut self) -> tokio::io::Result<()> {
-        match self {
-            IngestConnection::Tcp(conn) => conn.flush().await,
-            IngestConnection::Tls(conn) => conn.flush().await,
-        }
+        self.transport.flush().await
     }
 }
 
@@ -214,21 +219,40 @@ impl<T> IngestClient<T> {
     pub fn lower_to_connection(self) -> IngestConnection {
         self.common.connection
     }
+
+    /// The address of the server this client is connected to, or `None` if
+    /// it isn't using a network connection (e.g. when writing an archive).
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.common.connection.peer_addr()
+    }
 }
 
 impl IngestClient<UnauthenticatedState> {
+    /// Start building a new ingest client for `endpoint`; see [`IngestClientBuilder`].
+    pub fn builder(endpoint: Url) -> IngestClientBuilder {
+        IngestClientBuilder::new(endpoint)
+    }
+
     /// Create a new ingest client.
     pub async fn connect(
         endpoint: &Url,
         allow_insecure_tls: bool,
     ) -> Result<IngestClient<UnauthenticatedState>, IngestClientInitializationError> {
-        let connection = IngestConnection::connect(endpoint, allow_insecure_tls).await?;
-        let common = IngestClientCommon::new(Duration::from_secs(1), connection);
+        IngestClientBuilder::new(endpoint.clone())
+            .allow_insecure_tls(allow_insecure_tls)
+            .connect()
+            .await
+    }
 
-        Ok(IngestClient {
+    /// Create a new ingest client over an already-established connection.
+    pub fn from_connection(
+        connection: IngestConnection,
+        timeout: Duration,
+    ) -> IngestClient<UnauthenticatedState> {
+        IngestClient {
             state: UnauthenticatedState {},
-            common,
-        })
+            common: IngestClientCommon::new(timeout, connection),
+        }
     }
 
     /// Create a new ingest client.
@@ -237,27 +261,93 @@ impl IngestClient<UnauthenticatedState> {
         allow_insecure_tls: bool,
         timeout: Duration,
     ) -> Result<IngestClient<UnauthenticatedState>, IngestClientInitializationError> {
-        let connection = IngestConnection::connect(endpoint, allow_insecure_tls).await?;
-        let common = IngestClientCommon::new(timeout, connection);
+        IngestClientBuilder::new(endpoint.clone())
+            .allow_insecure_tls(allow_insecure_tls)
+            .timeout(timeout)
+            .connect()
+            .await
+    }
 
-        Ok(IngestClient {
-            state: UnauthenticatedState {},
-            common,
-        })
+    /// Check that the server at `endpoint` accepts `token`, without sending
+    /// anything else: connect, authenticate, then disconnect.
+    pub async fn verify_auth(
+        endpoint: &Url,
+        token: Vec<u8>,
+        allow_insecure_tls: bool,
+    ) -> Result<(), VerifyAuthError> {
+        let client = Self::connect(endpoint, allow_insecure_tls).await?;
+        let client = match client.authenticate(token).await {
+            Ok(client) => client,
+            Err(IngestError::AuthenticationError { message, .. }) => {
+                return Err(VerifyAuthError::Rejected { message })
+            }
+            Err(e) => return Err(e.into()),
+        };
+        // The token was accepted; a failure to hang up cleanly doesn't change that
+        let _ = client.lower_to_connection().shutdown().await;
+        Ok(())
+    }
+
+    /// Require the server to use ingest protocol `version`, one of
+    /// [`SUPPORTED_INGEST_PROTOCOL_VERSIONS`], rather than negotiating.
+    /// [`IngestClient::authenticate`] then fails with
+    /// [`IngestError::VersionMismatch`] if this client doesn't support the
+    /// version, or the server doesn't confirm that it's using it. Servers that
+    /// predate version pinning never confirm a version, so pinning fails
+    /// against them.
+    pub fn pin_protocol_version(mut self, version: u32) -> Self {
+        self.common.protocol_version = Some(version);
+        self
     }
 
     pub async fn authenticate(
         mut self,
         token: Vec<u8>,
     ) -> Result<IngestClient<ReadyState>, IngestError> {
+        if let Some(version) = self.common.protocol_version {
+            if !SUPPORTED_INGEST_PROTOCOL_VERSIONS.contains(&version) {
+                return Err(IngestError::VersionMismatch {
+                    message: Some(format!(
+                        "Ingest protocol version {version} is pinned, but this client only \
+                         supports versions {SUPPORTED_INGEST_PROTOCOL_VERSIONS:?}"
+                    )),
+                });
+            }
+        }
+
         let resp = self
             .common
-            .send_recv(&IngestMessage::AuthRequest { token })
+            .send_recv(&IngestMessage::AuthRequest {
+                token,
+                protocol_version: self.common.protocol_version,
+            })
             .await?;
 
         match resp {
-            IngestResponse::AuthResponse { ok, message } => {
+            IngestResponse::AuthResponse {
+                ok,
+                message,
+                protocol_version,
+            } => {
+                if let (true, Some(pinned)) = (ok, self.common.protocol_version) {
+                    // A server that predates pinning wouldn't have checked the version
+                    if protocol_version != Some(pinned) {
+                        return Err(IngestError::VersionMismatch {
+                            message: Some(match protocol_version {
+                                Some(v) => format!(
+                                    "Ingest protocol version {pinned} is pinned, but the server \
+                                     is using version {v}"
+                                ),
+                                None => format!(
+                                    "Ingest protocol version {pinned} is pinned, but the server \
+                                     didn't confirm which version it's using"
+                                ),
+                            }),
+                        });
+                    }
+                }
                 if ok {
+                    self.common.negotiated_protocol_version = protocol_version;
                     Ok(IngestClient {
                         state: ReadyState {},
                         common: self.common,
@@ -276,7 +366,121 @@ impl IngestClient<UnauthenticatedState> {
     }
 }
 
+/// Connection options for an [`IngestClient`].
+///
+/// There's no compression option: the ingest protocol has no way to negotiate
+/// it, and servers expect uncompressed messages.
+///
+/// ```no_run
+/// # async fn f() -> Result<(), Box<dyn std::error::Error>> {
+/// use auxon_sdk::ingest_client::IngestClient;
+/// use std::time::Duration;
+///
+/// let client = IngestClient::builder("modality-ingest-tls://example.com".parse()?)
+///     .timeout(Duration::from_secs(5))
+///     .connect()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct IngestClientBuilder {
+    endpoint: Url,
+    allow_insecure_tls: bool,
+    timeout: Duration,
+    root_certs: Vec<CertificateDer<'static>>,
+    tls_config: Option<Arc<ClientConfig>>,
+    protocol_version: Option<u32>,
+}
+
+impl IngestClientBuilder {
+    pub fn new(endpoint: Url) -> Self {
+        IngestClientBuilder {
+            endpoint,
+            allow_insecure_tls: false,
+            timeout: Duration::from_secs(1),
+            root_certs: Vec::new(),
+            tls_config: None,
+            protocol_version: None,
+        }
+    }
+
+    /// Skip certificate verification for `modality-ingest-tls` endpoints. Defaults to `false`.
+    pub fn allow_insecure_tls(mut self, allow: bool) -> Self {
+        self.allow_insecure_tls = allow;
+        self
+    }
+
+    /// How long to wait for responses from the server. Defaults to 1 second.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Trust `cert` as a root certificate. If any root certificates are given, they
+    /// replace the platform roots when verifying the server.
+    pub fn root_cert(mut self, cert: CertificateDer<'static>) -> Self {
+        self.root_certs.push(cert);
+        self
+    }
+
+    /// Use a complete rustls client configuration for TLS connections. This takes
+    /// precedence over [`IngestClientBuilder::root_cert`].
+    pub fn tls_config(mut self, config: Arc<ClientConfig>) -> Self {
+        self.tls_config = Some(config);
+        self
+    }
+
+    /// Require the server to use this ingest protocol version; see
+    /// [`IngestClient::pin_protocol_version`]. By default, no version is required.
+    pub fn protocol_version(mut self, version: u32) -> Self {
+        self.protocol_version = Some(version);
+        self
+    }
+
+    pub async fn connect(
+        self,
+    ) -> Result<IngestClient<UnauthenticatedState>, IngestClientInitializationError> {
+        let tls_config = match (self.tls_config, self.root_certs.is_empty()) {
+            (Some(config), _) => Some(config),
+            (None, true) => None,
+            (None, false) => {
+                let mut roots = RootCertStore::empty();
+                for cert in self.root_certs {
+                    roots
+                        .add(cert)
+                        .map_err(IngestClientInitializationError::RootCert)?;
+                }
+                Some(Arc::new(
+                    ClientConfig::builder()
+                        .with_root_certificates(roots)
+                        .with_no_client_auth(),
+                ))
+            }
+        };
+
+        let connection = IngestConnection::connect_with_tls_config(
+            &self.endpoint,
+            self.allow_insecure_tls,
+            tls_config,
+        )
+        .await?;
+
+        let mut client = IngestClient::from_connection(connection, self.timeout);
+        if let Some(version) = self.protocol_version {
+            client = client.pin_protocol_version(version);
+        }
+        Ok(client)
+    }
+}
+
 impl IngestClient<ReadyState> {
+    /// Flush everything sent so far, asking the server to write it out, and
+    /// shut down the connection; see [`IngestClient::<BoundTimelineState>::close`].
+    pub async fn close(self) -> Result<(), IngestError> {
+        self.common.close().await
+    }
+
     /// Create a fully authorized client connection, using the
     /// standard config file location and environment variables.
     pub async fn connect_with_standard_config(
@@ -380,6 +584,17 @@ impl IngestClient<BoundTimelineState> {
         self.common.flush().await
     }
 
+    /// Flush everything sent so far, asking the server to write it out, and
+    /// shut down the connection, returning any error along the way.
+    ///
+    /// The protocol has no goodbye message; the server just sees the
+    /// connection end. Dropping a client without calling this closes the
+    /// connection too, but only on a best-effort basis: buffered data may
+    /// not be sent, and errors go unreported.
+    pub async fn close(self) -> Result<(), IngestError> {
+        self.common.close().await
+    }
+
     pub async fn status(&mut self) -> Result<IngestStatus, IngestError> {
         let resp = self
             .common
@@ -412,7 +627,7 @@ impl IngestClientCommon {
         &mut self,
         attrs: impl IntoIterator<Item = (InternedAttrKey, AttrVal)>,
     ) -> Result<(), IngestError> {
-        let packed_attrs = PackedAttrKvs(attrs.into_iter().collect());
+        let packed_attrs = self.pack_attrs(attrs);
 
         self.send(&IngestMessage::TimelineMetadata {
             attrs: packed_attrs,
@@ -426,7 +641,7 @@ impl IngestClientCommon {
         ordering: u128,
         attrs: impl IntoIterator<Item = (InternedAttrKey, AttrVal)>,
     ) -> Result<(), IngestError> {
-        let packed_attrs = PackedAttrKvs(attrs.into_iter().collect());
+        let packed_attrs = self.pack_attrs(attrs);
 
         let be_ordering = ordering.to_be_bytes();
         let mut i = 0;
@@ -447,11 +662,40 @@ impl IngestClientCommon {
         Ok(())
     }
 
+    /// Servers using a protocol version older than
+    /// [`DURATION_INGEST_PROTOCOL_VERSION`] can't decode durations, so they're
+    /// sent as integer nanoseconds.
+    fn pack_attrs(
+        &self,
+        attrs: impl IntoIterator<Item = (InternedAttrKey, AttrVal)>,
+    ) -> PackedAttrKvs<InternedAttrKey> {
+        let durations_supported = self
+            .negotiated_protocol_version
+            .is_some_and(|v| v >= DURATION_INGEST_PROTOCOL_VERSION);
+        PackedAttrKvs(
+            attrs
+                .into_iter()
+                .map(|(k, v)| match v {
+                    AttrVal::Duration(ns) if !durations_supported => {
+                        (k, AttrVal::big_int(ns.get_raw().into()))
+                    }
+                    v => (k, v),
+                })
+                .collect(),
+        )
+    }
+
     pub async fn flush(&mut self) -> Result<(), IngestError> {
         self.send(&IngestMessage::Flush {}).await?;
         self.connection.flush().await?;
         Ok(())
     }
+
+    pub(crate) async fn close(mut self) -> Result<(), IngestError> {
+        self.flush().await?;
+        self.connection.shutdown().await?;
+        Ok(())
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
@@ -506,6 +750,9 @@ pub enum IngestClientInitializationError {
     #[error(transparent)]
     InvalidDnsName(#[from] tokio_rustls::rustls::pki_types::InvalidDnsNameError),
 
+    #[error("Invalid root certificate")]
+    RootCert(#[source] tokio_rustls::rustls::Error),
+
     #[error(transparent)]
     Io(#[from] std::io::Error),
 
@@ -516,6 +763,18 @@ pub enum IngestClientInitializationError {
     ParseIngestEndpoint(#[from] ParseIngestEndpointError),
 }
 
+#[derive(Debug, Error)]
+pub enum VerifyAuthError {
+    #[error("The server rejected the auth token: {message:?}")]
+    Rejected { message: Option<String> },
+
+    #[error(transparent)]
+    Connect(#[from] IngestClientInitializationError),
+
+    #[error(transparent)]
+    Ingest(#[from] IngestError),
+}
+
 #[derive(Error)]
 pub enum IngestError {
     #[error(transparent)]
@@ -547,6 +806,38 @@ pub enum IngestError {
 
     #[error("IO")]
     Io(#[from] std::io::Error),
+
+    #[error("Protocol version mismatch: {message:?}")]
+    VersionMismatch { message: Option<String> },
+
+    #[error("Unauthorized: {message:?}")]
+    Unauthorized { message: Option<String> },
+
+    #[error("Rate limited: {message:?}")]
+    RateLimited { message: Option<String> },
+
+    #[error("Internal server error: {message:?}")]
+    InternalServerError { message: Option<String> },
+
+    #[error("Protocol violation reported by server: {message:?}")]
+    ProtocolViolation { message: Option<String> },
+
+    #[error("Server error (code {code}): {message:?}")]
+    UnknownServerError { code: u32, message: Option<String> },
+}
+
+impl IngestError {
+    /// Classify an error reported by the server in an `ErrorResponse`.
+    pub fn from_server_error(code: ServerErrorCode, message: Option<String>) -> Self {
+        match code {
+            ServerErrorCode::VersionMismatch => IngestError::VersionMismatch { message },
+            ServerErrorCode::Unauthorized => IngestError::Unauthorized { message },
+            ServerErrorCode::RateLimited => IngestError::RateLimited { message },
+            ServerErrorCode::InternalServerError => IngestError::InternalServerError { message },
+            ServerErrorCode::ProtocolViolation => IngestError::ProtocolViolation { message },
+            ServerErrorCode::Unknown(code) => IngestError::UnknownServerError { code, message },
+        }
+    }
 }
 
 // Manual impl so we can skip the embedded 'client'
@@ -568,6 +859,31 @@ impl std::fmt::Debug for IngestError {
                 .field(arg0)
                 .finish(),
             Self::Io(arg0) => f.debug_tuple("Io").field(arg0).finish(),
+            Self::VersionMismatch { message } => f
+                .debug_struct("VersionMismatch")
+                .field("message", message)
+                .finish(),
+            Self::Unauthorized { message } => f
+                .debug_struct("Unauthorized")
+                .field("message", message)
+                .finish(),
+            Self::RateLimited { message } => f
+                .debug_struct("RateLimited")
+                .field("message", message)
+                .finish(),
+            Self::InternalServerError { message } => f
+                .debug_struct("InternalServerError")
+                .field("message", message)
+                .finish(),
+            Self::ProtocolViolation { message } => f
+                .debug_struct("ProtocolViolation")
+                .field("message", message)
+                .finish(),
+            Self::UnknownServerError { code, message } => f
+                .debug_struct("UnknownServerError")
+                .field("code", code)
+                .field("message", message)
+                .finish(),
         }
     }
 }
@@ -641,3 +957,273 @@ pub enum ParseIngestEndpointError {
     #[error("IO Error")]
     Io(#[from] std::io::Error),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{authed_transport, FakeIngestServer, RecordedTransport};
+
+    async fn status_error(resp: IngestResponse) -> IngestError {
+        let transport = authed_transport();
+        transport.push_response(&resp).unwrap();
+
+        let client = IngestClient::from_transport(transport, Duration::from_secs(1));
+        let mut client = client
+            .authenticate(vec![])
+            .await
+            .unwrap()
+            .open_timeline(TimelineId::allocate())
+            .await
+            .unwrap();
+        client.status().await.unwrap_err()
+    }
+
+    fn error_response(code: ServerErrorCode) -> IngestResponse {
+        IngestResponse::ErrorResponse {
+            code,
+            message: Some("details".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn close_flushes_pending_events() {
+        let transport = authed_transport();
+        let mut client = IngestClient::from_transport(transport.clone(), Duration::from_secs(1))
+            .authenticate(vec![])
+            .await
+            .unwrap()
+            .open_timeline(TimelineId::allocate())
+            .await
+            .unwrap();
+        let key = client
+            .declare_attr_key("event.name".to_string())
+            .await
+            .unwrap();
+        client.event(0, [(key, "pending".into())]).await.unwrap();
+        client.close().await.unwrap();
+
+        let sent: Vec<IngestMessage> = transport
+            .frames()
+            .iter()
+            .map(|f| minicbor::decode(f).unwrap())
+            .collect();
+        assert!(matches!(
+            sent.as_slice(),
+            [.., IngestMessage::Event { .. }, IngestMessage::Flush {}]
+        ));
+    }
+
+    #[tokio::test]
+    async fn verify_auth_against_stub_server() {
+        // The client hangs up right after authenticating
+        let mut server = FakeIngestServer::start_with(false, |token| token == b"good").await;
+        IngestClient::verify_auth(&server.url, b"good".to_vec(), false)
+            .await
+            .unwrap();
+        assert!(matches!(
+            server.next_connection().await.as_slice(),
+            [IngestMessage::AuthRequest { token, .. }] if token == b"good"
+        ));
+
+        let err = IngestClient::verify_auth(&server.url, b"bad".to_vec(), false)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, VerifyAuthError::Rejected { .. }));
+        assert!(matches!(
+            server.next_connection().await.as_slice(),
+            [IngestMessage::AuthRequest { token, .. }] if token == b"bad"
+        ));
+    }
+
+    #[tokio::test]
+    async fn peer_addr_is_the_resolved_server_address() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let url = Url::parse(&format!("modality-ingest://{addr}")).unwrap();
+
+        let (client, accepted) =
+            tokio::join!(IngestClient::connect(&url, false), listener.accept());
+        let client = client.unwrap();
+        let (_server_side, client_addr) = accepted.unwrap();
+        assert_eq!(client.peer_addr(), Some(addr));
+        assert_ne!(client.peer_addr(), Some(client_addr));
+
+        let recorded =
+            IngestClient::from_transport(RecordedTransport::new(), Duration::from_secs(1));
+        assert_eq!(recorded.peer_addr(), None);
+    }
+
+    #[tokio::test]
+    async fn server_error_responses_are_classified() {
+        let details = Some("details".to_string());
+
+        assert!(matches!(
+            status_error(error_response(ServerErrorCode::VersionMismatch)).await,
+            IngestError::VersionMismatch { message } if message == details
+        ));
+        assert!(matches!(
+            status_error(error_response(ServerErrorCode::Unauthorized)).await,
+            IngestError::Unauthorized { message } if message == details
+        ));
+        assert!(matches!(
+            status_error(error_response(ServerErrorCode::RateLimited)).await,
+            IngestError::RateLimited { message } if message == details
+        ));
+        assert!(matches!(
+            status_error(error_response(ServerErrorCode::InternalServerError)).await,
+            IngestError::InternalServerError { message } if message == details
+        ));
+        assert!(matches!(
+            status_error(error_response(ServerErrorCode::ProtocolViolation)).await,
+            IngestError::ProtocolViolation { message } if message == details
+        ));
+        assert!(matches!(
+            status_error(error_response(ServerErrorCode::Unknown(77))).await,
+            IngestError::UnknownServerError { code: 77, message } if message == details
+        ));
+        assert!(matches!(
+            status_error(IngestResponse::UnauthenticatedResponse {}).await,
+            IngestError::Unauthorized { message: None }
+        ));
+    }
+
+    #[tokio::test]
+    async fn pinned_protocol_version() {
+        // An unsupported version fails before anything is sent
+        let transport = RecordedTransport::new();
+        let client = IngestClient::from_transport(transport.clone(), Duration::from_secs(1))
+            .pin_protocol_version(99);
+        assert!(matches!(
+            client.authenticate(vec![]).await,
+            Err(IngestError::VersionMismatch { message: Some(_) })
+        ));
+        assert!(transport.frames().is_empty());
+
+        // A supported one is required of the server, which must confirm it
+        let transport = RecordedTransport::new();
+        transport
+            .push_response(&IngestResponse::AuthResponse {
+                ok: true,
+                message: None,
+                protocol_version: Some(1),
+            })
+            .unwrap();
+        let client = IngestClient::from_transport(transport.clone(), Duration::from_secs(1))
+            .pin_protocol_version(1);
+        client.authenticate(vec![]).await.unwrap();
+        assert!(matches!(
+            minicbor::decode(&transport.frames()[0]).unwrap(),
+            IngestMessage::AuthRequest {
+                protocol_version: Some(1),
+                ..
+            }
+        ));
+
+        // A server that doesn't confirm the version may not have checked it
+        for confirmed in [None, Some(2)] {
+            let transport = RecordedTransport::new();
+            transport
+                .push_response(&IngestResponse::AuthResponse {
+                    ok: true,
+                    message: None,
+                    protocol_version: confirmed,
+                })
+                .unwrap();
+            let client = IngestClient::from_transport(transport, Duration::from_secs(1))
+                .pin_protocol_version(1);
+            assert!(matches!(
+                client.authenticate(vec![]).await,
+                Err(IngestError::VersionMismatch { message: Some(_) })
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn durations_need_a_negotiated_protocol_version() {
+        use crate::api::Nanoseconds;
+
+        for (confirmed, expected) in [
+            (None, AttrVal::Integer(1_500)),
+            (Some(1), AttrVal::Integer(1_500)),
+            (
+                Some(DURATION_INGEST_PROTOCOL_VERSION),
+                AttrVal::Duration(Nanoseconds::from(1_500)),
+            ),
+        ] {
+            let transport = RecordedTransport::new();
+            transport
+                .push_response(&IngestResponse::AuthResponse {
+                    ok: true,
+                    message: None,
+                    protocol_version: confirmed,
+                })
+                .unwrap();
+            let mut client =
+                IngestClient::from_transport(transport.clone(), Duration::from_secs(1))
+                    .authenticate(vec![])
+                    .await
+                    .unwrap()
+                    .open_timeline(TimelineId::allocate())
+                    .await
+                    .unwrap();
+            let key = client
+                .declare_attr_key("event.latency".to_string())
+                .await
+                .unwrap();
+            client
+                .event(0, [(key, AttrVal::Duration(Nanoseconds::from(1_500)))])
+                .await
+                .unwrap();
+
+            let event = minicbor::decode(transport.frames().last().unwrap()).unwrap();
+            assert!(
+                matches!(&event, IngestMessage::Event { attrs, .. } if attrs.0 == [(key, expected.clone())]),
+                "{confirmed:?}: {event:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn auth_rejection_is_classified() {
+        let transport = RecordedTransport::new();
+        transport
+            .push_response(&error_response(ServerErrorCode::VersionMismatch))
+            .unwrap();
+        let client = IngestClient::from_transport(transport, Duration::from_secs(1));
+        assert!(matches!(
+            client.authenticate(vec![]).await,
+            Err(IngestError::VersionMismatch { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn builder_connects_with_options() {
+        use crate::tls::test_certs::CA_CERT;
+        use tokio_rustls::rustls::pki_types::pem::PemObject;
+
+        let server = FakeIngestServer::start_with(true, |_| true).await;
+        let url = server.url.clone();
+
+        let client = IngestClient::builder(url.clone())
+            .allow_insecure_tls(false)
+            .timeout(Duration::from_millis(2500))
+            .root_cert(CertificateDer::from_pem_slice(CA_CERT.as_bytes()).unwrap())
+            .connect()
+            .await
+            .unwrap();
+        assert!(client.peer_addr().is_some());
+        assert_eq!(client.common.timeout, Duration::from_millis(2500));
+        client.authenticate(vec![1, 2, 3]).await.unwrap();
+
+        // A config without our CA can't verify the server
+        let untrusted = IngestClient::builder(url)
+            .tls_config(Arc::new(
+                ClientConfig::builder()
+                    .with_root_certificates(RootCertStore::empty())
+                    .with_no_client_auth(),
+            ))
+            .connect()
+            .await;
+        assert!(untrusted.is_err());
+    }
+}
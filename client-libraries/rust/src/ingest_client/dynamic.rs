@@ -51,6 +51,11 @@ impl DynamicIngestClient {
         self.bound_timeline = None;
     }
 
+    /// The timeline the connection is currently bound to, if any
+    pub fn bound_timeline(&self) -> Option<TimelineId> {
+        self.bound_timeline
+    }
+
     pub async fn timeline_metadata(
         &mut self,
         attrs: impl IntoIterator<Item = (InternedAttrKey, AttrVal)>,
@@ -80,6 +85,11 @@ impl DynamicIngestClient {
         self.common.flush().await
     }
 
+    /// See [IngestClient::close](IngestClient::<BoundTimelineState>::close)
+    pub async fn close(self) -> Result<(), IngestError> {
+        self.common.close().await
+    }
+
     pub async fn status(&mut self) -> Result<IngestStatus, IngestError> {
         let resp = self
             .common
@@ -105,6 +115,16 @@ impl DynamicIngestClient {
             )),
         }
     }
+
+    /// See [IngestClient::peer_addr]
+    pub fn peer_addr(&self) -> Option<std::net::SocketAddr> {
+        self.common.connection.peer_addr()
+    }
+
+    /// The protocol version this client was pinned to, if any
+    pub(crate) fn protocol_version(&self) -> Option<u32> {
+        self.common.protocol_version
+    }
 }
 
 #[derive(Error, Debug)]
@@ -114,6 +134,21 @@ pub enum DynamicIngestError {
 
     #[error("Invalid state: a timeline must be bound before submitting events")]
     NoBoundTimeline,
+
+    #[error("Event has {count} attributes, more than the limit of {max}")]
+    TooManyAttrs { count: usize, max: usize },
+
+    #[error("Attribute '{key}' is a {len} byte string, longer than the limit of {max}")]
+    StringAttrTooLong { key: String, len: usize, max: usize },
+
+    #[error("The connection was lost, and reconnecting failed")]
+    ReconnectFailed(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    #[error("The connection is still in use by a background task")]
+    ConnectionInUse,
+
+    #[error("Attribute '{key}' is reserved")]
+    ReservedAttrKey { key: String },
 }
 
 #[cfg(feature = "pyo3")]
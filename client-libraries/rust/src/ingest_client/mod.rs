@@ -1,6 +1,11 @@
 //! A client library for the modality ingest plane protocol.
 
+pub mod archive;
 mod client;
 pub mod dynamic;
+#[cfg(any(test, feature = "test_support"))]
+pub mod recorded;
+pub mod transport;
 
 pub use client::*;
+pub use transport::IngestTransport;
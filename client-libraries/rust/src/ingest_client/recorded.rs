@@ -0,0 +1,199 @@
+//! An in-memory transport for exercising the ingest protocol without sockets.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use auxon_sdk::ingest_client::{recorded::RecordedTransport, IngestClient};
+//! use auxon_sdk::ingest_protocol::IngestResponse;
+//! use std::time::Duration;
+//!
+//! let transport = RecordedTransport::new();
+//! transport.push_response(&IngestResponse::AuthResponse { ok: true, message: None, protocol_version: None })?;
+//!
+//! let client = IngestClient::from_transport(transport.clone(), Duration::from_secs(1));
+//! let client = client.authenticate(vec![]).await?;
+//!
+//! // Everything the client wrote, one entry per length-prefixed frame
+//! let frames = transport.frames();
+//! # Ok(())
+//! # }
+//! ```
+
+use super::IngestTransport;
+use crate::ingest_protocol::IngestResponse;
+use std::{
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// A `Vec<u8>`-backed read/write pair.
+///
+/// Reads are served from canned response bytes, and everything written is
+/// captured. Clones share the same buffers, so a test can keep one clone for
+/// assertions while the client owns another.
+#[derive(Clone, Debug, Default)]
+pub struct RecordedTransport {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    inbound: Vec<u8>,
+    read_pos: usize,
+    outbound: Vec<u8>,
+}
+
+impl RecordedTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a response for the client to read, encoded as a length-prefixed frame.
+    pub fn push_response(
+        &self,
+        resp: &IngestResponse,
+    ) -> Result<(), minicbor::encode::Error<io::Error>> {
+        let buf = minicbor::to_vec(resp)?;
+        self.push_frame(&buf);
+        Ok(())
+    }
+
+    /// Queue an already-encoded payload for the client to read. The length prefix is added here.
+    pub fn push_frame(&self, payload: &[u8]) {
+        let mut inner = self.lock();
+        inner
+            .inbound
+            .extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        inner.inbound.extend_from_slice(payload);
+    }
+
+    /// Queue raw bytes for the client to read, exactly as given.
+    pub fn push_bytes(&self, bytes: &[u8]) {
+        self.lock().inbound.extend_from_slice(bytes);
+    }
+
+    /// All bytes written by the client so far.
+    pub fn written(&self) -> Vec<u8> {
+        self.lock().outbound.clone()
+    }
+
+    /// The payloads of the length-prefixed frames written by the client so far. A trailing
+    /// partial frame is not included.
+    pub fn frames(&self) -> Vec<Vec<u8>> {
+        let inner = self.lock();
+        let mut frames = Vec::new();
+        let mut rest = inner.outbound.as_slice();
+        while rest.len() >= 4 {
+            let len = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            if rest.len() < 4 + len {
+                break;
+            }
+            frames.push(rest[4..4 + len].to_vec());
+            rest = &rest[4 + len..];
+        }
+        frames
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl AsyncRead for RecordedTransport {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let mut inner = self.lock();
+        let start = inner.read_pos;
+        let n = buf.remaining().min(inner.inbound.len() - start);
+        buf.put_slice(&inner.inbound[start..start + n]);
+        inner.read_pos += n;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for RecordedTransport {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.lock().outbound.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl IngestTransport for RecordedTransport {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{AttrVal, TimelineId};
+    use crate::ingest_client::IngestClient;
+    use crate::ingest_protocol::{IngestMessage, InternedAttrKey, PackedAttrKvs};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn open_timeline_and_send_event() {
+        let transport = RecordedTransport::new();
+        transport
+            .push_response(&IngestResponse::AuthResponse {
+                ok: true,
+                message: None,
+                protocol_version: None,
+            })
+            .unwrap();
+
+        let client = IngestClient::from_transport(transport.clone(), Duration::from_secs(1));
+        let client = client.authenticate(vec![1, 2, 3]).await.unwrap();
+        let timeline_id = TimelineId::allocate();
+        let mut client = client.open_timeline(timeline_id).await.unwrap();
+        let key = client
+            .declare_attr_key("event.name".to_string())
+            .await
+            .unwrap();
+        client
+            .event(1, [(key, AttrVal::from("hello"))])
+            .await
+            .unwrap();
+
+        let expected: Vec<Vec<u8>> = [
+            IngestMessage::AuthRequest {
+                token: vec![1, 2, 3],
+                protocol_version: None,
+            },
+            IngestMessage::OpenTimeline { id: timeline_id },
+            IngestMessage::DeclareAttrKey {
+                name: "event.name".to_string(),
+                wire_id: InternedAttrKey::from(0),
+            },
+            IngestMessage::Event {
+                be_ordering: vec![1],
+                attrs: PackedAttrKvs(vec![(InternedAttrKey::from(0), AttrVal::from("hello"))]),
+            },
+        ]
+        .iter()
+        .map(|m| minicbor::to_vec(m).unwrap())
+        .collect();
+
+        assert_eq!(transport.frames(), expected);
+    }
+
+    #[tokio::test]
+    async fn missing_response_is_an_error() {
+        let client = IngestClient::from_transport(RecordedTransport::new(), Duration::from_secs(1));
+        assert!(client.authenticate(vec![]).await.is_err());
+    }
+}
@@ -0,0 +1,132 @@
+//! Ingest over transports of your own, e.g. an in-process channel, or a
+//! serial link that's bridged to a server elsewhere.
+//!
+//! Any async byte stream can carry the ingest protocol; implement
+//! [IngestTransport] for it, and its provided methods frame messages the way
+//! the server expects. Establish the stream however the transport requires,
+//! then hand it to [IngestClient::from_transport], and use the client as
+//! usual:
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use auxon_sdk::ingest_client::IngestClient;
+//! use std::time::Duration;
+//!
+//! // Stands in for your own transport; the other end goes to the server
+//! let (transport, _server_end) = tokio::io::duplex(64 * 1024);
+//!
+//! let client = IngestClient::from_transport(transport, Duration::from_secs(1))
+//!     .authenticate(vec![])
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{IngestClient, IngestConnection, UnauthenticatedState};
+use async_trait::async_trait;
+use std::{io, net::SocketAddr, time::Duration};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream},
+    net::TcpStream,
+};
+use tokio_rustls::client::TlsStream;
+
+/// A connected, bidirectional byte stream to an ingest server, which carries
+/// length-prefixed frames: a big-endian `u32` length, then that many bytes of
+/// CBOR-encoded message.
+///
+/// Writes are buffered as the transport sees fit; the client flushes it
+/// whenever it's flushed itself.
+#[async_trait]
+pub trait IngestTransport: AsyncRead + AsyncWrite + Send + Sync + Unpin {
+    /// The remote address, for transports that are network connections.
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        None
+    }
+
+    /// Write one frame. `payload` should NOT include the length prefix; that
+    /// is added by this method.
+    async fn write_frame(&mut self, payload: &[u8]) -> io::Result<()> {
+        // We have to use write_all here, because https://github.com/tokio-rs/tls/issues/41
+        self.write_all(&(payload.len() as u32).to_be_bytes())
+            .await?;
+        self.write_all(payload).await
+    }
+
+    /// Read one frame, returning its payload without the length prefix.
+    async fn read_frame(&mut self) -> io::Result<Vec<u8>> {
+        let len = self.read_u32().await?; // yes, this is big-endian
+        let mut payload = vec![0u8; len as usize];
+        self.read_exact(&mut payload).await?;
+        Ok(payload)
+    }
+}
+
+impl IngestTransport for TcpStream {
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        TcpStream::peer_addr(self).ok()
+    }
+}
+
+impl IngestTransport for TlsStream<TcpStream> {
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        self.get_ref().0.peer_addr().ok()
+    }
+}
+
+impl IngestTransport for DuplexStream {}
+
+impl IngestClient<UnauthenticatedState> {
+    /// Create a new ingest client that speaks over `transport`, which must
+    /// already be connected. See the [module docs](self).
+    pub fn from_transport(
+        transport: impl IngestTransport + 'static,
+        timeout: Duration,
+    ) -> IngestClient<UnauthenticatedState> {
+        IngestClient::from_connection(IngestConnection::new(transport), timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::TimelineId;
+    use crate::ingest_protocol::IngestMessage;
+    use crate::test_fixtures::serve_ingest_connection;
+
+    #[tokio::test]
+    async fn client_over_duplex_transport() {
+        let (transport, server) = tokio::io::duplex(64 * 1024);
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(serve_ingest_connection(server, |_| true, tx));
+
+        let client = IngestClient::from_transport(transport, Duration::from_secs(1));
+        assert_eq!(client.peer_addr(), None);
+        let mut client = client
+            .authenticate(b"token".to_vec())
+            .await
+            .unwrap()
+            .open_timeline(TimelineId::allocate())
+            .await
+            .unwrap();
+        let key = client
+            .declare_attr_key("event.name".to_string())
+            .await
+            .unwrap();
+        client.event(1, [(key, "hello".into())]).await.unwrap();
+        client.flush().await.unwrap();
+        drop(client);
+
+        let mut received = Vec::new();
+        while let Some(Some(msg)) = rx.recv().await {
+            received.push(msg);
+        }
+        assert!(matches!(received[0], IngestMessage::AuthRequest { .. }));
+        assert!(matches!(received[1], IngestMessage::OpenTimeline { .. }));
+        assert!(matches!(received.last(), Some(IngestMessage::Flush { .. })));
+        assert!(received
+            .iter()
+            .any(|m| matches!(m, IngestMessage::Event { .. })));
+    }
+}
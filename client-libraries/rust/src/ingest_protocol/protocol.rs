@@ -1,7 +1,20 @@
 use crate::api::{AttrVal, TimelineId};
 use minicbor::{decode, encode, Decode, Decoder, Encode, Encoder};
 
-#[derive(Debug, Decode)]
+/// The latest ingest protocol version, the one described by this module.
+pub const INGEST_PROTOCOL_VERSION: u32 = 2;
+
+/// The first ingest protocol version in which attribute values may be
+/// [`AttrVal::Duration`]s. On a connection using an older version, or whose
+/// server didn't confirm a version, the client sends durations as integer
+/// nanoseconds instead.
+pub const DURATION_INGEST_PROTOCOL_VERSION: u32 = 2;
+
+/// The ingest protocol versions a client can be pinned to; see
+/// [`IngestMessage::AuthRequest`].
+pub const SUPPORTED_INGEST_PROTOCOL_VERSIONS: &[u32] = &[1, INGEST_PROTOCOL_VERSION];
+
+#[derive(Debug, Decode, Encode)]
 pub enum IngestResponse {
     #[n(1)]
     AuthResponse {
@@ -10,11 +23,26 @@ pub enum IngestResponse {
 
         #[n(1)]
         message: Option<String>,
+
+        /// The protocol version the server will use for this connection.
+        /// Servers that predate version pinning leave this out.
+        #[n(2)]
+        protocol_version: Option<u32>,
     },
 
     #[n(2)]
     UnauthenticatedResponse {},
 
+    #[n(3)]
+    /// The server rejected the preceding message.
+    ErrorResponse {
+        #[n(0)]
+        code: ServerErrorCode,
+
+        #[n(1)]
+        message: Option<String>,
+    },
+
     #[n(101)]
     IngestStatusResponse {
         #[n(0)]
@@ -40,6 +68,14 @@ pub enum IngestMessage {
     AuthRequest {
         #[n(0)]
         token: Vec<u8>,
+
+        /// If given, the server must use exactly this protocol version, and
+        /// otherwise reject the connection with a version mismatch error,
+        /// rather than negotiating. It confirms the version in its
+        /// [`IngestResponse::AuthResponse`]. Servers that predate version
+        /// pinning ignore this field, and confirm nothing.
+        #[n(1)]
+        protocol_version: Option<u32>,
     },
 
     #[n(100)]
@@ -80,6 +116,62 @@ pub enum IngestMessage {
     },
 }
 
+/// The class of failure reported in an [`IngestResponse::ErrorResponse`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ServerErrorCode {
+    /// The client's protocol version isn't supported by the server
+    VersionMismatch,
+    /// The connection isn't (or is no longer) authorized to do this
+    Unauthorized,
+    /// The client is sending faster than the server will accept
+    RateLimited,
+    /// The server failed while handling an otherwise valid message
+    InternalServerError,
+    /// The client sent a malformed or out-of-sequence message
+    ProtocolViolation,
+    /// A code this client doesn't know about
+    Unknown(u32),
+}
+
+impl From<u32> for ServerErrorCode {
+    fn from(code: u32) -> Self {
+        match code {
+            1 => ServerErrorCode::VersionMismatch,
+            2 => ServerErrorCode::Unauthorized,
+            3 => ServerErrorCode::RateLimited,
+            4 => ServerErrorCode::InternalServerError,
+            5 => ServerErrorCode::ProtocolViolation,
+            c => ServerErrorCode::Unknown(c),
+        }
+    }
+}
+
+impl From<ServerErrorCode> for u32 {
+    fn from(code: ServerErrorCode) -> u32 {
+        match code {
+            ServerErrorCode::VersionMismatch => 1,
+            ServerErrorCode::Unauthorized => 2,
+            ServerErrorCode::RateLimited => 3,
+            ServerErrorCode::InternalServerError => 4,
+            ServerErrorCode::ProtocolViolation => 5,
+            ServerErrorCode::Unknown(c) => c,
+        }
+    }
+}
+
+impl Encode for ServerErrorCode {
+    fn encode<W: encode::Write>(&self, e: &mut Encoder<W>) -> Result<(), encode::Error<W::Error>> {
+        e.u32((*self).into())?;
+        Ok(())
+    }
+}
+
+impl<'b> Decode<'b> for ServerErrorCode {
+    fn decode(d: &mut Decoder<'b>) -> Result<Self, decode::Error> {
+        Ok(d.u32()?.into())
+    }
+}
+
 /// The numeric representation of an `AttrKey` after it has been declared on a connection.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 #[repr(transparent)]
@@ -136,7 +228,8 @@ impl<'b, K: Into<u32> + From<u32> + Copy + std::fmt::Debug> Decode<'b> for Packe
         let arr_len = d.array()?;
 
         if let Some(len) = arr_len {
-            let mut attrs = Vec::with_capacity(len as usize);
+            // The length comes off the wire, so don't trust it for preallocation
+            let mut attrs = Vec::with_capacity((len / 2).min(64) as usize);
             let mut remaining = len;
             while remaining >= 2 {
                 attrs.push((K::from(d.u32()?), AttrVal::decode(d)?));
@@ -151,3 +244,109 @@ impl<'b, K: Into<u32> + From<u32> + Copy + std::fmt::Debug> Decode<'b> for Packe
         }
     }
 }
+
+/// The size of the big-endian `u32` payload length that starts each frame
+pub const FRAME_LENGTH_PREFIX_SIZE: usize = 4;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProtocolError {
+    #[error("Frame is shorter than its length prefix")]
+    MissingLengthPrefix,
+
+    #[error("Frame length prefix is {expected} bytes, but the payload is {actual} bytes")]
+    LengthMismatch { expected: u32, actual: usize },
+
+    #[error("Malformed frame payload")]
+    Decode(#[from] decode::Error),
+}
+
+/// Decode a complete client-to-server frame: a length prefix, then a CBOR
+/// [IngestMessage] of exactly that length.
+///
+/// This has no side effects and doesn't panic on any input; allocations are
+/// bounded by the size of `frame`. That makes it suitable for fuzzing.
+pub fn decode_frame(frame: &[u8]) -> Result<IngestMessage, ProtocolError> {
+    minicbor::decode(frame_payload(frame)?).map_err(Into::into)
+}
+
+/// Decode a complete server-to-client frame; see [decode_frame].
+pub fn decode_response_frame(frame: &[u8]) -> Result<IngestResponse, ProtocolError> {
+    minicbor::decode(frame_payload(frame)?).map_err(Into::into)
+}
+
+fn frame_payload(frame: &[u8]) -> Result<&[u8], ProtocolError> {
+    let (prefix, payload) = frame
+        .split_first_chunk::<FRAME_LENGTH_PREFIX_SIZE>()
+        .ok_or(ProtocolError::MissingLengthPrefix)?;
+    let expected = u32::from_be_bytes(*prefix);
+    if usize::try_from(expected).ok() != Some(payload.len()) {
+        return Err(ProtocolError::LengthMismatch {
+            expected,
+            actual: payload.len(),
+        });
+    }
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn framed(payload: &[u8]) -> Vec<u8> {
+        let mut frame = (payload.len() as u32).to_be_bytes().to_vec();
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn decode_frame_round_trip() {
+        let id = TimelineId::allocate();
+        let frame = framed(&minicbor::to_vec(IngestMessage::OpenTimeline { id }).unwrap());
+        match decode_frame(&frame) {
+            Ok(IngestMessage::OpenTimeline { id: decoded }) => assert_eq!(decoded, id),
+            res => panic!("Unexpected result: {res:?}"),
+        }
+
+        assert!(matches!(
+            decode_frame(&frame[..3]),
+            Err(ProtocolError::MissingLengthPrefix)
+        ));
+        assert!(matches!(
+            decode_frame(&frame[..frame.len() - 1]),
+            Err(ProtocolError::LengthMismatch { .. })
+        ));
+        assert!(matches!(
+            decode_response_frame(&frame),
+            Err(ProtocolError::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn packed_attr_kvs_untrusted_length() {
+        // An array header claiming u64::MAX elements, and nothing else
+        let bytes = [0x9b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        assert!(minicbor::decode::<PackedAttrKvs<InternedAttrKey>>(&bytes).is_err());
+    }
+
+    #[test]
+    fn decode_frame_never_panics() {
+        proptest!(|(bytes in proptest::collection::vec(any::<u8>(), 0..64))| {
+            prop_assert!(decode_frame(&bytes).is_err());
+            prop_assert!(decode_response_frame(&bytes).is_err());
+        });
+
+        // Correctly framed garbage exercises the payload decoders. Random
+        // bytes could happen to be a valid message, so anything accepted must
+        // survive a round trip.
+        proptest!(|(payload in proptest::collection::vec(any::<u8>(), 0..256))| {
+            let frame = framed(&payload);
+            if let Ok(msg) = decode_frame(&frame) {
+                prop_assert!(decode_frame(&framed(&minicbor::to_vec(&msg).unwrap())).is_ok());
+            }
+            if let Ok(resp) = decode_response_frame(&frame) {
+                prop_assert!(decode_response_frame(&framed(&minicbor::to_vec(&resp).unwrap())).is_ok());
+            }
+        });
+    }
+}
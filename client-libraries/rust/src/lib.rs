@@ -1,9 +1,20 @@
 //! The Auxon SDK
+//!
+//! Without the default `std` feature, only the core [`api`] types are available,
+//! and the crate is `no_std` (requiring `alloc`).
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
 pub mod api;
+#[cfg(feature = "std")]
 pub mod auth_token;
+#[cfg(feature = "std")]
 pub mod reflector_config;
 
+#[cfg(feature = "modality")]
+pub mod archive;
 #[cfg(feature = "modality")]
 pub mod ingest_client;
 #[cfg(feature = "modality")]
@@ -25,3 +36,6 @@ pub mod tracing;
 
 #[cfg(feature = "modality")]
 pub(crate) mod tls;
+
+#[cfg(test)]
+mod test_fixtures;
@@ -1,5 +1,6 @@
 use crate::mutation_plane::protocol::{LeafwardsMessage, RootwardsMessage};
 use std::net::SocketAddr;
+use std::sync::Arc;
 use thiserror::Error;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{TcpSocket, TcpStream};
@@ -22,6 +23,17 @@ impl MutationParentConnection {
     pub async fn connect(
         endpoint: &Url,
         allow_insecure_tls: bool,
+    ) -> Result<MutationParentConnection, MutationParentClientInitializationError> {
+        Self::connect_with_tls_config(endpoint, allow_insecure_tls, None).await
+    }
+
+    /// Like [`MutationParentConnection::connect`], but `modality-mutation-tls` endpoints are
+    /// verified with `tls_config` instead of the platform roots, unless `allow_insecure_tls`
+    /// is set. This is the same TLS configuration the ingest client uses.
+    pub async fn connect_with_tls_config(
+        endpoint: &Url,
+        allow_insecure_tls: bool,
+        tls_config: Option<Arc<tokio_rustls::rustls::ClientConfig>>,
     ) -> Result<MutationParentConnection, MutationParentClientInitializationError> {
         let endpoint = RootwardsEndpoint::parse_and_resolve(endpoint, allow_insecure_tls).await?;
 
@@ -53,10 +65,10 @@ impl MutationParentConnection {
         })?;
 
         if let Some(tls_mode) = endpoint.tls_mode {
-            let config = match tls_mode {
-                TlsMode::Secure => crate::tls::SECURE.clone(),
-                TlsMode::Insecure => crate::tls::INSECURE.clone(),
-            };
+            let config = crate::tls::client_config(
+                matches!(tls_mode, TlsMode::Insecure),
+                tls_config.as_ref(),
+            );
             let cx = TlsConnector::from(config);
             let stream = cx.connect(endpoint.cert_domain.try_into()?, stream).await?;
             Ok(MutationParentConnection::Tls(stream))
@@ -235,3 +247,80 @@ pub enum ParseRootwardsEndpointError {
     #[error("IO Error")]
     Io(#[from] std::io::Error),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tls::test_certs::{self, CA_CERT};
+    use tokio::net::TcpListener;
+    use tokio_rustls::rustls::{
+        pki_types::{pem::PemObject, CertificateDer},
+        ClientConfig, RootCertStore,
+    };
+    use tokio_rustls::TlsAcceptor;
+
+    /// Serve TLS handshakes on localhost until the test ends, returning the endpoint url
+    async fn tls_server() -> Url {
+        let acceptor = TlsAcceptor::from(test_certs::server_config());
+
+        let addr = tokio::net::lookup_host(("localhost", 0))
+            .await
+            .unwrap()
+            .next()
+            .unwrap();
+        let listener = TcpListener::bind(addr).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let _stream = acceptor.accept(stream).await;
+                });
+            }
+        });
+
+        Url::parse(&format!(
+            "{MODALITY_MUTATION_TLS_URL_SCHEME}://localhost:{port}"
+        ))
+        .unwrap()
+    }
+
+    fn client_config(roots: RootCertStore) -> Option<Arc<ClientConfig>> {
+        Some(Arc::new(
+            ClientConfig::builder()
+                .with_root_certificates(roots)
+                .with_no_client_auth(),
+        ))
+    }
+
+    #[tokio::test]
+    async fn tls_connection_honors_configured_roots() {
+        let url = tls_server().await;
+
+        let untrusted = MutationParentConnection::connect_with_tls_config(
+            &url,
+            false,
+            client_config(RootCertStore::empty()),
+        )
+        .await;
+        assert!(untrusted.is_err());
+
+        let mut roots = RootCertStore::empty();
+        roots
+            .add(CertificateDer::from_pem_slice(CA_CERT.as_bytes()).unwrap())
+            .unwrap();
+        let trusted =
+            MutationParentConnection::connect_with_tls_config(&url, false, client_config(roots))
+                .await;
+        assert!(matches!(trusted, Ok(MutationParentConnection::Tls(_))));
+
+        let insecure = MutationParentConnection::connect_with_tls_config(
+            &url,
+            true,
+            client_config(RootCertStore::empty()),
+        )
+        .await;
+        assert!(matches!(insecure, Ok(MutationParentConnection::Tls(_))));
+    }
+}
@@ -2,6 +2,9 @@ use async_trait::async_trait;
 
 use crate::api::{AttrKey, AttrVal};
 use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::runtime::Handle;
+use tokio::sync::Mutex;
 
 /// "Infallible" operational view on a mutator actuator.
 #[async_trait]
@@ -19,6 +22,13 @@ pub trait MutatorActuator {
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
 
     async fn reset(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    /// Bring the mutator's description up to date with runtime state, e.g.
+    /// parameter ranges that depend on which devices are present. The mutator
+    /// server calls this before listing mutators. Does nothing by default.
+    async fn refresh_descriptor(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
 }
 
 /// A non-async version of MutatorActuator. This isn't supported by the provided http server, but
@@ -38,3 +48,83 @@ pub trait SyncMutatorActuator {
 
     fn reset(&mut self) -> Result<(), Box<dyn std::error::Error>>;
 }
+
+/// Holds a mutation in place, and resets its mutator when dropped, so that a
+/// host which bails out early (an error, a panic) doesn't leave the system
+/// perturbed.
+///
+/// The reset on drop is best-effort: it's spawned as a task, onto the current
+/// tokio runtime, or if dropped outside of one, onto the runtime the guard
+/// was created in. It won't happen if that runtime shuts down first, and
+/// reset errors are only logged. Use [ActiveMutationGuard::reset] to wait
+/// for the reset and see errors.
+pub struct ActiveMutationGuard<A: MutatorActuator + Send + ?Sized + 'static> {
+    actuator: Option<Arc<Mutex<A>>>,
+    mutation_id: uuid::Uuid,
+    runtime: Option<Handle>,
+}
+
+impl<A: MutatorActuator + Send + ?Sized + 'static> ActiveMutationGuard<A> {
+    /// Inject a mutation, and guard it.
+    pub async fn inject(
+        actuator: Arc<Mutex<A>>,
+        mutation_id: uuid::Uuid,
+        params: BTreeMap<AttrKey, AttrVal>,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        actuator.lock().await.inject(mutation_id, params).await?;
+        Ok(Self::new(actuator, mutation_id))
+    }
+
+    /// Guard a mutation that has already been injected.
+    pub fn new(actuator: Arc<Mutex<A>>, mutation_id: uuid::Uuid) -> Self {
+        ActiveMutationGuard {
+            actuator: Some(actuator),
+            mutation_id,
+            runtime: Handle::try_current().ok(),
+        }
+    }
+
+    pub fn mutation_id(&self) -> uuid::Uuid {
+        self.mutation_id
+    }
+
+    /// Reset the mutator now.
+    pub async fn reset(mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        match self.actuator.take() {
+            Some(actuator) => actuator.lock().await.reset().await,
+            None => Ok(()),
+        }
+    }
+
+    /// Drop the guard without resetting, leaving the mutation in place.
+    pub fn disarm(mut self) {
+        self.actuator = None;
+    }
+}
+
+impl<A: MutatorActuator + Send + ?Sized + 'static> Drop for ActiveMutationGuard<A> {
+    fn drop(&mut self) {
+        let Some(actuator) = self.actuator.take() else {
+            return;
+        };
+        let mutation_id = self.mutation_id;
+        let reset = async move {
+            if let Err(e) = actuator.lock().await.reset().await {
+                tracing::warn!(
+                    %mutation_id,
+                    err = e.as_ref() as &dyn std::error::Error,
+                    "Failed to reset mutator for dropped mutation guard"
+                );
+            }
+        };
+
+        if let Some(handle) = Handle::try_current().ok().or_else(|| self.runtime.take()) {
+            handle.spawn(reset);
+        } else {
+            tracing::warn!(
+                %mutation_id,
+                "No tokio runtime to reset mutator for dropped mutation guard"
+            );
+        }
+    }
+}
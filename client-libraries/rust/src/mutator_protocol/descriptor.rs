@@ -22,23 +22,114 @@ pub mod owned {
     use crate::mutator_protocol::{attrs, params_attributes::is_valid_single_key_segment_contents};
     use std::collections::{BTreeMap, HashMap};
 
-    #[derive(Debug, Clone, Default)]
+    #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
     pub struct OwnedMutatorDescriptor {
+        #[serde(skip_serializing_if = "Option::is_none")]
         pub name: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
         pub description: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
         pub layer: Option<MutatorLayer>,
+        #[serde(skip_serializing_if = "Option::is_none")]
         pub group: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
         pub operation: Option<MutatorOperation>,
+        #[serde(skip_serializing_if = "Option::is_none")]
         pub statefulness: Option<MutatorStatefulness>,
+        #[serde(skip_serializing_if = "Option::is_none")]
         pub organization_custom_metadata: Option<OrganizationCustomMetadata>,
         /// The parameters for mutations injected with this mutator
         pub params: Vec<OwnedMutatorParamDescriptor>,
     }
 
     impl OwnedMutatorDescriptor {
+        /// A descriptor for a mutator performing `operation`, with the
+        /// conventional single parameter for it: keyed by the operation's
+        /// [name](MutatorOperation::name), e.g. `set_to_value`, and taking a
+        /// value of `value_type`.
+        ///
+        /// Use the `with_*` methods to fill in the rest, e.g.
+        /// `OwnedMutatorDescriptor::for_operation(MutatorOperation::SetToValue, AttrType::Integer).with_name("speed")`.
+        pub fn for_operation(operation: MutatorOperation, value_type: AttrType) -> Self {
+            let param = OwnedMutatorParamDescriptor::new(value_type, operation.name().to_owned())
+                .expect("Operation names are valid parameter keys");
+            OwnedMutatorDescriptor {
+                operation: Some(operation),
+                params: vec![param],
+                ..Default::default()
+            }
+        }
+
+        pub fn with_name(mut self, s: &str) -> Self {
+            self.name = Some(s.to_owned());
+            self
+        }
+
+        pub fn with_description(mut self, s: &str) -> Self {
+            self.description = Some(s.to_owned());
+            self
+        }
+
+        pub fn with_layer(mut self, layer: MutatorLayer) -> Self {
+            self.layer = Some(layer);
+            self
+        }
+
+        pub fn with_group(mut self, s: &str) -> Self {
+            self.group = Some(s.to_owned());
+            self
+        }
+
+        pub fn with_statefulness(mut self, statefulness: MutatorStatefulness) -> Self {
+            self.statefulness = Some(statefulness);
+            self
+        }
+
+        /// Add a parameter, replacing any existing one with the same name; e.g. to
+        /// describe the one added by [`OwnedMutatorDescriptor::for_operation`] further.
+        pub fn with_param(mut self, param: OwnedMutatorParamDescriptor) -> Self {
+            self.params.retain(|p| p.name != param.name);
+            self.params.push(param);
+            self
+        }
+
+        /// Render this descriptor as JSON.
+        ///
+        /// Unlike the flattened attribute encoding, this is a structured document
+        /// (one object per parameter, enums as snake_case names) that is suitable
+        /// for documentation or out-of-band registration.
+        pub fn to_json(&self) -> Result<String, serde_json::Error> {
+            serde_json::to_string_pretty(self)
+        }
+
+        /// Parse a descriptor previously rendered with [`OwnedMutatorDescriptor::to_json`].
+        pub fn from_json(s: &str) -> Result<Self, serde_json::Error> {
+            serde_json::from_str(s)
+        }
+
+        /// Flatten this descriptor into attributes, in a canonical order that
+        /// doesn't depend on how the descriptor was built:
+        ///
+        /// 1. The mutator-level fields that are present, in the order name,
+        ///    description, layer, group, operation.
+        /// 2. Mutator-level organization custom metadata, sorted by key.
+        /// 3. Each parameter, sorted by parameter name. A parameter's fields are
+        ///    in the order name, value type, description, min, max, default,
+        ///    least effect value, distribution kind, distribution scaling, then
+        ///    the distribution option set and its custom metadata, each sorted by key.
+        ///
+        /// So a descriptor and its round trip through
+        /// [`OwnedMutatorDescriptor::try_from_description_attributes`] give the
+        /// same attribute stream. [`MutatorDescriptor::get_description_attributes`]
+        /// uses the same order.
         pub fn into_description_attributes(
             self,
         ) -> Box<dyn Iterator<Item = (AttrKey, AttrVal)> + 'static> {
+            Box::new(self.description_attributes().into_iter())
+        }
+
+        fn description_attributes(&self) -> Vec<(AttrKey, AttrVal)> {
             let mut all_mutator_attrs: Vec<(AttrKey, AttrVal)> = vec![];
             if let Some(mutator_name) = self.name.as_ref() {
                 all_mutator_attrs.push((attrs::mutator::NAME, mutator_name.into()))
@@ -63,7 +154,7 @@ pub mod owned {
                         .as_str(),
                 );
                 mutator_level_custom_metadata_prefix.push('.');
-                for (k, v) in organization_custom_metadata.attributes.iter() {
+                for (k, v) in organization_custom_metadata.sorted_attributes() {
                     all_mutator_attrs.push((
                         AttrKey::from(format!("{mutator_level_custom_metadata_prefix}{k}")),
                         v.clone(),
@@ -71,11 +162,13 @@ pub mod owned {
                 }
             }
 
-            for param in &self.params {
+            let mut params: Vec<&OwnedMutatorParamDescriptor> = self.params.iter().collect();
+            params.sort_by(|a, b| a.name.cmp(&b.name));
+            for param in params {
                 all_mutator_attrs.extend(param.mutator_params_param_key_prefixed_attributes());
             }
 
-            Box::new(all_mutator_attrs.into_iter())
+            all_mutator_attrs
         }
 
         pub fn try_from_description_attributes(
@@ -177,48 +270,15 @@ pub mod owned {
     }
 
     impl MutatorDescriptor for OwnedMutatorDescriptor {
+        /// Attributes are in the canonical order; see
+        /// [`OwnedMutatorDescriptor::into_description_attributes`].
         fn get_description_attributes(&self) -> Box<dyn Iterator<Item = (AttrKey, AttrVal)> + '_> {
-            let mut all_mutator_attrs: Vec<(AttrKey, AttrVal)> = vec![];
-            if let Some(mutator_name) = self.name.as_ref() {
-                all_mutator_attrs.push((attrs::mutator::NAME, mutator_name.into()))
-            }
-            if let Some(mutator_description) = self.description.as_ref() {
-                all_mutator_attrs.push((attrs::mutator::DESCRIPTION, mutator_description.into()))
-            }
-            if let Some(mutator_layer) = self.layer.as_ref() {
-                all_mutator_attrs.push((attrs::mutator::LAYER, mutator_layer.name().into()))
-            }
-            if let Some(mutator_group) = self.group.as_ref() {
-                all_mutator_attrs.push((attrs::mutator::GROUP, mutator_group.into()))
-            }
-            if let Some(mutator_operation) = self.operation.as_ref() {
-                all_mutator_attrs.push((attrs::mutator::OPERATION, mutator_operation.name().into()))
-            }
-            if let Some(organization_custom_metadata) = self.organization_custom_metadata.as_ref() {
-                let mut mutator_level_custom_metadata_prefix = "mutator.".to_string();
-                mutator_level_custom_metadata_prefix.push_str(
-                    organization_custom_metadata
-                        .organization_name_segment
-                        .as_str(),
-                );
-                mutator_level_custom_metadata_prefix.push('.');
-                for (k, v) in organization_custom_metadata.attributes.iter() {
-                    all_mutator_attrs.push((
-                        AttrKey::from(format!("{mutator_level_custom_metadata_prefix}{k}")),
-                        v.clone(),
-                    ));
-                }
-            }
-
-            for param in &self.params {
-                all_mutator_attrs.extend(param.mutator_params_param_key_prefixed_attributes());
-            }
-
-            Box::new(all_mutator_attrs.into_iter())
+            Box::new(self.description_attributes().into_iter())
         }
     }
 
-    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
     pub enum MutatorLayer {
         Implementational,
         Operational,
@@ -259,7 +319,8 @@ pub mod owned {
         }
     }
 
-    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
     pub enum MutatorStatefulness {
         /// Sticks. Has effect immediately and continuously. Stays until explicitly told to leave.
         Permanent,
@@ -306,7 +367,8 @@ pub mod owned {
         }
     }
 
-    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
     pub enum MutatorOperation {
         Delay,
         Duplicate,
@@ -388,7 +450,8 @@ pub mod owned {
         }
     }
 
-    #[derive(Debug, Clone)]
+    #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+    #[serde(try_from = "RawOrganizationCustomMetadata")]
     pub struct OrganizationCustomMetadata {
         /// Expected to be ASCII and not contain any periods.
         organization_name_segment: String,
@@ -397,6 +460,13 @@ pub mod owned {
     }
 
     impl OrganizationCustomMetadata {
+        /// The attributes, sorted by key
+        fn sorted_attributes(&self) -> Vec<(&String, &AttrVal)> {
+            let mut attributes: Vec<_> = self.attributes.iter().collect();
+            attributes.sort_by_key(|(k, _)| *k);
+            attributes
+        }
+
         pub fn empty(organization_name_segment: String) -> Option<Self> {
             if is_valid_single_key_segment_contents(organization_name_segment.as_str()) {
                 Some(OrganizationCustomMetadata {
@@ -426,23 +496,89 @@ pub mod owned {
         }
     }
 
-    #[derive(Debug, Clone)]
+    #[derive(serde::Deserialize)]
+    struct RawOrganizationCustomMetadata {
+        organization_name_segment: String,
+        #[serde(default)]
+        attributes: HashMap<String, AttrVal>,
+    }
+
+    impl TryFrom<RawOrganizationCustomMetadata> for OrganizationCustomMetadata {
+        type Error = String;
+
+        fn try_from(raw: RawOrganizationCustomMetadata) -> Result<Self, Self::Error> {
+            let segment = raw.organization_name_segment;
+            OrganizationCustomMetadata::new(segment.clone(), raw.attributes).ok_or_else(|| {
+                format!(
+                    "Invalid organization name segment '{segment}'. Must be ASCII with no periods."
+                )
+            })
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
     pub struct OwnedMutatorParamDescriptor {
+        #[serde(with = "attr_type_name")]
         pub value_type: AttrType,
         /// This is used as the parameter key interfix for parameter-specific attributes
         /// and as the value associated with the `mutator.params.<param-key>.name attribute`
         pub name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         pub description: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         pub value_min: Option<AttrVal>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         pub value_max: Option<AttrVal>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         pub default_value: Option<AttrVal>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         pub least_effect_value: Option<AttrVal>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         pub value_distribution_kind: Option<ValueDistributionKind>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         pub value_distribution_scaling: Option<ValueDistributionScaling>,
+        /// An empty option set has no attribute representation, and is read back as `None`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         pub value_distribution_option_set: Option<BTreeMap<String, AttrVal>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         pub organization_custom_metadata: Option<OrganizationCustomMetadata>,
     }
 
+    /// Parse the name used for an [`AttrType`] in the `value_type` param attribute.
+    fn attr_type_from_name(s: &str) -> Option<AttrType> {
+        match s {
+            "TimelineId" => Some(AttrType::TimelineId),
+            "String" => Some(AttrType::String),
+            "Integer" => Some(AttrType::Integer),
+            "BigInteger" => Some(AttrType::BigInt),
+            "Float" => Some(AttrType::Float),
+            "Bool" => Some(AttrType::Bool),
+            "Nanoseconds" => Some(AttrType::Nanoseconds),
+            "Duration" => Some(AttrType::Duration),
+            "LogicalTime" => Some(AttrType::LogicalTime),
+            "Any" => Some(AttrType::Any),
+            "Coordinate" => Some(AttrType::EventCoordinate),
+            _ => None,
+        }
+    }
+
+    /// Serde support for [`AttrType`], using the same names as the attribute encoding.
+    mod attr_type_name {
+        use super::attr_type_from_name;
+        use crate::api::AttrType;
+        use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(t: &AttrType, ser: S) -> Result<S::Ok, S::Error> {
+            ser.collect_str(t)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<AttrType, D::Error> {
+            let s = String::deserialize(de)?;
+            attr_type_from_name(&s)
+                .ok_or_else(|| D::Error::custom(format!("Unknown value type '{s}'")))
+        }
+    }
+
     impl Default for OwnedMutatorParamDescriptor {
         fn default() -> Self {
             Self {
@@ -565,7 +701,7 @@ pub mod owned {
                         .as_str(),
                 );
                 parameter_level_custom_metadata_prefix.push('.');
-                for (k, v) in organization_custom_metadata.attributes.iter() {
+                for (k, v) in organization_custom_metadata.sorted_attributes() {
                     param_attrs.push((
                         AttrKey::from(format!("{parameter_level_custom_metadata_prefix}{k}")),
                         v.clone(),
@@ -606,19 +742,7 @@ pub mod owned {
                         }
                     } else if post_key_with_period == MUTATOR_PARAMS_VALUE_TYPE_SUFFIX {
                         if let AttrVal::String(s) = v {
-                            value_type = match s.as_ref() {
-                                "TimelineId" => Some(AttrType::TimelineId),
-                                "String" => Some(AttrType::String),
-                                "Integer" => Some(AttrType::Integer),
-                                "BigInteger" => Some(AttrType::BigInt),
-                                "Float" => Some(AttrType::Float),
-                                "Bool" => Some(AttrType::Bool),
-                                "Nanoseconds" => Some(AttrType::Nanoseconds),
-                                "LogicalTime" => Some(AttrType::LogicalTime),
-                                "Any" => Some(AttrType::Any),
-                                "Coordinate" => Some(AttrType::EventCoordinate),
-                                _ => None,
-                            }
+                            value_type = attr_type_from_name(s.as_ref());
                         }
                     } else if post_key_with_period == MUTATOR_PARAMS_DESCRIPTION_SUFFIX {
                         if let AttrVal::String(s) = v {
@@ -764,9 +888,200 @@ pub mod owned {
 
             self
         }
+
+        /// The `(label, value)` pairs of the value distribution option set, ordered by label.
+        ///
+        /// Returns `None` when the parameter has no option set.
+        pub fn option_set_entries(&self) -> Option<Vec<(String, AttrVal)>> {
+            self.value_distribution_option_set.as_ref().map(|set| {
+                set.iter()
+                    .map(|(label, val)| (label.clone(), val.clone()))
+                    .collect()
+            })
+        }
+    }
+
+    /// Builds an [`OwnedMutatorParamDescriptor`], checking that the configured values
+    /// are coherent with each other and with the parameter's value type.
+    #[derive(Debug, Clone)]
+    pub struct OwnedMutatorParamDescriptorBuilder {
+        descriptor: OwnedMutatorParamDescriptor,
+    }
+
+    #[derive(Debug, thiserror::Error, Eq, PartialEq)]
+    pub enum ParamDescriptorBuildError {
+        #[error("Invalid parameter name '{0}'. Parameter names must be ASCII with no periods.")]
+        InvalidName(String),
+        #[error("The parameter's {field} is of type {actual}, but the parameter's value type is {expected}")]
+        ValueTypeMismatch {
+            field: &'static str,
+            expected: AttrType,
+            actual: AttrType,
+        },
+        #[error("The parameter's value_min is greater than its value_max")]
+        MinExceedsMax,
+        #[error("The parameter's {field} is outside of the value_min/value_max bounds")]
+        OutOfBounds { field: &'static str },
+    }
+
+    impl OwnedMutatorParamDescriptor {
+        pub fn builder(
+            value_type: AttrType,
+            name: impl Into<String>,
+        ) -> OwnedMutatorParamDescriptorBuilder {
+            OwnedMutatorParamDescriptorBuilder::new(value_type, name)
+        }
+    }
+
+    impl OwnedMutatorParamDescriptorBuilder {
+        pub fn new(value_type: AttrType, name: impl Into<String>) -> Self {
+            OwnedMutatorParamDescriptorBuilder {
+                descriptor: OwnedMutatorParamDescriptor {
+                    value_type,
+                    name: name.into(),
+                    ..Default::default()
+                },
+            }
+        }
+
+        pub fn description(mut self, s: impl Into<String>) -> Self {
+            self.descriptor.description = Some(s.into());
+            self
+        }
+
+        pub fn value_min(mut self, val: impl Into<AttrVal>) -> Self {
+            self.descriptor.value_min = Some(val.into());
+            self
+        }
+
+        pub fn value_max(mut self, val: impl Into<AttrVal>) -> Self {
+            self.descriptor.value_max = Some(val.into());
+            self
+        }
+
+        pub fn default_value(mut self, val: impl Into<AttrVal>) -> Self {
+            self.descriptor.default_value = Some(val.into());
+            self
+        }
+
+        pub fn least_effect_value(mut self, val: impl Into<AttrVal>) -> Self {
+            self.descriptor.least_effect_value = Some(val.into());
+            self
+        }
+
+        pub fn value_distribution_kind(mut self, kind: ValueDistributionKind) -> Self {
+            self.descriptor.value_distribution_kind = Some(kind);
+            self
+        }
+
+        pub fn value_distribution_scaling(mut self, scaling: ValueDistributionScaling) -> Self {
+            self.descriptor.value_distribution_scaling = Some(scaling);
+            self
+        }
+
+        pub fn value_distribution_option(
+            mut self,
+            key: impl Into<String>,
+            val: impl Into<AttrVal>,
+        ) -> Self {
+            self.descriptor
+                .value_distribution_option_set
+                .get_or_insert_with(Default::default)
+                .insert(key.into(), val.into());
+            self
+        }
+
+        pub fn organization_custom_metadata(
+            mut self,
+            metadata: OrganizationCustomMetadata,
+        ) -> Self {
+            self.descriptor.organization_custom_metadata = Some(metadata);
+            self
+        }
+
+        pub fn build(self) -> Result<OwnedMutatorParamDescriptor, ParamDescriptorBuildError> {
+            let d = self.descriptor;
+            if !is_valid_single_key_segment_contents(d.name.as_str()) {
+                return Err(ParamDescriptorBuildError::InvalidName(d.name));
+            }
+
+            let typed_fields = [
+                ("value_min", &d.value_min),
+                ("value_max", &d.value_max),
+                ("default_value", &d.default_value),
+                ("least_effect_value", &d.least_effect_value),
+            ];
+            for (field, val) in typed_fields {
+                if let Some(val) = val {
+                    if !value_matches_type(val, d.value_type) {
+                        return Err(ParamDescriptorBuildError::ValueTypeMismatch {
+                            field,
+                            expected: d.value_type,
+                            actual: val.attr_type(),
+                        });
+                    }
+                }
+            }
+
+            if let (Some(min), Some(max)) = (&d.value_min, &d.value_max) {
+                if compare_values(min, max) == Some(std::cmp::Ordering::Greater) {
+                    return Err(ParamDescriptorBuildError::MinExceedsMax);
+                }
+            }
+
+            for (field, val) in [
+                ("default_value", &d.default_value),
+                ("least_effect_value", &d.least_effect_value),
+            ] {
+                if let Some(val) = val {
+                    let below_min = d
+                        .value_min
+                        .as_ref()
+                        .and_then(|min| compare_values(val, min))
+                        == Some(std::cmp::Ordering::Less);
+                    let above_max = d
+                        .value_max
+                        .as_ref()
+                        .and_then(|max| compare_values(val, max))
+                        == Some(std::cmp::Ordering::Greater);
+                    if below_min || above_max {
+                        return Err(ParamDescriptorBuildError::OutOfBounds { field });
+                    }
+                }
+            }
+
+            Ok(d)
+        }
+    }
+
+    fn value_matches_type(val: &AttrVal, value_type: AttrType) -> bool {
+        match (value_type, val.attr_type()) {
+            (AttrType::Any, _) => true,
+            // Small big integers are stored as plain integers
+            (AttrType::BigInt, AttrType::Integer) => true,
+            (expected, actual) => expected == actual,
+        }
+    }
+
+    /// Orders two values of the same type, treating integers and big integers as one type.
+    fn compare_values(a: &AttrVal, b: &AttrVal) -> Option<std::cmp::Ordering> {
+        fn as_i128(v: &AttrVal) -> Option<i128> {
+            match v {
+                AttrVal::Integer(i) => Some(*i as i128),
+                AttrVal::BigInt(bi) => Some(**bi),
+                _ => None,
+            }
+        }
+
+        match (as_i128(a), as_i128(b)) {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            _ if a.attr_type() == b.attr_type() => Some(a.cmp(b)),
+            _ => None,
+        }
     }
 
-    #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+    #[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
     pub enum ValueDistributionKind {
         Continuous,
         Discrete,
@@ -803,7 +1118,8 @@ pub mod owned {
         }
     }
 
-    #[derive(Debug, Copy, Clone)]
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
     pub enum ValueDistributionScaling {
         Linear,
         Complex,
@@ -833,7 +1149,7 @@ pub mod owned {
                 if s == "complex" {
                     return Ok(ValueDistributionScaling::Complex);
                 }
-                if s == "discrete" {
+                if s == "circular" {
                     return Ok(ValueDistributionScaling::Circular);
                 }
             }
@@ -844,3 +1160,346 @@ pub mod owned {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::owned::*;
+    use super::MutatorDescriptor;
+    use crate::api::{
+        proptest_strategies::attr_val, AttrType, AttrVal, BigInt, Nanoseconds, TimelineId,
+    };
+    use proptest::prelude::*;
+    use std::collections::HashMap;
+
+    fn value_distribution_kind() -> impl Strategy<Value = ValueDistributionKind> {
+        prop_oneof![
+            Just(ValueDistributionKind::Continuous),
+            Just(ValueDistributionKind::Discrete),
+        ]
+    }
+
+    fn value_distribution_scaling() -> impl Strategy<Value = ValueDistributionScaling> {
+        prop_oneof![
+            Just(ValueDistributionScaling::Linear),
+            Just(ValueDistributionScaling::Complex),
+            Just(ValueDistributionScaling::Circular),
+        ]
+    }
+
+    fn param_descriptor() -> impl Strategy<Value = OwnedMutatorParamDescriptor> {
+        (
+            "[a-z_][a-z0-9_]{0,10}",
+            proptest::option::of(value_distribution_kind()),
+            proptest::option::of(value_distribution_scaling()),
+            proptest::option::of(proptest::collection::btree_map(
+                "[a-z][a-z0-9_]{0,8}",
+                attr_val(),
+                1..4,
+            )),
+        )
+            .prop_map(|(name, kind, scaling, option_set)| {
+                let mut p = OwnedMutatorParamDescriptor::new(AttrType::Any, name).unwrap();
+                p.value_distribution_kind = kind;
+                p.value_distribution_scaling = scaling;
+                p.value_distribution_option_set = option_set;
+                p
+            })
+    }
+
+    #[test]
+    fn param_descriptor_value_distribution_round_trip() {
+        proptest!(|(param in param_descriptor())| {
+            let attrs = param
+                .mutator_params_param_key_prefixed_attributes()
+                .collect();
+            let parsed = OwnedMutatorParamDescriptor::try_from_param_key_and_attributes(
+                param.name.clone(),
+                attrs,
+            )?;
+            prop_assert_eq!(parsed, param);
+        });
+    }
+
+    #[test]
+    fn descriptor_value_distribution_round_trip() {
+        let params =
+            proptest::collection::btree_map("[a-z_][a-z0-9_]{0,10}", param_descriptor(), 0..4)
+                .prop_map(|params| {
+                    params
+                        .into_iter()
+                        .map(|(name, mut p)| {
+                            p.name = name;
+                            p
+                        })
+                        .collect::<Vec<_>>()
+                });
+        proptest!(|(params in params)| {
+            let descriptor = OwnedMutatorDescriptor {
+                params,
+                ..Default::default()
+            };
+            let parsed = OwnedMutatorDescriptor::try_from_description_attributes(
+                descriptor.get_description_attributes(),
+            )?;
+            prop_assert_eq!(parsed, descriptor);
+        });
+    }
+
+    fn fully_populated_descriptor() -> OwnedMutatorDescriptor {
+        let custom_metadata = OrganizationCustomMetadata::new(
+            "acme".to_owned(),
+            HashMap::from([("team".to_owned(), AttrVal::from("robots"))]),
+        )
+        .unwrap();
+
+        let mut param = OwnedMutatorParamDescriptor::new(AttrType::Float, "velocity".to_owned())
+            .unwrap()
+            .with_description("How fast to go")
+            .with_value_min(-10.5)
+            .with_value_max(10.5)
+            .with_default_value(0.0)
+            .with_least_effect_value(0.0)
+            .with_value_distribution_kind(ValueDistributionKind::Continuous)
+            .with_value_distribution_scaling(ValueDistributionScaling::Circular)
+            .with_value_distribution_option("slow", AttrVal::from(1.0))
+            .with_value_distribution_option("fast", AttrVal::from(9.0));
+        param.organization_custom_metadata = Some(custom_metadata.clone());
+
+        let other_param = OwnedMutatorParamDescriptor::new(AttrType::BigInt, "big".to_owned())
+            .unwrap()
+            .with_default_value(BigInt::new_attr_val(i128::MAX))
+            .with_value_distribution_option("when", AttrVal::Timestamp(Nanoseconds::from(5)))
+            .with_value_distribution_option("where", TimelineId::zero().into());
+
+        OwnedMutatorDescriptor {
+            name: Some("thruster".to_owned()),
+            description: Some("Adjusts the thruster".to_owned()),
+            layer: Some(MutatorLayer::Operational),
+            group: Some("propulsion".to_owned()),
+            operation: Some(MutatorOperation::SetToValue),
+            statefulness: Some(MutatorStatefulness::Intermittent),
+            organization_custom_metadata: Some(custom_metadata),
+            params: vec![param, other_param],
+        }
+    }
+
+    #[test]
+    fn description_attributes_canonical_order() {
+        // Custom metadata isn't read back from attributes yet
+        let mut descriptor = fully_populated_descriptor();
+        descriptor.organization_custom_metadata = None;
+        for param in descriptor.params.iter_mut() {
+            param.organization_custom_metadata = None;
+        }
+        // Deliberately not sorted by name
+        assert_eq!(descriptor.params[0].name, "velocity");
+
+        let attrs: Vec<_> = descriptor.get_description_attributes().collect();
+        let round_tripped =
+            OwnedMutatorDescriptor::try_from_description_attributes(attrs.clone().into_iter())
+                .unwrap();
+        assert_eq!(round_tripped.params[0].name, "big");
+        let round_tripped_attrs: Vec<_> = round_tripped.get_description_attributes().collect();
+        assert_eq!(attrs, round_tripped_attrs);
+        assert_eq!(
+            attrs,
+            descriptor.into_description_attributes().collect::<Vec<_>>()
+        );
+
+        let keys: Vec<&str> = attrs.iter().map(|(k, _)| k.as_ref()).collect();
+        assert_eq!(
+            keys[..7],
+            [
+                "mutator.name",
+                "mutator.description",
+                "mutator.layer",
+                "mutator.group",
+                "mutator.operation",
+                "mutator.params.big.name",
+                "mutator.params.big.value_type",
+            ]
+        );
+    }
+
+    #[test]
+    fn builder_accepts_coherent_descriptor() {
+        let p = OwnedMutatorParamDescriptor::builder(AttrType::Integer, "count")
+            .description("How many")
+            .value_min(0)
+            .value_max(10)
+            .default_value(5)
+            .least_effect_value(0)
+            .value_distribution_kind(ValueDistributionKind::Discrete)
+            .value_distribution_option("few", 2)
+            .build()
+            .unwrap();
+        assert_eq!(p.name, "count");
+        assert_eq!(p.value_min, Some(AttrVal::Integer(0)));
+        assert_eq!(p.least_effect_value, Some(AttrVal::Integer(0)));
+
+        // Small big integers are stored as plain integers
+        OwnedMutatorParamDescriptor::builder(AttrType::BigInt, "big")
+            .value_min(BigInt::new_attr_val(-1))
+            .value_max(BigInt::new_attr_val(i128::MAX))
+            .least_effect_value(BigInt::new_attr_val(0))
+            .build()
+            .unwrap();
+    }
+
+    #[test]
+    fn builder_rejects_invalid_name() {
+        let err = OwnedMutatorParamDescriptor::builder(AttrType::Integer, "a.b")
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ParamDescriptorBuildError::InvalidName("a.b".to_owned())
+        );
+    }
+
+    #[test]
+    fn builder_rejects_mistyped_values() {
+        let base = || OwnedMutatorParamDescriptor::builder(AttrType::Float, "speed");
+        let expected = |field| ParamDescriptorBuildError::ValueTypeMismatch {
+            field,
+            expected: AttrType::Float,
+            actual: AttrType::Integer,
+        };
+        assert_eq!(
+            base().value_min(1).build().unwrap_err(),
+            expected("value_min")
+        );
+        assert_eq!(
+            base().value_max(1).build().unwrap_err(),
+            expected("value_max")
+        );
+        assert_eq!(
+            base().default_value(1).build().unwrap_err(),
+            expected("default_value")
+        );
+        assert_eq!(
+            base().least_effect_value(1).build().unwrap_err(),
+            expected("least_effect_value")
+        );
+    }
+
+    #[test]
+    fn option_set_entries_from_attributes() {
+        let param = OwnedMutatorParamDescriptor::new(AttrType::Any, "mode".to_owned())
+            .unwrap()
+            .with_value_distribution_option("slow", AttrVal::from(1))
+            .with_value_distribution_option("fast", AttrVal::from(10))
+            .with_value_distribution_option("off", AttrVal::from(false));
+        let parsed = OwnedMutatorParamDescriptor::try_from_param_key_and_attributes(
+            param.name.clone(),
+            param
+                .mutator_params_param_key_prefixed_attributes()
+                .collect(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            parsed.option_set_entries(),
+            Some(vec![
+                ("fast".to_owned(), AttrVal::from(10)),
+                ("off".to_owned(), AttrVal::from(false)),
+                ("slow".to_owned(), AttrVal::from(1)),
+            ])
+        );
+        assert_eq!(
+            OwnedMutatorParamDescriptor::new(AttrType::Any, "mode".to_owned())
+                .unwrap()
+                .option_set_entries(),
+            None
+        );
+    }
+
+    #[test]
+    fn builder_rejects_inverted_bounds() {
+        let err = OwnedMutatorParamDescriptor::builder(AttrType::Integer, "count")
+            .value_min(10)
+            .value_max(0)
+            .build()
+            .unwrap_err();
+        assert_eq!(err, ParamDescriptorBuildError::MinExceedsMax);
+    }
+
+    #[test]
+    fn builder_rejects_out_of_bounds_values() {
+        let base = || {
+            OwnedMutatorParamDescriptor::builder(AttrType::Float, "speed")
+                .value_min(0.0)
+                .value_max(1.0)
+        };
+        assert_eq!(
+            base().least_effect_value(1.5).build().unwrap_err(),
+            ParamDescriptorBuildError::OutOfBounds {
+                field: "least_effect_value"
+            }
+        );
+        assert_eq!(
+            base().least_effect_value(-0.5).build().unwrap_err(),
+            ParamDescriptorBuildError::OutOfBounds {
+                field: "least_effect_value"
+            }
+        );
+        assert_eq!(
+            base().default_value(2.0).build().unwrap_err(),
+            ParamDescriptorBuildError::OutOfBounds {
+                field: "default_value"
+            }
+        );
+    }
+
+    #[test]
+    fn descriptor_json_round_trip() {
+        let descriptor = fully_populated_descriptor();
+        let json = descriptor.to_json().unwrap();
+        let parsed = OwnedMutatorDescriptor::from_json(&json).unwrap();
+        assert_eq!(parsed, descriptor);
+    }
+
+    #[test]
+    fn descriptor_json_rejects_invalid_organization_segment() {
+        let json = r#"{
+            "organization_custom_metadata": {
+                "organization_name_segment": "not.valid",
+                "attributes": {}
+            },
+            "params": []
+        }"#;
+        assert!(OwnedMutatorDescriptor::from_json(json).is_err());
+    }
+
+    #[test]
+    fn descriptor_for_operation() {
+        let d =
+            OwnedMutatorDescriptor::for_operation(MutatorOperation::SetToValue, AttrType::Integer)
+                .with_name("speed");
+        assert_eq!(d.name.as_deref(), Some("speed"));
+        assert_eq!(d.operation, Some(MutatorOperation::SetToValue));
+        assert_eq!(
+            d.params,
+            vec![OwnedMutatorParamDescriptor::new(
+                AttrType::Integer,
+                MutatorOperation::SetToValue.name().to_owned()
+            )
+            .unwrap()]
+        );
+
+        let attrs: HashMap<_, _> = d.get_description_attributes().collect();
+        assert_eq!(
+            attrs.get(&"mutator.params.set_to_value.value_type".into()),
+            Some(&AttrVal::from("Integer"))
+        );
+
+        // Describing the conventional param further replaces it
+        let d = d.with_param(
+            OwnedMutatorParamDescriptor::new(AttrType::Integer, "set_to_value".to_owned())
+                .unwrap()
+                .with_value_max(100),
+        );
+        assert_eq!(d.params.len(), 1);
+        assert_eq!(d.params[0].value_max, Some(AttrVal::from(100)));
+    }
+}
@@ -62,6 +62,12 @@ pub mod mutator {
         async fn reset(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
             self.actuator.reset().await
         }
+
+        async fn refresh_descriptor(
+            &mut self,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            self.actuator.refresh_descriptor().await
+        }
     }
 
     impl<A: MutatorActuator, D: MutatorDescriptor> MutatorDescriptor for CombinedMutator<A, D> {
@@ -79,7 +85,7 @@ pub mod mutator {
 #[cfg(test)]
 mod tests {
     use crate::api::{AttrKey, AttrType, AttrVal};
-    use crate::mutator_protocol::actuator::MutatorActuator;
+    use crate::mutator_protocol::actuator::{ActiveMutationGuard, MutatorActuator};
     use crate::mutator_protocol::descriptor::owned::{
         MutatorOperation, OrganizationCustomMetadata, OwnedMutatorDescriptor,
         OwnedMutatorParamDescriptor,
@@ -88,6 +94,9 @@ mod tests {
     use crate::mutator_protocol::mutator::CombinedMutator;
     use async_trait::async_trait;
     use std::collections::{BTreeMap, HashMap};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
     use uuid::Uuid;
 
     pub struct OwnedValueMutator {
@@ -156,6 +165,65 @@ mod tests {
         }
     }
 
+    #[test]
+    fn mutation_guard_resets_on_drop() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let mutator = Arc::new(Mutex::new(OwnedValueMutator::new(5)));
+        let params = || std::iter::once((AttrKey::from("value"), AttrVal::Integer(7))).collect();
+
+        let reset_done = || async {
+            tokio::time::timeout(Duration::from_secs(5), async {
+                while mutator.lock().await.current() != 5 {
+                    tokio::task::yield_now().await;
+                }
+            })
+            .await
+            .unwrap()
+        };
+
+        // Dropped outside the runtime: the reset is spawned onto the runtime
+        // the guard was created in
+        let guard = rt
+            .block_on(ActiveMutationGuard::inject(
+                mutator.clone(),
+                Uuid::new_v4(),
+                params(),
+            ))
+            .unwrap();
+        assert_eq!(rt.block_on(mutator.lock()).current(), 7);
+        drop(guard);
+        rt.block_on(reset_done());
+
+        // Dropped inside the runtime: the reset is spawned onto it
+        rt.block_on(async {
+            let guard = ActiveMutationGuard::inject(mutator.clone(), Uuid::new_v4(), params())
+                .await
+                .unwrap();
+            drop(guard);
+            reset_done().await;
+        });
+
+        // Reset explicitly: done once it returns
+        rt.block_on(async {
+            let guard = ActiveMutationGuard::inject(mutator.clone(), Uuid::new_v4(), params())
+                .await
+                .unwrap();
+            guard.reset().await.unwrap();
+            assert_eq!(mutator.lock().await.current(), 5);
+        });
+
+        // Disarmed: the mutation stays
+        let guard = rt
+            .block_on(ActiveMutationGuard::inject(
+                mutator.clone(),
+                Uuid::new_v4(),
+                params(),
+            ))
+            .unwrap();
+        guard.disarm();
+        assert_eq!(rt.block_on(mutator.lock()).current(), 7);
+    }
+
     #[tokio::test]
     async fn description_and_actuation_in_one_spot() {
         let ovm = OwnedValueMutator::new(5);
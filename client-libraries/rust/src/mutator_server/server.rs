@@ -1,5 +1,9 @@
+use crate::mutator_protocol::descriptor::owned::{
+    OwnedMutatorDescriptor, ParamDescriptorFromAttrsError,
+};
 use crate::mutator_protocol::mutator::ActuatorDescriptor;
 use crate::mutator_server::MUTATOR_API_KEY_HEADER;
+use crate::reflector_config::{secret::SecretRefError, TopLevelMutation};
 use async_trait::async_trait;
 use axum::{extract::FromRequestParts, http::StatusCode, Router};
 use std::collections::BTreeMap;
@@ -17,20 +21,37 @@ pub async fn serve_mutators(
     required_api_key_value: Option<String>,
     addr: impl Into<SocketAddr>,
     shutdown_signal: impl Future<Output = ()> + Send + 'static,
-) {
+) -> Result<(), InvalidMutatorDescriptorError> {
     let listener = TcpListener::bind(addr.into()).unwrap();
     serve_mutators_on_listener(mutators, required_api_key_value, listener, shutdown_signal).await
 }
 
+/// Serve the mutators on an already-bound listener.
+///
+/// Every mutator's descriptor is checked first, with
+/// [validate_mutator_descriptors]; if any is invalid, nothing is served and
+/// the error is returned.
+///
+/// Descriptors are refreshed before each listing, and checked again. A
+/// mutator whose refreshed descriptor is invalid is retired: it's no longer
+/// listed, and new mutations for it are rejected as if it didn't exist,
+/// though its existing mutations can still be deleted.
 pub async fn serve_mutators_on_listener(
     mutators: BTreeMap<String, Box<dyn ActuatorDescriptor + Send>>,
     required_api_key_value: Option<String>,
     listener: TcpListener,
     shutdown_signal: impl Future<Output = ()> + Send + 'static,
-) {
+) -> Result<(), InvalidMutatorDescriptorError> {
+    validate_mutator_descriptors(&mutators)?;
+
     let store = mutator::Store {
         required_api_key_value,
-        mutators: Arc::new(tokio::sync::Mutex::new(mutators)),
+        mutators: Arc::new(
+            mutators
+                .into_iter()
+                .map(|(k, m)| (k, Arc::new(tokio::sync::Mutex::new(m.into()))))
+                .collect(),
+        ),
     };
 
     let routes = Router::new()
@@ -52,6 +73,83 @@ pub async fn serve_mutators_on_listener(
             "Error running mutator http server"
         );
     }
+    Ok(())
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Mutator '{mutator}' has an invalid descriptor")]
+pub struct InvalidMutatorDescriptorError {
+    pub mutator: String,
+    #[source]
+    pub error: ParamDescriptorFromAttrsError,
+}
+
+/// Check that each mutator's descriptor attributes can be read back as an
+/// [OwnedMutatorDescriptor], i.e. that clients will be able to make sense of
+/// them. Fails on the first mutator (by key) that doesn't.
+///
+/// The same check is made whenever a served mutator's descriptor is
+/// refreshed; a mutator that fails it then is retired, see
+/// [serve_mutators_on_listener].
+pub fn validate_mutator_descriptors(
+    mutators: &BTreeMap<String, Box<dyn ActuatorDescriptor + Send>>,
+) -> Result<(), InvalidMutatorDescriptorError> {
+    for (key, mutator) in mutators {
+        validate_mutator_descriptor(key, mutator.as_ref())?;
+    }
+    Ok(())
+}
+
+fn validate_mutator_descriptor(
+    key: &str,
+    mutator: &(dyn ActuatorDescriptor + Send),
+) -> Result<(), InvalidMutatorDescriptorError> {
+    OwnedMutatorDescriptor::try_from_description_attributes(mutator.get_description_attributes())
+        .map_err(|error| InvalidMutatorDescriptorError {
+        mutator: key.to_owned(),
+        error,
+    })?;
+    Ok(())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ServeMutatorsWithConfigError {
+    #[error("Failed to resolve the mutator HTTP API key")]
+    ApiKey(#[from] SecretRefError),
+
+    #[error(transparent)]
+    InvalidDescriptor(#[from] InvalidMutatorDescriptorError),
+}
+
+/// Like [serve_mutators], but the `mutator_apikey` header must match the
+/// `mutation.mutator-http-api-key` setting, if there is one. A secret
+/// reference is resolved with the built-in schemes.
+pub async fn serve_mutators_with_config(
+    mutators: BTreeMap<String, Box<dyn ActuatorDescriptor + Send>>,
+    mutation_config: &TopLevelMutation,
+    addr: impl Into<SocketAddr>,
+    shutdown_signal: impl Future<Output = ()> + Send + 'static,
+) -> Result<(), ServeMutatorsWithConfigError> {
+    let listener = TcpListener::bind(addr.into()).unwrap();
+    serve_mutators_on_listener_with_config(mutators, mutation_config, listener, shutdown_signal)
+        .await
+}
+
+/// Like [serve_mutators_on_listener], with the API key taken from the config; see
+/// [serve_mutators_with_config].
+pub async fn serve_mutators_on_listener_with_config(
+    mutators: BTreeMap<String, Box<dyn ActuatorDescriptor + Send>>,
+    mutation_config: &TopLevelMutation,
+    listener: TcpListener,
+    shutdown_signal: impl Future<Output = ()> + Send + 'static,
+) -> Result<(), ServeMutatorsWithConfigError> {
+    let required_api_key_value = mutation_config
+        .mutator_http_api_key
+        .as_ref()
+        .map(|k| k.resolve())
+        .transpose()?;
+    serve_mutators_on_listener(mutators, required_api_key_value, listener, shutdown_signal).await?;
+    Ok(())
 }
 
 fn swagger_routes() -> Router {
@@ -95,7 +193,7 @@ pub(crate) mod mutator {
     use axum::routing::{get, post};
     use axum::{Json, Router};
 
-    use super::ValidApiKeyHeader;
+    use super::{validate_mutator_descriptor, ValidApiKeyHeader};
 
     /// See: <https://github.com/seanmonstar/warp/issues/242>.
     mod url_path_part {
@@ -133,10 +231,38 @@ pub(crate) mod mutator {
         }
     }
 
+    pub struct ServedMutator {
+        pub actuator_descriptor: Box<dyn ActuatorDescriptor + Send>,
+        /// Set once a refreshed descriptor fails validation
+        pub retired: bool,
+    }
+
+    impl From<Box<dyn ActuatorDescriptor + Send>> for ServedMutator {
+        fn from(actuator_descriptor: Box<dyn ActuatorDescriptor + Send>) -> Self {
+            Self {
+                actuator_descriptor,
+                retired: false,
+            }
+        }
+    }
+
+    pub type SharedMutator = Arc<tokio::sync::Mutex<ServedMutator>>;
+
+    /// The set of mutators is fixed once serving starts, so only each
+    /// mutator is locked, and a slow one doesn't hold up the others.
     #[derive(Clone)]
     pub struct Store {
         pub required_api_key_value: Option<String>,
-        pub mutators: Arc<tokio::sync::Mutex<BTreeMap<String, Box<dyn ActuatorDescriptor + Send>>>>,
+        pub mutators: Arc<BTreeMap<String, SharedMutator>>,
+    }
+
+    impl Store {
+        fn mutator(&self, mutator_correlation_id: &str) -> Result<SharedMutator, StatusCode> {
+            self.mutators
+                .get(mutator_correlation_id)
+                .cloned()
+                .ok_or(StatusCode::NOT_FOUND)
+        }
     }
 
     pub fn routes() -> Router<Store> {
@@ -163,11 +289,30 @@ pub(crate) mod mutator {
         State(store): State<Store>,
         _h: ValidApiKeyHeader,
     ) -> Result<Json<GetAllMutatorsResponse>, Infallible> {
-        let mutators_map = store.mutators.lock().await;
-
         let mut mutator_components: Vec<Mutator> = vec![];
-        for (corr_id, actuator_descriptor) in mutators_map.iter() {
-            let attr_iter = actuator_descriptor.get_description_attributes();
+        for (corr_id, served) in store.mutators.iter() {
+            let mut served = served.lock().await;
+            if served.retired {
+                continue;
+            }
+            if let Err(err) = served.actuator_descriptor.refresh_descriptor().await {
+                tracing::warn!(
+                    err = err.as_ref() as &dyn std::error::Error,
+                    mutator_correlation_id = %corr_id,
+                    "Failed to refresh mutator descriptor, serving the previous one"
+                );
+            } else if let Err(err) =
+                validate_mutator_descriptor(corr_id, served.actuator_descriptor.as_ref())
+            {
+                tracing::error!(
+                    err = &err as &dyn std::error::Error,
+                    mutator_correlation_id = %corr_id,
+                    "Refreshed mutator descriptor is invalid, retiring the mutator"
+                );
+                served.retired = true;
+                continue;
+            }
+            let attr_iter = served.actuator_descriptor.get_description_attributes();
             mutator_components.push(Mutator {
                 mutator_correlation_id: corr_id.clone(),
                 attributes: attr_iter.collect(),
@@ -203,13 +348,13 @@ pub(crate) mod mutator {
         Json(mutation): Json<Mutation>,
     ) -> Result<StatusCode, StatusCode> {
         tracing::debug!(%mutator_correlation_id);
-        let mut mutators = store.mutators.lock().await;
-
-        let actuator_descriptor = mutators
-            .get_mut(&mutator_correlation_id)
-            .ok_or(StatusCode::NOT_FOUND)?;
+        let mut served = store.mutator(&mutator_correlation_id)?.lock_owned().await;
+        if served.retired {
+            return Err(StatusCode::NOT_FOUND);
+        }
 
-        match actuator_descriptor
+        match served
+            .actuator_descriptor
             .inject(mutation.mutation, mutation.params)
             .await
         {
@@ -247,12 +392,8 @@ pub(crate) mod mutator {
         _h: ValidApiKeyHeader,
     ) -> Result<StatusCode, StatusCode> {
         tracing::debug!(%mutator_correlation_id);
-        let mut mutators = store.mutators.lock().await;
-
-        let actuator_descriptor = mutators
-            .get_mut(&mutator_correlation_id)
-            .ok_or(StatusCode::NOT_FOUND)?;
-        match actuator_descriptor.reset().await {
+        let mut served = store.mutator(&mutator_correlation_id)?.lock_owned().await;
+        match served.actuator_descriptor.reset().await {
             Ok(()) => Ok(StatusCode::OK),
             Err(err) => {
                 tracing::error!(
@@ -309,7 +450,7 @@ mod tests {
     use async_trait::async_trait;
     use std::net::TcpListener;
     use std::str::FromStr;
-    use std::sync::atomic::{AtomicI64, Ordering};
+    use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
     use std::time::Duration;
     use tokio::sync::oneshot::Sender;
     use uuid::Uuid;
@@ -384,7 +525,436 @@ mod tests {
 
         let _ = shutdown_tx.send(());
         let join_res = join_handle.await;
-        assert!(join_res.is_ok());
+        assert!(join_res.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn api_key_from_config() {
+        use crate::reflector_config::ConfigSecret;
+        use std::io::Write;
+
+        let mut key_file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(key_file, "s3cret").unwrap();
+        let mutation_config = TopLevelMutation {
+            mutator_http_api_key: Some(ConfigSecret::Ref(format!(
+                "file:{}",
+                key_file.path().display()
+            ))),
+            ..Default::default()
+        };
+
+        let listener = TcpListener::bind("localhost:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let mut mutators: BTreeMap<_, Box<dyn ActuatorDescriptor + Send + 'static>> =
+            BTreeMap::new();
+        mutators.insert(
+            "abc".to_string(),
+            Box::new(AtomicMutator::new(Arc::new(AtomicI64::new(0)))),
+        );
+        let join_handle = tokio::spawn(async move {
+            serve_mutators_on_listener_with_config(mutators, &mutation_config, listener, async {
+                shutdown_rx.await.ok();
+            })
+            .await
+        });
+
+        let mutator_url = reqwest::Url::from_str(&format!("http://{}/mutator", addr)).unwrap();
+        let client = reqwest::Client::builder().build().unwrap();
+        let get = |key: Option<&'static str>| {
+            let mut req = client.get(mutator_url.clone());
+            if let Some(key) = key {
+                req = req.header(MUTATOR_API_KEY_HEADER, key);
+            }
+            async move { req.send().await.unwrap().status() }
+        };
+
+        assert_eq!(get(Some("s3cret")).await, reqwest::StatusCode::OK);
+        assert_eq!(get(Some("wrong")).await, reqwest::StatusCode::UNAUTHORIZED);
+        assert_eq!(get(None).await, reqwest::StatusCode::BAD_REQUEST);
+
+        let _ = shutdown_tx.send(());
+        assert!(join_handle.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn descriptors_are_refreshed_before_listing() {
+        let listener = TcpListener::bind("localhost:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let device_count = Arc::new(AtomicI64::new(2));
+        let mut mutators: BTreeMap<_, Box<dyn ActuatorDescriptor + Send + 'static>> =
+            BTreeMap::new();
+        mutators.insert(
+            "dev".to_string(),
+            Box::new(DeviceIndexMutator {
+                device_count: device_count.clone(),
+                max_index: 0,
+            }),
+        );
+        let join_handle = tokio::spawn(serve_mutators_on_listener(
+            mutators,
+            None,
+            listener,
+            async {
+                shutdown_rx.await.ok();
+            },
+        ));
+
+        let mutator_url = reqwest::Url::from_str(&format!("http://{}/mutator", addr)).unwrap();
+        let client = reqwest::Client::builder().build().unwrap();
+        let value_max = || async {
+            let mutators: Vec<Mutator> = client
+                .get(mutator_url.clone())
+                .header(MUTATOR_API_KEY_HEADER, "whatever")
+                .send()
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+            mutators[0]
+                .attributes
+                .iter()
+                .find(|(k, _)| k.as_ref().ends_with(".value_max"))
+                .map(|(_, v)| v.clone())
+                .unwrap()
+        };
+
+        assert_eq!(value_max().await, AttrVal::Integer(1));
+        device_count.store(5, Ordering::SeqCst);
+        assert_eq!(value_max().await, AttrVal::Integer(4));
+
+        let _ = shutdown_tx.send(());
+        join_handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn invalid_refreshed_descriptor_retires_the_mutator() {
+        let listener = TcpListener::bind("localhost:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let break_descriptor = Arc::new(AtomicBool::new(false));
+        let mut mutators: BTreeMap<_, Box<dyn ActuatorDescriptor + Send + 'static>> =
+            BTreeMap::new();
+        mutators.insert(
+            "dev".to_string(),
+            Box::new(BreakableDescriptorMutator {
+                break_descriptor: break_descriptor.clone(),
+                broken: false,
+            }),
+        );
+        let join_handle = tokio::spawn(serve_mutators_on_listener(
+            mutators,
+            None,
+            listener,
+            async {
+                shutdown_rx.await.ok();
+            },
+        ));
+
+        let client = reqwest::Client::builder().build().unwrap();
+        let list = || async {
+            client
+                .get(format!("http://{addr}/mutator"))
+                .header(MUTATOR_API_KEY_HEADER, "whatever")
+                .send()
+                .await
+                .unwrap()
+                .json::<Vec<Mutator>>()
+                .await
+                .unwrap()
+        };
+        let mutation_url = format!("http://{addr}/mutator/dev/mutation");
+
+        assert_eq!(list().await.len(), 1);
+
+        break_descriptor.store(true, Ordering::SeqCst);
+        assert!(list().await.is_empty());
+
+        let mutation = Mutation {
+            mutation: Default::default(),
+            params: BTreeMap::new(),
+        };
+        let resp = client
+            .post(&mutation_url)
+            .json(&mutation)
+            .header(MUTATOR_API_KEY_HEADER, "whatever")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::NOT_FOUND);
+
+        let resp = client
+            .delete(&mutation_url)
+            .header(MUTATOR_API_KEY_HEADER, "whatever")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::OK);
+
+        // Stays retired, even once the descriptor would be valid again
+        break_descriptor.store(false, Ordering::SeqCst);
+        assert!(list().await.is_empty());
+
+        let _ = shutdown_tx.send(());
+        join_handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn invalid_descriptor_fails_startup_validation() {
+        use crate::mutator_protocol::mutator::CombinedMutator;
+
+        /// Declares a parameter, but never names it
+        struct UnnamedParamDescriptor;
+        impl MutatorDescriptor for UnnamedParamDescriptor {
+            fn get_description_attributes(
+                &self,
+            ) -> Box<dyn Iterator<Item = (AttrKey, AttrVal)> + '_> {
+                Box::new(
+                    [
+                        (AttrKey::from("mutator.name"), AttrVal::from("bad")),
+                        (
+                            AttrKey::from("mutator.params.x.value_type"),
+                            AttrVal::from("Integer"),
+                        ),
+                    ]
+                    .into_iter(),
+                )
+            }
+        }
+
+        let mut mutators: BTreeMap<_, Box<dyn ActuatorDescriptor + Send + 'static>> =
+            BTreeMap::new();
+        mutators.insert(
+            "good".to_string(),
+            Box::new(AtomicMutator::new(Arc::new(AtomicI64::new(0)))),
+        );
+        assert!(validate_mutator_descriptors(&mutators).is_ok());
+
+        mutators.insert(
+            "oops".to_string(),
+            Box::new(CombinedMutator::new(
+                AtomicMutator::new(Arc::new(AtomicI64::new(0))),
+                UnnamedParamDescriptor,
+            )),
+        );
+        let err = validate_mutator_descriptors(&mutators).unwrap_err();
+        assert_eq!(err.mutator, "oops");
+        assert_eq!(
+            err.error,
+            ParamDescriptorFromAttrsError::MissingParameterNameAttribute
+        );
+
+        // The server refuses to start, rather than waiting for the shutdown signal
+        let listener = TcpListener::bind("localhost:0").unwrap();
+        let err = tokio::time::timeout(
+            Duration::from_secs(5),
+            serve_mutators_on_listener(mutators, None, listener, std::future::pending()),
+        )
+        .await
+        .expect("server should not have started")
+        .unwrap_err();
+        assert_eq!(err.mutator, "oops");
+    }
+
+    #[tokio::test]
+    async fn slow_refresh_does_not_block_other_mutators() {
+        let listener = TcpListener::bind("localhost:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let release = Arc::new(tokio::sync::Notify::new());
+        let fast_state = Arc::new(AtomicI64::new(0));
+        let mut mutators: BTreeMap<_, Box<dyn ActuatorDescriptor + Send + 'static>> =
+            BTreeMap::new();
+        // Listed first, and stalls listing until released
+        mutators.insert(
+            "a_slow".to_string(),
+            Box::new(StalledRefreshMutator {
+                release: release.clone(),
+            }),
+        );
+        mutators.insert(
+            "b_fast".to_string(),
+            Box::new(AtomicMutator::new(fast_state.clone())),
+        );
+        let join_handle = tokio::spawn(serve_mutators_on_listener(
+            mutators,
+            None,
+            listener,
+            async {
+                shutdown_rx.await.ok();
+            },
+        ));
+
+        let client = reqwest::Client::builder().build().unwrap();
+        let list = tokio::spawn(
+            client
+                .get(format!("http://{addr}/mutator"))
+                .header(MUTATOR_API_KEY_HEADER, "whatever")
+                .send(),
+        );
+        // Give the listing time to reach the slow mutator
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!list.is_finished());
+
+        let mutation = Mutation {
+            mutation: Default::default(),
+            params: std::iter::once((
+                AttrKey::from(MutatorOperation::SetToValue.name()),
+                AttrVal::Integer(42),
+            ))
+            .collect(),
+        };
+        let inject = client
+            .post(format!("http://{addr}/mutator/b_fast/mutation"))
+            .json(&mutation)
+            .header(MUTATOR_API_KEY_HEADER, "whatever")
+            .send();
+        let resp = tokio::time::timeout(Duration::from_secs(5), inject)
+            .await
+            .expect("injection should not wait for the slow refresh")
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::CREATED);
+        assert_eq!(fast_state.load(Ordering::SeqCst), 42);
+
+        release.notify_one();
+        let resp = list.await.unwrap().unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::OK);
+
+        let _ = shutdown_tx.send(());
+        join_handle.await.unwrap().unwrap();
+    }
+
+    /// Refreshes its descriptor only once released
+    pub struct StalledRefreshMutator {
+        release: Arc<tokio::sync::Notify>,
+    }
+
+    impl ActuatorDescriptor for StalledRefreshMutator {}
+
+    impl MutatorDescriptor for StalledRefreshMutator {
+        fn get_description_attributes(&self) -> Box<dyn Iterator<Item = (AttrKey, AttrVal)> + '_> {
+            let attrs: Vec<_> = AtomicMutator::description()
+                .get_description_attributes()
+                .collect();
+            Box::new(attrs.into_iter())
+        }
+    }
+
+    #[async_trait]
+    impl MutatorActuator for StalledRefreshMutator {
+        async fn inject(
+            &mut self,
+            _mutation_id: Uuid,
+            _params: BTreeMap<AttrKey, AttrVal>,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            Ok(())
+        }
+
+        async fn reset(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            Ok(())
+        }
+
+        async fn refresh_descriptor(
+            &mut self,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            self.release.notified().await;
+            Ok(())
+        }
+    }
+
+    /// Refreshes to a descriptor with an unnamed parameter once told to
+    pub struct BreakableDescriptorMutator {
+        break_descriptor: Arc<AtomicBool>,
+        broken: bool,
+    }
+
+    impl ActuatorDescriptor for BreakableDescriptorMutator {}
+
+    impl MutatorDescriptor for BreakableDescriptorMutator {
+        fn get_description_attributes(&self) -> Box<dyn Iterator<Item = (AttrKey, AttrVal)> + '_> {
+            let mut attrs: Vec<_> = AtomicMutator::description()
+                .get_description_attributes()
+                .collect();
+            if self.broken {
+                attrs.push((
+                    AttrKey::from("mutator.params.x.value_type"),
+                    AttrVal::from("Integer"),
+                ));
+            }
+            Box::new(attrs.into_iter())
+        }
+    }
+
+    #[async_trait]
+    impl MutatorActuator for BreakableDescriptorMutator {
+        async fn inject(
+            &mut self,
+            _mutation_id: Uuid,
+            _params: BTreeMap<AttrKey, AttrVal>,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            Ok(())
+        }
+
+        async fn reset(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            Ok(())
+        }
+
+        async fn refresh_descriptor(
+            &mut self,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            self.broken = self.break_descriptor.load(Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    /// Selects a device by index; the valid indices depend on how many devices there are
+    pub struct DeviceIndexMutator {
+        device_count: Arc<AtomicI64>,
+        max_index: i64,
+    }
+
+    impl ActuatorDescriptor for DeviceIndexMutator {}
+
+    impl MutatorDescriptor for DeviceIndexMutator {
+        fn get_description_attributes(&self) -> Box<dyn Iterator<Item = (AttrKey, AttrVal)> + '_> {
+            let desc = OwnedMutatorDescriptor {
+                name: Some("device".into()),
+                params: vec![
+                    OwnedMutatorParamDescriptor::builder(AttrType::Integer, "index")
+                        .value_min(0)
+                        .value_max(self.max_index)
+                        .build()
+                        .unwrap(),
+                ],
+                ..Default::default()
+            };
+            let attrs: Vec<_> = desc.get_description_attributes().collect();
+            Box::new(attrs.into_iter())
+        }
+    }
+
+    #[async_trait]
+    impl MutatorActuator for DeviceIndexMutator {
+        async fn inject(
+            &mut self,
+            _mutation_id: Uuid,
+            _params: BTreeMap<AttrKey, AttrVal>,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            Ok(())
+        }
+
+        async fn reset(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            Ok(())
+        }
+
+        async fn refresh_descriptor(
+            &mut self,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            self.max_index = self.device_count.load(Ordering::SeqCst) - 1;
+            Ok(())
+        }
     }
 
     pub struct AtomicMutator {
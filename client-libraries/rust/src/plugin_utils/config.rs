@@ -11,20 +11,24 @@
 //! processed as well:
 //!
 //! * `MODALITY_REFLECTOR_CONFIG` indicates the path to a toml
-//!   formatted reflector config file, which is read if given.
+//!   formatted reflector config file, which is read if given. With
+//!   the `config_http` feature, this may also be an `http://` or
+//!   `https://` URL to fetch the config from.
 //!
 //! * `MODALITY_AUTH_TOKEN` sets the authentication token to use
 //!   for the backend connection. If not given, it is read from
 //!   the user profile directory
 //!
-//! * `MODALITY_CLIENT_TIMEOUT` Backend connection timeout, in
-//!   seconds. Defaults to 1 second if not given.
+//! * `MODALITY_CLIENT_TIMEOUT` Backend connection timeout, either in
+//!   seconds (`1.5`) or with a unit (`250ms`, `2s`). Defaults to 1
+//!   second if not given.
 //!
 //! * `MODALITY_RUN_ID` is attached as the `timeline.run_id` attribute
 //!   to all timelines; a uuid is generated if not given.
 //!
 //! * `MODALITY_TIME_DOMAIN` is attached as the `timeline.time_domain`
-//!   attribtue to all timelines, if given.
+//!   attribtue to all timelines, if given. Time domains are made of
+//!   ASCII letters, digits, and `_ - . :`; see [validate_time_domain].
 //!
 //! * `MODALITY_INGEST_URL`: The modality-ingest connection url
 //!   where the client will try to connect. If not given, falls back to a url formed from
@@ -36,6 +40,19 @@
 //!   via plaintext, but use TLS connections and ports when connecting
 //!   to any other host.
 //!
+//! * `MODALITY_CONFIG_CONFLICT`: What to do when `MODALITY_INGEST_URL`
+//!   disagrees with the config file's `ingest.protocol-parent-url`:
+//!   `override` (the default) uses the environment's value, and `error`
+//!   fails with a [ConfigConflictError] instead, to catch a plugin
+//!   pointed at two different backends.
+//!
+//! * `MODALITY_MAX_EVENTS_PER_SECOND`: Pace events sent by the ingest
+//!   client to at most this many per second. Unlimited if not given.
+//!
+//! * `MODALITY_MAX_EVENT_BURST`: How many events may be sent
+//!   back-to-back before `MODALITY_MAX_EVENTS_PER_SECOND` pacing
+//!   applies. Defaults to 1.
+//!
 //! * `ADDITIONAL_TIMELINE_ATTRIBUTES`: A
 //!   comma-separated list of attr=value pairs, which will be attached
 //!   to all timelines.
@@ -82,22 +99,33 @@ use crate::{
     auth_token::AuthToken,
     ingest_client::IngestClient,
     reflector_config::{
-        AttrKeyEqValuePair, ConfigLoadError, SemanticErrorExplanation, TomlValue, TopLevelIngest,
-        TopLevelMutation, CONFIG_ENV_VAR,
+        duration::parse_duration,
+        secret::{split_secret_ref, ConfigSecret},
+        url_host, AttrKeyEqValuePair, ConfigDiffEntry, ConfigLoadError, SemanticErrorExplanation,
+        TomlValue, TopLevelIngest, TopLevelMutation, CONFIG_ENV_VAR,
     },
 };
-use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde::{
+    de::{DeserializeOwned, IntoDeserializer},
+    Deserialize, Serialize,
+};
 use std::{
     collections::BTreeMap,
     env,
+    num::NonZeroU32,
     path::{Path, PathBuf},
-    str::FromStr as _,
     time::Duration,
 };
 use url::Url;
 
+pub use crate::reflector_config::resolve::PluginKind;
+
 /// Plugin configuration structure; contains both common elements, and
 /// plugin-specific elements, based on the type param `T`.
+///
+/// Build one with one of the `load` functions, or [Config::from_toml_str];
+/// new fields may be added without a breaking release.
+#[non_exhaustive]
 pub struct Config<T> {
     /// Common ingest configuration; mostly connection-related.
     pub ingest: TopLevelIngest,
@@ -108,6 +136,12 @@ pub struct Config<T> {
     /// The plugin-specific portion of the configuration.
     pub plugin: T,
 
+    /// The raw `metadata` table that `plugin` was deserialized from,
+    /// including environment variable overrides and with secret
+    /// references resolved. This is kept whatever `T` is, so it's also
+    /// available from [Config::load_common].
+    pub metadata: BTreeMap<String, TomlValue>,
+
     /// The client connection timeout. This is automatically used when
     /// you call [Config::connect_and_authenticate_ingest].
     pub client_timeout: Option<Duration>,
@@ -124,7 +158,7 @@ pub struct Config<T> {
 #[derive(Deserialize)]
 struct EnvConfig {
     // MODALITY_CLIENT_TIMEOUT Environment variable
-    modality_client_timeout: Option<f32>,
+    modality_client_timeout: Option<String>,
 
     // MODALITY_RUN_ID Environment variable
     modality_run_id: Option<String>,
@@ -137,8 +171,25 @@ impl Config<()> {
     /// Load common config only. This is useful if you're writing a plugin
     /// with no specific configuartion options, or if you're connecting
     /// from a context other than a reflector plugin.
+    ///
+    /// Any `metadata` in the config file is still available as
+    /// [Config::metadata]. Only the built-in secret reference schemes are
+    /// supported; see [Config::load_common_with_secrets].
     pub fn load_common() -> Result<Config<()>, Box<dyn std::error::Error + Send + Sync>> {
-        Self::load_custom("__NONE__", |_, _| Ok(None))
+        Self::load_common_with_secrets(|_, _| Ok(None))
+    }
+
+    /// Load common config only, like [Config::load_common], but with a
+    /// `resolve_secret` hook for secret references, as in
+    /// [Config::load_custom_with_secrets].
+    pub fn load_common_with_secrets(
+        resolve_secret: impl Fn(
+            &str,
+            &str,
+        )
+            -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Result<Config<()>, Box<dyn std::error::Error + Send + Sync>> {
+        Self::load_impl("__NONE__", |_, _| Ok(None), resolve_secret, false)
     }
 }
 
@@ -192,67 +243,148 @@ impl<T: Serialize + DeserializeOwned> Config<T> {
             Box<dyn std::error::Error + Send + Sync>,
         >,
     ) -> Result<Config<T>, Box<dyn std::error::Error + Send + Sync>> {
-        let mut cfg = None;
-
-        // load from MODALITY_REFLECTOR_CONFIG
-        if let Ok(env_path) = env::var(CONFIG_ENV_VAR) {
-            let path = Path::new(&env_path);
-
-            // Look at the file content to determine which section should be used.
-            let content = &std::fs::read_to_string(path)?;
-            let mut raw_toml: crate::reflector_config::raw_toml::Config =
-                toml::from_str(content).map_err(|e| ConfigLoadError::ConfigFileToml {
-                    path: path.to_owned(),
-                    error: e,
-                })?;
-
-            // The 'metadata' entry is set up by the reflector on behalf of whatever plugin it's running,
-            // so prefer it if it's present.
-            if raw_toml.metadata.is_empty() {
-                // if not, find the right plugin section and copy it to the top level
-                copy_relevant_plugin_section_to_top_level_metadata(&mut raw_toml)?;
-            }
+        Self::load_custom_with_secrets(env_prefix, map_env_val, |_, _| Ok(None))
+    }
 
-            let r: Result<crate::reflector_config::Config, SemanticErrorExplanation> =
-                raw_toml.try_into();
-            cfg = Some(r.map_err(|semantics| ConfigLoadError::DefinitionSemantics {
-                explanation: semantics.0,
-            })?);
-        }
+    /// Load configuration, like [Config::load_custom], but also
+    /// allows passing a `resolve_secret` hook.
+    ///
+    /// Plugin metadata values, and the `mutation.mutator-http-api-key`
+    /// setting, may be written as a secret reference instead of
+    /// plaintext, e.g. `password = { secret-ref = "env:DB_PASS" }`.
+    /// Each reference is split into a scheme and the rest at the first
+    /// `:`, and passed to `resolve_secret`. If it returns `Ok(None)`, the
+    /// built-in schemes are tried:
+    ///
+    /// * `env:NAME` reads the `NAME` environment variable.
+    ///
+    /// * `file:PATH` reads the file at `PATH`, without its trailing newline.
+    ///
+    /// Plain values are used as-is. [Config::load] and
+    /// [Config::load_custom] support only the built-in schemes.
+    pub fn load_custom_with_secrets(
+        env_prefix: &str,
+        map_env_val: impl Fn(
+            &str,
+            &str,
+        ) -> Result<
+            Option<(String, TomlValue)>,
+            Box<dyn std::error::Error + Send + Sync>,
+        >,
+        resolve_secret: impl Fn(
+            &str,
+            &str,
+        )
+            -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Result<Config<T>, Box<dyn std::error::Error + Send + Sync>> {
+        Self::load_impl(env_prefix, map_env_val, resolve_secret, true)
+    }
 
-        let cfg = cfg.unwrap_or_default();
+    /// Parse a config from TOML `content` alone, without reading the
+    /// environment: there are no environment overrides or `${VAR}`
+    /// substitutions, and secret references are left unresolved. `plugin` is
+    /// deserialized from the top-level `metadata` table. `run_id` is a fresh
+    /// UUID, and there's no time domain or client timeout.
+    ///
+    /// This is mostly useful for testing a plugin's config type; unlike
+    /// [Config::load], it's unaffected by other tests changing environment
+    /// variables.
+    pub fn from_toml_str(
+        content: &str,
+    ) -> Result<Config<T>, Box<dyn std::error::Error + Send + Sync>> {
+        let raw_toml: crate::reflector_config::raw_toml::Config =
+            toml::from_str(content).map_err(|error| ConfigLoadError::ConfigToml { error })?;
+        let r: Result<crate::reflector_config::Config, SemanticErrorExplanation> =
+            raw_toml.try_into();
+        let cfg = r.map_err(ConfigLoadError::from)?;
+
+        let plugin: T = TomlValue::Table(cfg.metadata.clone().into_iter().collect()).try_into()?;
+        Ok(Config {
+            ingest: cfg.ingest.unwrap_or_default(),
+            mutation: cfg.mutation.unwrap_or_default(),
+            plugin,
+            metadata: cfg.metadata,
+            client_timeout: None,
+            run_id: uuid::Uuid::new_v4().to_string(),
+            time_domain: None,
+        })
+    }
+
+    /// If `plugin_from_metadata` is false, `plugin` is deserialized from `()`
+    /// instead, and no plugin settings are taken from the environment, leaving
+    /// the config file's metadata only in [Config::metadata].
+    fn load_impl(
+        env_prefix: &str,
+        map_env_val: impl Fn(
+            &str,
+            &str,
+        ) -> Result<
+            Option<(String, TomlValue)>,
+            Box<dyn std::error::Error + Send + Sync>,
+        >,
+        resolve_secret: impl Fn(
+            &str,
+            &str,
+        )
+            -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>>,
+        plugin_from_metadata: bool,
+    ) -> Result<Config<T>, Box<dyn std::error::Error + Send + Sync>> {
+        let cfg = load_config_file()?;
 
         let mut ingest = cfg.ingest.clone().unwrap_or_default();
         override_ingest_config_from_env(&mut ingest)?;
 
         let mut mutation = cfg.mutation.clone().unwrap_or_default();
         override_mutation_config_from_env(&mut mutation)?;
+        if let Some(ConfigSecret::Ref(reference)) = &mutation.mutator_http_api_key {
+            mutation.mutator_http_api_key = Some(ConfigSecret::Plain(resolve_secret_ref(
+                reference,
+                &resolve_secret,
+            )?));
+        }
 
         let env_config = envy::from_env::<EnvConfig>()?;
 
         // Load plugin-specific config from the 'metdata' entry
         let mut plugin_toml = cfg.metadata.clone();
-        merge_plugin_config_from_env::<T>(env_prefix, map_env_val, &mut plugin_toml)?;
+        if plugin_from_metadata {
+            merge_plugin_config_from_env::<T>(env_prefix, map_env_val, &mut plugin_toml)?;
+        }
+        for val in plugin_toml.values_mut() {
+            resolve_secret_refs(val, &resolve_secret)?;
+        }
 
         // deserialize from merged toml values to the actual struct
-        let plugin: T = TomlValue::Table(plugin_toml.into_iter().collect()).try_into()?;
+        let plugin: T = if plugin_from_metadata {
+            TomlValue::Table(plugin_toml.clone().into_iter().collect()).try_into()?
+        } else {
+            T::deserialize(IntoDeserializer::<serde::de::value::Error>::into_deserializer(()))?
+        };
 
         // syntheisze a uuid runid if none was given
         let run_id = env_config
             .modality_run_id
             .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
 
+        let time_domain = env_config.modality_time_domain;
+        if let Some(td) = &time_domain {
+            validate_time_domain(td)?;
+        }
+
         let client_timeout = env_config
             .modality_client_timeout
-            .map(Duration::from_secs_f32);
+            .as_deref()
+            .map(parse_duration)
+            .transpose()?;
 
         Ok(Config {
             ingest,
             mutation,
             plugin,
+            metadata: plugin_toml,
             client_timeout,
             run_id,
-            time_domain: env_config.modality_time_domain,
+            time_domain,
         })
     }
 
@@ -274,26 +406,39 @@ impl<T: Serialize + DeserializeOwned> Config<T> {
             Url::parse("modality-ingest://127.0.0.1")?
         };
 
-        // load from MODALITY_AUTH_TOKEN or from the user profile
-        let auth_token = AuthToken::load()?;
-
-        let client = IngestClient::connect_with_timeout(
-            &protocol_parent_url,
-            self.ingest.allow_insecure_tls,
-            self.client_timeout
-                .unwrap_or_else(|| Duration::from_secs(1)),
-        )
-        .await?
-        .authenticate(auth_token.into())
-        .await?;
+        // load from MODALITY_AUTH_TOKEN or from the user profile, once per process
+        let auth_token = AuthToken::load_cached()?;
+
+        let timeout = self
+            .client_timeout
+            .unwrap_or_else(|| Duration::from_secs(1));
+        let mut builder = IngestClient::builder(protocol_parent_url.clone())
+            .allow_insecure_tls(self.ingest.allow_insecure_tls)
+            .timeout(timeout);
+        if let Some(version) = self.ingest.protocol_version {
+            builder = builder.protocol_version(version);
+        }
+        let client = builder
+            .connect()
+            .await?
+            .authenticate(auth_token.as_ref().clone().into())
+            .await?;
 
-        Ok(super::ingest::Client::new(
+        let mut client = super::ingest::Client::new(
             client,
             self.ingest.timeline_attributes.clone(),
             Some(self.run_id.clone()),
             self.time_domain.clone(),
         )
-        .await?)
+        .await?;
+
+        client.set_reconnect_endpoint(protocol_parent_url, self.ingest.allow_insecure_tls, timeout);
+
+        if let Some(rate) = self.ingest.max_events_per_second.and_then(NonZeroU32::new) {
+            client.set_rate_limit(rate, self.ingest.max_event_burst.unwrap_or(1));
+        }
+
+        Ok(client)
     }
 
     /// Connect to the configured Modality backend for mutation,
@@ -307,28 +452,133 @@ impl<T: Serialize + DeserializeOwned> Config<T> {
     ) -> Result<super::mutation::MutatorHost, Box<dyn std::error::Error + Send + Sync>> {
         let ingest = self.connect_and_authenticate_ingest().await?;
 
-        // TODO handle top level mutator_attributes configuration
         let protocol_parent_url = if let Some(url) = &self.mutation.protocol_parent_url {
             url.clone()
         } else {
             Url::parse("modality-mutation://127.0.0.1")?
         };
 
-        // load from MODALITY_AUTH_TOKEN or from the user profile
-        let auth_token = AuthToken::load()?;
+        // shared with the ingest connection
+        let auth_token = AuthToken::load_cached()?;
 
-        let client = super::mutation::MutatorHost::connect_and_authenticate(
+        let mut client = super::mutation::MutatorHost::connect_and_authenticate(
             &protocol_parent_url,
             self.mutation.allow_insecure_tls,
-            auth_token,
+            auth_token.as_ref().clone(),
             Some(ingest),
         )
         .await?;
+        client.add_mutator_attributes(self.mutation.mutator_attributes.clone())?;
 
         Ok(client)
     }
 }
 
+/// Load the config file given in `MODALITY_REFLECTOR_CONFIG`, if any, with
+/// the relevant plugin section copied to the top-level metadata. Without the
+/// environment variable, this is the default config.
+fn load_config_file(
+) -> Result<crate::reflector_config::Config, Box<dyn std::error::Error + Send + Sync>> {
+    let Ok(env_path) = env::var(CONFIG_ENV_VAR) else {
+        return Ok(Default::default());
+    };
+    let path = Path::new(&env_path);
+
+    // Look at the file content to determine which section should be used.
+    let (content, base_dir) = crate::reflector_config::read_config_source(path)?;
+    let mut raw_toml: crate::reflector_config::raw_toml::Config = toml::from_str(&content)
+        .map_err(|e| ConfigLoadError::ConfigFileToml {
+            path: path.to_owned(),
+            error: e,
+        })?;
+    raw_toml.substitute_env_vars()?;
+    raw_toml.load_timeline_attributes_file(base_dir)?;
+    crate::reflector_config::warn_unknown_keys(&raw_toml.unknown_keys());
+
+    // The 'metadata' entry is set up by the reflector on behalf of whatever plugin it's running,
+    // so prefer it if it's present.
+    if raw_toml.metadata.is_empty() {
+        // if not, find the right plugin section and copy it to the top level
+        copy_relevant_plugin_section_to_top_level_metadata(&mut raw_toml)?;
+    }
+
+    let r: Result<crate::reflector_config::Config, SemanticErrorExplanation> = raw_toml.try_into();
+    Ok(r.map_err(ConfigLoadError::from)?)
+}
+
+/// A setting that an environment variable changed from what the config file
+/// says; see [env_override_report].
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvOverride {
+    /// Dotted path of the setting, e.g. `ingest.protocol-parent-url`
+    pub field_path: String,
+    /// The config file's value, if it sets one
+    pub file_value: Option<TomlValue>,
+    /// The value in effect once environment variables are applied
+    pub env_value: TomlValue,
+}
+
+/// Compare the ingest and mutation settings from the config file alone
+/// (given in `MODALITY_REFLECTOR_CONFIG`) with those [Config::load] would
+/// produce once environment variables are applied, and list every setting the
+/// environment changed, in path order. Lists, like the timeline attributes,
+/// are compared as a whole.
+///
+/// This is meant for auditing a plugin's environment; plugin-specific
+/// settings, which depend on the plugin's config type, aren't covered.
+/// Plaintext secrets are redacted, as in [reflector_config::diff](crate::reflector_config::diff).
+pub fn env_override_report() -> Result<Vec<EnvOverride>, Box<dyn std::error::Error + Send + Sync>> {
+    let cfg = load_config_file()?;
+    let file_only = crate::reflector_config::Config {
+        ingest: Some(cfg.ingest.unwrap_or_default()),
+        mutation: Some(cfg.mutation.unwrap_or_default()),
+        ..Default::default()
+    };
+
+    let mut with_env = file_only.clone();
+    override_ingest_config_from_env(with_env.ingest.get_or_insert_with(Default::default))?;
+    override_mutation_config_from_env(with_env.mutation.get_or_insert_with(Default::default))?;
+
+    Ok(crate::reflector_config::diff(&file_only, &with_env)?
+        .entries
+        .into_iter()
+        .filter_map(|entry| match entry {
+            ConfigDiffEntry::Added { path, value } => Some(EnvOverride {
+                field_path: path,
+                file_value: None,
+                env_value: value,
+            }),
+            ConfigDiffEntry::Changed {
+                path,
+                before,
+                after,
+            } => Some(EnvOverride {
+                field_path: path,
+                file_value: Some(before),
+                env_value: after,
+            }),
+            // The environment only ever adds to or replaces file settings
+            ConfigDiffEntry::Removed { .. } => None,
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Invalid time domain '{0}'; time domains must be non-empty, and only contain ASCII letters, digits, '_', '-', '.' or ':'")]
+pub struct InvalidTimeDomainError(pub String);
+
+/// Check that `time_domain` is usable as a `timeline.time_domain`
+/// value. Timelines are only correlated when their time domains match
+/// exactly, so values that are easy to get subtly wrong (e.g. with
+/// stray whitespace from a shell script) are rejected.
+pub fn validate_time_domain(time_domain: &str) -> Result<(), InvalidTimeDomainError> {
+    let valid_char = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | ':');
+    if time_domain.is_empty() || !time_domain.chars().all(valid_char) {
+        return Err(InvalidTimeDomainError(time_domain.to_owned()));
+    }
+    Ok(())
+}
+
 /// We don't have a 'metadata' section, so we might be dealing with a reflector-style config file. Here we pull the confiruation from
 /// one of the 'plugin.*' sections based on sniffing the executable name, and put that data in the 'metadata' section, so the caller
 /// can find it all in that one place.
@@ -423,8 +673,81 @@ fn merge_plugin_config_from_env<T: Serialize + DeserializeOwned>(
     Ok(())
 }
 
+pub use crate::reflector_config::secret::SecretRefError;
+
+/// Replace every `{ secret-ref = "..." }` table in `val` (at any depth) with the secret it
+/// refers to.
+fn resolve_secret_refs(
+    val: &mut TomlValue,
+    resolve_secret: &impl Fn(
+        &str,
+        &str,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if let Some(ConfigSecret::Ref(reference)) = ConfigSecret::from_toml(val) {
+        *val = TomlValue::String(resolve_secret_ref(&reference, resolve_secret)?);
+        return Ok(());
+    }
+
+    match val {
+        TomlValue::Table(table) => {
+            for (_, v) in table.iter_mut() {
+                resolve_secret_refs(v, resolve_secret)?;
+            }
+        }
+        TomlValue::Array(items) => {
+            for v in items.iter_mut() {
+                resolve_secret_refs(v, resolve_secret)?;
+            }
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+fn resolve_secret_ref(
+    reference: &str,
+    resolve_secret: &impl Fn(
+        &str,
+        &str,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>>,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let (scheme, rest) = split_secret_ref(reference)?;
+    if let Some(secret) = resolve_secret(scheme, rest)? {
+        return Ok(secret);
+    }
+
+    Ok(crate::reflector_config::secret::resolve_secret_ref(
+        reference,
+    )?)
+}
+
+/// What to do when an environment variable disagrees with a setting made
+/// in the config file; set by `MODALITY_CONFIG_CONFLICT`.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+enum ConfigConflictPolicy {
+    /// The environment variable wins
+    #[default]
+    Override,
+    /// Fail with a [ConfigConflictError]
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{env_var} is '{env_value}', but the config file sets {setting} to '{file_value}' (and MODALITY_CONFIG_CONFLICT=error)")]
+pub struct ConfigConflictError {
+    pub env_var: &'static str,
+    pub env_value: String,
+    pub setting: &'static str,
+    pub file_value: String,
+}
+
 #[derive(Deserialize)]
 struct IngestEnvOverrides {
+    // MODALITY_CONFIG_CONFLICT environment variable
+    modality_config_conflict: Option<ConfigConflictPolicy>,
+
     // MODALITY_ingest_URL environment variable
     modality_ingest_url: Option<Url>,
 
@@ -437,18 +760,39 @@ struct IngestEnvOverrides {
     // MODALITY_REFLECTOR_PROTOCOL_CHILD_PORT environment variable
     ingest_protocol_child_port: Option<u16>,
 
+    // MODALITY_MAX_EVENTS_PER_SECOND environment variable
+    modality_max_events_per_second: Option<u32>,
+
+    // MODALITY_MAX_EVENT_BURST environment variable
+    modality_max_event_burst: Option<u32>,
+
     // ADDITIONAL_TIMELINE_ATTRIBUTES environment variable
-    additional_timeline_attributes: Option<Vec<String>>,
+    additional_timeline_attributes: Option<String>,
 
     // OVERRIDE_TIMELINE_ATTRIBUTES environment variable
-    override_timeline_attributes: Option<Vec<String>>,
+    override_timeline_attributes: Option<String>,
 }
 
 fn override_ingest_config_from_env(
     ingest: &mut TopLevelIngest,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let ingest_env_overrides = envy::from_env::<IngestEnvOverrides>()?;
+    let conflict_policy = ingest_env_overrides
+        .modality_config_conflict
+        .unwrap_or_default();
     if let Some(u) = ingest_env_overrides.modality_ingest_url {
+        match &ingest.protocol_parent_url {
+            Some(file_url) if *file_url != u && conflict_policy == ConfigConflictPolicy::Error => {
+                return Err(ConfigConflictError {
+                    env_var: "MODALITY_INGEST_URL",
+                    env_value: u.to_string(),
+                    setting: "ingest.protocol-parent-url",
+                    file_value: file_url.to_string(),
+                }
+                .into());
+            }
+            _ => (),
+        }
         ingest.protocol_parent_url = Some(u);
     } else if ingest.protocol_parent_url.is_none() {
         if let Some(host) = ingest_env_overrides.modality_host {
@@ -457,8 +801,10 @@ fn override_ingest_config_from_env(
             } else {
                 "modality-ingest-tls"
             };
-            ingest.protocol_parent_url =
-                Some(url::Url::parse(&format!("{scheme}://{host}")).map_err(|e| e.to_string())?);
+            ingest.protocol_parent_url = Some(
+                url::Url::parse(&format!("{scheme}://{}", url_host(&host)))
+                    .map_err(|e| e.to_string())?,
+            );
         }
     }
     if let Some(b) = ingest_env_overrides.modality_allow_insecure_tls {
@@ -467,25 +813,27 @@ fn override_ingest_config_from_env(
     if let Some(p) = ingest_env_overrides.ingest_protocol_child_port {
         ingest.protocol_child_port = Some(p);
     }
+    if let Some(r) = ingest_env_overrides.modality_max_events_per_second {
+        ingest.max_events_per_second = Some(r);
+    }
+    if let Some(b) = ingest_env_overrides.modality_max_event_burst {
+        ingest.max_event_burst = Some(b);
+    }
 
-    if let Some(strs) = ingest_env_overrides.additional_timeline_attributes {
-        for s in strs {
-            let kvp = AttrKeyEqValuePair::from_str(&s)?;
-            ingest
-                .timeline_attributes
-                .additional_timeline_attributes
-                .push(kvp);
-        }
+    if let Some(s) = ingest_env_overrides.additional_timeline_attributes {
+        let kvps = AttrKeyEqValuePair::parse_many(&s, ',').map_err(|(_, e)| e)?;
+        ingest
+            .timeline_attributes
+            .additional_timeline_attributes
+            .extend(kvps);
     }
 
-    if let Some(strs) = ingest_env_overrides.override_timeline_attributes {
-        for s in strs {
-            let kvp = AttrKeyEqValuePair::from_str(&s)?;
-            ingest
-                .timeline_attributes
-                .override_timeline_attributes
-                .push(kvp);
-        }
+    if let Some(s) = ingest_env_overrides.override_timeline_attributes {
+        let kvps = AttrKeyEqValuePair::parse_many(&s, ',').map_err(|(_, e)| e)?;
+        ingest
+            .timeline_attributes
+            .override_timeline_attributes
+            .extend(kvps);
     }
 
     Ok(())
@@ -505,8 +853,8 @@ struct MutationEnvOverrides {
     // MODALITY_ALLOW_INSECURE_TLS environment variable
     modality_allow_insecure_tls: Option<bool>,
 
-    // ADDITIONAL_TIMELINE_ATTRIBUTES environment variable
-    additional_mutator_attributes: Option<Vec<String>>,
+    // ADDITIONAL_MUTATOR_ATTRIBUTES environment variable
+    additional_mutator_attributes: Option<String>,
 }
 
 fn override_mutation_config_from_env(
@@ -524,6 +872,7 @@ fn override_mutation_config_from_env(
         let host = u
             .host()
             .ok_or_else(|| "Ingest url must have a host component".to_string())?;
+        // url::Host displays IPv6 addresses in brackets already
         mutation.protocol_parent_url =
             Some(url::Url::parse(&format!("{scheme}://{host}")).map_err(|e| e.to_string())?);
     } else if mutation.protocol_parent_url.is_none() {
@@ -533,22 +882,22 @@ fn override_mutation_config_from_env(
             } else {
                 "modality-mutation-tls"
             };
-            mutation.protocol_parent_url =
-                Some(url::Url::parse(&format!("{scheme}://{host}")).map_err(|e| e.to_string())?);
+            mutation.protocol_parent_url = Some(
+                url::Url::parse(&format!("{scheme}://{}", url_host(&host)))
+                    .map_err(|e| e.to_string())?,
+            );
         }
     }
     if let Some(b) = mutation_env_overrides.modality_allow_insecure_tls {
         mutation.allow_insecure_tls = b;
     }
 
-    if let Some(strs) = mutation_env_overrides.additional_mutator_attributes {
-        for s in strs {
-            let kvp = AttrKeyEqValuePair::from_str(&s)?;
-            mutation
-                .mutator_attributes
-                .additional_mutator_attributes
-                .push(kvp);
-        }
+    if let Some(s) = mutation_env_overrides.additional_mutator_attributes {
+        let kvps = AttrKeyEqValuePair::parse_many(&s, ',').map_err(|(_, e)| e)?;
+        mutation
+            .mutator_attributes
+            .additional_mutator_attributes
+            .extend(kvps);
     }
 
     Ok(())
@@ -563,7 +912,7 @@ fn override_mutation_config_from_env(
 ///
 /// Also supports no postfix at all, since kind is implied by the plugin directory it lives in.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
-struct AliasablePluginFileStem {
+pub struct AliasablePluginFileStem {
     filename: String,
     path: PathBuf,
 }
@@ -628,7 +977,6 @@ impl AliasablePluginFileStem {
                 .unwrap_or(false)
     }
 
-    #[allow(unused)]
     pub fn looks_like_mutator(&self) -> bool {
         self.filename.ends_with("-mutator")
             || self.filename.ends_with("-mutators")
@@ -645,6 +993,23 @@ impl AliasablePluginFileStem {
     }
 }
 
+impl PluginKind {
+    /// The kind of plugin `stem` looks like, going by its name and directory; see
+    /// [AliasablePluginFileStem]. Where it looks like more than one, collector
+    /// wins over importer, and importer over mutator.
+    pub fn detect(stem: &AliasablePluginFileStem) -> Option<PluginKind> {
+        if stem.looks_like_collector() {
+            Some(PluginKind::Collector)
+        } else if stem.looks_like_importer() {
+            Some(PluginKind::Importer)
+        } else if stem.looks_like_mutator() {
+            Some(PluginKind::Mutator)
+        } else {
+            None
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -674,6 +1039,33 @@ mod tests {
         check_alias("/dir/foo", "foo");
     }
 
+    #[test]
+    fn plugin_kind_detection() {
+        let kind = |p| PluginKind::detect(&apfs(p));
+        for p in [
+            "/dir/foo-import",
+            "/dir/foo-importer",
+            "/dir/foo-importers",
+            "/dir/importers/foo",
+        ] {
+            assert_eq!(kind(p), Some(PluginKind::Importer), "{p}");
+        }
+        for p in [
+            "/dir/foo-collector",
+            "/dir/foo-collectors",
+            "/dir/collectors/foo",
+            "/dir/importers/foo-collector",
+        ] {
+            assert_eq!(kind(p), Some(PluginKind::Collector), "{p}");
+        }
+        for p in ["/dir/foo-mutator", "/dir/foo-mutators", "/dir/mutators/foo"] {
+            assert_eq!(kind(p), Some(PluginKind::Mutator), "{p}");
+        }
+        for p in ["/dir/foo", "/dir/foo-importerx", "/dir/plugins/foo"] {
+            assert_eq!(kind(p), None, "{p}");
+        }
+    }
+
     #[test]
     fn type_heuristics() {
         assert!(apfs("/dir/foo-import").looks_like_importer());
@@ -719,6 +1111,7 @@ mod tests {
         env::remove_var("ADDITIONAL_TIMELINE_ATTRIBUTES");
         env::remove_var("OVERRIDE_TIMELINE_ATTRIBUTES");
         env::remove_var("MODALITY_ALLOW_INSECURE_TLS");
+        env::remove_var("MODALITY_CONFIG_CONFLICT");
     }
 
     #[test]
@@ -745,6 +1138,14 @@ mod tests {
         env::set_var("MODALITY_CLIENT_TIMEOUT", "42");
         let cfg = Config::<CustomConfig>::load("TEST_").unwrap();
         assert_eq!(cfg.client_timeout, Some(Duration::from_secs(42)));
+        env::set_var("MODALITY_CLIENT_TIMEOUT", "1.5");
+        let cfg = Config::<CustomConfig>::load("TEST_").unwrap();
+        assert_eq!(cfg.client_timeout, Some(Duration::from_millis(1500)));
+        env::set_var("MODALITY_CLIENT_TIMEOUT", "250ms");
+        let cfg = Config::<CustomConfig>::load("TEST_").unwrap();
+        assert_eq!(cfg.client_timeout, Some(Duration::from_millis(250)));
+        env::set_var("MODALITY_CLIENT_TIMEOUT", "soon");
+        assert!(Config::<CustomConfig>::load("TEST_").is_err());
         env::remove_var("MODALITY_CLIENT_TIMEOUT");
 
         // Load run id from the environment
@@ -757,6 +1158,15 @@ mod tests {
         env::set_var("MODALITY_TIME_DOMAIN", "42");
         let cfg = Config::<CustomConfig>::load("TEST_").unwrap();
         assert_eq!(cfg.time_domain.unwrap(), "42");
+        env::set_var("MODALITY_TIME_DOMAIN", "lab-rig.3:clock_a");
+        let cfg = Config::<CustomConfig>::load("TEST_").unwrap();
+        assert_eq!(cfg.time_domain.unwrap(), "lab-rig.3:clock_a");
+        env::set_var("MODALITY_TIME_DOMAIN", "lab rig ");
+        let err = Config::<CustomConfig>::load("TEST_").err().unwrap();
+        assert_eq!(
+            err.downcast_ref::<InvalidTimeDomainError>(),
+            Some(&InvalidTimeDomainError("lab rig ".to_owned()))
+        );
         env::remove_var("MODALITY_TIME_DOMAIN");
 
         // Load reflector protocol parent url from the environment
@@ -915,6 +1325,140 @@ val = 42
         clear_relevant_env_vars();
     }
 
+    #[test]
+    fn config_from_toml_str() {
+        let content = "
+[ingest]
+protocol-parent-url = 'modality-ingest://auxon.io:9077'
+
+[metadata]
+val = 42
+name = '${MODALITY_HOST}'
+";
+        let cfg = Config::<CustomConfig>::from_toml_str(content).unwrap();
+        assert_eq!(cfg.plugin.val, Some(42));
+        assert_eq!(
+            cfg.ingest.protocol_parent_url,
+            Url::parse("modality-ingest://auxon.io:9077").ok()
+        );
+        // Left as written, rather than substituted from the environment
+        assert_eq!(
+            cfg.metadata.get("name"),
+            Some(&TomlValue::String("${MODALITY_HOST}".to_owned()))
+        );
+        assert_eq!(cfg.client_timeout, None);
+        assert_eq!(cfg.time_domain, None);
+
+        assert!(Config::<CustomConfig>::from_toml_str("[metadata]\nval = 'x'").is_err());
+        assert!(Config::<CustomConfig>::from_toml_str("[ingest").is_err());
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn load_common_keeps_metadata() {
+        clear_relevant_env_vars();
+
+        let content = "
+[metadata]
+val = 42
+nested = { name = 'foo' }
+";
+        let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+        write!(tmpfile, "{content}").unwrap();
+
+        env::set_var("MODALITY_REFLECTOR_CONFIG", tmpfile.path());
+        let cfg = Config::<()>::load_common();
+        env::remove_var("MODALITY_REFLECTOR_CONFIG");
+        let cfg = cfg.unwrap();
+
+        assert_eq!(
+            cfg.metadata.keys().collect::<Vec<_>>(),
+            vec!["nested", "val"]
+        );
+        assert_eq!(cfg.metadata["val"], TomlValue::Integer(42));
+        assert_eq!(
+            cfg.metadata["nested"].get("name"),
+            Some(&TomlValue::String("foo".to_owned()))
+        );
+
+        clear_relevant_env_vars();
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct SecretConfig {
+        db_password: Option<String>,
+        api_key: Option<String>,
+        vault_token: Option<String>,
+        plain: Option<String>,
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn metadata_secret_refs() {
+        clear_relevant_env_vars();
+
+        let mut secret_file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(secret_file, "file-secret").unwrap();
+
+        let content = format!(
+            r#"
+[mutation]
+mutator-http-api-key = {{ secret-ref = "vault:mutator/key" }}
+
+[metadata]
+db_password = {{ secret-ref = "env:TEST_DB_PASS" }}
+api_key = {{ secret-ref = "file:{}" }}
+vault_token = {{ secret-ref = "vault:kv/token" }}
+plain = "not-a-secret"
+"#,
+            secret_file.path().display()
+        );
+        let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+        write!(tmpfile, "{content}").unwrap();
+
+        env::set_var("MODALITY_REFLECTOR_CONFIG", tmpfile.path());
+        env::set_var("TEST_DB_PASS", "env-secret");
+
+        let resolve_vault = |scheme: &str, reference: &str| match scheme {
+            "vault" => Ok(Some(format!("vault-secret-for-{reference}"))),
+            _ => Ok(None),
+        };
+
+        // The built-in schemes don't know about 'vault'
+        assert!(Config::<SecretConfig>::load("TEST_").is_err());
+        assert!(Config::<()>::load_common().is_err());
+
+        let common = Config::<()>::load_common_with_secrets(resolve_vault).unwrap();
+        let cfg = Config::<SecretConfig>::load_custom_with_secrets(
+            "TEST_",
+            |_, _| Ok(None),
+            resolve_vault,
+        )
+        .unwrap();
+
+        env::remove_var("MODALITY_REFLECTOR_CONFIG");
+        env::remove_var("TEST_DB_PASS");
+        clear_relevant_env_vars();
+
+        assert_eq!(cfg.plugin.db_password.as_deref(), Some("env-secret"));
+        assert_eq!(cfg.plugin.api_key.as_deref(), Some("file-secret"));
+        assert_eq!(
+            cfg.plugin.vault_token.as_deref(),
+            Some("vault-secret-for-kv/token")
+        );
+        assert_eq!(cfg.plugin.plain.as_deref(), Some("not-a-secret"));
+        assert_eq!(
+            common.metadata["vault_token"],
+            TomlValue::String("vault-secret-for-kv/token".to_owned())
+        );
+        assert_eq!(
+            cfg.mutation.mutator_http_api_key,
+            Some(ConfigSecret::Plain(
+                "vault-secret-for-mutator/key".to_owned()
+            ))
+        );
+    }
+
     #[test]
     #[serial_test::serial]
     fn named_ingest_metadata_section_from_config_file() {
@@ -1009,4 +1553,169 @@ val = 42
         env::remove_var("TEST_VAL");
         clear_relevant_env_vars();
     }
+
+    #[test]
+    #[serial_test::serial]
+    fn env_override_report_lists_deltas() {
+        clear_relevant_env_vars();
+
+        let content = "
+[ingest]
+protocol-parent-url = 'modality-ingest-tls://auxon.io:9077'
+allow-insecure-tls = true
+";
+        let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+        write!(tmpfile, "{content}").unwrap();
+        env::set_var("MODALITY_REFLECTOR_CONFIG", tmpfile.path());
+        assert_eq!(env_override_report().unwrap(), vec![]);
+
+        env::set_var("MODALITY_INGEST_URL", "modality-ingest://foo");
+        env::set_var("MODALITY_ALLOW_INSECURE_TLS", "true");
+        assert_eq!(
+            env_override_report().unwrap(),
+            vec![
+                EnvOverride {
+                    field_path: "ingest.protocol-parent-url".to_owned(),
+                    file_value: Some("modality-ingest-tls://auxon.io:9077".into()),
+                    env_value: "modality-ingest://foo".into(),
+                },
+                EnvOverride {
+                    field_path: "mutation.allow-insecure-tls".to_owned(),
+                    file_value: None,
+                    env_value: true.into(),
+                },
+                EnvOverride {
+                    field_path: "mutation.protocol-parent-url".to_owned(),
+                    file_value: None,
+                    env_value: "modality-mutation://foo".into(),
+                },
+            ]
+        );
+
+        clear_relevant_env_vars();
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn ipv6_modality_host() {
+        clear_relevant_env_vars();
+
+        for (host, ingest_url, mutation_url) in [
+            (
+                "::1",
+                "modality-ingest-tls://[::1]",
+                "modality-mutation-tls://[::1]",
+            ),
+            (
+                "2001:db8::1",
+                "modality-ingest-tls://[2001:db8::1]",
+                "modality-mutation-tls://[2001:db8::1]",
+            ),
+        ] {
+            env::set_var("MODALITY_HOST", host);
+            let cfg = Config::<()>::load_common().unwrap();
+            let url = cfg.ingest.protocol_parent_url.unwrap();
+            assert_eq!(url, Url::parse(ingest_url).unwrap());
+            assert_eq!(url.host(), Some(url::Host::Ipv6(host.parse().unwrap())));
+            assert_eq!(
+                cfg.mutation.protocol_parent_url,
+                Url::parse(mutation_url).ok()
+            );
+        }
+
+        // Mutation urls derived from an IPv6 ingest url keep the brackets
+        env::remove_var("MODALITY_HOST");
+        env::set_var("MODALITY_INGEST_URL", "modality-ingest://[::1]:14182");
+        let cfg = Config::<()>::load_common().unwrap();
+        assert_eq!(
+            cfg.mutation.protocol_parent_url,
+            Url::parse("modality-mutation://[::1]").ok()
+        );
+
+        clear_relevant_env_vars();
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn strict_config_conflicts() {
+        clear_relevant_env_vars();
+
+        let content = "
+[ingest]
+protocol-parent-url = 'modality-ingest-tls://auxon.io:9077'
+";
+        let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+        write!(tmpfile, "{content}").unwrap();
+        env::set_var("MODALITY_REFLECTOR_CONFIG", tmpfile.path());
+        env::set_var("MODALITY_INGEST_URL", "modality-ingest://foo");
+
+        // Lenient by default
+        let cfg = Config::<()>::load_common().unwrap();
+        assert_eq!(
+            cfg.ingest.protocol_parent_url,
+            Url::parse("modality-ingest://foo").ok()
+        );
+
+        env::set_var("MODALITY_CONFIG_CONFLICT", "error");
+        let err = Config::<()>::load_common().err().unwrap();
+        assert_eq!(
+            err.downcast_ref::<ConfigConflictError>(),
+            Some(&ConfigConflictError {
+                env_var: "MODALITY_INGEST_URL",
+                env_value: "modality-ingest://foo".to_owned(),
+                setting: "ingest.protocol-parent-url",
+                file_value: "modality-ingest-tls://auxon.io:9077".to_owned(),
+            })
+        );
+
+        // Agreeing values are fine
+        env::set_var("MODALITY_INGEST_URL", "modality-ingest-tls://auxon.io:9077");
+        assert!(Config::<()>::load_common().is_ok());
+
+        clear_relevant_env_vars();
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn reconnect_reloads_auth_token() {
+        use crate::auth_token::{token_user_file::*, AuthToken, MODALITY_AUTH_TOKEN_ENV_VAR};
+        use crate::test_fixtures::FakeIngestServer;
+
+        let context_dir = tempfile::tempdir().unwrap();
+        let token_path = context_dir.path().join(USER_AUTH_TOKEN_FILE_NAME);
+        env::remove_var(MODALITY_AUTH_TOKEN_ENV_VAR);
+        env::set_var("MODALITY_CONTEXT_DIR", context_dir.path());
+        AuthToken::invalidate_cached();
+
+        let mut server = FakeIngestServer::start().await;
+        let cfg = Config {
+            ingest: TopLevelIngest {
+                protocol_parent_url: Some(server.url.clone()),
+                ..Default::default()
+            },
+            mutation: Default::default(),
+            plugin: (),
+            metadata: Default::default(),
+            client_timeout: None,
+            run_id: "run".to_owned(),
+            time_domain: None,
+        };
+
+        write_user_auth_token_file(&token_path, AuthToken::from(vec![1, 2, 3])).unwrap();
+        let mut client = cfg.connect_and_authenticate_ingest().await.unwrap();
+        assert_eq!(server.next_token().await, vec![1, 2, 3]);
+
+        // Rotate the token, then force a reconnect
+        write_user_auth_token_file(&token_path, AuthToken::from(vec![4, 5, 6])).unwrap();
+        client.reconnect().await.unwrap();
+        assert_eq!(server.next_token().await, vec![4, 5, 6]);
+        // and refreshed the cache for other connections
+        assert_eq!(
+            *AuthToken::load_cached().unwrap(),
+            AuthToken::from(vec![4, 5, 6])
+        );
+
+        env::remove_var("MODALITY_CONTEXT_DIR");
+        AuthToken::invalidate_cached();
+    }
 }
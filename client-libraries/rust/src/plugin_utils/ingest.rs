@@ -1,12 +1,31 @@
 use crate::{
-    api::{AttrVal, Nanoseconds, TimelineId},
+    api::{AttrKey, AttrVal, Nanoseconds, TimelineId},
+    auth_token::{AuthToken, LoadAuthTokenError},
     ingest_client::{
+        archive::{upload_archive, ArchiveError},
         dynamic::{DynamicIngestClient, DynamicIngestError},
-        IngestClient, IngestStatus, ReadyState,
+        IngestClient, IngestClientInitializationError, IngestError, IngestStatus, ReadyState,
     },
     ingest_protocol::InternedAttrKey,
+    reflector_config::{attr_val::TomlAttrValError, TimelineAttributes, TomlValue},
 };
-use std::{collections::BTreeMap, time::SystemTime};
+use std::{
+    collections::BTreeMap,
+    num::NonZeroU32,
+    ops::{Deref, DerefMut},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+use tokio::{
+    sync::{oneshot, Mutex},
+    task::JoinHandle,
+    time::{Instant, Interval, MissedTickBehavior},
+};
+use url::Url;
 
 // for backwards compatibility
 pub use super::config::Config;
@@ -21,20 +40,221 @@ pub use super::config::Config;
 ///   - Sets `timeline.run_id` and `timeline.time_domain`
 ///
 ///   - Correctly applies `additional_timeline_attributes` anad
-///     `override_timeline_attributes`
+///     `override_timeline_attributes`, expanding the placeholders in
+///     their string values; see [Client::new].
 ///
 /// - Automatically sets `event.timestamp` if it's not given manually.
+///
+/// - Optionally numbers the events on each timeline; see
+///   [Client::enable_auto_sequence].
+///
+/// - Optionally paces events to a maximum rate; see [Client::set_rate_limit].
+///
+/// - Optionally caps the number of attributes per event, and the length of
+///   string attribute values; see [Client::set_max_attrs_per_event] and
+///   [Client::set_max_string_attr_len].
+///
+/// - Can signal when the server is falling behind, so callers can slow
+///   down; see [Client::set_backpressure_limit].
+///
+/// - Can re-establish its connection with a freshly loaded auth token; see
+///   [Client::reconnect].
+///
+/// - Optionally attaches the config's `metadata` entries to timelines; see
+///   [Client::set_metadata_timeline_attrs].
+///
+/// - Optionally sends a heartbeat event at a regular interval, so a stalled
+///   plugin can be told apart from an idle one; see [Client::start_heartbeat].
+///
+/// - Optionally checks that an idle connection is still alive, and
+///   reconnects if it isn't; see [Client::set_idle_ping].
 pub struct Client {
-    inner: DynamicIngestClient,
+    /// Shared with the heartbeat and idle ping tasks, if any
+    inner: Arc<Mutex<DynamicIngestClient>>,
+    /// Shared with the idle ping task, if any
+    activity: Arc<ConnectionActivity>,
+    peer_addr: Option<std::net::SocketAddr>,
+    endpoint: Option<ReconnectEndpoint>,
+    current_timeline: Option<TimelineId>,
     run_id: Option<String>,
     time_domain: Option<String>,
+    timeline_attr_cfg: TimelineAttributes,
 
     timeline_keys: BTreeMap<String, InternedAttrKey>,
     event_keys: BTreeMap<String, InternedAttrKey>,
 
     additional_timeline_attributes: Vec<(InternedAttrKey, AttrVal)>,
     override_timeline_attributes: Vec<(InternedAttrKey, AttrVal)>,
+    /// `timeline.metadata.*` attrs, from [Client::set_metadata_timeline_attrs]
+    metadata_timeline_attributes: Vec<(String, AttrVal)>,
     enable_auto_timestamp: bool,
+    /// The next `event.internal.sequence` for each timeline, if enabled
+    auto_sequence: Option<BTreeMap<TimelineId, u64>>,
+    rate_limiter: Option<RateLimiter>,
+    max_attrs_per_event: Option<(usize, AttrLimitPolicy)>,
+    max_string_attr_len: Option<(usize, AttrLimitPolicy)>,
+    backpressure_limit: Option<u64>,
+    unwritten_events: u64,
+    heartbeat: Option<HeartbeatTask>,
+    idle_ping: Option<IdlePingTask>,
+}
+
+/// What [Client::send_event] does with an event that goes over a limit set
+/// by [Client::set_max_attrs_per_event] or [Client::set_max_string_attr_len].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AttrLimitPolicy {
+    /// Send the event cut down to fit the limit, and log a warning.
+    #[default]
+    Truncate,
+
+    /// Don't send the event; return [DynamicIngestError::TooManyAttrs] or
+    /// [DynamicIngestError::StringAttrTooLong].
+    Error,
+}
+
+/// Appended to string attribute values cut short by [Client::set_max_string_attr_len]
+pub const TRUNCATED_STRING_MARKER: &str = "…";
+
+/// Suffix of the attribute key marking that a string attribute was cut short
+/// by [Client::set_max_string_attr_len]
+pub const TRUNCATED_ATTR_KEY_SUFFIX: &str = ".truncated";
+
+/// Suffix of the attribute key holding the unit of a measurement attribute,
+/// e.g. `event.voltage.unit = "V"` alongside `event.voltage = 3.3`; see
+/// [Client::send_measurement]
+pub const UNIT_ATTR_KEY_SUFFIX: &str = ".unit";
+
+/// Which of the attributes given to [Client::send_timeline_attrs] were sent.
+/// Keys are normalized, i.e. start with `timeline.`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TimelineAttrsReport {
+    pub accepted: Vec<String>,
+    pub rejected: Vec<(String, TimelineAttrRejection)>,
+}
+
+impl TimelineAttrsReport {
+    pub fn all_accepted(&self) -> bool {
+        self.rejected.is_empty()
+    }
+}
+
+/// Why [Client::send_timeline_attrs] left out an attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelineAttrRejection {
+    /// The attribute is [reserved](AttrKey::is_reserved); the client sets it
+    /// itself, or leaves it to Modality
+    Reserved,
+
+    /// The attribute is set by `override_timeline_attributes` in the config
+    Overridden,
+}
+
+/// A periodic event sent by [Client::start_heartbeat_with]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heartbeat {
+    interval: Duration,
+    event_name: String,
+    timeline: Option<TimelineId>,
+    attrs: Vec<(String, AttrVal)>,
+}
+
+impl Heartbeat {
+    /// Send an event named `event_name` every `interval`, on a timeline of
+    /// its own; see [Client::start_heartbeat_with].
+    ///
+    /// Panics if `interval` is zero.
+    pub fn new(interval: Duration, event_name: impl Into<String>) -> Self {
+        assert!(!interval.is_zero(), "Heartbeat interval must be non-zero");
+        Heartbeat {
+            interval,
+            event_name: event_name.into(),
+            timeline: None,
+            attrs: Vec::new(),
+        }
+    }
+
+    /// Send heartbeats on `timeline`, rather than on one allocated for them.
+    /// Nothing else should be sent on it, since heartbeats are ordered by
+    /// their own counter.
+    pub fn with_timeline(mut self, timeline: TimelineId) -> Self {
+        self.timeline = Some(timeline);
+        self
+    }
+
+    /// Add an attribute to every heartbeat event. As with [Client::send_event],
+    /// prepending "event." to `key` is optional.
+    pub fn with_attr(mut self, key: impl Into<String>, value: impl Into<AttrVal>) -> Self {
+        self.attrs.push((key.into(), value.into()));
+        self
+    }
+}
+
+struct HeartbeatTask {
+    heartbeat: Heartbeat,
+    /// Where heartbeats go: the heartbeat's own timeline, or one allocated for it
+    timeline: TimelineId,
+    /// The ordering of the next heartbeat, carried over when restarted on a
+    /// new connection
+    next_ordering: Arc<AtomicU64>,
+    /// Dropping this stops the task
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+struct IdlePingTask {
+    idle_timeout: Duration,
+    /// Dropping this stops the task
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+/// When a [Client] last used its connection, and whether an idle ping found
+/// it dead
+struct ConnectionActivity {
+    last_write: std::sync::Mutex<Instant>,
+    lost: AtomicBool,
+}
+
+impl ConnectionActivity {
+    fn new() -> Self {
+        ConnectionActivity {
+            last_write: std::sync::Mutex::new(Instant::now()),
+            lost: AtomicBool::new(false),
+        }
+    }
+
+    fn touch(&self) {
+        *self.last_write.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+    }
+
+    fn last_write(&self) -> Instant {
+        *self.last_write.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Where a [Client] reconnects to
+struct ReconnectEndpoint {
+    url: Url,
+    allow_insecure_tls: bool,
+    timeout: Duration,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReconnectError {
+    #[error("No endpoint to reconnect to; see Client::set_reconnect_endpoint")]
+    NoEndpoint,
+
+    #[error("Failed to load the auth token")]
+    AuthToken(#[from] LoadAuthTokenError),
+
+    #[error(transparent)]
+    Connect(#[from] IngestClientInitializationError),
+
+    #[error(transparent)]
+    Ingest(#[from] IngestError),
+
+    #[error(transparent)]
+    DynamicIngest(#[from] DynamicIngestError),
 }
 
 impl Client {
@@ -46,7 +266,16 @@ impl Client {
     ///
     /// * `timeline_attr_cfg`: Configuration structure from the
     ///   reflector config which contains additional / ovverriding
-    ///   timeline attributes.
+    ///   timeline attributes. These placeholders are expanded in
+    ///   string values, once, when the client is created:
+    ///
+    ///   - `{hostname}`: The name of the host this process runs on
+    ///   - `{pid}`: The id of this process
+    ///   - `{plugin}`: The file name of this process's executable
+    ///   - `{run_id}`: The `run_id` given here; left as-is if there is none
+    ///
+    ///   This is separate from (and happens after) environment variable
+    ///   substitution in the config file. Other text in braces is left alone.
     ///
     /// * `run_id`: If given, `timeline.run_id` will be set to this
     ///   value for all emitted timelines.
@@ -60,27 +289,210 @@ impl Client {
         time_domain: Option<String>,
     ) -> Result<Self, DynamicIngestError> {
         let mut client = Self {
-            inner: client.into(),
+            peer_addr: client.peer_addr(),
+            inner: Arc::new(Mutex::new(client.into())),
+            activity: Arc::new(ConnectionActivity::new()),
+            endpoint: None,
+            current_timeline: None,
             run_id,
             time_domain,
+            timeline_attr_cfg: Default::default(),
             timeline_keys: Default::default(),
             event_keys: Default::default(),
             additional_timeline_attributes: Default::default(),
             override_timeline_attributes: Default::default(),
+            metadata_timeline_attributes: Default::default(),
             enable_auto_timestamp: true,
+            auto_sequence: None,
+            rate_limiter: None,
+            max_attrs_per_event: None,
+            max_string_attr_len: None,
+            backpressure_limit: None,
+            unwritten_events: 0,
+            heartbeat: None,
+            idle_ping: None,
         };
 
-        for kvp in timeline_attr_cfg.additional_timeline_attributes.into_iter() {
-            let k = client.prep_timeline_attr(kvp.0.as_ref()).await?;
-            client.additional_timeline_attributes.push((k, kvp.1));
+        client.timeline_attr_cfg = client.expand_timeline_attr_templates(timeline_attr_cfg);
+        client.prep_configured_timeline_attrs().await?;
+        Ok(client)
+    }
+
+    fn expand_timeline_attr_templates(&self, mut cfg: TimelineAttributes) -> TimelineAttributes {
+        let mut placeholders = vec![
+            ("{hostname}", hostname()),
+            ("{pid}", std::process::id().to_string()),
+            (
+                "{plugin}",
+                std::env::current_exe()
+                    .ok()
+                    .and_then(|p| p.file_name().map(|f| f.to_string_lossy().into_owned()))
+                    .unwrap_or_default(),
+            ),
+        ];
+        if let Some(run_id) = &self.run_id {
+            placeholders.push(("{run_id}", run_id.clone()));
         }
 
-        for kvp in timeline_attr_cfg.override_timeline_attributes.into_iter() {
-            let k = client.prep_timeline_attr(kvp.0.as_ref()).await?;
-            client.override_timeline_attributes.push((k, kvp.1));
+        for kvp in cfg
+            .additional_timeline_attributes
+            .iter_mut()
+            .chain(cfg.override_timeline_attributes.iter_mut())
+        {
+            if let AttrVal::String(s) = &kvp.1 {
+                if s.as_ref().contains('{') {
+                    let expanded = placeholders
+                        .iter()
+                        .fold(s.to_string(), |acc, (p, v)| acc.replace(p, v));
+                    kvp.1 = AttrVal::String(expanded.into());
+                }
+            }
         }
+        cfg
+    }
 
-        Ok(client)
+    async fn prep_configured_timeline_attrs(&mut self) -> Result<(), DynamicIngestError> {
+        let cfg = self.timeline_attr_cfg.clone();
+        self.additional_timeline_attributes.clear();
+        self.override_timeline_attributes.clear();
+
+        for kvp in cfg.additional_timeline_attributes.into_iter() {
+            let k = self.prep_timeline_attr(kvp.0.as_ref()).await?;
+            self.additional_timeline_attributes.push((k, kvp.1));
+        }
+
+        for kvp in cfg.override_timeline_attributes.into_iter() {
+            let k = self.prep_timeline_attr(kvp.0.as_ref()).await?;
+            self.override_timeline_attributes.push((k, kvp.1));
+        }
+
+        Ok(())
+    }
+
+    /// Set the endpoint used by [Client::reconnect]. This is done for you by
+    /// [Config::connect_and_authenticate_ingest].
+    pub fn set_reconnect_endpoint(
+        &mut self,
+        url: Url,
+        allow_insecure_tls: bool,
+        timeout: Duration,
+    ) {
+        self.endpoint = Some(ReconnectEndpoint {
+            url,
+            allow_insecure_tls,
+            timeout,
+        });
+    }
+
+    /// Replace the connection with a new one to the same endpoint.
+    ///
+    /// The auth token is loaded again, rather than reusing the original one, so a
+    /// rotated `MODALITY_AUTH_TOKEN` or token file is picked up; the token cached by
+    /// [AuthToken::load_cached] is replaced too. Attr
+    /// keys are re-declared on the new connection and the current timeline, if any, is
+    /// reopened. Timeline attributes are not resent. A pinned protocol version
+    /// is pinned on the new connection too, and a running heartbeat or idle ping
+    /// is restarted on it.
+    pub async fn reconnect(&mut self) -> Result<(), ReconnectError> {
+        let Some(endpoint) = self.endpoint.as_ref() else {
+            return Err(ReconnectError::NoEndpoint);
+        };
+
+        // Pick up a rotated token, for other connections too
+        AuthToken::invalidate_cached();
+        let auth_token = AuthToken::load_cached()?;
+        let mut builder = IngestClient::builder(endpoint.url.clone())
+            .allow_insecure_tls(endpoint.allow_insecure_tls)
+            .timeout(endpoint.timeout);
+        if let Some(version) = self.inner.lock().await.protocol_version() {
+            builder = builder.protocol_version(version);
+        }
+        let client = builder
+            .connect()
+            .await?
+            .authenticate(auth_token.as_ref().clone().into())
+            .await?;
+
+        self.peer_addr = client.peer_addr();
+        *self.inner.lock().await = client.into();
+        self.unwritten_events = 0;
+        self.timeline_keys.clear();
+        self.event_keys.clear();
+        self.prep_configured_timeline_attrs().await?;
+        if let Some(id) = self.current_timeline {
+            self.inner.lock().await.open_timeline(id).await?;
+        }
+        if let Some(heartbeat) = self.heartbeat.take() {
+            self.spawn_heartbeat(
+                heartbeat.heartbeat,
+                heartbeat.timeline,
+                heartbeat.next_ordering,
+            )
+            .await?;
+        }
+        self.activity.lost.store(false, Ordering::Relaxed);
+        self.activity.touch();
+        if let Some(idle_ping) = self.idle_ping.take() {
+            self.set_idle_ping(idle_ping.idle_timeout);
+        }
+
+        Ok(())
+    }
+
+    /// Check that the connection is still alive whenever it goes unused for
+    /// `idle_timeout`, by sending a status request and waiting for the reply,
+    /// within the connection's timeout. Any setting already made is replaced.
+    ///
+    /// A dead connection, e.g. one dropped by a NAT or proxy along the way,
+    /// otherwise goes unnoticed until some later write fails. If a ping fails,
+    /// a warning is logged and pings stop, and the next
+    /// [Client::switch_timeline], [Client::send_timeline_attrs] or
+    /// [Client::send_event] calls [Client::reconnect] first; see
+    /// [Client::connection_lost]. Only those calls count as using the
+    /// connection; heartbeats don't.
+    ///
+    /// Off by default.
+    ///
+    /// # Panics
+    ///
+    /// If `idle_timeout` is zero, or if called outside a Tokio runtime, since
+    /// the pings are sent from a spawned task.
+    pub fn set_idle_ping(&mut self, idle_timeout: Duration) {
+        assert!(!idle_timeout.is_zero(), "idle_timeout must be non-zero");
+        let (stop, stopped) = oneshot::channel();
+        let task = tokio::spawn(run_idle_ping(
+            self.inner.clone(),
+            self.activity.clone(),
+            stopped,
+            idle_timeout,
+        ));
+        self.idle_ping = Some(IdlePingTask {
+            idle_timeout,
+            stop,
+            task,
+        });
+    }
+
+    /// Stop the pings started by [Client::set_idle_ping].
+    pub fn clear_idle_ping(&mut self) {
+        // Dropping the sender stops the task
+        self.idle_ping = None;
+    }
+
+    /// True if an idle ping found the connection dead, and it hasn't been
+    /// reconnected since; see [Client::set_idle_ping].
+    pub fn connection_lost(&self) -> bool {
+        self.activity.lost.load(Ordering::Relaxed)
+    }
+
+    async fn reconnect_if_lost(&mut self) -> Result<(), DynamicIngestError> {
+        if !self.connection_lost() {
+            return Ok(());
+        }
+        tracing::info!("Reconnecting after a failed idle ping");
+        self.reconnect()
+            .await
+            .map_err(|e| DynamicIngestError::ReconnectFailed(Box::new(e)))
     }
 
     /// Disable automatic `timestamp` attribute generation.
@@ -94,6 +506,143 @@ impl Client {
         self.enable_auto_timestamp = false;
     }
 
+    /// Add an `event.internal.sequence` attribute to every event: a
+    /// counter that starts at 0 for each timeline and goes up by one
+    /// with each event sent on it. This orders events independently of
+    /// their timestamps, e.g. ones that share a timestamp. Switching
+    /// back to a timeline continues its count.
+    ///
+    /// A sequence number given in the `event_attrs` of
+    /// [Client::send_event] is sent as-is instead, and doesn't advance
+    /// the counter.
+    ///
+    /// This is unrelated to the `event.internal.rs.tick` attribute
+    /// the tracing layers add, which is the time since the tracing
+    /// subscriber started, and isn't added by this client.
+    pub fn enable_auto_sequence(&mut self) {
+        if self.auto_sequence.is_none() {
+            self.auto_sequence = Some(Default::default());
+        }
+    }
+
+    /// Limit the rate at which events are sent.
+    ///
+    /// Events are paced with a token bucket: up to `burst` events may be
+    /// sent back-to-back, after which [Client::send_event] waits until the
+    /// sustained rate of `events_per_second` allows another one. Events are
+    /// never dropped. A `burst` of zero is treated as one.
+    ///
+    /// [Config::connect_and_authenticate_ingest] applies this from the
+    /// `max-events-per-second` and `max-event-burst` ingest settings.
+    pub fn set_rate_limit(&mut self, events_per_second: NonZeroU32, burst: u32) {
+        self.rate_limiter = Some(RateLimiter::new(events_per_second, burst));
+    }
+
+    /// Remove any limit set by [Client::set_rate_limit].
+    pub fn clear_rate_limit(&mut self) {
+        self.rate_limiter = None;
+    }
+
+    /// Limit the number of attributes [Client::send_event] accepts per event,
+    /// as a guard against runaway event width. `event.name` and the automatic
+    /// `event.timestamp` don't count towards the limit, but the `.truncated`
+    /// companions added by [Client::set_max_string_attr_len] do.
+    ///
+    /// There is no limit by default.
+    pub fn set_max_attrs_per_event(&mut self, max: usize, policy: AttrLimitPolicy) {
+        self.max_attrs_per_event = Some((max, policy));
+    }
+
+    /// Remove any limit set by [Client::set_max_attrs_per_event].
+    pub fn clear_max_attrs_per_event(&mut self) {
+        self.max_attrs_per_event = None;
+    }
+
+    /// Limit the length, in bytes, of the string attribute values
+    /// [Client::send_event] accepts, as a guard against accidentally
+    /// attaching huge strings to events.
+    ///
+    /// Under [AttrLimitPolicy::Truncate], an over-long value is cut to `max`
+    /// bytes (at a character boundary), ending with [TRUNCATED_STRING_MARKER],
+    /// and a companion `<key>.truncated = true` attribute is added to the event.
+    ///
+    /// There is no limit by default.
+    pub fn set_max_string_attr_len(&mut self, max: usize, policy: AttrLimitPolicy) {
+        self.max_string_attr_len = Some((max, policy));
+    }
+
+    /// Remove any limit set by [Client::set_max_string_attr_len].
+    pub fn clear_max_string_attr_len(&mut self) {
+        self.max_string_attr_len = None;
+    }
+
+    /// Attach entries of the config's `metadata` table (see
+    /// [Config::metadata]) to every timeline sent with
+    /// [Client::send_timeline_attrs], as `timeline.metadata.<key>`
+    /// attributes, e.g. to record which deployment or site the data came
+    /// from. Only the entries named in `keys` are attached, or all of them
+    /// if it's `None`; names without an entry are ignored.
+    ///
+    /// Values are converted as by [AttrVal]'s `TryFrom<&TomlValue>`, so
+    /// arrays and tables are attached as inline TOML strings. Configured
+    /// timeline attributes, and those given to `send_timeline_attrs`, take
+    /// precedence over these.
+    ///
+    /// Nothing from `metadata` is attached unless this is called.
+    pub fn set_metadata_timeline_attrs(
+        &mut self,
+        metadata: &BTreeMap<String, TomlValue>,
+        keys: Option<&[&str]>,
+    ) -> Result<(), TomlAttrValError> {
+        self.metadata_timeline_attributes = metadata
+            .iter()
+            .filter(|(k, _)| keys.map_or(true, |keys| keys.contains(&k.as_str())))
+            .map(|(k, v)| Ok((format!("timeline.metadata.{k}"), AttrVal::try_from(v)?)))
+            .collect::<Result<_, TomlAttrValError>>()?;
+        Ok(())
+    }
+
+    /// Stop attaching the attributes set by [Client::set_metadata_timeline_attrs].
+    pub fn clear_metadata_timeline_attrs(&mut self) {
+        self.metadata_timeline_attributes.clear();
+    }
+
+    /// Report backpressure once `max_unwritten` events have been sent that
+    /// the server hasn't yet written; see [Client::is_backpressured].
+    ///
+    /// Sending never blocks on this limit; it's up to the caller (e.g. an
+    /// importer's read loop) to pause while the client is backpressured.
+    pub fn set_backpressure_limit(&mut self, max_unwritten: u64) {
+        self.backpressure_limit = Some(max_unwritten);
+    }
+
+    pub fn clear_backpressure_limit(&mut self) {
+        self.backpressure_limit = None;
+    }
+
+    /// The address of the ingest server this client is connected to; see
+    /// [IngestClient::peer_addr].
+    pub fn peer_addr(&self) -> Option<std::net::SocketAddr> {
+        self.peer_addr
+    }
+
+    /// The number of events that may not have been written by the server yet:
+    /// those pending as of the last [Client::status], plus those sent since.
+    pub fn unwritten_events(&self) -> u64 {
+        self.unwritten_events
+    }
+
+    /// Whether [Client::unwritten_events] has reached the limit set by
+    /// [Client::set_backpressure_limit]. Always false if there is no limit.
+    ///
+    /// This only changes as events are sent and on calls to
+    /// [Client::status], so a caller pausing for backpressure should poll
+    /// `status` until it clears.
+    pub fn is_backpressured(&self) -> bool {
+        self.backpressure_limit
+            .is_some_and(|max| self.unwritten_events >= max)
+    }
+
     /// Set the current timeline to `id`. All subsequent timeline
     /// attrs and events will are attached to the current
     /// timeline.
@@ -103,7 +652,10 @@ impl Client {
     /// `Client::send_timeline_attrs` or `Client::send_event`.
     /// </div>
     pub async fn switch_timeline(&mut self, id: TimelineId) -> Result<(), DynamicIngestError> {
-        self.inner.open_timeline(id).await?;
+        self.reconnect_if_lost().await?;
+        self.inner.lock().await.open_timeline(id).await?;
+        self.activity.touch();
+        self.current_timeline = Some(id);
         Ok(())
     }
 
@@ -120,13 +672,18 @@ impl Client {
     ///   `client.send_timeline_attrs("tl", [("attr1", 42.into())]).await?;`
     ///
     ///   These keys are automatically normalized, so you prepending "timeline." is optional.
+    ///
+    /// Returns which of `timeline_attrs` were sent, and which were left out: those
+    /// that are [reserved](AttrKey::is_reserved), such as `timeline.name`, and
+    /// those replaced by `override_timeline_attributes`.
     pub async fn send_timeline_attrs(
         &mut self,
         name: &str,
         timeline_attrs: impl IntoIterator<Item = (&str, AttrVal)>,
-    ) -> Result<(), DynamicIngestError> {
-        let mut interned_attrs =
-            vec![(self.prep_timeline_attr("timeline.name").await?, name.into())];
+    ) -> Result<TimelineAttrsReport, DynamicIngestError> {
+        self.reconnect_if_lost().await?;
+        let name_key = self.prep_timeline_attr("timeline.name").await?;
+        let mut interned_attrs = vec![(name_key, name.into())];
 
         if let Some(run_id) = self.run_id.clone() {
             let k = self.prep_timeline_attr("timeline.run_id").await?;
@@ -138,25 +695,44 @@ impl Client {
             interned_attrs.push((k, AttrVal::String(time_domain.into())));
         }
 
+        for (k, v) in self.metadata_timeline_attributes.clone() {
+            let k = self.prep_timeline_attr(&k).await?;
+            interned_attrs.push((k, v));
+        }
         interned_attrs.extend(self.additional_timeline_attributes.iter().cloned());
         interned_attrs.extend(self.override_timeline_attributes.iter().cloned());
 
+        let mut report = TimelineAttrsReport::default();
         for (k, v) in timeline_attrs {
-            let k = self.prep_timeline_attr(k).await?;
+            let key = normalize_timeline_key(k);
+            if AttrKey::from(key.as_str()).is_reserved() {
+                report.rejected.push((key, TimelineAttrRejection::Reserved));
+                continue;
+            }
+            let k = self.prep_timeline_attr(&key).await?;
             if self
                 .override_timeline_attributes
                 .iter()
                 .any(|(ko, _)| k == *ko)
             {
+                report
+                    .rejected
+                    .push((key, TimelineAttrRejection::Overridden));
                 continue;
             }
 
+            report.accepted.push(key);
             interned_attrs.push((k, v));
         }
 
-        self.inner.timeline_metadata(interned_attrs).await?;
+        self.inner
+            .lock()
+            .await
+            .timeline_metadata(interned_attrs)
+            .await?;
+        self.activity.touch();
 
-        Ok(())
+        Ok(report)
     }
 
     async fn prep_timeline_attr(&mut self, k: &str) -> Result<InternedAttrKey, DynamicIngestError> {
@@ -164,7 +740,12 @@ impl Client {
         let int_key = if let Some(ik) = self.timeline_keys.get(&key) {
             *ik
         } else {
-            let ik = self.inner.declare_attr_key(key.clone()).await?;
+            let ik = self
+                .inner
+                .lock()
+                .await
+                .declare_attr_key(key.clone())
+                .await?;
             self.timeline_keys.insert(key, ik);
             ik
         };
@@ -197,14 +778,99 @@ impl Client {
     ///     to populate the `event.timestamp` attr. If you want to
     ///     handle timestamps completely manually, you can disable
     ///     this behavior using [Client::disable_auto_timestamp].
+    ///
+    /// If a rate limit is set, this waits until the event is allowed to go out.
+    ///
+    /// If an attribute count or string length limit is set and `attrs` exceeds it,
+    /// the event is truncated or rejected according to the [AttrLimitPolicy].
     pub async fn send_event(
         &mut self,
         name: &str,
         ordering: u128,
         attrs: impl IntoIterator<Item = (&str, AttrVal)>,
     ) -> Result<(), DynamicIngestError> {
+        let mut attrs: Vec<(&str, AttrVal)> = attrs.into_iter().collect();
+
+        // String lengths come first, so that the `.truncated` companions count against the
+        // attribute limit
+        let mut truncated = vec![false; attrs.len()];
+        if let Some((max, policy)) = self.max_string_attr_len {
+            for ((k, v), truncated) in attrs.iter_mut().zip(truncated.iter_mut()) {
+                let AttrVal::String(s) = v else {
+                    continue;
+                };
+                if s.len() <= max {
+                    continue;
+                }
+                match policy {
+                    AttrLimitPolicy::Truncate => {
+                        tracing::warn!(
+                            event = name,
+                            attr = *k,
+                            len = s.len(),
+                            max,
+                            "String attribute too long, truncating"
+                        );
+                        *v = AttrVal::String(truncate_string(s, max).into());
+                        *truncated = true;
+                    }
+                    AttrLimitPolicy::Error => {
+                        return Err(DynamicIngestError::StringAttrTooLong {
+                            key: k.to_string(),
+                            len: s.len(),
+                            max,
+                        });
+                    }
+                }
+            }
+        }
+
+        if let Some((max, policy)) = self.max_attrs_per_event {
+            let count = attrs.len() + truncated.iter().filter(|t| **t).count();
+            if count > max {
+                match policy {
+                    AttrLimitPolicy::Truncate => {
+                        tracing::warn!(
+                            event = name,
+                            count,
+                            max,
+                            "Too many event attributes, truncating"
+                        );
+                        // Keep attributes in order, each with its companion, while they fit
+                        let mut used = 0;
+                        let kept = truncated
+                            .iter()
+                            .take_while(|t| {
+                                used += if **t { 2 } else { 1 };
+                                used <= max
+                            })
+                            .count();
+                        attrs.truncate(kept);
+                        truncated.truncate(kept);
+                    }
+                    AttrLimitPolicy::Error => {
+                        return Err(DynamicIngestError::TooManyAttrs { count, max });
+                    }
+                }
+            }
+        }
+
+        let truncated_keys: Vec<String> = attrs
+            .iter()
+            .zip(&truncated)
+            .filter(|(_, truncated)| **truncated)
+            .map(|((k, _), _)| format!("{k}{TRUNCATED_ATTR_KEY_SUFFIX}"))
+            .collect();
+        attrs.extend(truncated_keys.iter().map(|k| (k.as_str(), true.into())));
+
+        if let Some(limiter) = self.rate_limiter.as_mut() {
+            limiter.acquire().await;
+        }
+        self.reconnect_if_lost().await?;
+
         let mut interned_attrs = Vec::new();
         let mut have_timestamp = false;
+        let mut have_sequence = false;
 
         interned_attrs.push((self.prep_event_attr("event.name").await?, name.into()));
 
@@ -212,6 +878,9 @@ impl Client {
             if self.enable_auto_timestamp && (k == "timestamp" || k == "event.timestamp") {
                 have_timestamp = true;
             }
+            if k == "internal.sequence" || k == "event.internal.sequence" {
+                have_sequence = true;
+            }
 
             interned_attrs.push((self.prep_event_attr(k).await?, v));
         }
@@ -219,27 +888,233 @@ impl Client {
         if self.enable_auto_timestamp && !have_timestamp {
             interned_attrs.push((
                 self.prep_event_attr("event.timestamp").await?,
-                Nanoseconds::from(
-                    SystemTime::now()
-                        .duration_since(SystemTime::UNIX_EPOCH)
-                        .unwrap()
-                        .as_nanos() as u64,
-                )
-                .into(),
+                Nanoseconds::from(SystemTime::now()).into(),
+            ));
+        }
+
+        let sequence = match (&self.auto_sequence, self.current_timeline) {
+            (Some(next), Some(tl)) if !have_sequence => {
+                Some((tl, next.get(&tl).copied().unwrap_or(0)))
+            }
+            _ => None,
+        };
+        if let Some((_, seq)) = sequence {
+            interned_attrs.push((
+                self.prep_event_attr("event.internal.sequence").await?,
+                seq.into(),
             ));
         }
 
-        self.inner.event(ordering, interned_attrs).await?;
+        self.inner
+            .lock()
+            .await
+            .event(ordering, interned_attrs)
+            .await?;
+        self.activity.touch();
+        self.unwritten_events += 1;
+        if let (Some((tl, seq)), Some(next)) = (sequence, self.auto_sequence.as_mut()) {
+            next.insert(tl, seq + 1);
+        }
         Ok(())
     }
 
+    /// Create an event on the current timeline with `event.timestamp` set to
+    /// `timestamp`, replacing any timestamp in `attrs`. Otherwise like
+    /// [Client::send_event], and sets the timestamp even if automatic
+    /// timestamps are disabled.
+    pub async fn send_event_at(
+        &mut self,
+        name: &str,
+        ordering: u128,
+        timestamp: impl Into<Nanoseconds>,
+        attrs: impl IntoIterator<Item = (&str, AttrVal)>,
+    ) -> Result<(), DynamicIngestError> {
+        let timestamp: AttrVal = timestamp.into().into();
+        let attrs = attrs
+            .into_iter()
+            .filter(|(k, _)| *k != "timestamp" && *k != "event.timestamp")
+            .chain([("event.timestamp", timestamp)]);
+        self.send_event(name, ordering, attrs).await
+    }
+
+    /// Create an event named `name` recording a measurement, on the current
+    /// timeline at `timestamp`. The value goes in the attribute `key`, and its
+    /// unit, e.g. `"V"` or `"degC"`, in `<key>.unit` (see
+    /// [UNIT_ATTR_KEY_SUFFIX]), so tools can show or convert it. `key` is
+    /// normalized like the keys given to [Client::send_event], so prepending
+    /// "event." is optional. Otherwise like [Client::send_event_at].
+    ///
+    /// Fails with [DynamicIngestError::ReservedAttrKey] if either key is
+    /// [reserved](AttrKey::is_reserved), e.g. `event.timestamp`.
+    pub async fn send_measurement(
+        &mut self,
+        name: &str,
+        ordering: u128,
+        key: &str,
+        value: impl Into<AttrVal>,
+        unit: &str,
+        timestamp: impl Into<Nanoseconds>,
+    ) -> Result<(), DynamicIngestError> {
+        let key = normalize_event_key(key);
+        let unit_key = format!("{key}{UNIT_ATTR_KEY_SUFFIX}");
+        for k in [&key, &unit_key] {
+            if AttrKey::from(k.as_str()).is_reserved() {
+                return Err(DynamicIngestError::ReservedAttrKey { key: k.clone() });
+            }
+        }
+        self.send_event_at(
+            name,
+            ordering,
+            timestamp,
+            [
+                (key.as_str(), value.into()),
+                (unit_key.as_str(), unit.into()),
+            ],
+        )
+        .await
+    }
+
     pub async fn flush(&mut self) -> Result<(), DynamicIngestError> {
-        self.inner.flush().await?;
+        self.inner.lock().await.flush().await?;
+        Ok(())
+    }
+
+    /// Stop any heartbeat and idle ping, then flush everything sent so far and shut down
+    /// the connection; see [IngestClient::close](IngestClient::<crate::ingest_client::BoundTimelineState>::close).
+    pub async fn close(mut self) -> Result<(), DynamicIngestError> {
+        if let Some(heartbeat) = self.heartbeat.take() {
+            drop(heartbeat.stop);
+            let _ = heartbeat.task.await;
+        }
+        if let Some(idle_ping) = self.idle_ping.take() {
+            drop(idle_ping.stop);
+            let _ = idle_ping.task.await;
+        }
+        // Only the heartbeat and idle ping tasks share the connection, and
+        // they've finished
+        let inner = Arc::try_unwrap(self.inner).map_err(|_| DynamicIngestError::ConnectionInUse)?;
+        inner.into_inner().close().await?;
+        Ok(())
+    }
+
+    /// Send an event named `event_name` every `interval`, on a timeline of its
+    /// own, until the heartbeat is stopped or the client is closed. See
+    /// [Client::start_heartbeat_with] for the details, and more options.
+    ///
+    /// Panics if `interval` is zero.
+    pub async fn start_heartbeat(
+        &mut self,
+        interval: Duration,
+        event_name: &str,
+    ) -> Result<(), DynamicIngestError> {
+        self.start_heartbeat_with(Heartbeat::new(interval, event_name))
+            .await
+    }
+
+    /// Start sending a heartbeat event at a regular interval, from a background
+    /// task, whether or not anything else is being sent. Monitoring can then
+    /// alert when heartbeats stop arriving. Any heartbeat already running is
+    /// replaced.
+    ///
+    /// Heartbeats are ordered by their own counter, from 0, so they go on a
+    /// timeline of their own: the one given by [Heartbeat::with_timeline], or
+    /// else a new one, named after the heartbeat event and given the
+    /// configured timeline attributes. The count carries on across
+    /// [Client::reconnect]. Each heartbeat gets `event.timestamp` (unless
+    /// [Client::disable_auto_timestamp] was called), but not rate limiting or
+    /// auto sequence numbers.
+    ///
+    /// If sending a heartbeat fails, a warning is logged and heartbeats stop.
+    pub async fn start_heartbeat_with(
+        &mut self,
+        heartbeat: Heartbeat,
+    ) -> Result<(), DynamicIngestError> {
+        self.stop_heartbeat();
+
+        let timeline = match heartbeat.timeline {
+            Some(timeline) => timeline,
+            None => {
+                let timeline = TimelineId::allocate();
+                self.send_heartbeat_timeline_attrs(timeline, &heartbeat.event_name)
+                    .await?;
+                timeline
+            }
+        };
+        self.spawn_heartbeat(heartbeat, timeline, Arc::new(AtomicU64::new(0)))
+            .await
+    }
+
+    /// Name a heartbeat's own timeline, leaving the client on its current one
+    async fn send_heartbeat_timeline_attrs(
+        &mut self,
+        timeline: TimelineId,
+        name: &str,
+    ) -> Result<(), DynamicIngestError> {
+        self.inner.lock().await.open_timeline(timeline).await?;
+        let sent = self.send_timeline_attrs(name, []).await;
+        let mut inner = self.inner.lock().await;
+        match self.current_timeline {
+            Some(id) => inner.open_timeline(id).await?,
+            None => inner.close_timeline(),
+        }
+        sent.map(|_| ())
+    }
+
+    async fn spawn_heartbeat(
+        &mut self,
+        heartbeat: Heartbeat,
+        timeline: TimelineId,
+        next_ordering: Arc<AtomicU64>,
+    ) -> Result<(), DynamicIngestError> {
+        let mut attrs = vec![(
+            self.prep_event_attr("event.name").await?,
+            heartbeat.event_name.as_str().into(),
+        )];
+        for (k, v) in heartbeat.attrs.clone() {
+            attrs.push((self.prep_event_attr(&k).await?, v));
+        }
+        let timestamp_key = if self.enable_auto_timestamp {
+            Some(self.prep_event_attr("event.timestamp").await?)
+        } else {
+            None
+        };
+
+        // Timed from now, rather than from when the task first gets to run
+        let mut ticker =
+            tokio::time::interval_at(Instant::now() + heartbeat.interval, heartbeat.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        let (stop, stopped) = oneshot::channel();
+        let task = tokio::spawn(run_heartbeat(
+            self.inner.clone(),
+            stopped,
+            ticker,
+            timeline,
+            next_ordering.clone(),
+            attrs,
+            timestamp_key,
+        ));
+        self.heartbeat = Some(HeartbeatTask {
+            heartbeat,
+            timeline,
+            next_ordering,
+            stop,
+            task,
+        });
         Ok(())
     }
 
+    /// Stop sending heartbeats, if started with [Client::start_heartbeat]. A
+    /// heartbeat already being sent is allowed to finish.
+    pub fn stop_heartbeat(&mut self) {
+        // Dropping the sender stops the task
+        self.heartbeat = None;
+    }
+
     pub async fn status(&mut self) -> Result<IngestStatus, DynamicIngestError> {
-        Ok(self.inner.status().await?)
+        let status = self.inner.lock().await.status().await?;
+        self.unwritten_events = status.events_pending;
+        Ok(status)
     }
 
     async fn prep_event_attr(&mut self, k: &str) -> Result<InternedAttrKey, DynamicIngestError> {
@@ -247,13 +1122,416 @@ impl Client {
         let int_key = if let Some(ik) = self.event_keys.get(&key) {
             *ik
         } else {
-            let ik = self.inner.declare_attr_key(key.clone()).await?;
-            self.timeline_keys.insert(key, ik);
+            let ik = self
+                .inner
+                .lock()
+                .await
+                .declare_attr_key(key.clone())
+                .await?;
+            self.event_keys.insert(key, ik);
             ik
         };
 
         Ok(int_key)
     }
+
+    /// The names of all the attr keys this client has declared on its
+    /// current connection, timeline and event keys alike, in sorted order.
+    ///
+    /// Useful for spotting a runaway number of dynamically generated keys.
+    pub fn declared_attr_keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self
+            .timeline_keys
+            .keys()
+            .chain(self.event_keys.keys())
+            .cloned()
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// Send the attributes in `manifest` to each of its timelines, as loaded by
+    /// [load_timeline_attr_manifest]. Each timeline's `name` (or
+    /// `timeline.name`) attribute, if it has one, is used as its timeline name;
+    /// otherwise the timeline id is. Returns the report from
+    /// [Client::send_timeline_attrs] for each timeline.
+    ///
+    /// This leaves the last timeline in the manifest as the current one.
+    pub async fn apply_timeline_attr_manifest(
+        &mut self,
+        manifest: &TimelineAttrManifest,
+    ) -> Result<BTreeMap<TimelineId, TimelineAttrsReport>, DynamicIngestError> {
+        let mut reports = BTreeMap::new();
+        for (timeline_id, attrs) in manifest {
+            let mut name = None;
+            let mut timeline_attrs = Vec::new();
+            for (k, v) in attrs {
+                if normalize_timeline_key(k.as_ref()) == "timeline.name" {
+                    name = Some(v.to_string());
+                } else {
+                    timeline_attrs.push((k.as_ref(), v.clone()));
+                }
+            }
+            let name = name.unwrap_or_else(|| timeline_id.to_string());
+
+            self.switch_timeline(*timeline_id).await?;
+            let report = self.send_timeline_attrs(&name, timeline_attrs).await?;
+            reports.insert(*timeline_id, report);
+        }
+        Ok(reports)
+    }
+}
+
+async fn run_heartbeat(
+    inner: Arc<Mutex<DynamicIngestClient>>,
+    mut stopped: oneshot::Receiver<()>,
+    mut ticker: Interval,
+    timeline: TimelineId,
+    next_ordering: Arc<AtomicU64>,
+    attrs: Vec<(InternedAttrKey, AttrVal)>,
+    timestamp_key: Option<InternedAttrKey>,
+) {
+    loop {
+        // Only the wait is cancelled, never a send partway through
+        tokio::select! {
+            _ = &mut stopped => return,
+            _ = ticker.tick() => (),
+        }
+
+        let mut attrs = attrs.clone();
+        if let Some(k) = timestamp_key {
+            attrs.push((k, Nanoseconds::from(SystemTime::now()).into()));
+        }
+
+        let ordering = next_ordering.fetch_add(1, Ordering::Relaxed).into();
+        let mut inner = inner.lock().await;
+        if let Err(e) = send_heartbeat(&mut inner, timeline, ordering, attrs).await {
+            tracing::warn!(err = %e, "Failed to send heartbeat, stopping heartbeats");
+            return;
+        }
+    }
+}
+
+async fn run_idle_ping(
+    inner: Arc<Mutex<DynamicIngestClient>>,
+    activity: Arc<ConnectionActivity>,
+    mut stopped: oneshot::Receiver<()>,
+    idle_timeout: Duration,
+) {
+    loop {
+        // Only the wait is cancelled, never a ping partway through
+        tokio::select! {
+            _ = &mut stopped => return,
+            _ = tokio::time::sleep_until(activity.last_write() + idle_timeout) => (),
+        }
+        if activity.last_write().elapsed() < idle_timeout {
+            // Used while we slept
+            continue;
+        }
+
+        let mut inner = inner.lock().await;
+        if let Err(e) = inner.status().await {
+            tracing::warn!(err = %e, "Idle connection failed a ping, reconnecting on next use");
+            activity.lost.store(true, Ordering::Relaxed);
+            return;
+        }
+        activity.touch();
+    }
+}
+
+/// Send one heartbeat on `target`, leaving the client bound to the same
+/// timeline as before
+async fn send_heartbeat(
+    inner: &mut DynamicIngestClient,
+    target: TimelineId,
+    ordering: u128,
+    attrs: Vec<(InternedAttrKey, AttrVal)>,
+) -> Result<(), DynamicIngestError> {
+    let bound = inner.bound_timeline();
+    if bound != Some(target) {
+        inner.open_timeline(target).await?;
+    }
+    inner.event(ordering, attrs).await?;
+    match bound {
+        Some(id) if id != target => inner.open_timeline(id).await?,
+        Some(_) => (),
+        None => inner.close_timeline(),
+    }
+    Ok(())
+}
+
+/// A [Client] that writes to an ingest archive file instead of a live
+/// connection, for capturing events where Modality isn't reachable. The
+/// archive can be uploaded later with [FileSink::upload]; see
+/// [crate::ingest_client::archive] for the format.
+///
+/// All of [Client]'s sending methods are available through `Deref`. Anything
+/// that needs a response from the server, like [Client::status], fails.
+pub struct FileSink {
+    client: Client,
+}
+
+impl FileSink {
+    /// Create (or truncate) the archive at `path`. The other arguments are as
+    /// for [Client::new].
+    pub async fn create(
+        path: impl AsRef<Path>,
+        timeline_attr_cfg: TimelineAttributes,
+        run_id: Option<String>,
+        time_domain: Option<String>,
+    ) -> Result<Self, DynamicIngestError> {
+        let client = IngestClient::create_archive(path).await?;
+        let client = Client::new(client, timeline_attr_cfg, run_id, time_domain).await?;
+        Ok(FileSink { client })
+    }
+
+    /// Flush everything sent so far out to the file, and close it.
+    pub async fn finish(self) -> Result<(), DynamicIngestError> {
+        self.client.close().await
+    }
+
+    /// Send the contents of the archive at `path` through `client`, which
+    /// should be connected to a real server. Returns the number of messages
+    /// sent. See [upload_archive].
+    pub async fn upload(
+        path: impl AsRef<Path>,
+        client: &mut IngestClient<ReadyState>,
+    ) -> Result<u64, ArchiveError> {
+        let file = tokio::fs::File::open(path).await?;
+        upload_archive(tokio::io::BufReader::new(file), client).await
+    }
+}
+
+impl Deref for FileSink {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        &self.client
+    }
+}
+
+impl DerefMut for FileSink {
+    fn deref_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
+
+/// Flatten a JSON value into attributes with dotted keys under `prefix`, for
+/// sending structured data with [Client::send_event] or
+/// [Client::send_timeline_attrs]. E.g. with the prefix `"motor"`,
+/// `{"temp": 40.5, "faults": ["stall"]}` becomes `motor.temp = 40.5` and
+/// `motor.faults.0 = "stall"`.
+///
+/// * Object members and array elements are nested under their key or index.
+/// * Numbers become integers where they fit, big integers for large unsigned
+///   values, and floats otherwise.
+/// * Nulls, and empty objects and arrays, produce no attributes.
+///
+/// With an empty `prefix`, the top-level members become the keys; a top-level
+/// scalar then has no key, and is dropped.
+pub fn flatten_json_attrs(prefix: &str, value: &serde_json::Value) -> Vec<(AttrKey, AttrVal)> {
+    let mut attrs = Vec::new();
+    flatten_json_into(prefix.to_owned(), value, &mut attrs);
+    attrs
+}
+
+fn flatten_json_into(key: String, value: &serde_json::Value, out: &mut Vec<(AttrKey, AttrVal)>) {
+    use serde_json::Value;
+
+    let nested = |child: &str| {
+        if key.is_empty() {
+            child.to_owned()
+        } else {
+            format!("{key}.{child}")
+        }
+    };
+
+    let val = match value {
+        Value::Null => return,
+        Value::Bool(b) => AttrVal::from(*b),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                AttrVal::from(i)
+            } else if let Some(u) = n.as_u64() {
+                AttrVal::from(u)
+            } else {
+                AttrVal::from(n.as_f64().unwrap_or(f64::NAN))
+            }
+        }
+        Value::String(s) => AttrVal::from(s.as_str()),
+        Value::Array(elements) => {
+            for (idx, element) in elements.iter().enumerate() {
+                flatten_json_into(nested(&idx.to_string()), element, out);
+            }
+            return;
+        }
+        Value::Object(members) => {
+            for (k, member) in members {
+                flatten_json_into(nested(k), member, out);
+            }
+            return;
+        }
+    };
+
+    if !key.is_empty() {
+        out.push((AttrKey::new(key), val));
+    }
+}
+
+/// Timeline attributes to apply to many timelines, keyed by timeline; see
+/// [load_timeline_attr_manifest] and [Client::apply_timeline_attr_manifest].
+pub type TimelineAttrManifest = BTreeMap<TimelineId, Vec<(AttrKey, AttrVal)>>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AttrManifestError {
+    #[error("Failed to read the attribute manifest")]
+    Csv(#[from] csv::Error),
+
+    #[error("Line {line}: expected 3 fields (timeline_id, key, value), found {found}")]
+    FieldCount { line: u64, found: usize },
+
+    #[error("Line {line}: invalid timeline id '{timeline_id}'")]
+    TimelineId {
+        line: u64,
+        timeline_id: String,
+        #[source]
+        error: uuid::Error,
+    },
+
+    #[error("Line {line}: empty attribute key")]
+    EmptyKey { line: u64 },
+}
+
+/// Load a manifest of timeline attributes from the CSV file at `path`, for
+/// use with [Client::apply_timeline_attr_manifest]. Files ending in `.tsv`
+/// are read as tab-separated instead.
+///
+/// See [parse_timeline_attr_manifest] for the format.
+pub fn load_timeline_attr_manifest(
+    path: impl AsRef<Path>,
+) -> Result<TimelineAttrManifest, AttrManifestError> {
+    let path = path.as_ref();
+    let delimiter = match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("tsv") => b'\t',
+        _ => b',',
+    };
+    let file = std::fs::File::open(path).map_err(csv::Error::from)?;
+    parse_timeline_attr_manifest(file, delimiter)
+}
+
+/// Parse a manifest of timeline attributes, one `timeline_id,key,value`
+/// record per line, separated by `delimiter`.
+///
+/// * An optional header line, `timeline_id,key,value`, is skipped. Blank
+///   lines, and lines starting with `#`, are ignored.
+/// * Fields may be double-quoted, to hold the delimiter or line breaks; a
+///   doubled quote inside a quoted field is a literal quote. Whitespace
+///   around fields is trimmed.
+/// * The value's type is inferred the same way as `AttrVal::from_str`, after
+///   the field's own quoting is removed. To keep a value like `42` or `true`
+///   as a string, quote it again with single quotes: `'42'`.
+/// * Attributes are kept in file order for each timeline; a key that is
+///   repeated for the same timeline appears more than once.
+pub fn parse_timeline_attr_manifest(
+    reader: impl std::io::Read,
+    delimiter: u8,
+) -> Result<TimelineAttrManifest, AttrManifestError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .comment(Some(b'#'))
+        .from_reader(reader);
+
+    let mut manifest = TimelineAttrManifest::new();
+    for (idx, record) in reader.records().enumerate() {
+        let record = record?;
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+        if record.len() != 3 {
+            return Err(AttrManifestError::FieldCount {
+                line,
+                found: record.len(),
+            });
+        }
+
+        let (timeline_id, key, value) = (&record[0], &record[1], &record[2]);
+        if idx == 0
+            && timeline_id.eq_ignore_ascii_case("timeline_id")
+            && key.eq_ignore_ascii_case("key")
+            && value.eq_ignore_ascii_case("value")
+        {
+            continue;
+        }
+
+        let timeline_id = uuid::Uuid::parse_str(timeline_id)
+            .map(TimelineId::from)
+            .map_err(|error| AttrManifestError::TimelineId {
+                line,
+                timeline_id: timeline_id.to_owned(),
+                error,
+            })?;
+        if key.is_empty() {
+            return Err(AttrManifestError::EmptyKey { line });
+        }
+        let Ok(value) = value.parse::<AttrVal>();
+
+        manifest
+            .entry(timeline_id)
+            .or_default()
+            .push((AttrKey::new(key.to_owned()), value));
+    }
+
+    Ok(manifest)
+}
+
+/// A token bucket, refilled continuously at `rate` tokens per second up to `capacity`.
+struct RateLimiter {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    fn new(events_per_second: NonZeroU32, burst: u32) -> Self {
+        let capacity = f64::from(burst.max(1));
+        Self {
+            rate: f64::from(events_per_second.get()),
+            capacity,
+            tokens: capacity,
+            last_refill: Instant::now(),
+        }
+    }
+
+    async fn acquire(&mut self) {
+        loop {
+            let now = Instant::now();
+            let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+            self.last_refill = now;
+
+            if self.tokens >= 1.0 {
+                self.tokens -= 1.0;
+                return;
+            }
+
+            let wait = (1.0 - self.tokens) / self.rate;
+            tokio::time::sleep(Duration::from_secs_f64(wait)).await;
+        }
+    }
+}
+
+#[cfg(unix)]
+fn hostname() -> String {
+    nix::unistd::gethostname()
+        .map(|h| h.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(not(unix))]
+fn hostname() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_default()
 }
 
 fn normalize_timeline_key(s: &str) -> String {
@@ -264,6 +1542,20 @@ fn normalize_timeline_key(s: &str) -> String {
     }
 }
 
+/// Cut `s` to at most `max` bytes, at a character boundary, ending with
+/// [TRUNCATED_STRING_MARKER] if there's room for it.
+fn truncate_string(s: &str, max: usize) -> String {
+    let (keep, marker) = match max.checked_sub(TRUNCATED_STRING_MARKER.len()) {
+        Some(keep) => (keep, TRUNCATED_STRING_MARKER),
+        None => (max, ""),
+    };
+    let mut end = keep;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{marker}", &s[..end])
+}
+
 fn normalize_event_key(s: &str) -> String {
     if s.starts_with("event.") {
         s.to_owned()
@@ -271,3 +1563,867 @@ fn normalize_event_key(s: &str) -> String {
         format!("event.{s}")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest_protocol::{IngestMessage, IngestResponse};
+    use crate::test_fixtures::{
+        authed_transport, sent_attrs, sent_event_attrs, sent_messages, sent_timeline_attrs,
+        NamedAttr, RecordedTransport, SentAttrs,
+    };
+
+    async fn recorded_client() -> (Client, RecordedTransport) {
+        recorded_client_with_attrs(Default::default(), None).await
+    }
+
+    async fn recorded_client_with_attrs(
+        timeline_attr_cfg: TimelineAttributes,
+        run_id: Option<String>,
+    ) -> (Client, RecordedTransport) {
+        let transport = authed_transport();
+        let client = IngestClient::from_transport(transport.clone(), Duration::from_secs(1))
+            .authenticate(vec![])
+            .await
+            .unwrap();
+
+        let client = Client::new(client, timeline_attr_cfg, run_id, None)
+            .await
+            .unwrap();
+        (client, transport)
+    }
+
+    #[tokio::test]
+    async fn backpressure_signal() {
+        let (mut client, transport) = recorded_client().await;
+        client
+            .switch_timeline(TimelineId::allocate())
+            .await
+            .unwrap();
+        client.send_event("ev", 0, []).await.unwrap();
+        assert!(!client.is_backpressured());
+
+        client.set_backpressure_limit(5);
+        for i in 1..5 {
+            assert!(!client.is_backpressured());
+            client.send_event("ev", i, []).await.unwrap();
+        }
+        assert_eq!(client.unwritten_events(), 5);
+        assert!(client.is_backpressured());
+
+        let status = |events_pending| IngestResponse::IngestStatusResponse {
+            current_timeline: None,
+            events_received: 5,
+            events_written: 5 - events_pending,
+            events_pending,
+            error_count: None,
+        };
+        transport.push_response(&status(3)).unwrap();
+        client.status().await.unwrap();
+        assert_eq!(client.unwritten_events(), 3);
+        assert!(!client.is_backpressured());
+
+        client.send_event("ev", 5, []).await.unwrap();
+        client.send_event("ev", 6, []).await.unwrap();
+        assert!(client.is_backpressured());
+
+        client.clear_backpressure_limit();
+        assert!(!client.is_backpressured());
+    }
+
+    #[tokio::test]
+    async fn timeline_attr_templates() {
+        let timeline_attr_cfg = TimelineAttributes {
+            additional_timeline_attributes: vec![
+                "pid = \"{pid}\"".parse().unwrap(),
+                "tag = \"{run_id}-{unknown}\"".parse().unwrap(),
+            ],
+            override_timeline_attributes: vec!["timeline.name = \"collector@{hostname}\""
+                .parse()
+                .unwrap()],
+        };
+        let (client, _transport) =
+            recorded_client_with_attrs(timeline_attr_cfg, Some("r1".to_owned())).await;
+
+        let name = &client.override_timeline_attributes[0].1;
+        assert_eq!(
+            name,
+            &AttrVal::String(format!("collector@{}", hostname()).into())
+        );
+        assert!(!hostname().is_empty());
+
+        let additional: Vec<_> = client
+            .additional_timeline_attributes
+            .iter()
+            .map(|(_, v)| v.clone())
+            .collect();
+        assert_eq!(
+            additional,
+            vec![
+                AttrVal::String(std::process::id().to_string().into()),
+                AttrVal::String("r1-{unknown}".into()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn metadata_timeline_attrs() {
+        let (mut client, transport) = recorded_client().await;
+        let metadata = BTreeMap::from([
+            ("deployment".to_owned(), TomlValue::from("prod")),
+            ("site".to_owned(), TomlValue::from("lab")),
+            ("poll-interval".to_owned(), TomlValue::from(5)),
+        ]);
+        client
+            .set_metadata_timeline_attrs(&metadata, Some(&["deployment", "poll-interval"]))
+            .unwrap();
+        client
+            .switch_timeline(TimelineId::allocate())
+            .await
+            .unwrap();
+        client.send_timeline_attrs("tl", []).await.unwrap();
+
+        let sent: BTreeMap<String, AttrVal> = sent_timeline_attrs(&sent_messages(&transport))
+            .into_iter()
+            .flat_map(|(_, attrs)| attrs)
+            .collect();
+        assert_eq!(
+            sent,
+            BTreeMap::from([
+                ("timeline.name".to_owned(), AttrVal::from("tl")),
+                (
+                    "timeline.metadata.deployment".to_owned(),
+                    AttrVal::from("prod")
+                ),
+                (
+                    "timeline.metadata.poll-interval".to_owned(),
+                    AttrVal::from(5)
+                ),
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn timeline_attrs_report() {
+        let timeline_attr_cfg = TimelineAttributes {
+            additional_timeline_attributes: vec![],
+            override_timeline_attributes: vec!["owner = \"cfg\"".parse().unwrap()],
+        };
+        let (mut client, transport) =
+            recorded_client_with_attrs(timeline_attr_cfg, Some("r1".to_owned())).await;
+        client
+            .switch_timeline(TimelineId::allocate())
+            .await
+            .unwrap();
+
+        let report = client
+            .send_timeline_attrs(
+                "tl",
+                [
+                    ("run_id", "mine".into()),
+                    ("timeline.name", "other".into()),
+                    ("timeline.owner", "me".into()),
+                    ("speed", 42.into()),
+                ],
+            )
+            .await
+            .unwrap();
+        assert!(!report.all_accepted());
+        assert_eq!(report.accepted, vec!["timeline.speed".to_owned()]);
+        assert_eq!(
+            report.rejected,
+            vec![
+                (
+                    "timeline.run_id".to_owned(),
+                    TimelineAttrRejection::Reserved
+                ),
+                ("timeline.name".to_owned(), TimelineAttrRejection::Reserved),
+                (
+                    "timeline.owner".to_owned(),
+                    TimelineAttrRejection::Overridden
+                ),
+            ]
+        );
+
+        // Rejected values aren't sent; the configured ones are
+        let sent: Vec<(String, AttrVal)> = sent_timeline_attrs(&sent_messages(&transport))
+            .into_iter()
+            .flat_map(|(_, attrs)| attrs)
+            .collect();
+        assert_eq!(
+            sent,
+            vec![
+                ("timeline.name".to_owned(), AttrVal::from("tl")),
+                ("timeline.run_id".to_owned(), AttrVal::from("r1")),
+                ("timeline.owner".to_owned(), AttrVal::from("cfg")),
+                ("timeline.speed".to_owned(), AttrVal::from(42)),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limit_paces_events() {
+        let (mut client, _transport) = recorded_client().await;
+        client.set_rate_limit(NonZeroU32::new(50).unwrap(), 5);
+        client
+            .switch_timeline(TimelineId::allocate())
+            .await
+            .unwrap();
+
+        // The first 5 go out as a burst, the remaining 20 at 50/s. The clock is paused, so this
+        // measures how long the limiter slept for, not how long the test took.
+        let start = tokio::time::Instant::now();
+        for i in 0..5 {
+            client.send_event("ev", i, []).await.unwrap();
+        }
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        for i in 5..25 {
+            client.send_event("ev", i, []).await.unwrap();
+        }
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(400), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(450), "{elapsed:?}");
+    }
+
+    #[tokio::test]
+    async fn declared_attr_keys_snapshot() {
+        let (mut client, transport) = recorded_client().await;
+        client
+            .switch_timeline(TimelineId::allocate())
+            .await
+            .unwrap();
+        assert!(client.declared_attr_keys().is_empty());
+
+        client.send_timeline_attrs("tl", []).await.unwrap();
+        client
+            .send_event("a", 0, [("x", 1.into()), ("event.y", 2.into())])
+            .await
+            .unwrap();
+        client.send_event("b", 1, [("x", 3.into())]).await.unwrap();
+
+        assert_eq!(
+            client.declared_attr_keys(),
+            vec![
+                "event.name",
+                "event.timestamp",
+                "event.x",
+                "event.y",
+                "timeline.name"
+            ]
+        );
+
+        // Each key is only declared once
+        let declared = transport
+            .frames()
+            .iter()
+            .filter(|f| {
+                matches!(
+                    minicbor::decode::<IngestMessage>(f),
+                    Ok(IngestMessage::DeclareAttrKey { .. })
+                )
+            })
+            .count();
+        assert_eq!(declared, 5);
+    }
+
+    #[tokio::test]
+    async fn auto_sequence_per_timeline() {
+        let (mut client, transport) = recorded_client().await;
+        client.disable_auto_timestamp();
+        client.enable_auto_sequence();
+
+        let (tl_a, tl_b) = (TimelineId::allocate(), TimelineId::allocate());
+        client.switch_timeline(tl_a).await.unwrap();
+        client.send_event("ev", 0, []).await.unwrap();
+        client.send_event("ev", 0, []).await.unwrap();
+        client.switch_timeline(tl_b).await.unwrap();
+        client.send_event("ev", 0, []).await.unwrap();
+        client.switch_timeline(tl_a).await.unwrap();
+        client
+            .send_event("ev", 0, [("internal.sequence", 100.into())])
+            .await
+            .unwrap();
+        client.send_event("ev", 0, []).await.unwrap();
+
+        let sequences: Vec<Option<AttrVal>> = sent_event_attrs(&sent_messages(&transport))
+            .into_iter()
+            .map(|attrs| {
+                attrs
+                    .into_iter()
+                    .find(|(k, _)| k == "event.internal.sequence")
+                    .map(|(_, v)| v)
+            })
+            .collect();
+        assert_eq!(
+            sequences,
+            vec![
+                Some(0u64.into()),
+                Some(1u64.into()),
+                Some(0u64.into()),
+                Some(100.into()),
+                Some(2u64.into()),
+            ]
+        );
+    }
+
+    fn sent_events(transport: &RecordedTransport) -> Vec<usize> {
+        sent_event_attrs(&sent_messages(transport))
+            .iter()
+            .map(Vec::len)
+            .collect()
+    }
+
+    /// Send the same things through a live client and a file sink
+    async fn send_sample_events(client: &mut Client) {
+        client.disable_auto_timestamp();
+        let tl = TimelineId::from(uuid::Uuid::from_u128(1));
+        client.switch_timeline(tl).await.unwrap();
+        client
+            .send_timeline_attrs("sample", [("kind", "test".into())])
+            .await
+            .unwrap();
+        client
+            .send_event("first", 0, [("n", 1.into()), ("text", "hi".into())])
+            .await
+            .unwrap();
+        client.send_event("second", 1, []).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn file_sink_replays_into_ingest() {
+        let (mut live, live_transport) =
+            recorded_client_with_attrs(Default::default(), Some("run".to_owned())).await;
+        send_sample_events(&mut live).await;
+        live.flush().await.unwrap();
+
+        let archive = tempfile::NamedTempFile::new().unwrap();
+        let mut sink = FileSink::create(
+            archive.path(),
+            Default::default(),
+            Some("run".to_owned()),
+            None,
+        )
+        .await
+        .unwrap();
+        send_sample_events(&mut sink).await;
+        sink.finish().await.unwrap();
+
+        let transport = authed_transport();
+        let mut client = IngestClient::from_transport(transport.clone(), Duration::from_secs(1))
+            .authenticate(vec![])
+            .await
+            .unwrap();
+        let sent = FileSink::upload(archive.path(), &mut client).await.unwrap();
+
+        let frames = transport.frames();
+        assert_eq!(sent as usize, frames.len() - 1);
+        assert_eq!(frames, live_transport.frames());
+    }
+
+    #[test]
+    fn flatten_json_nested_object() {
+        let value = serde_json::json!({
+            "motor": {
+                "temp": 40.5,
+                "rpm": 1200,
+                "serial": u64::MAX,
+                "ok": true,
+                "fault": null,
+                "config": { "mode": "eco", "limits": {} },
+            },
+        });
+        let attrs: BTreeMap<String, AttrVal> = flatten_json_attrs("", &value)
+            .into_iter()
+            .map(|(k, v)| (k.as_ref().to_owned(), v))
+            .collect();
+        assert_eq!(
+            attrs,
+            BTreeMap::from([
+                ("motor.temp".to_owned(), AttrVal::from(40.5)),
+                ("motor.rpm".to_owned(), AttrVal::Integer(1200)),
+                ("motor.serial".to_owned(), AttrVal::from(u64::MAX)),
+                ("motor.ok".to_owned(), AttrVal::Bool(true)),
+                ("motor.config.mode".to_owned(), AttrVal::from("eco")),
+            ])
+        );
+    }
+
+    #[test]
+    fn flatten_json_array() {
+        let value = serde_json::json!(["stall", { "code": 7 }, [1, 2]]);
+        let attrs: Vec<(String, AttrVal)> = flatten_json_attrs("faults", &value)
+            .into_iter()
+            .map(|(k, v)| (k.as_ref().to_owned(), v))
+            .collect();
+        assert_eq!(
+            attrs,
+            vec![
+                ("faults.0".to_owned(), AttrVal::from("stall")),
+                ("faults.1.code".to_owned(), AttrVal::Integer(7)),
+                ("faults.2.0".to_owned(), AttrVal::Integer(1)),
+                ("faults.2.1".to_owned(), AttrVal::Integer(2)),
+            ]
+        );
+
+        assert!(flatten_json_attrs("", &serde_json::json!(5)).is_empty());
+    }
+
+    #[tokio::test]
+    async fn timeline_attr_manifest() {
+        let tl_a = TimelineId::from(uuid::Uuid::from_u128(0xa));
+        let tl_b = TimelineId::from(uuid::Uuid::from_u128(0xb));
+        let csv = format!(
+            "timeline_id,key,value
+# board inventory
+{tl_a},name,sensor-board-1
+{tl_a},serial,1234
+{tl_a}, location ,\"bay 3, rack 2\"
+{tl_b},timeline.firmware,\"'2'\"
+
+{tl_b},calibrated,TRUE
+{tl_b},gain,0.5
+"
+        );
+        let manifest = parse_timeline_attr_manifest(csv.as_bytes(), b',').unwrap();
+        assert_eq!(
+            manifest,
+            TimelineAttrManifest::from([
+                (
+                    tl_a,
+                    vec![
+                        (
+                            AttrKey::new("name".to_owned()),
+                            AttrVal::from("sensor-board-1")
+                        ),
+                        (AttrKey::new("serial".to_owned()), AttrVal::Integer(1234)),
+                        (
+                            AttrKey::new("location".to_owned()),
+                            AttrVal::from("bay 3, rack 2")
+                        ),
+                    ]
+                ),
+                (
+                    tl_b,
+                    vec![
+                        (
+                            AttrKey::new("timeline.firmware".to_owned()),
+                            AttrVal::from("2")
+                        ),
+                        (AttrKey::new("calibrated".to_owned()), AttrVal::Bool(true)),
+                        (AttrKey::new("gain".to_owned()), AttrVal::from(0.5)),
+                    ]
+                ),
+            ])
+        );
+
+        let tsv = format!("{tl_a}\tname\tx\n{tl_a}\tonly-two-fields\n");
+        assert!(matches!(
+            parse_timeline_attr_manifest(tsv.as_bytes(), b'\t'),
+            Err(AttrManifestError::FieldCount { line: 2, found: 2 })
+        ));
+        assert!(matches!(
+            parse_timeline_attr_manifest("nope,k,v".as_bytes(), b','),
+            Err(AttrManifestError::TimelineId { line: 1, .. })
+        ));
+
+        let (mut client, transport) = recorded_client().await;
+        let reports = client
+            .apply_timeline_attr_manifest(&manifest)
+            .await
+            .unwrap();
+        assert!(reports.values().all(|r| r.all_accepted()));
+
+        let mut sent: BTreeMap<TimelineId, BTreeMap<String, AttrVal>> = BTreeMap::new();
+        for (timeline, attrs) in sent_timeline_attrs(&sent_messages(&transport)) {
+            sent.entry(timeline.unwrap()).or_default().extend(attrs);
+        }
+        assert_eq!(
+            sent,
+            BTreeMap::from([
+                (
+                    tl_a,
+                    BTreeMap::from([
+                        ("timeline.name".to_owned(), AttrVal::from("sensor-board-1")),
+                        ("timeline.serial".to_owned(), AttrVal::Integer(1234)),
+                        (
+                            "timeline.location".to_owned(),
+                            AttrVal::from("bay 3, rack 2")
+                        ),
+                    ])
+                ),
+                (
+                    tl_b,
+                    BTreeMap::from([
+                        ("timeline.name".to_owned(), AttrVal::from(tl_b.to_string())),
+                        ("timeline.firmware".to_owned(), AttrVal::from("2")),
+                        ("timeline.calibrated".to_owned(), AttrVal::Bool(true)),
+                        ("timeline.gain".to_owned(), AttrVal::from(0.5)),
+                    ])
+                ),
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn max_string_attr_len_policies() {
+        let (mut client, transport) = recorded_client().await;
+        client.disable_auto_timestamp();
+        client
+            .switch_timeline(TimelineId::allocate())
+            .await
+            .unwrap();
+        let long = "x".repeat(100);
+
+        client.set_max_string_attr_len(16, AttrLimitPolicy::Truncate);
+        client
+            .send_event("e", 0, [("text", long.as_str().into()), ("n", 1.into())])
+            .await
+            .unwrap();
+
+        client.set_max_string_attr_len(16, AttrLimitPolicy::Error);
+        assert!(matches!(
+            client
+                .send_event("e", 1, [("text", long.as_str().into())])
+                .await,
+            Err(DynamicIngestError::StringAttrTooLong {
+                len: 100,
+                max: 16,
+                ..
+            })
+        ));
+
+        client.clear_max_string_attr_len();
+        client
+            .send_event("e", 2, [("text", long.as_str().into())])
+            .await
+            .unwrap();
+        client.flush().await.unwrap();
+
+        let events: Vec<BTreeMap<String, AttrVal>> = sent_event_attrs(&sent_messages(&transport))
+            .into_iter()
+            .map(|attrs| attrs.into_iter().collect())
+            .collect();
+        assert_eq!(events.len(), 2);
+
+        let AttrVal::String(truncated) = &events[0]["event.text"] else {
+            panic!("expected a string");
+        };
+        assert_eq!(truncated.len(), 16);
+        assert_eq!(truncated.as_ref(), format!("{}…", "x".repeat(13)));
+        assert_eq!(events[0]["event.text.truncated"], AttrVal::Bool(true));
+        assert_eq!(events[0]["event.n"], AttrVal::Integer(1));
+
+        assert_eq!(events[1]["event.text"], AttrVal::String(long.into()));
+        assert!(!events[1].contains_key("event.text.truncated"));
+    }
+
+    #[test]
+    fn truncate_string_respects_char_boundaries() {
+        assert_eq!(truncate_string("abcdef", 5), "ab…");
+        assert_eq!(truncate_string("ééé", 5), "é…");
+        assert_eq!(truncate_string("abcdef", 2), "ab");
+        assert_eq!(truncate_string("ééé", 1), "");
+    }
+
+    #[tokio::test]
+    async fn max_attrs_per_event_policies() {
+        let (mut client, transport) = recorded_client().await;
+        client.disable_auto_timestamp();
+        client
+            .switch_timeline(TimelineId::allocate())
+            .await
+            .unwrap();
+        let attrs = || [("a", 1.into()), ("b", 2.into()), ("c", 3.into())];
+
+        // Unlimited by default
+        client.send_event("ev", 0, attrs()).await.unwrap();
+
+        client.set_max_attrs_per_event(2, AttrLimitPolicy::Truncate);
+        client.send_event("ev", 1, attrs()).await.unwrap();
+
+        client.set_max_attrs_per_event(2, AttrLimitPolicy::Error);
+        let err = client.send_event("ev", 2, attrs()).await.unwrap_err();
+        assert!(matches!(
+            err,
+            DynamicIngestError::TooManyAttrs { count: 3, max: 2 }
+        ));
+        client
+            .send_event("ev", 3, [("a", 1.into()), ("b", 2.into())])
+            .await
+            .unwrap();
+
+        // Counts include event.name
+        assert_eq!(sent_events(&transport), vec![4, 3, 3]);
+    }
+
+    #[tokio::test]
+    async fn truncated_companions_count_against_max_attrs() {
+        let (mut client, transport) = recorded_client().await;
+        client.disable_auto_timestamp();
+        client
+            .switch_timeline(TimelineId::allocate())
+            .await
+            .unwrap();
+        client.set_max_string_attr_len(4, AttrLimitPolicy::Truncate);
+        let long = "x".repeat(10);
+        let attrs = || {
+            [
+                ("a", 1.into()),
+                ("text", long.as_str().into()),
+                ("c", 3.into()),
+            ]
+        };
+
+        client.set_max_attrs_per_event(3, AttrLimitPolicy::Truncate);
+        client.send_event("ev", 0, attrs()).await.unwrap();
+
+        client.set_max_attrs_per_event(2, AttrLimitPolicy::Truncate);
+        client.send_event("ev", 1, attrs()).await.unwrap();
+
+        client.set_max_attrs_per_event(3, AttrLimitPolicy::Error);
+        assert!(matches!(
+            client.send_event("ev", 2, attrs()).await,
+            Err(DynamicIngestError::TooManyAttrs { count: 4, max: 3 })
+        ));
+        client.flush().await.unwrap();
+
+        let events: Vec<BTreeMap<String, AttrVal>> = sent_event_attrs(&sent_messages(&transport))
+            .into_iter()
+            .map(|attrs| attrs.into_iter().collect())
+            .collect();
+        assert_eq!(events.len(), 2);
+        // event.name, plus 'a' and 'text' with its companion
+        assert_eq!(events[0].len(), 4);
+        assert_eq!(events[0]["event.text.truncated"], AttrVal::Bool(true));
+        // The truncated 'text' doesn't fit with its companion, so it's dropped
+        assert_eq!(events[1].len(), 2);
+        assert!(events[1].contains_key("event.a"));
+    }
+
+    /// The events sent on `timeline`: their orderings and attributes
+    fn events_on(sent: &[SentAttrs], timeline: TimelineId) -> Vec<(&[u8], &[NamedAttr])> {
+        sent.iter()
+            .filter_map(|s| match s {
+                SentAttrs::Event {
+                    timeline: Some(tl),
+                    be_ordering,
+                    attrs,
+                } if *tl == timeline => Some((be_ordering.as_slice(), attrs.as_slice())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn heartbeat_cadence() {
+        let (mut client, transport) = recorded_client().await;
+        let (tl, heartbeat_tl) = (TimelineId::allocate(), TimelineId::allocate());
+        client.switch_timeline(tl).await.unwrap();
+        client
+            .start_heartbeat_with(
+                Heartbeat::new(Duration::from_millis(50), "heartbeat")
+                    .with_timeline(heartbeat_tl)
+                    .with_attr("plugin", "test"),
+            )
+            .await
+            .unwrap();
+        for _ in 0..6 {
+            advance(Duration::from_millis(50)).await;
+        }
+        client.send_event("ev", 0, []).await.unwrap();
+        client.close().await.unwrap();
+
+        let sent = sent_attrs(&sent_messages(&transport));
+        let heartbeats = events_on(&sent, heartbeat_tl);
+        assert_eq!(heartbeats.len(), 6);
+        let (be_ordering, attrs) = heartbeats[1];
+        assert_eq!(be_ordering, [1]);
+        assert!(attrs.contains(&("event.name".to_owned(), "heartbeat".into())));
+        assert!(attrs.contains(&("event.plugin".to_owned(), "test".into())));
+
+        // The client's own event still went to its own timeline
+        let own = events_on(&sent, tl);
+        assert_eq!(own.len(), 1);
+        assert!(own[0].1.contains(&("event.name".to_owned(), "ev".into())));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn heartbeat_gets_its_own_timeline() {
+        let (mut client, transport) = recorded_client().await;
+        let tl = TimelineId::allocate();
+        client.switch_timeline(tl).await.unwrap();
+        client
+            .start_heartbeat(Duration::from_millis(50), "heartbeat")
+            .await
+            .unwrap();
+        for _ in 0..2 {
+            advance(Duration::from_millis(50)).await;
+        }
+        client.send_event("ev", 0, []).await.unwrap();
+        client.close().await.unwrap();
+
+        // The heartbeat timeline is named, without disturbing the client's own
+        let messages = sent_messages(&transport);
+        let heartbeat_tl = sent_timeline_attrs(&messages)
+            .into_iter()
+            .find(|(_, attrs)| attrs.contains(&("timeline.name".to_owned(), "heartbeat".into())))
+            .and_then(|(timeline, _)| timeline)
+            .unwrap();
+        assert_ne!(heartbeat_tl, tl);
+
+        let sent = sent_attrs(&messages);
+        let orderings: Vec<&[u8]> = events_on(&sent, heartbeat_tl)
+            .into_iter()
+            .map(|(ordering, _)| ordering)
+            .collect();
+        assert_eq!(orderings, vec![[0], [1]]);
+        assert_eq!(events_on(&sent, tl).len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_ping() {
+        let (mut client, transport) = recorded_client().await;
+        let pings = || {
+            transport
+                .frames()
+                .iter()
+                .filter(|f| {
+                    matches!(
+                        minicbor::decode::<IngestMessage>(f),
+                        Ok(IngestMessage::IngestStatusRequest {})
+                    )
+                })
+                .count()
+        };
+        transport
+            .push_response(&IngestResponse::IngestStatusResponse {
+                current_timeline: None,
+                events_received: 0,
+                events_written: 0,
+                events_pending: 0,
+                error_count: None,
+            })
+            .unwrap();
+
+        client.set_idle_ping(Duration::from_millis(100));
+        client
+            .switch_timeline(TimelineId::allocate())
+            .await
+            .unwrap();
+
+        // Kept busy, so no pings
+        for i in 0..5 {
+            advance(Duration::from_millis(40)).await;
+            client.send_event("ev", i, []).await.unwrap();
+        }
+        assert_eq!(pings(), 0);
+
+        // Idle; the ping is answered
+        advance(Duration::from_millis(150)).await;
+        assert_eq!(pings(), 1);
+        assert!(!client.connection_lost());
+
+        // Idle again; with no answer, the connection is given up on
+        advance(Duration::from_millis(100)).await;
+        assert_eq!(pings(), 2);
+        assert!(client.connection_lost());
+        advance(Duration::from_millis(150)).await;
+        assert_eq!(pings(), 2);
+
+        // The next use tries to reconnect, which fails without an endpoint
+        assert!(matches!(
+            client.send_event("ev", 5, []).await,
+            Err(DynamicIngestError::ReconnectFailed(_))
+        ));
+    }
+
+    /// Move paused time on, and let the background tasks catch up
+    async fn advance(duration: Duration) {
+        tokio::time::advance(duration).await;
+        for _ in 0..8 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn send_measurement() {
+        let (mut client, transport) = recorded_client().await;
+        client
+            .switch_timeline(TimelineId::allocate())
+            .await
+            .unwrap();
+        client
+            .send_measurement(
+                "sample",
+                0,
+                "supply.voltage",
+                3.3,
+                "V",
+                Nanoseconds::from(1000),
+            )
+            .await
+            .unwrap();
+
+        let attrs: BTreeMap<String, AttrVal> = sent_event_attrs(&sent_messages(&transport))
+            .pop()
+            .unwrap()
+            .into_iter()
+            .collect();
+        let expected = [
+            ("event.name", AttrVal::from("sample")),
+            ("event.supply.voltage", AttrVal::from(3.3)),
+            ("event.supply.voltage.unit", AttrVal::from("V")),
+            (
+                "event.timestamp",
+                AttrVal::Timestamp(Nanoseconds::from(1000)),
+            ),
+        ];
+        assert_eq!(
+            attrs,
+            expected
+                .into_iter()
+                .map(|(k, v)| (k.to_owned(), v))
+                .collect()
+        );
+
+        // Keys are checked once normalized
+        for (key, reserved) in [
+            ("timestamp", "event.timestamp"),
+            ("event.internal.value", "event.internal.value"),
+        ] {
+            let err = client
+                .send_measurement("sample", 1, key, 3.3, "V", Nanoseconds::from(2000))
+                .await
+                .unwrap_err();
+            assert!(matches!(err, DynamicIngestError::ReservedAttrKey { key } if key == reserved));
+        }
+        assert_eq!(sent_event_attrs(&sent_messages(&transport)).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn send_event_at_system_time() {
+        let (mut client, transport) = recorded_client().await;
+        client
+            .switch_timeline(TimelineId::allocate())
+            .await
+            .unwrap();
+        let t = SystemTime::UNIX_EPOCH + Duration::new(1_700_000_000, 5);
+        client
+            .send_event_at("ev", 0, t, [("timestamp", 1.into())])
+            .await
+            .unwrap();
+
+        let timestamps: Vec<AttrVal> = sent_event_attrs(&sent_messages(&transport))
+            .into_iter()
+            .flatten()
+            .filter(|(k, _)| k == "event.timestamp")
+            .map(|(_, v)| v)
+            .collect();
+        assert_eq!(
+            timestamps,
+            vec![AttrVal::Timestamp(Nanoseconds::from(
+                1_700_000_000_000_000_005
+            ))]
+        );
+    }
+}
@@ -9,7 +9,10 @@ pub mod ingest;
 #[cfg(feature = "deviant")]
 pub mod mutation;
 
-use crate::api::types::{AttrKey, AttrVal};
+#[cfg(feature = "pyo3")]
+pub mod python;
+
+use crate::api::types::{AttrKey, AttrKeyCase, AttrVal};
 use crate::auth_token::{self, AuthToken, MODALITY_AUTH_TOKEN_ENV_VAR};
 use crate::reflector_config::{self, AttrKeyEqValuePair, ConfigLoadError, TopLevelIngest};
 use clap::Parser;
@@ -18,6 +21,7 @@ use std::future::Future;
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
 use url::Url;
 
 pub const MODALITY_STORAGE_SERVICE_PORT_DEFAULT: u16 = 14182;
@@ -29,11 +33,39 @@ pub const CLI_TEMPLATE: &str = "\
             {all-args}\
         ";
 
+/// Give a plugin's command line the standard help layout, [CLI_TEMPLATE], so
+/// `--help` looks the same across plugins.
+///
+/// With the derive API, either set the attribute directly,
+/// `#[command(help_template = CLI_TEMPLATE)]`, or apply this to
+/// `Opts::command()` and parse with [clap::FromArgMatches]:
+///
+/// ```
+/// use auxon_sdk::plugin_utils::apply_cli_template;
+/// use clap::{CommandFactory, FromArgMatches, Parser};
+///
+/// /// My collector
+/// #[derive(Parser)]
+/// struct Opts {
+///     #[clap(long)]
+///     port: Option<u16>,
+/// }
+///
+/// let matches = apply_cli_template(Opts::command()).get_matches_from(["my-collector"]);
+/// let opts = Opts::from_arg_matches(&matches).unwrap();
+/// # assert!(opts.port.is_none());
+/// ```
+pub fn apply_cli_template(cmd: clap::Command) -> clap::Command {
+    cmd.help_template(CLI_TEMPLATE)
+}
+
 /// Handles boilerplate setup for:
 /// * tracing_subscriber configuration
 /// * Signal pipe fixup
 /// * Printing out errors
 /// * Exit code management
+/// * Reading the auth token from the environment; an empty token is used if
+///   there is none, unless [MODALITY_REQUIRE_AUTH_TOKEN_ENV_VAR] is set
 ///
 /// The server constructor function consumes config, custom cli args, and a shutdown signal future,
 /// then returns an indefinitely-running future that represents the server.
@@ -56,6 +88,47 @@ where
         Opts,
         Pin<Box<dyn Future<Output = ()> + Send + 'static>>,
     ) -> ServerFuture,
+{
+    server_main_with_on_shutdown(server_constructor, || async {})
+}
+
+/// Like [server_main], but runs `on_shutdown` once the server is done, for
+/// cleanup like closing files or sending a final summary event.
+///
+/// The shutdown happens in this order:
+/// 1. The server future finishes, or a CTRL+C style signal arrives, in
+///    which case the shutdown signal future completes and the server future
+///    is dropped.
+/// 2. `on_shutdown` runs to completion, on the same tokio runtime as the
+///    server.
+/// 3. The runtime is shut down.
+/// 4. With the `modality_tracing` feature, the tracing ingest handle is
+///    finished, flushing any trace events, including those from
+///    `on_shutdown`.
+///
+/// Returns the process's desired exit code, decided by step 1.
+pub fn server_main_with_on_shutdown<
+    Opts,
+    ServerFuture,
+    ServerConstructor,
+    OnShutdown,
+    OnShutdownFuture,
+>(
+    server_constructor: ServerConstructor,
+    on_shutdown: OnShutdown,
+) -> i32
+where
+    Opts: Parser,
+    Opts: BearingConfigFilePath,
+    ServerFuture: Future<Output = Result<(), Box<dyn std::error::Error + 'static>>> + 'static,
+    ServerConstructor: FnOnce(
+        reflector_config::Config,
+        AuthToken,
+        Opts,
+        Pin<Box<dyn Future<Output = ()> + Send + 'static>>,
+    ) -> ServerFuture,
+    OnShutdown: FnOnce() -> OnShutdownFuture,
+    OnShutdownFuture: Future<Output = ()>,
 {
     let _ = reset_signal_pipe_handler();
     let opts = match Opts::try_parse_from(std::env::args()) {
@@ -66,13 +139,13 @@ where
         {
             // Need to print to stdout for these command variants in support of manual generation
             if let Err(e) = e.print() {
-                error_print(&e);
+                print_error_chain(&e);
                 return exitcode::SOFTWARE;
             }
             return exitcode::OK;
         }
         Err(e) => {
-            error_print(&e);
+            print_error_chain(&e);
             return exitcode::SOFTWARE;
         }
     };
@@ -88,9 +161,11 @@ where
                 );
                 let exit_code = match &config_load_error {
                     ConfigLoadError::Io(_) => exitcode::IOERR,
+                    #[cfg(feature = "config_http")]
+                    ConfigLoadError::Fetch { .. } => exitcode::UNAVAILABLE,
                     _ => exitcode::CONFIG,
                 };
-                error_print(&config_load_error);
+                print_error_chain(&config_load_error);
                 return exit_code;
             }
         }
@@ -105,9 +180,11 @@ where
                 );
                 let exit_code = match &config_load_error {
                     ConfigLoadError::Io(_) => exitcode::IOERR,
+                    #[cfg(feature = "config_http")]
+                    ConfigLoadError::Fetch { .. } => exitcode::UNAVAILABLE,
                     _ => exitcode::CONFIG,
                 };
-                error_print(&config_load_error);
+                print_error_chain(&config_load_error);
                 return exit_code;
             }
         }
@@ -169,7 +246,7 @@ where
                 }
                 Err(modality_init_err) => {
                     eprintln!("Modality tracing layer initialization error.");
-                    error_print(&modality_init_err);
+                    print_error_chain(&modality_init_err);
                     // Only do trace output through the stdout formatter
                     (
                         tracing::Dispatch::new(
@@ -191,24 +268,9 @@ where
         maybe_modality_ingest_handle
     };
 
-    let auth_token = if let Ok(auth_token_env_str) = std::env::var(MODALITY_AUTH_TOKEN_ENV_VAR) {
-        match auth_token::decode_auth_token_hex(auth_token_env_str.as_str()) {
-            Ok(at) => at,
-            Err(auth_token_deserialization_err) => {
-                tracing::error!(
-                    err = &auth_token_deserialization_err as &dyn std::error::Error,
-                    "Failed to interpret auth token provide by environment variable, exiting."
-                );
-                error_print(&auth_token_deserialization_err);
-                return exitcode::CONFIG;
-            }
-        }
-    } else {
-        tracing::warn!(
-            "No auth token provided by environment variable {}, falling back to empty auth token",
-            MODALITY_AUTH_TOKEN_ENV_VAR
-        );
-        AuthToken::from(vec![])
+    let auth_token = match server_auth_token_from_env() {
+        Ok(at) => at,
+        Err(exit_code) => return exit_code,
     };
 
     let runtime = tokio::runtime::Builder::new_multi_thread()
@@ -229,9 +291,41 @@ where
         }),
     );
 
+    run_server(
+        runtime,
+        ctrlc,
+        shutdown_tx,
+        server_done,
+        on_shutdown,
+        move || {
+            #[cfg(feature = "modality_tracing")]
+            {
+                if let Some(modality_ingest_handle) = maybe_modality {
+                    modality_ingest_handle.finish();
+                }
+            }
+        },
+    )
+}
+
+/// Steps 1 to 4 of the shutdown order described at [server_main_with_on_shutdown],
+/// with `finish_ingest` as step 4. Returns the exit code.
+fn run_server<ServerFuture, OnShutdown, OnShutdownFuture>(
+    runtime: tokio::runtime::Runtime,
+    ctrlc: impl Future<Output = std::io::Result<()>>,
+    shutdown_tx: tokio::sync::oneshot::Sender<()>,
+    server_done: ServerFuture,
+    on_shutdown: OnShutdown,
+    finish_ingest: impl FnOnce(),
+) -> i32
+where
+    ServerFuture: Future<Output = Result<(), Box<dyn std::error::Error + 'static>>>,
+    OnShutdown: FnOnce() -> OnShutdownFuture,
+    OnShutdownFuture: Future<Output = ()>,
+{
     let mut maybe_shutdown_tx = Some(shutdown_tx);
     let out_exit_code = runtime.block_on(async {
-        tokio::select! {
+        let exit_code = tokio::select! {
             signal_result = ctrlc => {
                 match signal_result {
                     Ok(()) => {
@@ -245,7 +339,7 @@ where
                         if let Some(shutdown_tx) = maybe_shutdown_tx.take() {
                             let _ = shutdown_tx.send(());
                         }
-                        error_print(&io_err);
+                        print_error_chain(&io_err);
                         tracing::error!("Failed to install ctrl+c handler, exiting.");
                         exitcode::IOERR
                     }
@@ -259,40 +353,183 @@ where
                     },
                     Err(e) => {
                         tracing::error!("Server crashed early, exiting.");
-                        error_print(e.as_ref());
+                        print_error_chain(e.as_ref());
                         exitcode::SOFTWARE
                     }
                 }
             }
-        }
+        };
+        on_shutdown().await;
+        exit_code
     });
     // Drop the runtime a little ahead of function exit
     // in order to ensure that the shutdown_tx side of
     // the shutdown signal channel does not drop first.
     std::mem::drop(runtime);
-    #[cfg(feature = "modality_tracing")]
+    finish_ingest();
+    let _maybe_shutdown_tx = maybe_shutdown_tx;
+    out_exit_code
+}
+
+/// How long the panic hook installed by [install_flush_on_panic] waits for
+/// the ingest flush to finish.
+#[cfg(feature = "modality_tracing")]
+pub const FLUSH_ON_PANIC_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Install a panic hook that flushes the tracing ingest thread or task behind
+/// `handle`, so the trace events leading up to a panic still reach Modality.
+///
+/// The flush is best-effort: the hook waits at most [FLUSH_ON_PANIC_TIMEOUT]
+/// for it, then calls the previously installed panic hook.
+#[cfg(feature = "modality_tracing")]
+pub fn install_flush_on_panic(handle: crate::tracing::ModalityIngestFlushHandle) {
+    let prev_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        handle.flush_blocking(FLUSH_ON_PANIC_TIMEOUT);
+        prev_hook(info);
+    }));
+}
+
+/// If set to `1` or `true`, [server_main] exits with `exitcode::CONFIG` when
+/// no auth token is provided, instead of carrying on with an empty one.
+pub const MODALITY_REQUIRE_AUTH_TOKEN_ENV_VAR: &str = "MODALITY_REQUIRE_AUTH_TOKEN";
+
+/// Read the auth token for [server_main] from the environment, or the exit
+/// code to give up with.
+fn server_auth_token_from_env() -> Result<AuthToken, i32> {
+    if let Ok(auth_token_env_str) = std::env::var(MODALITY_AUTH_TOKEN_ENV_VAR) {
+        match auth_token::decode_auth_token_hex(auth_token_env_str.as_str()) {
+            Ok(at) => Ok(at),
+            Err(auth_token_deserialization_err) => {
+                tracing::error!(
+                    err = &auth_token_deserialization_err as &dyn std::error::Error,
+                    "Failed to interpret auth token provide by environment variable, exiting."
+                );
+                print_error_chain(&auth_token_deserialization_err);
+                Err(exitcode::CONFIG)
+            }
+        }
+    } else if std::env::var(MODALITY_REQUIRE_AUTH_TOKEN_ENV_VAR)
+        .is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
     {
-        if let Some(modality_ingest_handle) = maybe_modality {
-            modality_ingest_handle.finish();
+        tracing::error!(
+            "No auth token provided by environment variable {}, and {} is set, exiting.",
+            MODALITY_AUTH_TOKEN_ENV_VAR,
+            MODALITY_REQUIRE_AUTH_TOKEN_ENV_VAR
+        );
+        eprintln!(
+            "No auth token provided. Set {MODALITY_AUTH_TOKEN_ENV_VAR} to a hex-encoded auth token, \
+             or unset {MODALITY_REQUIRE_AUTH_TOKEN_ENV_VAR} to connect with an empty one."
+        );
+        Err(exitcode::CONFIG)
+    } else {
+        tracing::warn!(
+            "No auth token provided by environment variable {}, falling back to empty auth token",
+            MODALITY_AUTH_TOKEN_ENV_VAR
+        );
+        Ok(AuthToken::from(vec![]))
+    }
+}
+
+/// If set to a non-empty value, colored output is disabled; see
+/// <https://no-color.org>.
+pub const NO_COLOR_ENV_VAR: &str = "NO_COLOR";
+
+/// Whether [print_error_chain] and [init_tracing] may use ANSI colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    /// Use colors when writing to a terminal, unless [NO_COLOR_ENV_VAR] is set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+static COLOR_CHOICE: AtomicU8 = AtomicU8::new(ColorChoice::Auto as u8);
+
+/// Set the color policy for the SDK's own output. To affect [init_tracing],
+/// call this first.
+pub fn set_color_choice(choice: ColorChoice) {
+    COLOR_CHOICE.store(choice as u8, Ordering::Relaxed);
+}
+
+pub fn color_choice() -> ColorChoice {
+    match COLOR_CHOICE.load(Ordering::Relaxed) {
+        x if x == ColorChoice::Always as u8 => ColorChoice::Always,
+        x if x == ColorChoice::Never as u8 => ColorChoice::Never,
+        _ => ColorChoice::Auto,
+    }
+}
+
+/// Whether output to a stream may be colored, under the current
+/// [ColorChoice]. `is_terminal` says whether the stream is a terminal.
+pub fn color_enabled(is_terminal: bool) -> bool {
+    match color_choice() {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            is_terminal && std::env::var_os(NO_COLOR_ENV_VAR).map_or(true, |v| v.is_empty())
         }
     }
-    let _maybe_shutdown_tx = maybe_shutdown_tx;
-    out_exit_code
 }
 
-pub(crate) fn error_print(err: &dyn std::error::Error) {
-    fn print_err_node(err: &dyn std::error::Error) {
-        eprintln!("{err}");
+/// Print `err` to stderr, followed by a "Caused by: " line for each
+/// error in its `source()` chain. This is the format used for the
+/// SDK's own error output.
+///
+/// Errors may carry their own ANSI styling; it's removed unless
+/// [color_enabled] for stderr.
+pub fn print_error_chain(err: &dyn std::error::Error) {
+    use std::io::IsTerminal;
+    eprint!(
+        "{}",
+        error_chain_output(err, color_enabled(std::io::stderr().is_terminal()))
+    );
+}
+
+fn error_chain_output(err: &dyn std::error::Error, color: bool) -> String {
+    let out = format_error_chain(err);
+    if color {
+        out
+    } else {
+        strip_ansi_escapes(&out)
     }
+}
 
-    print_err_node(err);
+/// Remove ANSI escape sequences, e.g. colors, from `s`
+fn strip_ansi_escapes(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        // A control sequence runs up to a final byte; other escapes are two
+        // characters long
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Render `err` and its `source()` chain, one error per line, in the
+/// same format as [print_error_chain].
+pub fn format_error_chain(err: &dyn std::error::Error) -> String {
+    let mut out = format!("{err}\n");
 
     let mut cause = err.source();
     while let Some(err) = cause {
-        eprint!("Caused by: ");
-        print_err_node(err);
+        out.push_str(&format!("Caused by: {err}\n"));
         cause = err.source();
     }
+
+    out
 }
 
 // Used to prevent panics on broken pipes.
@@ -346,15 +583,85 @@ pub enum ProtocolParentError {
     IngestProtocolParentAddressResolution(Url),
 }
 
+/// Merge timeline attributes from the config file and the command line, with the
+/// command line taking precedence. Keys are used exactly as given; see
+/// [`merge_timeline_attrs_normalized`] to fold keys that differ only in whitespace or case.
 pub fn merge_timeline_attrs(
     cli_provided_attrs: &[AttrKeyEqValuePair],
     cfg: &reflector_config::Config,
 ) -> BTreeMap<AttrKey, AttrVal> {
-    // Merge additional and override timeline attrs from cfg and opts
-    // TODO deal with conflicting reserved attrs in #2098
+    merge_timeline_attrs_inner(cli_provided_attrs, cfg, None)
+}
+
+/// Like [`merge_timeline_attrs`], but every key is passed through
+/// [`AttrKey::normalized`] with the given case policy before merging, so that
+/// e.g. `" Foo "` and `"foo"` end up as one attribute under [`AttrKeyCase::Lowercase`].
+pub fn merge_timeline_attrs_normalized(
+    cli_provided_attrs: &[AttrKeyEqValuePair],
+    cfg: &reflector_config::Config,
+    case: AttrKeyCase,
+) -> BTreeMap<AttrKey, AttrVal> {
+    merge_timeline_attrs_inner(cli_provided_attrs, cfg, Some(case))
+}
+
+/// What [`merge_timeline_attrs_checked`] does when config or the command line
+/// sets one of the [`reserved_attribute_keys`](crate::api::reserved_attribute_keys)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReservedAttrPolicy {
+    /// Keep the attribute
+    Allow,
+    /// Drop the attribute, and log a warning
+    #[default]
+    Warn,
+    /// Fail with [`ReservedAttrKeyError`]
+    Reject,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("The timeline attribute '{0}' is reserved, and can't be set by configuration")]
+pub struct ReservedAttrKeyError(pub AttrKey);
+
+/// Like [`merge_timeline_attrs`], but reserved attribute keys are handled
+/// according to `policy`, so that configuration can't clobber attributes
+/// with a special meaning.
+pub fn merge_timeline_attrs_checked(
+    cli_provided_attrs: &[AttrKeyEqValuePair],
+    cfg: &reflector_config::Config,
+    policy: ReservedAttrPolicy,
+) -> Result<BTreeMap<AttrKey, AttrVal>, ReservedAttrKeyError> {
+    let mut timeline_attrs = merge_timeline_attrs(cli_provided_attrs, cfg);
+    if policy == ReservedAttrPolicy::Allow {
+        return Ok(timeline_attrs);
+    }
+    if let Some(key) = timeline_attrs.keys().find(|k| k.is_reserved()).cloned() {
+        if policy == ReservedAttrPolicy::Reject {
+            return Err(ReservedAttrKeyError(key));
+        }
+    }
+    timeline_attrs.retain(|k, _| {
+        let reserved = k.is_reserved();
+        if reserved {
+            tracing::warn!("Ignoring reserved timeline attribute '{k}' set by configuration");
+        }
+        !reserved
+    });
+    Ok(timeline_attrs)
+}
+
+fn merge_timeline_attrs_inner(
+    cli_provided_attrs: &[AttrKeyEqValuePair],
+    cfg: &reflector_config::Config,
+    normalize: Option<AttrKeyCase>,
+) -> BTreeMap<AttrKey, AttrVal> {
+    // Merge additional and override timeline attrs from cfg and opts;
+    // see merge_timeline_attrs_checked for dealing with reserved attrs
     let mut timeline_attrs = BTreeMap::new();
 
-    fn ensure_timeline_prefix(k: AttrKey) -> AttrKey {
+    let prep_key = |k: AttrKey| -> AttrKey {
+        let k = match normalize {
+            Some(case) => k.normalized(case),
+            None => k,
+        };
         if k.as_ref().starts_with("timeline.") {
             k
         } else if k.as_ref().starts_with('.') {
@@ -362,7 +669,7 @@ pub fn merge_timeline_attrs(
         } else {
             AttrKey::from("timeline.".to_owned() + k.as_ref())
         }
-    }
+    };
     if let Some(tli) = &cfg.ingest {
         for kvp in tli
             .timeline_attributes
@@ -370,7 +677,7 @@ pub fn merge_timeline_attrs(
             .iter()
             .cloned()
         {
-            let _ = timeline_attrs.insert(ensure_timeline_prefix(kvp.0), kvp.1);
+            let _ = timeline_attrs.insert(prep_key(kvp.0), kvp.1);
         }
         for kvp in tli
             .timeline_attributes
@@ -378,23 +685,69 @@ pub fn merge_timeline_attrs(
             .iter()
             .cloned()
         {
-            let _ = timeline_attrs.insert(ensure_timeline_prefix(kvp.0), kvp.1);
+            let _ = timeline_attrs.insert(prep_key(kvp.0), kvp.1);
         }
     }
     // The CLI-provided attrs will take precedence over config
     for kvp in cli_provided_attrs.iter().cloned() {
-        let _ = timeline_attrs.insert(ensure_timeline_prefix(kvp.0), kvp.1);
+        let _ = timeline_attrs.insert(prep_key(kvp.0), kvp.1);
     }
     timeline_attrs
 }
 
 /// Initialize the `tracing` crate with `tracing_subscriber::EnvFilter`. If
 /// `RUST_LOG` is not set, default to setting the current module to 'info'.
+/// Output is colored according to [color_enabled] for stdout.
 /// Will panic if the tracing subscriber cannot be initialized.
 #[macro_export]
 macro_rules! init_tracing {
     () => {
-        let builder = ::tracing_subscriber::fmt::Subscriber::builder();
+        let builder = ::tracing_subscriber::fmt::Subscriber::builder().with_ansi(
+            $crate::plugin_utils::color_enabled(::std::io::IsTerminal::is_terminal(
+                &::std::io::stdout(),
+            )),
+        );
+        let env_filter = ::std::env::var(tracing_subscriber::EnvFilter::DEFAULT_ENV)
+            .map(::tracing_subscriber::EnvFilter::new)
+            .unwrap_or_else(|_| {
+                ::tracing_subscriber::EnvFilter::new(format!(
+                    "{}={}",
+                    env!("CARGO_PKG_NAME").replace('-', "_"),
+                    ::tracing::Level::INFO
+                ))
+            });
+        let builder = builder.with_env_filter(env_filter);
+        let subscriber = builder.finish();
+        use ::tracing_subscriber::util::SubscriberInitExt;
+        subscriber
+            .try_init()
+            .expect("Unable to initialize tracing subscriber");
+    };
+    ($env_filter:expr) => {
+        let builder = ::tracing_subscriber::fmt::Subscriber::builder().with_ansi(
+            $crate::plugin_utils::color_enabled(::std::io::IsTerminal::is_terminal(
+                &::std::io::stdout(),
+            )),
+        );
+        let env_filter = ::std::env::var(tracing_subscriber::EnvFilter::DEFAULT_ENV)
+            .map(::tracing_subscriber::EnvFilter::new)
+            .unwrap_or_else(|_| $env_filter);
+        let builder = builder.with_env_filter(env_filter);
+        let subscriber = builder.finish();
+        use ::tracing_subscriber::util::SubscriberInitExt;
+        subscriber
+            .try_init()
+            .expect("Unable to initialize tracing subscriber");
+    };
+}
+
+/// Like [init_tracing], but emits one JSON object per line, for running
+/// under log aggregation. The `EnvFilter` defaults are the same.
+/// Will panic if the tracing subscriber cannot be initialized.
+#[macro_export]
+macro_rules! init_tracing_json {
+    () => {
+        let builder = ::tracing_subscriber::fmt::Subscriber::builder().json();
         let env_filter = ::std::env::var(tracing_subscriber::EnvFilter::DEFAULT_ENV)
             .map(::tracing_subscriber::EnvFilter::new)
             .unwrap_or_else(|_| {
@@ -412,7 +765,7 @@ macro_rules! init_tracing {
             .expect("Unable to initialize tracing subscriber");
     };
     ($env_filter:expr) => {
-        let builder = ::tracing_subscriber::fmt::Subscriber::builder();
+        let builder = ::tracing_subscriber::fmt::Subscriber::builder().json();
         let env_filter = ::std::env::var(tracing_subscriber::EnvFilter::DEFAULT_ENV)
             .map(::tracing_subscriber::EnvFilter::new)
             .unwrap_or_else(|_| $env_filter);
@@ -424,3 +777,282 @@ macro_rules! init_tracing {
             .expect("Unable to initialize tracing subscriber");
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "modality_tracing")]
+    #[test]
+    #[serial_test::serial]
+    fn flush_on_panic() {
+        use crate::ingest_protocol::IngestMessage;
+        use crate::tracing::blocking::{ModalityLayer, Options};
+        use std::time::Duration;
+
+        let (addr, received) = crate::test_fixtures::FakeIngestServer::start_blocking();
+        let (layer, ingest_handle) = ModalityLayer::init_with_options(
+            Options::new()
+                .with_auth("00112233")
+                .with_server_address(addr),
+        )
+        .unwrap();
+        install_flush_on_panic(ingest_handle.flush_handle());
+
+        let panicked = std::thread::spawn(move || {
+            tracing::subscriber::with_default(layer.into_subscriber(), || {
+                tracing::info!("before the panic");
+                panic!("flush_on_panic test");
+            })
+        })
+        .join();
+        // Put the default hook back for the other tests
+        let _ = std::panic::take_hook();
+        assert!(panicked.is_err());
+
+        // The event and a flush arrive without finishing the ingest thread
+        let mut saw_event = false;
+        loop {
+            match received.recv_timeout(Duration::from_secs(5)).unwrap() {
+                IngestMessage::Event { .. } => saw_event = true,
+                IngestMessage::Flush {} => break,
+                _ => (),
+            }
+        }
+        assert!(saw_event);
+
+        ingest_handle.finish();
+    }
+
+    #[cfg(feature = "modality_tracing")]
+    #[test]
+    #[serial_test::serial]
+    fn on_shutdown_events_are_flushed() {
+        use crate::ingest_protocol::IngestMessage;
+        use crate::tracing::blocking::{ModalityLayer, Options};
+
+        let (addr, received) = crate::test_fixtures::FakeIngestServer::start_blocking();
+        let (layer, ingest_handle) = ModalityLayer::init_with_options(
+            Options::new()
+                .with_auth("00112233")
+                .with_server_address(addr),
+        )
+        .unwrap();
+        let _subscriber = tracing::subscriber::set_default(layer.into_subscriber());
+
+        // The runtime drives everything on this thread, so the events it
+        // traces go to the subscriber above
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let (shutdown_tx, _shutdown_rx) = tokio::sync::oneshot::channel();
+        let exit_code = run_server(
+            runtime,
+            std::future::pending(),
+            shutdown_tx,
+            async { Ok(()) },
+            || async { tracing::info!(final_summary = 3, "shutting down") },
+            move || ingest_handle.finish(),
+        );
+        assert_eq!(exit_code, exitcode::OK);
+
+        // Everything sent before the ingest thread finished has arrived
+        let messages: Vec<IngestMessage> = received.iter().collect();
+        let summary = crate::test_fixtures::sent_event_attrs(&messages)
+            .into_iter()
+            .flatten()
+            .find(|(k, _)| k == "event.final_summary")
+            .map(|(_, v)| v);
+        assert_eq!(summary, Some(AttrVal::from(3)));
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn missing_auth_token_strictness() {
+        std::env::remove_var(MODALITY_AUTH_TOKEN_ENV_VAR);
+        std::env::remove_var(MODALITY_REQUIRE_AUTH_TOKEN_ENV_VAR);
+        assert_eq!(server_auth_token_from_env().unwrap().as_ref(), &[] as &[u8]);
+
+        std::env::set_var(MODALITY_REQUIRE_AUTH_TOKEN_ENV_VAR, "1");
+        assert_eq!(server_auth_token_from_env().err(), Some(exitcode::CONFIG));
+
+        std::env::set_var(MODALITY_AUTH_TOKEN_ENV_VAR, "abcd");
+        assert_eq!(
+            server_auth_token_from_env().unwrap().as_ref(),
+            &[0xab, 0xcd]
+        );
+
+        std::env::remove_var(MODALITY_AUTH_TOKEN_ENV_VAR);
+        std::env::remove_var(MODALITY_REQUIRE_AUTH_TOKEN_ENV_VAR);
+    }
+
+    #[test]
+    fn cli_template_help() {
+        let mut cmd = apply_cli_template(
+            clap::Command::new("sample-collector")
+                .about("Collects samples")
+                .arg(
+                    clap::Arg::new("port")
+                        .long("port")
+                        .help("Port to listen on"),
+                ),
+        );
+        let help = cmd.render_help().to_string();
+        assert!(
+            help.starts_with("Collects samples\n\nUSAGE:\n    sample-collector [OPTIONS]\n\n"),
+            "{help}"
+        );
+        assert!(help.contains("--port <port>"));
+        assert!(help.contains("Port to listen on"));
+    }
+
+    #[test]
+    fn merge_timeline_attrs_key_normalization() {
+        let cfg = reflector_config::Config {
+            ingest: Some(TopLevelIngest {
+                timeline_attributes: reflector_config::TimelineAttributes {
+                    additional_timeline_attributes: vec![AttrKeyEqValuePair(
+                        AttrKey::from(" Foo "),
+                        AttrVal::from(1),
+                    )],
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let cli = [AttrKeyEqValuePair(AttrKey::from("foo"), AttrVal::from(2))];
+
+        let merged = merge_timeline_attrs(&cli, &cfg);
+        assert_eq!(merged.len(), 2);
+
+        let merged = merge_timeline_attrs_normalized(&cli, &cfg, AttrKeyCase::Lowercase);
+        assert_eq!(
+            merged.into_iter().collect::<Vec<_>>(),
+            vec![(AttrKey::from("timeline.foo"), AttrVal::from(2))]
+        );
+    }
+
+    #[test]
+    fn merge_timeline_attrs_reserved_keys() {
+        let cfg = reflector_config::Config {
+            ingest: Some(TopLevelIngest {
+                timeline_attributes: reflector_config::TimelineAttributes {
+                    override_timeline_attributes: vec![
+                        "run_id = \"mine\"".parse().unwrap(),
+                        "owner = \"me\"".parse().unwrap(),
+                    ],
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let cli = ["internal.clock = 1".parse().unwrap()];
+
+        let allowed = merge_timeline_attrs_checked(&cli, &cfg, ReservedAttrPolicy::Allow).unwrap();
+        assert_eq!(allowed, merge_timeline_attrs(&cli, &cfg));
+        assert_eq!(allowed.len(), 3);
+
+        let warned = merge_timeline_attrs_checked(&cli, &cfg, ReservedAttrPolicy::Warn).unwrap();
+        assert_eq!(
+            warned.into_iter().collect::<Vec<_>>(),
+            vec![(AttrKey::from("timeline.owner"), AttrVal::from("me"))]
+        );
+
+        assert_eq!(
+            merge_timeline_attrs_checked(&cli, &cfg, ReservedAttrPolicy::Reject),
+            Err(ReservedAttrKeyError(AttrKey::from(
+                "timeline.internal.clock"
+            )))
+        );
+    }
+
+    #[test]
+    fn error_chain_format() {
+        #[derive(Debug, thiserror::Error)]
+        #[error("Failed to load config")]
+        struct Outer(#[source] std::io::Error);
+
+        let err = Outer(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no such file",
+        ));
+        let formatted = format_error_chain(&err);
+        let lines: Vec<&str> = formatted.lines().collect();
+        assert_eq!(
+            lines,
+            vec!["Failed to load config", "Caused by: no such file"]
+        );
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn no_color_strips_error_escapes() {
+        #[derive(Debug, thiserror::Error)]
+        #[error("\x1b[1;31mbad\x1b[0m input")]
+        struct Styled;
+
+        std::env::set_var(NO_COLOR_ENV_VAR, "1");
+        let disabled = color_enabled(true);
+        std::env::remove_var(NO_COLOR_ENV_VAR);
+        assert!(!disabled);
+        assert!(color_enabled(true));
+        assert!(!color_enabled(false));
+
+        let out = error_chain_output(&Styled, disabled);
+        assert!(!out.contains('\x1b'));
+        assert_eq!(out, "bad input\n");
+        assert_eq!(
+            error_chain_output(&Styled, true),
+            format_error_chain(&Styled)
+        );
+
+        set_color_choice(ColorChoice::Never);
+        assert!(!color_enabled(true));
+        set_color_choice(ColorChoice::Always);
+        assert!(color_enabled(false));
+        set_color_choice(ColorChoice::Auto);
+    }
+
+    const JSON_TRACING_CHILD_ENV_VAR: &str = "AUXON_SDK_TEST_JSON_TRACING_CHILD";
+
+    /// Installs the global subscriber, so it only does anything when run
+    /// in a child process by `init_tracing_json_emits_json_lines`
+    #[test]
+    fn init_tracing_json_child() {
+        if std::env::var_os(JSON_TRACING_CHILD_ENV_VAR).is_none() {
+            return;
+        }
+        crate::init_tracing_json!();
+        tracing::info!(answer = 42, "hello from json");
+    }
+
+    #[test]
+    fn init_tracing_json_emits_json_lines() {
+        let out = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([
+                "--exact",
+                "plugin_utils::tests::init_tracing_json_child",
+                "--nocapture",
+            ])
+            .env(JSON_TRACING_CHILD_ENV_VAR, "1")
+            .env_remove("RUST_LOG")
+            .output()
+            .unwrap();
+        assert!(out.status.success());
+
+        let stdout = String::from_utf8(out.stdout).unwrap();
+        let events: Vec<serde_json::Value> = stdout
+            .lines()
+            // The test harness may prefix the line with the test name
+            .filter_map(|l| l.find('{').map(|i| &l[i..]))
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["level"], "INFO");
+        assert_eq!(events[0]["fields"]["message"], "hello from json");
+        assert_eq!(events[0]["fields"]["answer"], 42);
+    }
+}
@@ -25,10 +25,11 @@ use crate::{
         },
         mutator::ActuatorDescriptor,
     },
+    reflector_config::{MergeMutatorAttributesError, MutatorAttributes},
 };
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap, HashSet},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 pub trait Mutator {
@@ -41,16 +42,166 @@ pub trait Mutator {
     fn reset(&mut self);
 }
 
+/// The outcome of a Python mutator's `inject` method.
+///
+/// Python mutators may return one of these from `inject` to report whether the
+/// mutation took effect. Returning `None` counts as success, and raising an
+/// exception counts as failure. See [`py_inject_result`].
+#[cfg(feature = "pyo3")]
+#[pyo3::pyclass]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MutationResult {
+    #[pyo3(get)]
+    pub success: bool,
+    #[pyo3(get)]
+    pub message: Option<String>,
+}
+
+#[cfg(feature = "pyo3")]
+#[pyo3::pymethods]
+impl MutationResult {
+    #[staticmethod]
+    pub fn ok() -> Self {
+        MutationResult {
+            success: true,
+            message: None,
+        }
+    }
+
+    #[staticmethod]
+    #[pyo3(signature = (message = None))]
+    pub fn failed(message: Option<String>) -> Self {
+        MutationResult {
+            success: false,
+            message,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        match (&self.message, self.success) {
+            (_, true) => "MutationResult.ok()".to_owned(),
+            (Some(m), false) => format!("MutationResult.failed({m:?})"),
+            (None, false) => "MutationResult.failed()".to_owned(),
+        }
+    }
+}
+
+/// Interpret the outcome of calling a Python mutator's `inject` method.
+///
+/// A raised exception, or a returned [`MutationResult`] that isn't successful,
+/// becomes an error. Any other return value (typically `None`) is a success.
+#[cfg(feature = "pyo3")]
+pub fn py_inject_result(
+    res: pyo3::PyResult<pyo3::Bound<'_, pyo3::PyAny>>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    use pyo3::prelude::*;
+
+    let ret = res?;
+    match ret.extract::<MutationResult>() {
+        Ok(MutationResult {
+            success: false,
+            message,
+        }) => Err(message
+            .unwrap_or_else(|| "Mutator reported a failed injection".to_owned())
+            .into()),
+        _ => Ok(()),
+    }
+}
+
+/// Call a Python mutator's `inject` method with `mutation_id` and `params`,
+/// returning whether the mutation took effect, as [`Mutator::inject`] does.
+/// Failures, as interpreted by [`py_inject_result`], are logged.
+#[cfg(feature = "pyo3")]
+pub fn py_inject(
+    mutator: &pyo3::Bound<'_, pyo3::PyAny>,
+    mutation_id: MutationId,
+    params: pyo3::PyObject,
+) -> bool {
+    use pyo3::prelude::*;
+
+    let res = mutator.call_method1(pyo3::intern!(mutator.py(), "inject"), (mutation_id, params));
+    if let Err(e) = py_inject_result(res) {
+        warn!(
+            err = e.as_ref() as &dyn std::error::Error,
+            "Failed to inject mutation for Python mutator"
+        );
+        false
+    } else {
+        true
+    }
+}
+
+/// A mutator's registration state, as seen by its [`MutatorHost`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MutatorStatus {
+    /// Registered, with no active mutations
+    Idle,
+    /// Registered, with this many mutations currently injected
+    Active(usize),
+    /// Not (or no longer) hosted
+    Deregistered,
+}
+
+/// A handle to a mutator registered with [`MutatorHost::register`].
+///
+/// The host owns the mutator and its connection, so operations on the handle
+/// borrow the host they were registered with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MutatorHandle {
+    mutator_id: MutatorId,
+}
+
+impl MutatorHandle {
+    pub fn id(&self) -> MutatorId {
+        self.mutator_id
+    }
+
+    pub fn status(&self, host: &MutatorHost) -> MutatorStatus {
+        host.mutator_status(&self.mutator_id)
+    }
+
+    /// Clear any mutations still active on the mutator, stop hosting it, and retire it from the
+    /// mutation plane.
+    pub async fn deregister(self, host: &mut MutatorHost) -> Result<(), CommsError> {
+        host.retire_mutator(self.mutator_id).await
+    }
+}
+
+/// Counters describing a [`MutatorHost`]'s mutation plane connection, as returned by
+/// [`MutatorHost::connection_stats`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MutationConnectionStats {
+    /// Number of mutators currently registered
+    pub registered_mutators: usize,
+    /// Number of mutations successfully injected into a hosted mutator
+    pub mutations_injected: u64,
+    /// Number of mutations a hosted mutator failed to inject
+    pub mutations_failed: u64,
+    /// Number of times a hosted mutator was reset
+    pub resets: u64,
+    /// The most recent communication error, if any
+    pub last_error: Option<String>,
+    /// Time since the connection was authenticated
+    pub uptime: Duration,
+}
+
 pub struct MutatorHost {
     participant_id: ParticipantId,
     pub mutation_conn: MutationParentConnection,
     mutators: BTreeMap<MutatorId, Box<dyn Mutator + Send>>,
     active_mutations: HashMap<MutatorId, HashSet<MutationId>>,
 
+    connected_at: Instant,
+    mutations_injected: u64,
+    mutations_failed: u64,
+    resets: u64,
+    last_error: Option<String>,
+
     ingest: Option<super::ingest::Client>,
     ingest_ordering: u128,
     log_comms: bool,
     log_inject_and_clear: bool,
+    mutator_attributes: MutatorAttributes,
 }
 
 impl MutatorHost {
@@ -154,10 +305,17 @@ impl MutatorHost {
             mutators: Default::default(),
             active_mutations: Default::default(),
 
+            connected_at: Instant::now(),
+            mutations_injected: 0,
+            mutations_failed: 0,
+            resets: 0,
+            last_error: None,
+
             ingest,
             ingest_ordering: 0,
             log_comms: true,
             log_inject_and_clear: true,
+            mutator_attributes: Default::default(),
         };
 
         conn.send_event("mutation_plane_connected", []).await;
@@ -177,14 +335,55 @@ impl MutatorHost {
         self.log_inject_and_clear = false;
     }
 
+    /// Apply `attrs` to the description of every mutator this host announces, including ones
+    /// already registered, the next time they're announced. Additional attributes are only
+    /// added to descriptions that don't already have them, while override attributes replace
+    /// whatever the mutator describes itself with. Keys are given the `mutator.` prefix if they
+    /// don't already have it.
+    ///
+    /// [`Config::connect_and_authenticate_mutation`](super::config::Config::connect_and_authenticate_mutation)
+    /// does this with the config's `mutation.mutator-attributes`.
+    pub fn add_mutator_attributes(
+        &mut self,
+        attrs: MutatorAttributes,
+    ) -> Result<(), MergeMutatorAttributesError> {
+        self.mutator_attributes.merge(attrs)
+    }
+
+    /// A snapshot of the connection's counters.
+    pub fn connection_stats(&self) -> MutationConnectionStats {
+        MutationConnectionStats {
+            registered_mutators: self.mutators.len(),
+            mutations_injected: self.mutations_injected,
+            mutations_failed: self.mutations_failed,
+            resets: self.resets,
+            last_error: self.last_error.clone(),
+            uptime: self.connected_at.elapsed(),
+        }
+    }
+
     pub async fn register_mutator(
         &mut self,
         mutator: Box<dyn Mutator + Send>,
     ) -> Result<(), CommsError> {
+        self.register(mutator).await.map(|_| ())
+    }
+
+    /// Host the given mutator and announce it to the mutation plane, returning a handle that
+    /// can later be used to query its status or deregister it.
+    pub async fn register(
+        &mut self,
+        mutator: Box<dyn Mutator + Send>,
+    ) -> Result<MutatorHandle, CommsError> {
         let mutator_id = mutator.id();
-        let ann = mutator_announcement(self.participant_id, mutator.as_ref(), &mutator_id);
+        let ann = mutator_announcement(
+            self.participant_id,
+            mutator.as_ref(),
+            &mutator_id,
+            &self.mutator_attributes,
+        );
         self.mutators.insert(mutator.id(), mutator);
-        self.mutation_conn.write_msg(&ann).await?;
+        self.write_msg(&ann).await?;
 
         self.send_event(
             "modality.mutator.announced",
@@ -192,16 +391,77 @@ impl MutatorHost {
         )
         .await;
 
+        Ok(MutatorHandle { mutator_id })
+    }
+
+    /// The ids of all mutators currently hosted.
+    pub fn list_mutators(&self) -> Vec<MutatorId> {
+        self.mutators.keys().copied().collect()
+    }
+
+    fn mutator_status(&self, mutator_id: &MutatorId) -> MutatorStatus {
+        if !self.mutators.contains_key(mutator_id) {
+            return MutatorStatus::Deregistered;
+        }
+
+        match self.active_mutations.get(mutator_id).map(HashSet::len) {
+            Some(n) if n > 0 => MutatorStatus::Active(n),
+            _ => MutatorStatus::Idle,
+        }
+    }
+
+    async fn retire_mutator(&mut self, mutator_id: MutatorId) -> Result<(), CommsError> {
+        let Some(mut mutator) = self.mutators.remove(&mutator_id) else {
+            warn!(%mutator_id, "Cannot deregister mutator, it is not hosted by this client");
+            return Ok(());
+        };
+
+        if let Some(active_mutation_ids) = self.active_mutations.remove(&mutator_id) {
+            for mutation_id in active_mutation_ids.iter() {
+                tracing::debug!(%mutator_id, %mutation_id, "Clearing mutation");
+                mutator.clear_mutation(mutation_id);
+            }
+            if !active_mutation_ids.is_empty() {
+                mutator.reset();
+                self.resets += 1;
+            }
+        }
+
+        self.write_msg(&RootwardsMessage::MutatorRetirement {
+            participant_id: self.participant_id,
+            mutator_id,
+        })
+        .await?;
+
+        self.send_event(
+            "modality.mutator.retired",
+            [("event.mutator.id", mutator_id_to_attr_val(mutator_id))],
+        )
+        .await;
+
         Ok(())
     }
 
     pub async fn message_loop(&mut self) -> Result<(), CommsError> {
         loop {
-            let msg = self.mutation_conn.read_msg().await?;
+            let msg = self.mutation_conn.read_msg().await;
+            let msg = self.record_comms_result(msg)?;
             self.handle_message(msg).await;
         }
     }
 
+    async fn write_msg(&mut self, msg: &RootwardsMessage) -> Result<(), CommsError> {
+        let res = self.mutation_conn.write_msg(msg).await;
+        self.record_comms_result(res)
+    }
+
+    fn record_comms_result<T>(&mut self, res: Result<T, CommsError>) -> Result<T, CommsError> {
+        if let Err(e) = &res {
+            self.last_error = Some(e.to_string());
+        }
+        res
+    }
+
     pub async fn handle_message(&mut self, msg: LeafwardsMessage) {
         trace!(?msg, "handle_message");
         match msg {
@@ -259,13 +519,18 @@ impl MutatorHost {
         let mut announces = Vec::with_capacity(self.mutators.len());
         let mut mutator_ids = Vec::with_capacity(self.mutators.len());
         for (mutator_id, mutator) in self.mutators.iter() {
-            let ann = mutator_announcement(self.participant_id, mutator.as_ref(), mutator_id);
+            let ann = mutator_announcement(
+                self.participant_id,
+                mutator.as_ref(),
+                mutator_id,
+                &self.mutator_attributes,
+            );
             announces.push(ann);
             mutator_ids.push(*mutator_id);
         }
 
         for ann in announces.into_iter() {
-            if let Err(e) = self.mutation_conn.write_msg(&ann).await {
+            if let Err(e) = self.write_msg(&ann).await {
                 error!(
                     err = &e as &dyn std::error::Error,
                     "Failed to announce mutator; aborting batch announce"
@@ -338,6 +603,7 @@ impl MutatorHost {
         if reset_if_active {
             println!("E");
             mutator.reset();
+            self.resets += 1;
         }
     }
 
@@ -367,6 +633,7 @@ impl MutatorHost {
 
             if reset_if_active {
                 mutator.reset();
+                self.resets += 1;
             }
         }
 
@@ -400,6 +667,7 @@ impl MutatorHost {
             }
 
             mutator.reset();
+            self.resets += 1;
         }
 
         for (mutator_id, mutation_id) in cleared_mutations {
@@ -437,6 +705,11 @@ impl MutatorHost {
         };
 
         let success = mutator.inject(mutation_id, attr_kvs_to_map(params));
+        if success {
+            self.mutations_injected += 1;
+        } else {
+            self.mutations_failed += 1;
+        }
         self.active_mutations
             .entry(mutator_id)
             .or_default()
@@ -486,8 +759,9 @@ fn mutator_announcement(
     participant_id: ParticipantId,
     m: &(impl Mutator + ?Sized),
     mutator_id: &MutatorId,
+    configured_attrs: &MutatorAttributes,
 ) -> RootwardsMessage {
-    let mutator_attrs = m
+    let mut mutator_attrs: Vec<crate::mutation_plane::types::AttrKv> = m
         .descriptor()
         .get_description_attributes()
         .map(|(k, value)| crate::mutation_plane::types::AttrKv {
@@ -495,6 +769,25 @@ fn mutator_announcement(
             value,
         })
         .collect();
+
+    for kvp in configured_attrs.additional_mutator_attributes.iter() {
+        let key = normalize_mutator_key(kvp.0.as_ref());
+        if !mutator_attrs.iter().any(|kv| kv.key == key) {
+            mutator_attrs.push(crate::mutation_plane::types::AttrKv {
+                key,
+                value: kvp.1.clone(),
+            });
+        }
+    }
+    for kvp in configured_attrs.override_mutator_attributes.iter() {
+        let key = normalize_mutator_key(kvp.0.as_ref());
+        mutator_attrs.retain(|kv| kv.key != key);
+        mutator_attrs.push(crate::mutation_plane::types::AttrKv {
+            key,
+            value: kvp.1.clone(),
+        });
+    }
+
     RootwardsMessage::MutatorAnnouncement {
         participant_id,
         mutator_id: *mutator_id,
@@ -502,6 +795,14 @@ fn mutator_announcement(
     }
 }
 
+fn normalize_mutator_key(k: &str) -> String {
+    if k.starts_with("mutator.") {
+        k.to_owned()
+    } else {
+        format!("mutator.{k}")
+    }
+}
+
 const MUTATION_PROTOCOL_PARENT_URL_ENV_VAR: &str = "MUTATION_PROTOCOL_PARENT_URL";
 const MUTATION_PROTOCOL_PARENT_URL_DEFAULT: &str = "modality-mutation://127.0.0.1:14192";
 
@@ -539,3 +840,264 @@ pub fn mutator_id_to_attr_val(mutator_id: MutatorId) -> AttrVal {
 fn uuid_to_integer_attr_val(u: &Uuid) -> AttrVal {
     i128::from_le_bytes(*u.as_bytes()).into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::fake_mutation_plane;
+
+    struct TestMutator {
+        id: MutatorId,
+    }
+
+    impl Mutator for TestMutator {
+        fn id(&self) -> MutatorId {
+            self.id
+        }
+
+        fn descriptor(&self) -> OwnedMutatorDescriptor {
+            OwnedMutatorDescriptor {
+                name: Some("test-mutator".to_owned()),
+                ..Default::default()
+            }
+        }
+
+        fn inject(&mut self, _mutation_id: MutationId, _params: BTreeMap<String, AttrVal>) -> bool {
+            true
+        }
+
+        fn clear_mutation(&mut self, _mutation_id: &MutationId) {}
+
+        fn reset(&mut self) {}
+    }
+
+    #[tokio::test]
+    async fn register_and_deregister_via_handle() {
+        let (url, mut rootwards) = fake_mutation_plane().await;
+        let mut host =
+            MutatorHost::connect_and_authenticate(&url, false, vec![0u8; 4].into(), None)
+                .await
+                .unwrap();
+
+        let mutator_id = MutatorId::allocate();
+        let handle = host
+            .register(Box::new(TestMutator { id: mutator_id }))
+            .await
+            .unwrap();
+        assert_eq!(handle.id(), mutator_id);
+        assert_eq!(host.list_mutators(), vec![mutator_id]);
+        assert_eq!(handle.status(&host), MutatorStatus::Idle);
+        assert!(matches!(
+            rootwards.recv().await,
+            Some(RootwardsMessage::MutatorAnnouncement { mutator_id: id, .. }) if id == mutator_id
+        ));
+
+        handle.deregister(&mut host).await.unwrap();
+        assert!(host.list_mutators().is_empty());
+        assert_eq!(handle.status(&host), MutatorStatus::Deregistered);
+        assert!(matches!(
+            rootwards.recv().await,
+            Some(RootwardsMessage::MutatorRetirement { mutator_id: id, .. }) if id == mutator_id
+        ));
+    }
+
+    #[tokio::test]
+    async fn configured_mutator_attributes_are_announced() {
+        let (url, mut rootwards) = fake_mutation_plane().await;
+        let mut host =
+            MutatorHost::connect_and_authenticate(&url, false, vec![0u8; 4].into(), None)
+                .await
+                .unwrap();
+        host.add_mutator_attributes(MutatorAttributes {
+            additional_mutator_attributes: vec![
+                "site = \"lab\"".parse().unwrap(),
+                "mutator.name = \"ignored\"".parse().unwrap(),
+            ],
+            override_mutator_attributes: vec!["group = \"ops\"".parse().unwrap()],
+        })
+        .unwrap();
+
+        host.register(Box::new(TestMutator {
+            id: MutatorId::allocate(),
+        }))
+        .await
+        .unwrap();
+        let Some(RootwardsMessage::MutatorAnnouncement { mutator_attrs, .. }) =
+            rootwards.recv().await
+        else {
+            panic!("expected a mutator announcement");
+        };
+        let attrs: BTreeMap<String, AttrVal> = mutator_attrs
+            .0
+            .into_iter()
+            .map(|kv| (kv.key, kv.value))
+            .collect();
+        assert_eq!(attrs["mutator.site"], AttrVal::from("lab"));
+        assert_eq!(attrs["mutator.name"], AttrVal::from("test-mutator"));
+        assert_eq!(attrs["mutator.group"], AttrVal::from("ops"));
+    }
+
+    #[tokio::test]
+    async fn connection_stats_count_injections_and_resets() {
+        let (url, _rootwards) = fake_mutation_plane().await;
+        let mut host =
+            MutatorHost::connect_and_authenticate(&url, false, vec![0u8; 4].into(), None)
+                .await
+                .unwrap();
+
+        let mutator_id = MutatorId::allocate();
+        host.register(Box::new(TestMutator { id: mutator_id }))
+            .await
+            .unwrap();
+
+        for _ in 0..2 {
+            host.handle_message(LeafwardsMessage::NewMutation {
+                mutator_id,
+                mutation_id: Uuid::new_v4().into(),
+                maybe_trigger_mask: None,
+                params: crate::mutation_plane::types::AttrKvs(vec![]),
+            })
+            .await;
+        }
+        host.handle_message(LeafwardsMessage::ClearMutations {})
+            .await;
+
+        let stats = host.connection_stats();
+        assert_eq!(stats.registered_mutators, 1);
+        assert_eq!(stats.mutations_injected, 2);
+        assert_eq!(stats.mutations_failed, 0);
+        assert_eq!(stats.resets, 1);
+        assert_eq!(stats.last_error, None);
+    }
+
+    #[cfg(feature = "pyo3")]
+    struct PyTestMutator {
+        id: MutatorId,
+        obj: pyo3::PyObject,
+    }
+
+    #[cfg(feature = "pyo3")]
+    impl Mutator for PyTestMutator {
+        fn id(&self) -> MutatorId {
+            self.id
+        }
+
+        fn descriptor(&self) -> OwnedMutatorDescriptor {
+            OwnedMutatorDescriptor::default()
+        }
+
+        fn inject(&mut self, mutation_id: MutationId, _params: BTreeMap<String, AttrVal>) -> bool {
+            use pyo3::prelude::*;
+            Python::with_gil(|py| py_inject(self.obj.bind(py), mutation_id, py.None()))
+        }
+
+        fn clear_mutation(&mut self, _mutation_id: &MutationId) {}
+
+        fn reset(&mut self) {}
+    }
+
+    #[cfg(feature = "pyo3")]
+    #[tokio::test]
+    async fn python_mutator_failures_are_recorded() {
+        use crate::ingest_client::IngestClient;
+        use crate::plugin_utils::ingest::Client;
+        use crate::test_fixtures::{authed_transport, sent_event_attrs, sent_messages};
+        use pyo3::prelude::*;
+
+        pyo3::prepare_freethreaded_python();
+        let (raises, fails, succeeds) = Python::with_gil(|py| {
+            let globals = pyo3::types::PyDict::new_bound(py);
+            globals
+                .set_item("MutationResult", py.get_type_bound::<MutationResult>())
+                .unwrap();
+            py.run_bound(
+                r#"
+class Raises:
+    def inject(self, mutation_id, params):
+        raise RuntimeError("no can do")
+
+class Fails:
+    def inject(self, mutation_id, params):
+        return MutationResult.failed("not today")
+
+class Succeeds:
+    def inject(self, mutation_id, params):
+        pass
+"#,
+                Some(&globals),
+                None,
+            )
+            .unwrap();
+
+            let new = |class: &str| globals.get_item(class).unwrap().unwrap().call0().unwrap();
+            let raises = new("Raises");
+            assert_eq!(
+                py_inject_result(raises.call_method1("inject", (0, ())))
+                    .unwrap_err()
+                    .to_string(),
+                "RuntimeError: no can do"
+            );
+            let fails = new("Fails");
+            assert_eq!(
+                py_inject_result(fails.call_method1("inject", (0, ())))
+                    .unwrap_err()
+                    .to_string(),
+                "not today"
+            );
+            (raises.unbind(), fails.unbind(), new("Succeeds").unbind())
+        });
+
+        let transport = authed_transport();
+        let ingest = IngestClient::from_transport(transport.clone(), Duration::from_secs(1))
+            .authenticate(vec![])
+            .await
+            .unwrap();
+        let ingest = Client::new(ingest, Default::default(), None, None)
+            .await
+            .unwrap();
+
+        let (url, _rootwards) = fake_mutation_plane().await;
+        let mut host =
+            MutatorHost::connect_and_authenticate(&url, false, vec![0u8; 4].into(), Some(ingest))
+                .await
+                .unwrap();
+
+        for obj in [raises, fails, succeeds] {
+            let mutator_id = MutatorId::allocate();
+            host.register(Box::new(PyTestMutator {
+                id: mutator_id,
+                obj,
+            }))
+            .await
+            .unwrap();
+            host.handle_message(LeafwardsMessage::NewMutation {
+                mutator_id,
+                mutation_id: Uuid::new_v4().into(),
+                maybe_trigger_mask: None,
+                params: crate::mutation_plane::types::AttrKvs(vec![]),
+            })
+            .await;
+        }
+
+        let stats = host.connection_stats();
+        assert_eq!(stats.mutations_injected, 1);
+        assert_eq!(stats.mutations_failed, 2);
+
+        let injected: Vec<Option<AttrVal>> = sent_event_attrs(&sent_messages(&transport))
+            .into_iter()
+            .filter(|attrs| {
+                attrs.contains(&("event.name".to_owned(), "modality.mutation.injected".into()))
+            })
+            .map(|attrs| {
+                attrs
+                    .into_iter()
+                    .find(|(k, _)| k == "event.mutation.success")
+                    .map(|(_, v)| v)
+            })
+            .collect();
+        assert_eq!(
+            injected,
+            vec![Some(false.into()), Some(false.into()), Some(true.into())]
+        );
+    }
+}
@@ -0,0 +1,79 @@
+//! Conversions between Python values and attribute maps, shared by the Python
+//! bindings.
+
+use crate::api::types::{py_uuid_class, AttrKey, AttrVal};
+use pyo3::{prelude::*, types::PyDict};
+use std::collections::BTreeMap;
+
+/// Convert a Python dict of attributes into an attribute map.
+///
+/// Keys must be strings. Values are converted as for [`AttrVal`]'s
+/// `FromPyObject` impl: `bool` to [`AttrVal::Bool`], `int` to
+/// [`AttrVal::Integer`] (or [`AttrVal::BigInt`] if it doesn't fit in an
+/// `i64`), `float` to [`AttrVal::Float`], `str` to [`AttrVal::String`] and
+/// `uuid.UUID` to [`AttrVal::TimelineId`].
+pub fn py_dict_to_attr_map(dict: &Bound<'_, PyDict>) -> PyResult<BTreeMap<AttrKey, AttrVal>> {
+    let mut attrs = BTreeMap::new();
+    for (k, v) in dict.iter() {
+        let k = k.extract::<String>()?;
+        let v = v.extract::<AttrVal>()?;
+        attrs.insert(AttrKey::from(k), v);
+    }
+    Ok(attrs)
+}
+
+/// Convert an attribute map into a Python dict; the inverse of
+/// [`py_dict_to_attr_map`]. Timeline ids come back as `uuid.UUID`s.
+pub fn attr_map_to_py_dict<'py>(
+    py: Python<'py>,
+    attrs: BTreeMap<AttrKey, AttrVal>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    for (k, v) in attrs {
+        let v = match v {
+            AttrVal::TimelineId(tid) => py_uuid_class(py)?.call1((tid.to_string(),))?.unbind(),
+            v => v.into_py(py),
+        };
+        dict.set_item(k.as_ref(), v)?;
+    }
+    Ok(dict)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{BigInt, TimelineId, Uuid};
+
+    #[test]
+    fn py_dict_attr_map_round_trip() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let dict = py
+                .eval_bound(
+                    "{'i': 42, 'big': 2**100, 'f': 1.5, 's': 'hi', 'b': True, \
+                      'tl': __import__('uuid').UUID('3cd8734a-1747-4cfd-a305-2e8b3aa41c8f')}",
+                    None,
+                    None,
+                )
+                .unwrap()
+                .downcast_into::<PyDict>()
+                .unwrap();
+
+            let attrs = py_dict_to_attr_map(&dict).unwrap();
+            let tl =
+                TimelineId::from(Uuid::parse_str("3cd8734a-1747-4cfd-a305-2e8b3aa41c8f").unwrap());
+            assert_eq!(attrs[&AttrKey::from("i")], AttrVal::Integer(42));
+            assert_eq!(
+                attrs[&AttrKey::from("big")],
+                BigInt::new_attr_val(1i128 << 100)
+            );
+            assert_eq!(attrs[&AttrKey::from("f")], AttrVal::from(1.5));
+            assert_eq!(attrs[&AttrKey::from("s")], AttrVal::from("hi"));
+            assert_eq!(attrs[&AttrKey::from("b")], AttrVal::Bool(true));
+            assert_eq!(attrs[&AttrKey::from("tl")], AttrVal::from(tl));
+
+            let back = attr_map_to_py_dict(py, attrs).unwrap();
+            assert!(back.eq(&dict).unwrap());
+        });
+    }
+}
@@ -0,0 +1,300 @@
+//! Conversions between TOML values and attribute values, e.g. for turning
+//! plugin metadata from the config into event or timeline attributes.
+//!
+//! | TOML                | [AttrVal]                                         |
+//! |---------------------|---------------------------------------------------|
+//! | string              | `String`                                          |
+//! | integer             | `Integer`                                         |
+//! | float               | `Float`                                           |
+//! | boolean             | `Bool`                                            |
+//! | datetime            | `Timestamp`, in nanoseconds since the Unix epoch  |
+//! | array, table        | `String`, holding the value's inline TOML text    |
+//!
+//! Datetimes without an offset are taken to be in UTC, and a bare date is
+//! midnight UTC. A bare time, or a datetime before the epoch, can't be
+//! converted.
+//!
+//! Going the other way always succeeds: a `BigInt` that doesn't fit in a
+//! TOML integer, and the variants with no TOML counterpart (`Duration`,
+//! `LogicalTime`, `EventCoordinate` and `TimelineId`), become strings
+//! holding their display form.
+use super::TomlValue;
+use crate::api::{AttrVal, Nanoseconds};
+use toml::value::Datetime;
+
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+const SECS_PER_DAY: i128 = 86_400;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TomlAttrValError {
+    #[error("The TOML datetime '{0}' has no date, so it can't be a timestamp")]
+    DatetimeWithoutDate(String),
+
+    #[error("The TOML datetime '{0}' is outside of the range of timestamps")]
+    DatetimeOutOfRange(String),
+
+    #[error("The TOML datetime '{0}' couldn't be interpreted")]
+    MalformedDatetime(String),
+}
+
+impl TryFrom<&TomlValue> for AttrVal {
+    type Error = TomlAttrValError;
+
+    fn try_from(value: &TomlValue) -> Result<Self, Self::Error> {
+        Ok(match value {
+            TomlValue::String(s) => s.as_str().into(),
+            TomlValue::Integer(i) => AttrVal::Integer(*i),
+            TomlValue::Float(f) => (*f).into(),
+            TomlValue::Boolean(b) => (*b).into(),
+            TomlValue::Datetime(dt) => AttrVal::Timestamp(datetime_to_timestamp(dt)?),
+            TomlValue::Array(_) | TomlValue::Table(_) => inline_toml(value).into(),
+        })
+    }
+}
+
+impl TryFrom<TomlValue> for AttrVal {
+    type Error = TomlAttrValError;
+
+    fn try_from(value: TomlValue) -> Result<Self, Self::Error> {
+        match value {
+            TomlValue::String(s) => Ok(s.into()),
+            value => AttrVal::try_from(&value),
+        }
+    }
+}
+
+impl From<AttrVal> for TomlValue {
+    fn from(val: AttrVal) -> Self {
+        match val {
+            AttrVal::String(s) => TomlValue::String(s.into_owned()),
+            AttrVal::Integer(i) => TomlValue::Integer(i),
+            AttrVal::BigInt(bi) => match i64::try_from(*bi.as_ref()) {
+                Ok(i) => TomlValue::Integer(i),
+                Err(_) => TomlValue::String(bi.to_string()),
+            },
+            AttrVal::Float(f) => TomlValue::Float(f.into_inner()),
+            AttrVal::Bool(b) => TomlValue::Boolean(b),
+            AttrVal::Timestamp(ns) => TomlValue::Datetime(timestamp_to_datetime(ns)),
+            val @ (AttrVal::Duration(_)
+            | AttrVal::LogicalTime(_)
+            | AttrVal::EventCoordinate(_)
+            | AttrVal::TimelineId(_)) => TomlValue::String(val.to_string()),
+        }
+    }
+}
+
+/// Render a value as inline TOML, e.g. `{ a = 1, b = [true] }`. toml's own
+/// `Display` writes tables out as documents instead.
+pub(crate) fn inline_toml(value: &TomlValue) -> String {
+    match value {
+        TomlValue::Array(elements) => {
+            let elements: Vec<String> = elements.iter().map(inline_toml).collect();
+            format!("[{}]", elements.join(", "))
+        }
+        TomlValue::Table(table) if table.is_empty() => "{}".to_owned(),
+        TomlValue::Table(table) => {
+            let members: Vec<String> = table
+                .iter()
+                .map(|(k, v)| {
+                    let bare = !k.is_empty()
+                        && k.chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+                    let key = if bare {
+                        k.clone()
+                    } else {
+                        TomlValue::String(k.clone()).to_string()
+                    };
+                    format!("{key} = {}", inline_toml(v))
+                })
+                .collect();
+            format!("{{ {} }}", members.join(", "))
+        }
+        scalar => scalar.to_string(),
+    }
+}
+
+/// toml 0.5 doesn't expose the parts of a [Datetime], so this works from its
+/// RFC 3339 text form: `<date>[T<time>[<offset>]]`, or just `<time>`.
+fn datetime_to_timestamp(dt: &Datetime) -> Result<Nanoseconds, TomlAttrValError> {
+    let text = dt.to_string();
+    let malformed = || TomlAttrValError::MalformedDatetime(text.clone());
+
+    let (date, rest) = match text.split_once(['T', 't', ' ']) {
+        Some((date, rest)) => (date, Some(rest)),
+        None if text.contains(':') => return Err(TomlAttrValError::DatetimeWithoutDate(text)),
+        None => (text.as_str(), None),
+    };
+
+    let mut date_parts = date.splitn(3, '-').map(|p| p.parse::<i64>());
+    let (Some(Ok(year)), Some(Ok(month)), Some(Ok(day))) =
+        (date_parts.next(), date_parts.next(), date_parts.next())
+    else {
+        return Err(malformed());
+    };
+    let mut secs = i128::from(days_from_civil(year, month, day)) * SECS_PER_DAY;
+    let mut nanos = 0;
+
+    if let Some(rest) = rest {
+        let (time, offset_secs) = if let Some(time) = rest.strip_suffix(['Z', 'z']) {
+            (time, 0)
+        } else if let Some(idx) = rest.rfind(['+', '-']) {
+            let (time, offset) = rest.split_at(idx);
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            let (h, m) = offset[1..].split_once(':').ok_or_else(malformed)?;
+            let h: i128 = h.parse().map_err(|_| malformed())?;
+            let m: i128 = m.parse().map_err(|_| malformed())?;
+            (time, sign * (h * 3600 + m * 60))
+        } else {
+            (rest, 0)
+        };
+
+        let (hms, frac) = time.split_once('.').unwrap_or((time, ""));
+        let mut hms_parts = hms.splitn(3, ':').map(|p| p.parse::<i128>());
+        let (Some(Ok(h)), Some(Ok(m)), Some(Ok(s))) =
+            (hms_parts.next(), hms_parts.next(), hms_parts.next())
+        else {
+            return Err(malformed());
+        };
+        secs += h * 3600 + m * 60 + s - offset_secs;
+
+        if !frac.is_empty() {
+            // Only nanosecond precision is kept
+            let digits = &frac[..frac.len().min(9)];
+            let frac_nanos: i128 = digits.parse().map_err(|_| malformed())?;
+            nanos = frac_nanos * 10i128.pow(9 - digits.len() as u32);
+        }
+    }
+
+    u64::try_from(secs * NANOS_PER_SEC + nanos)
+        .map(Nanoseconds::from)
+        .map_err(|_| TomlAttrValError::DatetimeOutOfRange(text))
+}
+
+fn timestamp_to_datetime(ns: Nanoseconds) -> Datetime {
+    let total = i128::from(ns.get_raw());
+    let secs = total / NANOS_PER_SEC;
+    let nanos = total % NANOS_PER_SEC;
+    let days = secs / SECS_PER_DAY;
+    let day_secs = secs % SECS_PER_DAY;
+    let (year, month, day) = civil_from_days(days as i64);
+
+    let mut text = format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+        day_secs / 3600,
+        day_secs % 3600 / 60,
+        day_secs % 60
+    );
+    if nanos != 0 {
+        text.push_str(&format!(".{nanos:09}"));
+    }
+    text.push('Z');
+    text.parse()
+        .expect("formatted timestamp is a valid TOML datetime")
+}
+
+/// Days since 1970-01-01 of the given proleptic Gregorian date.
+///
+/// From Howard Hinnant's date algorithms,
+/// <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// The inverse of [days_from_civil]
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toml_val(s: &str) -> TomlValue {
+        let table: toml::value::Table = toml::from_str(&format!("v = {s}")).unwrap();
+        table["v"].clone()
+    }
+
+    #[test]
+    fn toml_scalars_to_attr_val() {
+        assert_eq!(
+            AttrVal::try_from(toml_val("\"hi\"")),
+            Ok(AttrVal::from("hi"))
+        );
+        assert_eq!(
+            AttrVal::try_from(toml_val("-42")),
+            Ok(AttrVal::Integer(-42))
+        );
+        assert_eq!(AttrVal::try_from(toml_val("1.5")), Ok(AttrVal::from(1.5)));
+        assert_eq!(AttrVal::try_from(toml_val("true")), Ok(AttrVal::Bool(true)));
+        assert_eq!(
+            AttrVal::try_from(toml_val("[1, \"a\"]")),
+            Ok(AttrVal::from("[1, \"a\"]"))
+        );
+        assert_eq!(
+            AttrVal::try_from(toml_val("{ a = 1, \"b c\" = [{ d = false }] }")),
+            Ok(AttrVal::from("{ a = 1, \"b c\" = [{ d = false }] }"))
+        );
+    }
+
+    #[test]
+    fn toml_datetime_to_timestamp() {
+        let ts = |s: &str| AttrVal::try_from(toml_val(s));
+        let nanos = |n: u64| Ok(AttrVal::Timestamp(Nanoseconds::from(n)));
+
+        assert_eq!(ts("1970-01-01T00:00:00Z"), nanos(0));
+        assert_eq!(
+            ts("2023-06-15T12:30:45.123456789Z"),
+            nanos(1_686_832_245_123_456_789)
+        );
+        assert_eq!(
+            ts("2023-06-15T14:30:45.5+02:00"),
+            nanos(1_686_832_245_500_000_000)
+        );
+        assert_eq!(ts("2023-06-15T12:30:45"), nanos(1_686_832_245_000_000_000));
+        assert_eq!(ts("2023-06-15"), nanos(1_686_787_200_000_000_000));
+
+        assert!(matches!(
+            ts("12:30:45"),
+            Err(TomlAttrValError::DatetimeWithoutDate(_))
+        ));
+        assert!(matches!(
+            ts("1969-12-31T23:59:59Z"),
+            Err(TomlAttrValError::DatetimeOutOfRange(_))
+        ));
+    }
+
+    #[test]
+    fn attr_val_to_toml() {
+        assert_eq!(TomlValue::from(AttrVal::from("hi")), toml_val("\"hi\""));
+        assert_eq!(TomlValue::from(AttrVal::Integer(7)), toml_val("7"));
+        assert_eq!(
+            TomlValue::from(AttrVal::from(u64::MAX)),
+            TomlValue::String(u64::MAX.to_string())
+        );
+        assert_eq!(TomlValue::from(AttrVal::from(2.5)), toml_val("2.5"));
+        assert_eq!(TomlValue::from(AttrVal::Bool(false)), toml_val("false"));
+        assert_eq!(
+            TomlValue::from(AttrVal::Duration(Nanoseconds::from(5))),
+            toml_val("\"5ns\"")
+        );
+
+        let ts = AttrVal::Timestamp(Nanoseconds::from(1_686_832_245_123_000_000));
+        let toml = TomlValue::from(ts.clone());
+        assert_eq!(toml, toml_val("2023-06-15T12:30:45.123000000Z"));
+        assert_eq!(AttrVal::try_from(toml), Ok(ts));
+    }
+}
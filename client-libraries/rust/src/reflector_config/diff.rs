@@ -0,0 +1,282 @@
+//! Structured differences between two configs, setting by setting, e.g. for
+//! reviewing a config change.
+use super::{
+    attr_val::inline_toml,
+    raw_toml,
+    secret::{REDACTED, SECRET_PATHS},
+    Config, TomlValue,
+};
+use std::fmt;
+
+/// The settings that differ between two configs, in path order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigDiff {
+    pub entries: Vec<ConfigDiffEntry>,
+}
+
+/// One difference between two configs. Paths are dotted, in the config's
+/// own key names, e.g. `plugins.ingest.collectors.lttng-live.shutdown-signal`.
+///
+/// A table present on only one side, like a newly added plugin section, is a
+/// single entry holding the whole table. Arrays are compared as a whole.
+/// Plaintext secrets, like `mutation.mutator-http-api-key`, are replaced with
+/// `<redacted>`; secret references are kept as they are.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigDiffEntry {
+    Added {
+        path: String,
+        value: TomlValue,
+    },
+    Removed {
+        path: String,
+        value: TomlValue,
+    },
+    Changed {
+        path: String,
+        before: TomlValue,
+        after: TomlValue,
+    },
+}
+
+impl ConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl ConfigDiffEntry {
+    pub fn path(&self) -> &str {
+        match self {
+            ConfigDiffEntry::Added { path, .. }
+            | ConfigDiffEntry::Removed { path, .. }
+            | ConfigDiffEntry::Changed { path, .. } => path,
+        }
+    }
+}
+
+/// One line per entry: `+ path = value`, `- path = value`, or
+/// `~ path = before -> after`.
+impl fmt::Display for ConfigDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{entry}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ConfigDiffEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigDiffEntry::Added { path, value } => {
+                write!(f, "+ {path} = {}", inline_toml(value))
+            }
+            ConfigDiffEntry::Removed { path, value } => {
+                write!(f, "- {path} = {}", inline_toml(value))
+            }
+            ConfigDiffEntry::Changed {
+                path,
+                before,
+                after,
+            } => write!(
+                f,
+                "~ {path} = {} -> {}",
+                inline_toml(before),
+                inline_toml(after)
+            ),
+        }
+    }
+}
+
+/// Compare two configs, as they would be written out by
+/// [try_to_string](super::try_to_string).
+pub fn diff(old: &Config, new: &Config) -> Result<ConfigDiff, toml::ser::Error> {
+    let mut diff = ConfigDiff::default();
+    diff_values("", &to_toml(old)?, &to_toml(new)?, &mut diff.entries);
+    for entry in diff.entries.iter_mut() {
+        match entry {
+            ConfigDiffEntry::Added { path, value } | ConfigDiffEntry::Removed { path, value } => {
+                redact_secrets(path, value)
+            }
+            ConfigDiffEntry::Changed {
+                path,
+                before,
+                after,
+            } => {
+                redact_secrets(path, before);
+                redact_secrets(path, after);
+            }
+        }
+    }
+    Ok(diff)
+}
+
+pub(crate) fn to_toml(config: &Config) -> Result<TomlValue, toml::ser::Error> {
+    let raw: raw_toml::Config = config.clone().into();
+    TomlValue::try_from(raw)
+}
+
+/// Redact any plaintext secrets in `value`, the setting at `path`, or within it
+/// if it's a table.
+fn redact_secrets(path: &str, value: &mut TomlValue) {
+    for secret_path in SECRET_PATHS {
+        let rest = if path.is_empty() {
+            Some(*secret_path)
+        } else if path == *secret_path {
+            Some("")
+        } else {
+            secret_path
+                .strip_prefix(path)
+                .and_then(|rest| rest.strip_prefix('.'))
+        };
+        let Some(rest) = rest else {
+            continue;
+        };
+        let mut secret = Some(&mut *value);
+        for key in rest.split('.').filter(|k| !k.is_empty()) {
+            secret = secret.and_then(|v| v.get_mut(key));
+        }
+        if let Some(TomlValue::String(s)) = secret {
+            *s = REDACTED.to_owned();
+        }
+    }
+}
+
+fn diff_values(path: &str, old: &TomlValue, new: &TomlValue, out: &mut Vec<ConfigDiffEntry>) {
+    match (old, new) {
+        (TomlValue::Table(old), TomlValue::Table(new)) => {
+            let child_path = |k: &str| {
+                if path.is_empty() {
+                    k.to_owned()
+                } else {
+                    format!("{path}.{k}")
+                }
+            };
+            let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for k in keys {
+                match (old.get(k), new.get(k)) {
+                    (Some(o), Some(n)) => diff_values(&child_path(k), o, n, out),
+                    (Some(o), None) => out.push(ConfigDiffEntry::Removed {
+                        path: child_path(k),
+                        value: o.clone(),
+                    }),
+                    (None, Some(n)) => out.push(ConfigDiffEntry::Added {
+                        path: child_path(k),
+                        value: n.clone(),
+                    }),
+                    (None, None) => (),
+                }
+            }
+        }
+        (o, n) if o != n => out.push(ConfigDiffEntry::Changed {
+            path: path.to_owned(),
+            before: o.clone(),
+            after: n.clone(),
+        }),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reflector_config::try_from_str;
+
+    const OLD: &str = r#"
+[ingest]
+protocol-parent-url = "modality-ingest://localhost:14188"
+additional-timeline-attributes = ['site = "lab"', 'rig = 3']
+
+[plugins.ingest.collectors.can0]
+shutdown-signal = "SIGINT"
+"#;
+
+    fn diff_against_old(new: &str) -> ConfigDiff {
+        diff(&try_from_str(OLD).unwrap(), &try_from_str(new).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn identical_configs() {
+        assert!(diff_against_old(OLD).is_empty());
+    }
+
+    #[test]
+    fn changed_port() {
+        let d = diff_against_old(&OLD.replace("14188", "14189"));
+        assert_eq!(
+            d.entries,
+            vec![ConfigDiffEntry::Changed {
+                path: "ingest.protocol-parent-url".to_owned(),
+                before: "modality-ingest://localhost:14188".into(),
+                after: "modality-ingest://localhost:14189".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn added_collector() {
+        let new =
+            format!("{OLD}\n[plugins.ingest.collectors.can1]\nshutdown-signal = \"SIGTERM\"\n");
+        let d = diff_against_old(&new);
+        let mut member = toml::value::Table::new();
+        member.insert("shutdown-signal".to_owned(), "SIGTERM".into());
+        assert_eq!(
+            d.entries,
+            vec![ConfigDiffEntry::Added {
+                path: "plugins.ingest.collectors.can1".to_owned(),
+                value: TomlValue::Table(member),
+            }]
+        );
+        assert_eq!(
+            d.to_string(),
+            "+ plugins.ingest.collectors.can1 = { shutdown-signal = \"SIGTERM\" }\n"
+        );
+    }
+
+    #[test]
+    fn removed_attribute() {
+        let d = diff_against_old(&OLD.replace(", 'rig = 3'", ""));
+        assert_eq!(d.entries.len(), 1);
+        assert_eq!(d.entries[0].path(), "ingest.additional-timeline-attributes");
+        assert_eq!(
+            d.to_string(),
+            "~ ingest.additional-timeline-attributes = [\"site = \\\"lab\\\"\", \"rig = 3\"] -> [\"site = \\\"lab\\\"\"]\n"
+        );
+
+        let d = diff_against_old(&OLD.replace("shutdown-signal = \"SIGINT\"", ""));
+        assert_eq!(
+            d.entries,
+            vec![ConfigDiffEntry::Removed {
+                path: "plugins.ingest.collectors.can0.shutdown-signal".to_owned(),
+                value: "SIGINT".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn secrets_are_redacted() {
+        let d = diff_against_old(&format!(
+            "{OLD}\n[mutation]\nmutator-http-api-key = \"hunter2\"\n"
+        ));
+        assert_eq!(d.entries.len(), 1);
+        assert_eq!(d.entries[0].path(), "mutation");
+        assert!(!d.to_string().contains("hunter2"));
+        assert_eq!(
+            d.to_string(),
+            "+ mutation = { mutator-http-api-key = \"<redacted>\" }\n"
+        );
+
+        let with_key = |key: &str| format!("{OLD}\n[mutation]\nmutator-http-api-key = {key}\n");
+        let d = diff(
+            &try_from_str(&with_key("\"hunter2\"")).unwrap(),
+            &try_from_str(&with_key("{ secret-ref = \"env:API_KEY\" }")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            d.to_string(),
+            "~ mutation.mutator-http-api-key = \"<redacted>\" -> { secret-ref = \"env:API_KEY\" }\n"
+        );
+    }
+}
@@ -0,0 +1,73 @@
+//! Human-readable durations for settings, e.g. `"250ms"`, `"1.5s"` or `"1m30s"`.
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("'{0}' is not a valid duration; expected e.g. '1.5', '250ms', '2s' or '1m30s'")]
+pub struct ParseDurationError(pub String);
+
+/// Parse a duration given as one or more numbers, each followed by a unit:
+/// `ns`, `us` (or `µs`), `ms`, `s`, `m` (or `min`) or `h`. A number on its
+/// own, like `1.5`, is a number of seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, ParseDurationError> {
+    let err = || ParseDurationError(s.to_owned());
+    let trimmed = s.trim();
+    if trimmed.is_empty() {
+        return Err(err());
+    }
+    if let Ok(secs) = trimmed.parse::<f64>() {
+        return Duration::try_from_secs_f64(secs).map_err(|_| err());
+    }
+
+    let mut total = Duration::ZERO;
+    let mut rest = trimmed;
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .ok_or_else(err)?;
+        let number: f64 = rest[..number_len].parse().map_err(|_| err())?;
+        rest = rest[number_len..].trim_start();
+
+        let unit_len = rest
+            .find(|c: char| !c.is_alphabetic())
+            .unwrap_or(rest.len());
+        let unit_secs = match &rest[..unit_len] {
+            "ns" => 1e-9,
+            "us" | "µs" => 1e-6,
+            "ms" => 1e-3,
+            "s" => 1.0,
+            "m" | "min" => 60.0,
+            "h" => 3600.0,
+            _ => return Err(err()),
+        };
+        rest = rest[unit_len..].trim_start();
+
+        let part = Duration::try_from_secs_f64(number * unit_secs).map_err(|_| err())?;
+        total = total.checked_add(part).ok_or_else(err)?;
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("1.5"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("42"), Ok(Duration::from_secs(42)));
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration(" 2 s "), Ok(Duration::from_secs(2)));
+        assert_eq!(parse_duration("1m30s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(3600)));
+        assert_eq!(parse_duration("10us"), Ok(Duration::from_micros(10)));
+        assert_eq!(parse_duration("0.5ms"), Ok(Duration::from_micros(500)));
+
+        for bad in ["", "ms", "5 parsecs", "-1", "-1s", "1s2", "1..5s"] {
+            assert_eq!(
+                parse_duration(bad),
+                Err(ParseDurationError(bad.to_owned())),
+                "{bad}"
+            );
+        }
+    }
+}
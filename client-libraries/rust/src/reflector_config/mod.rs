@@ -1,18 +1,36 @@
 //! A format definition and parser for the `modality-reflector`, to be used
 //! by custom reflector plugins that can be hosted within the reflector itself.
 
+pub mod attr_val;
+pub mod diff;
+pub mod duration;
 pub mod resolve;
+pub mod secret;
+pub mod template;
 
+#[cfg(any(test, feature = "test_support"))]
+pub mod proptest_strategies;
+
+pub use diff::{diff, ConfigDiff, ConfigDiffEntry};
 pub use refined::*;
 use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
+pub use template::{template_toml, TemplateBuilder};
 use thiserror::Error;
 pub use toml::Value as TomlValue;
 
 pub const CONFIG_ENV_VAR: &str = "MODALITY_REFLECTOR_CONFIG";
 
+/// If set, sent as the `Authorization` header when fetching a config from a URL
+#[cfg(feature = "config_http")]
+pub const CONFIG_URL_AUTH_HEADER_ENV_VAR: &str = "MODALITY_REFLECTOR_CONFIG_AUTH_HEADER";
+
+/// How long fetching a config from a URL may take
+#[cfg(feature = "config_http")]
+pub const CONFIG_FETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
 pub const MODALITY_STORAGE_SERVICE_PORT_DEFAULT: u16 = 14182;
-pub const MODALITY_STORAGE_SERVICE_TLS_PORT_DEFAULT: u16 = 14183;
+pub const MODALITY_STORAGE_SERVICE_TLS_PORT_DEFAULT: u16 = 14184;
 
 pub const MODALITY_REFLECTOR_INGEST_CONNECT_PORT_DEFAULT: u16 = 14188;
 pub const MODALITY_REFLECTOR_INGEST_CONNECT_TLS_PORT_DEFAULT: u16 = 14189;
@@ -42,6 +60,10 @@ pub(crate) mod raw_toml {
 
         #[serde(skip_serializing_if = "BTreeMap::is_empty")]
         pub(crate) metadata: BTreeMap<String, TomlValue>,
+
+        /// Keys not recognized by this format, kept so they can be reported
+        #[serde(flatten)]
+        pub(crate) unknown_keys: BTreeMap<String, TomlValue>,
     }
 
     #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -56,11 +78,27 @@ pub(crate) mod raw_toml {
         #[serde(skip_serializing_if = "Option::is_none")]
         pub(crate) max_write_batch_staleness_millis: Option<u64>,
 
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(crate) max_events_per_second: Option<u32>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(crate) max_event_burst: Option<u32>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(crate) protocol_version: Option<u32>,
+
         #[serde(skip_serializing_if = "Option::is_none")]
         pub(crate) protocol_child_port: Option<u16>,
 
         #[serde(flatten)]
         pub(crate) timeline_attributes: TimelineAttributes,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(crate) timeline_attributes_file: Option<PathBuf>,
+
+        /// Keys not recognized by this format, kept so they can be reported
+        #[serde(flatten)]
+        pub(crate) unknown_keys: BTreeMap<String, TomlValue>,
     }
 
     #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -78,11 +116,19 @@ pub(crate) mod raw_toml {
         #[serde(skip_serializing_if = "Option::is_none")]
         pub(crate) mutator_http_api_port: Option<u16>,
 
+        /// A string, or a `{ secret-ref = "..." }` table
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(crate) mutator_http_api_key: Option<TomlValue>,
+
         #[serde(flatten)]
         pub(crate) mutator_attributes: MutatorAttributes,
 
         #[serde(skip_serializing_if = "Vec::is_empty")]
         pub(crate) external_mutator_urls: Vec<String>,
+
+        /// Keys not recognized by this format, kept so they can be reported
+        #[serde(flatten)]
+        pub(crate) unknown_keys: BTreeMap<String, TomlValue>,
     }
 
     #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -99,6 +145,10 @@ pub(crate) mod raw_toml {
 
         #[serde(skip_serializing_if = "Option::is_none")]
         pub(crate) mutation: Option<PluginsMutation>,
+
+        /// Keys not recognized by this format, kept so they can be reported
+        #[serde(flatten)]
+        pub(crate) unknown_keys: BTreeMap<String, TomlValue>,
     }
 
     #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -109,6 +159,10 @@ pub(crate) mod raw_toml {
 
         #[serde(skip_serializing_if = "Vec::is_empty")]
         pub(crate) ranges: Vec<[u16; 2]>,
+
+        /// Keys not recognized by this format, kept so they can be reported
+        #[serde(flatten)]
+        pub(crate) unknown_keys: BTreeMap<String, TomlValue>,
     }
 
     #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -139,6 +193,10 @@ pub(crate) mod raw_toml {
 
         #[serde(skip_serializing_if = "BTreeMap::is_empty")]
         pub(crate) importers: BTreeMap<String, PluginsIngestMember>,
+
+        /// Keys not recognized by this format, kept so they can be reported
+        #[serde(flatten)]
+        pub(crate) unknown_keys: BTreeMap<String, TomlValue>,
     }
 
     #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -154,6 +212,10 @@ pub(crate) mod raw_toml {
 
         #[serde(skip_serializing_if = "BTreeMap::is_empty")]
         pub(crate) metadata: BTreeMap<String, TomlValue>,
+
+        /// Keys not recognized by this format, kept so they can be reported
+        #[serde(flatten)]
+        pub(crate) unknown_keys: BTreeMap<String, TomlValue>,
     }
 
     #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -161,6 +223,10 @@ pub(crate) mod raw_toml {
     pub(crate) struct PluginsMutation {
         #[serde(skip_serializing_if = "BTreeMap::is_empty")]
         pub(crate) mutators: BTreeMap<String, PluginsMutationMember>,
+
+        /// Keys not recognized by this format, kept so they can be reported
+        #[serde(flatten)]
+        pub(crate) unknown_keys: BTreeMap<String, TomlValue>,
     }
 
     #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -176,6 +242,10 @@ pub(crate) mod raw_toml {
 
         #[serde(skip_serializing_if = "BTreeMap::is_empty")]
         pub(crate) metadata: BTreeMap<String, TomlValue>,
+
+        /// Keys not recognized by this format, kept so they can be reported
+        #[serde(flatten)]
+        pub(crate) unknown_keys: BTreeMap<String, TomlValue>,
     }
 
     #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -185,6 +255,217 @@ pub(crate) mod raw_toml {
         pub(crate) shutdown_timeout_millis: Option<u64>,
     }
 
+    impl Config {
+        /// Read the `ingest.timeline-attributes-file`, if any, and append its entries to
+        /// `ingest.additional-timeline-attributes`.
+        ///
+        /// The file holds one `key = value` pair per line, using the same grammar as the inline
+        /// entries; blank lines and lines starting with `#` are skipped. A relative path is
+        /// resolved against `base_dir` when one is given.
+        pub(crate) fn load_timeline_attributes_file(
+            &mut self,
+            base_dir: Option<&Path>,
+        ) -> Result<(), ConfigLoadError> {
+            let Some(ingest) = self.ingest.as_mut() else {
+                return Ok(());
+            };
+            let Some(file) = ingest.timeline_attributes_file.take() else {
+                return Ok(());
+            };
+
+            let path = match base_dir {
+                Some(base_dir) if file.is_relative() => base_dir.join(file),
+                _ => file,
+            };
+            let content = std::fs::read_to_string(&path)
+                .map_err(|error| ConfigLoadError::TimelineAttributesFile { path, error })?;
+
+            ingest
+                .timeline_attributes
+                .additional_timeline_attributes
+                .extend(
+                    content
+                        .lines()
+                        .map(str::trim)
+                        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                        .map(ToOwned::to_owned),
+                );
+            Ok(())
+        }
+
+        /// Dotted paths of every key in the config that isn't part of the format
+        pub(crate) fn unknown_keys(&self) -> Vec<String> {
+            let mut keys = Vec::new();
+            collect_unknown("", &self.unknown_keys, &mut keys);
+
+            if let Some(ingest) = &self.ingest {
+                collect_unknown("ingest", &ingest.unknown_keys, &mut keys);
+            }
+            if let Some(mutation) = &self.mutation {
+                collect_unknown("mutation", &mutation.unknown_keys, &mut keys);
+            }
+            if let Some(plugins) = &self.plugins {
+                collect_unknown("plugins", &plugins.unknown_keys, &mut keys);
+                if let Some(ports) = &plugins.available_ports {
+                    collect_unknown("plugins.available-ports", &ports.unknown_keys, &mut keys);
+                }
+                if let Some(ingest) = &plugins.ingest {
+                    collect_unknown("plugins.ingest", &ingest.unknown_keys, &mut keys);
+                    for (kind, members) in [
+                        ("collectors", &ingest.collectors),
+                        ("importers", &ingest.importers),
+                    ] {
+                        for (name, member) in members {
+                            collect_unknown(
+                                &format!("plugins.ingest.{kind}.{name}"),
+                                &member.unknown_keys,
+                                &mut keys,
+                            );
+                        }
+                    }
+                }
+                if let Some(mutation) = &plugins.mutation {
+                    collect_unknown("plugins.mutation", &mutation.unknown_keys, &mut keys);
+                    for (name, member) in &mutation.mutators {
+                        collect_unknown(
+                            &format!("plugins.mutation.mutators.{name}"),
+                            &member.unknown_keys,
+                            &mut keys,
+                        );
+                    }
+                }
+            }
+
+            keys
+        }
+
+        /// Apply environment variable substitution to the free-form string settings: URLs,
+        /// paths, plugin names, shutdown signals and string values (at any depth) in `metadata`
+        /// tables. The grammar is the same as for attribute pairs; see [`AttrKeyEqValuePair`].
+        ///
+        /// Attribute pair strings are left untouched here since they are substituted when
+        /// parsed, and doing it twice would expand `${...}` sequences produced by the first pass.
+        pub(crate) fn substitute_env_vars(&mut self) -> Result<(), ConfigLoadError> {
+            if let Some(ingest) = self.ingest.as_mut() {
+                envsub_opt(
+                    "ingest.protocol-parent-url",
+                    &mut ingest.protocol_parent_url,
+                )?;
+                envsub_path(
+                    "ingest.timeline-attributes-file",
+                    &mut ingest.timeline_attributes_file,
+                )?;
+            }
+
+            if let Some(mutation) = self.mutation.as_mut() {
+                envsub_opt(
+                    "mutation.protocol-parent-url",
+                    &mut mutation.protocol_parent_url,
+                )?;
+                for (idx, url) in mutation.external_mutator_urls.iter_mut().enumerate() {
+                    envsub_field(&format!("mutation.external-mutator-urls[{idx}]"), url)?;
+                }
+            }
+
+            if let Some(plugins) = self.plugins.as_mut() {
+                envsub_path("plugins.plugins-dir", &mut plugins.plugins_dir)?;
+                if let Some(ingest) = plugins.ingest.as_mut() {
+                    for (kind, members) in [
+                        ("collectors", &mut ingest.collectors),
+                        ("importers", &mut ingest.importers),
+                    ] {
+                        for (name, member) in members.iter_mut() {
+                            let path = format!("plugins.ingest.{kind}.{name}");
+                            envsub_opt(&format!("{path}.plugin"), &mut member.plugin)?;
+                            envsub_opt(
+                                &format!("{path}.shutdown-signal"),
+                                &mut member.shutdown.shutdown_signal,
+                            )?;
+                            envsub_metadata(&format!("{path}.metadata"), &mut member.metadata)?;
+                        }
+                    }
+                }
+                if let Some(mutation) = plugins.mutation.as_mut() {
+                    for (name, member) in mutation.mutators.iter_mut() {
+                        let path = format!("plugins.mutation.mutators.{name}");
+                        envsub_opt(&format!("{path}.plugin"), &mut member.plugin)?;
+                        envsub_opt(
+                            &format!("{path}.shutdown-signal"),
+                            &mut member.shutdown.shutdown_signal,
+                        )?;
+                        envsub_metadata(&format!("{path}.metadata"), &mut member.metadata)?;
+                    }
+                }
+            }
+
+            envsub_metadata("metadata", &mut self.metadata)
+        }
+    }
+
+    fn collect_unknown(path: &str, unknown: &BTreeMap<String, TomlValue>, keys: &mut Vec<String>) {
+        for k in unknown.keys() {
+            if path.is_empty() {
+                keys.push(k.clone());
+            } else {
+                keys.push(format!("{path}.{k}"));
+            }
+        }
+    }
+
+    fn envsub_field(path: &str, s: &mut String) -> Result<(), ConfigLoadError> {
+        *s = envsub(s).map_err(|error| ConfigLoadError::EnvSub {
+            path: path.to_owned(),
+            error,
+        })?;
+        Ok(())
+    }
+
+    fn envsub_opt(path: &str, s: &mut Option<String>) -> Result<(), ConfigLoadError> {
+        match s {
+            Some(s) => envsub_field(path, s),
+            None => Ok(()),
+        }
+    }
+
+    /// Paths that aren't valid unicode can't contain a substitution expression and are left as-is
+    fn envsub_path(path: &str, p: &mut Option<PathBuf>) -> Result<(), ConfigLoadError> {
+        if let Some(s) = p.as_ref().and_then(|p| p.to_str()) {
+            let mut s = s.to_owned();
+            envsub_field(path, &mut s)?;
+            *p = Some(PathBuf::from(s));
+        }
+        Ok(())
+    }
+
+    fn envsub_metadata(
+        path: &str,
+        metadata: &mut BTreeMap<String, TomlValue>,
+    ) -> Result<(), ConfigLoadError> {
+        for (k, v) in metadata.iter_mut() {
+            envsub_toml_value(&format!("{path}.{k}"), v)?;
+        }
+        Ok(())
+    }
+
+    fn envsub_toml_value(path: &str, value: &mut TomlValue) -> Result<(), ConfigLoadError> {
+        match value {
+            TomlValue::String(s) => envsub_field(path, s),
+            TomlValue::Array(items) => {
+                for (idx, item) in items.iter_mut().enumerate() {
+                    envsub_toml_value(&format!("{path}[{idx}]"), item)?;
+                }
+                Ok(())
+            }
+            TomlValue::Table(table) => {
+                for (k, v) in table.iter_mut() {
+                    envsub_toml_value(&format!("{path}.{k}"), v)?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
     #[cfg(test)]
     pub(crate) fn try_raw_to_string_pretty(config: &Config) -> Result<String, toml::ser::Error> {
         // Slightly unexpected detour through toml::Value to work around some
@@ -213,14 +494,16 @@ pub(crate) mod raw_toml {
             &self,
             plugin_name: S,
         ) -> Option<&PluginsIngestMember> {
-            find_member_by_plugin_name(&self.collectors, plugin_name)
+            // Templates are still among the members in the raw sections
+            find_member_by_plugin_name(&self.collectors, &self.collectors, plugin_name)
         }
 
         pub(crate) fn find_importer_member_by_plugin_name<S: AsRef<str>>(
             &self,
             plugin_name: S,
         ) -> Option<&PluginsIngestMember> {
-            find_member_by_plugin_name(&self.importers, plugin_name)
+            // Templates are still among the members in the raw sections
+            find_member_by_plugin_name(&self.importers, &self.importers, plugin_name)
         }
     }
 
@@ -230,7 +513,8 @@ pub(crate) mod raw_toml {
             &self,
             plugin_name: S,
         ) -> Option<&PluginsMutationMember> {
-            find_member_by_plugin_name(&self.mutators, plugin_name)
+            // Templates are still among the members in the raw sections
+            find_member_by_plugin_name(&self.mutators, &self.mutators, plugin_name)
         }
     }
 
@@ -241,6 +525,7 @@ pub(crate) mod raw_toml {
                 mutation: value.mutation.map(Into::into),
                 plugins: value.plugins.map(Into::into),
                 metadata: value.metadata,
+                unknown_keys: Default::default(),
             }
         }
     }
@@ -258,8 +543,13 @@ pub(crate) mod raw_toml {
                         millis as u64
                     }
                 }),
+                max_events_per_second: value.max_events_per_second,
+                max_event_burst: value.max_event_burst,
+                protocol_version: value.protocol_version,
                 protocol_child_port: value.protocol_child_port.map(Into::into),
                 timeline_attributes: value.timeline_attributes.into(),
+                timeline_attributes_file: None,
+                unknown_keys: Default::default(),
             }
         }
     }
@@ -270,12 +560,14 @@ pub(crate) mod raw_toml {
                 allow_insecure_tls: value.allow_insecure_tls,
                 protocol_child_port: value.protocol_child_port.map(Into::into),
                 mutator_http_api_port: value.mutator_http_api_port.map(Into::into),
+                mutator_http_api_key: value.mutator_http_api_key.map(|k| k.to_toml()),
                 mutator_attributes: value.mutator_attributes.into(),
                 external_mutator_urls: value
                     .external_mutator_urls
                     .into_iter()
                     .map(Into::into)
                     .collect(),
+                unknown_keys: Default::default(),
             }
         }
     }
@@ -286,6 +578,7 @@ pub(crate) mod raw_toml {
                 plugins_dir: value.plugins_dir,
                 ingest: value.ingest.map(Into::into),
                 mutation: value.mutation.map(Into::into),
+                unknown_keys: Default::default(),
             }
         }
     }
@@ -327,13 +620,16 @@ pub(crate) mod raw_toml {
                 collectors: value
                     .collectors
                     .into_iter()
+                    .chain(value.collector_templates)
                     .map(|(k, v)| (k, v.into()))
                     .collect(),
                 importers: value
                     .importers
                     .into_iter()
+                    .chain(value.importer_templates)
                     .map(|(k, v)| (k, v.into()))
                     .collect(),
+                unknown_keys: Default::default(),
             }
         }
     }
@@ -343,8 +639,10 @@ pub(crate) mod raw_toml {
                 mutators: value
                     .mutators
                     .into_iter()
+                    .chain(value.mutator_templates)
                     .map(|(k, v)| (k, v.into()))
                     .collect(),
+                unknown_keys: Default::default(),
             }
         }
     }
@@ -355,6 +653,7 @@ pub(crate) mod raw_toml {
                 timeline_attributes: value.timeline_attributes.into(),
                 shutdown: value.shutdown.into(),
                 metadata: value.metadata,
+                unknown_keys: Default::default(),
             }
         }
     }
@@ -365,6 +664,7 @@ pub(crate) mod raw_toml {
                 mutator_attributes: value.mutator_attributes.into(),
                 shutdown: value.shutdown.into(),
                 metadata: value.metadata,
+                unknown_keys: Default::default(),
             }
         }
     }
@@ -394,6 +694,7 @@ pub(crate) mod raw_toml {
                     .into_iter()
                     .map(|inclusive_range| [inclusive_range.start(), inclusive_range.end()])
                     .collect(),
+                unknown_keys: Default::default(),
             }
         }
     }
@@ -401,8 +702,9 @@ pub(crate) mod raw_toml {
 
 /// Public-facing, more-semantically-enriched configuration types
 mod refined {
+    pub use super::secret::ConfigSecret;
     use super::TomlValue;
-    use crate::api::types::{AttrKey, AttrVal};
+    use crate::api::types::{AttrKey, AttrKeyPattern, AttrVal, Nanoseconds};
     use lazy_static::lazy_static;
     use regex::{Captures, Regex};
     use std::collections::BTreeMap;
@@ -428,6 +730,14 @@ mod refined {
         pub protocol_child_port: Option<u16>,
         pub timeline_attributes: TimelineAttributes,
         pub max_write_batch_staleness: Option<Duration>,
+        /// Sustained rate, in events per second, that plugin ingest clients are paced to
+        pub max_events_per_second: Option<u32>,
+        /// Number of events that may be sent back-to-back before pacing kicks in
+        pub max_event_burst: Option<u32>,
+        /// Require this ingest protocol version when connecting, rather than
+        /// negotiating; one of
+        /// [`SUPPORTED_INGEST_PROTOCOL_VERSIONS`](crate::ingest_protocol::SUPPORTED_INGEST_PROTOCOL_VERSIONS).
+        pub protocol_version: Option<u32>,
     }
 
     #[derive(Debug, Clone, Default, PartialEq, Eq)]
@@ -436,6 +746,9 @@ mod refined {
         pub allow_insecure_tls: bool,
         pub protocol_child_port: Option<u16>,
         pub mutator_http_api_port: Option<u16>,
+        /// The key clients of the mutator HTTP API must present in the
+        /// `mutator_apikey` header
+        pub mutator_http_api_key: Option<ConfigSecret>,
         pub mutator_attributes: MutatorAttributes,
         pub external_mutator_urls: Vec<Url>,
     }
@@ -463,7 +776,7 @@ mod refined {
     impl InclusivePortRange {
         pub fn new(start: u16, end: u16) -> Result<Self, SemanticErrorExplanation> {
             if start > end {
-                Err(SemanticErrorExplanation(format!("Port range start must <= end, but provided start {start} was > provided end {end}")))
+                Err(SemanticErrorExplanation::new(format!("Port range start must <= end, but provided start {start} was > provided end {end}")))
             } else {
                 Ok(InclusivePortRange { start, end })
             }
@@ -527,6 +840,11 @@ mod refined {
     pub struct PluginsIngest {
         pub collectors: BTreeMap<String, PluginsIngestMember>,
         pub importers: BTreeMap<String, PluginsIngestMember>,
+        /// Template sections (see [is_member_template]), kept apart from the
+        /// collectors once those have inherited their settings
+        pub collector_templates: BTreeMap<String, PluginsIngestMember>,
+        /// Template sections for importers, as with `collector_templates`
+        pub importer_templates: BTreeMap<String, PluginsIngestMember>,
     }
     #[derive(Debug, Clone, Default, PartialEq)]
     pub struct PluginsIngestMember {
@@ -538,6 +856,9 @@ mod refined {
     #[derive(Debug, Clone, Default, PartialEq)]
     pub struct PluginsMutation {
         pub mutators: BTreeMap<String, PluginsMutationMember>,
+        /// Template sections (see [is_member_template]), kept apart from the
+        /// mutators once those have inherited their settings
+        pub mutator_templates: BTreeMap<String, PluginsMutationMember>,
     }
     #[derive(Debug, Clone, Default, PartialEq)]
     pub struct PluginsMutationMember {
@@ -560,6 +881,9 @@ mod refined {
         #[error("The key '{0}' starts with an invalid character.")]
         InvalidKey(String),
 
+        #[error("The value '{0}' looks like a number but could not be parsed as one. Quote it if it is meant to be a string.")]
+        AmbiguousValue(String),
+
         #[error(transparent)]
         EnvSub(#[from] EnvSubError),
     }
@@ -572,6 +896,21 @@ mod refined {
     /// * `${NAME}`
     /// * `${NAME-default}`
     /// * `${NAME:-default}`
+    ///
+    /// A literal `${` is written `$${`.
+    ///
+    /// When loaded as part of a config, values may also refer to other attributes of the same
+    /// section with `${attr:KEY}` expressions, which are resolved after parsing.
+    ///
+    /// Durations are written as a count of nanoseconds with an `ns` suffix, e.g. `3000ns`, and
+    /// timestamps (nanoseconds since the Unix epoch) the same way with a leading `@`, e.g.
+    /// `@1700000000000000000ns`. These are also how they're displayed, so both round-trip.
+    ///
+    /// Unquoted values that are a number followed by a few stray letters (e.g. `port = 12ab`) are
+    /// rejected with [`AttrKeyValuePairParseError::AmbiguousValue`] instead of becoming strings.
+    /// Other values that merely start with a digit, like `10.0.0.1`, `1.2.3`, `2024-01-31` or an
+    /// abbreviated git hash, are still strings. Only the literal text is checked; values that
+    /// came from an environment variable are taken as they are.
     #[derive(Clone, Debug, PartialEq, Eq, PartialOrd)]
     pub struct AttrKeyEqValuePair(pub AttrKey, pub AttrVal);
 
@@ -600,13 +939,80 @@ mod refined {
                 return Err(AttrKeyValuePairParseError::InvalidKey(key.to_string()));
             }
 
-            let val: Result<_, std::convert::Infallible> = val_str.parse();
-            let val = val.unwrap();
+            let val = match parse_nanoseconds_literal(val_str) {
+                Some(val) => val,
+                None => {
+                    let val: Result<_, std::convert::Infallible> = val_str.parse();
+                    val.unwrap()
+                }
+            };
+
+            // Unquoted values that are a number with some trailing letters are almost certainly
+            // typos, rather than intended strings. Only check text written literally in the
+            // input, not text that was substituted in from the environment.
+            let is_literal = input.trim_end().ends_with(val_str);
+            if matches!(val, AttrVal::String(_)) && is_literal && looks_like_numeric_typo(val_str) {
+                return Err(AttrKeyValuePairParseError::AmbiguousValue(
+                    val_str.to_string(),
+                ));
+            }
 
             Ok(AttrKeyEqValuePair(AttrKey::new(key.to_string()), val))
         }
     }
 
+    impl AttrKeyEqValuePair {
+        /// Parse a list of `key = value` pairs separated by `separator`, e.g.
+        /// `"a = 1, b = true"` with `','`, or one pair per line with `'\n'`.
+        /// Blank entries are skipped. There is no quoting of the separator, so
+        /// values can't contain it.
+        ///
+        /// On failure, returns the (zero-based) index of the first bad entry
+        /// among the separated entries, along with its error.
+        pub fn parse_many(
+            s: &str,
+            separator: char,
+        ) -> Result<Vec<AttrKeyEqValuePair>, (usize, AttrKeyValuePairParseError)> {
+            s.split(separator)
+                .enumerate()
+                .filter(|(_, entry)| !entry.trim().is_empty())
+                .map(|(idx, entry)| AttrKeyEqValuePair::from_str(entry).map_err(|e| (idx, e)))
+                .collect()
+        }
+    }
+
+    /// `3000ns` as a duration, or `@3000ns` as a timestamp
+    fn parse_nanoseconds_literal(s: &str) -> Option<AttrVal> {
+        let (is_timestamp, s) = match s.strip_prefix('@') {
+            Some(s) => (true, s),
+            None => (false, s),
+        };
+        let ns = Nanoseconds::from(s.strip_suffix("ns")?.parse::<u64>().ok()?);
+        Some(if is_timestamp {
+            AttrVal::Timestamp(ns)
+        } else {
+            AttrVal::Duration(ns)
+        })
+    }
+
+    /// A number followed only by letters, like `12ab` or `-.5e`. Runs of 7 or more hex digits
+    /// are left alone, since they're likely abbreviated hashes.
+    fn looks_like_numeric_typo(s: &str) -> bool {
+        if s.len() >= 7 && s.chars().all(|c| c.is_ascii_hexdigit()) {
+            return false;
+        }
+
+        let s = s.strip_prefix(['-', '+']).unwrap_or(s);
+        let suffix_start = s
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(s.len());
+        let (number, suffix) = s.split_at(suffix_start);
+        number.contains(|c: char| c.is_ascii_digit())
+            && number.matches('.').count() <= 1
+            && !suffix.is_empty()
+            && suffix.chars().all(|c| c.is_ascii_alphabetic())
+    }
+
     impl TryFrom<String> for AttrKeyEqValuePair {
         type Error = AttrKeyValuePairParseError;
 
@@ -641,266 +1047,623 @@ mod refined {
                     s.push('\"');
                     s
                 }
+                // Debug always includes a '.' or exponent, so it doesn't parse back as an integer
+                AttrVal::Float(fp) => format!("{:?}", fp.0),
+                AttrVal::Duration(ns) => format!("{}ns", ns.get_raw()),
+                AttrVal::Timestamp(ns) => format!("@{}ns", ns.get_raw()),
                 v => v.to_string(),
             };
             write!(f, "{} = {}", self.0, val_s)
         }
     }
 
-    #[derive(Debug)]
-    pub struct SemanticErrorExplanation(pub String);
+    /// A semantic problem found while refining the raw TOML content.
+    ///
+    /// `path` is the dotted location of the offending entry, e.g.
+    /// `plugins.ingest.collectors.lttng-live.additional-timeline-attributes[1]`,
+    /// and is empty when the problem isn't tied to a particular entry.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct SemanticErrorExplanation {
+        pub path: String,
+        pub message: String,
+    }
+
+    impl SemanticErrorExplanation {
+        pub fn new<S: Into<String>>(message: S) -> Self {
+            SemanticErrorExplanation {
+                path: String::new(),
+                message: message.into(),
+            }
+        }
 
-    use crate::reflector_config::raw_toml;
-    impl TryFrom<raw_toml::Config> for Config {
-        type Error = SemanticErrorExplanation;
+        /// Prefix the path with the given (kebab-case) field or table key name.
+        pub(crate) fn in_field(mut self, name: &str) -> Self {
+            self.path = if self.path.is_empty() || self.path.starts_with('[') {
+                format!("{name}{}", self.path)
+            } else {
+                format!("{name}.{}", self.path)
+            };
+            self
+        }
 
-        fn try_from(value: raw_toml::Config) -> Result<Self, Self::Error> {
-            Ok(Self {
-                ingest: if let Some(ingest) = value.ingest {
-                    Some(ingest.try_into()?)
-                } else {
-                    None
-                },
-                mutation: if let Some(mutation) = value.mutation {
-                    Some(mutation.try_into()?)
-                } else {
-                    None
-                },
-                plugins: if let Some(plugins) = value.plugins {
-                    Some(plugins.try_into()?)
-                } else {
-                    None
-                },
-                metadata: value.metadata,
-            })
+        /// Prefix the path with the given array index.
+        pub(crate) fn in_index(mut self, index: usize) -> Self {
+            self.path = if self.path.is_empty() || self.path.starts_with('[') {
+                format!("[{index}]{}", self.path)
+            } else {
+                format!("[{index}].{}", self.path)
+            };
+            self
         }
     }
 
-    impl TryFrom<raw_toml::TopLevelIngest> for TopLevelIngest {
-        type Error = SemanticErrorExplanation;
-
-        fn try_from(value: raw_toml::TopLevelIngest) -> Result<Self, Self::Error> {
-            Ok(Self {
-                protocol_parent_url: if let Some(u) = value.protocol_parent_url {
-                    Some(url::Url::from_str(&u).map_err(|parse_err| {
-                        SemanticErrorExplanation(format!(
-                            "ingest.protocol-parent-url could not be parsed. {parse_err}"
-                        ))
-                    })?)
-                } else {
-                    None
-                },
-                protocol_child_port: value.protocol_child_port,
-                timeline_attributes: value.timeline_attributes.try_into()?,
-                allow_insecure_tls: value.allow_insecure_tls,
-                max_write_batch_staleness: value
-                    .max_write_batch_staleness_millis
-                    .map(Duration::from_millis),
-            })
+    impl fmt::Display for SemanticErrorExplanation {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(&self.message)
         }
     }
-    impl TryFrom<raw_toml::TimelineAttributes> for TimelineAttributes {
-        type Error = SemanticErrorExplanation;
 
-        fn try_from(value: raw_toml::TimelineAttributes) -> Result<Self, Self::Error> {
-            Ok(Self {
-                additional_timeline_attributes: value
-                    .additional_timeline_attributes
-                    .into_iter()
-                    .map(AttrKeyEqValuePair::try_from)
-                    .collect::<Result<Vec<_>, AttrKeyValuePairParseError>>()
-                    .map_err(|e| {
-                        SemanticErrorExplanation(format!(
-                            "Error in additional-timeline-attributes member. {e}"
-                        ))
-                    })?,
-                override_timeline_attributes: value
-                    .override_timeline_attributes
-                    .into_iter()
-                    .map(AttrKeyEqValuePair::try_from)
-                    .collect::<Result<Vec<_>, AttrKeyValuePairParseError>>()
-                    .map_err(|e| {
-                        SemanticErrorExplanation(format!(
-                            "Error in override-timeline-attributes member. {e}"
-                        ))
-                    })?,
+    impl std::error::Error for SemanticErrorExplanation {}
+
+    fn attr_kv_pairs(field: &str, values: Vec<String>) -> Refined<Vec<AttrKeyEqValuePair>> {
+        refine_each(values.into_iter().enumerate().map(|(idx, v)| {
+            AttrKeyEqValuePair::try_from(v).map_err(|e| {
+                vec![
+                    SemanticErrorExplanation::new(format!("Error in {field} member. {e}"))
+                        .in_index(idx)
+                        .in_field(field),
+                ]
             })
-        }
+        }))
     }
-    impl TryFrom<raw_toml::MutatorAttributes> for MutatorAttributes {
-        type Error = SemanticErrorExplanation;
 
-        fn try_from(value: raw_toml::MutatorAttributes) -> Result<Self, Self::Error> {
-            Ok(Self {
-                additional_mutator_attributes: value
-                    .additional_mutator_attributes
-                    .into_iter()
-                    .map(AttrKeyEqValuePair::try_from)
-                    .collect::<Result<Vec<_>, AttrKeyValuePairParseError>>()
-                    .map_err(|e| {
-                        SemanticErrorExplanation(format!(
-                            "Error in additional-mutator-attributes member. {e}"
-                        ))
-                    })?,
-                override_mutator_attributes: value
-                    .override_mutator_attributes
-                    .into_iter()
-                    .map(AttrKeyEqValuePair::try_from)
-                    .collect::<Result<Vec<_>, AttrKeyValuePairParseError>>()
-                    .map_err(|e| {
-                        SemanticErrorExplanation(format!(
-                            "Error in override-mutator-attributes member. {e}"
-                        ))
-                    })?,
+    /// Resolve references to other attributes in string attribute values, within one scope:
+    /// the additional and override attributes of a single config section. This runs after
+    /// environment variable substitution, and supports the following expressions:
+    /// * `${attr:KEY}`
+    /// * `${attr:KEY-default}`
+    /// * `${attr:KEY:-default}`
+    ///
+    /// `KEY` is matched against the attribute keys as written, and may only contain letters,
+    /// digits, `_` and `.`. A value that is a single reference takes on the referenced value,
+    /// including its type; otherwise the referenced values are interpolated into the string.
+    /// When a key is both additional and overridden, references see the override.
+    fn resolve_attr_refs(
+        scope: [(&str, &mut Vec<AttrKeyEqValuePair>); 2],
+    ) -> Result<(), SemanticErrorExplanation> {
+        let entries: Vec<(&str, usize, &AttrKeyEqValuePair)> = scope
+            .iter()
+            .flat_map(|(field, kvs)| {
+                kvs.iter()
+                    .enumerate()
+                    .map(move |(idx, kv)| (*field, idx, kv))
             })
+            .collect();
+        // Later entries (the overrides) win
+        let by_key: BTreeMap<&AttrKey, usize> = entries
+            .iter()
+            .enumerate()
+            .map(|(i, (_, _, kv))| (&kv.0, i))
+            .collect();
+
+        let mut resolved = vec![None; entries.len()];
+        for i in 0..entries.len() {
+            resolve_attr_ref(i, &entries, &by_key, &mut resolved, &mut Vec::new()).map_err(
+                |message| {
+                    let (field, idx, _) = entries[i];
+                    SemanticErrorExplanation::new(format!("Error in {field} member. {message}"))
+                        .in_index(idx)
+                        .in_field(field)
+                },
+            )?;
+        }
+        drop(entries);
+
+        let mut resolved = resolved.into_iter().flatten();
+        for (_, kvs) in scope {
+            for kv in kvs.iter_mut() {
+                kv.1 = resolved.next().expect("every attribute was resolved");
+            }
         }
+        Ok(())
     }
 
-    impl TryFrom<raw_toml::TopLevelMutation> for TopLevelMutation {
-        type Error = SemanticErrorExplanation;
+    fn resolve_attr_ref(
+        i: usize,
+        entries: &[(&str, usize, &AttrKeyEqValuePair)],
+        by_key: &BTreeMap<&AttrKey, usize>,
+        resolved: &mut [Option<AttrVal>],
+        visiting: &mut Vec<usize>,
+    ) -> Result<AttrVal, String> {
+        lazy_static! {
+            // Matches the following patterns with named capture groups:
+            // * '${attr:KEY}' : key = 'KEY'
+            // * '${attr:KEY-default}' : key = 'KEY', def = 'default'
+            // * '${attr:KEY:-default}' : key = 'KEY', def = 'default'
+            static ref ATTR_REF_RE: Regex =
+                Regex::new(r"\$\{attr:(?P<key>[a-zA-Z_][a-zA-Z0-9_.]*)(:?-(?P<def>.*?))?\}")
+                    .expect("Could not construct attribute reference Regex");
+        }
 
-        fn try_from(value: raw_toml::TopLevelMutation) -> Result<Self, Self::Error> {
-            Ok(Self {
-                protocol_parent_url: if let Some(u) = value.protocol_parent_url {
-                    Some(url::Url::from_str(&u).map_err(|parse_err| SemanticErrorExplanation(format!("mutation.protocol-parent-url could not be parsed. {parse_err}")))?)
-                } else {
-                    None
+        if let Some(v) = &resolved[i] {
+            return Ok(v.clone());
+        }
+        if let Some(start) = visiting.iter().position(|j| *j == i) {
+            let cycle: Vec<String> = visiting[start..]
+                .iter()
+                .chain([&i])
+                .map(|j| entries[*j].2 .0.to_string())
+                .collect();
+            return Err(format!(
+                "Cyclic attribute reference: {}",
+                cycle.join(" -> ")
+            ));
+        }
+
+        let value = &entries[i].2 .1;
+        let s = match value {
+            AttrVal::String(s) if ATTR_REF_RE.is_match(s.as_ref()) => s.as_ref(),
+            _ => {
+                resolved[i] = Some(value.clone());
+                return Ok(value.clone());
+            }
+        };
+
+        visiting.push(i);
+        let mut lookup = |caps: &Captures| -> Result<AttrVal, String> {
+            let key = &caps["key"];
+            match by_key.get(&AttrKey::from(key)) {
+                Some(j) => resolve_attr_ref(*j, entries, by_key, resolved, visiting),
+                None => match caps.name("def") {
+                    Some(def) => Ok(AttrVal::from(def.as_str())),
+                    None => Err(format!(
+                        "The attribute '{key}' is referenced, but isn't set here and no default value is specified"
+                    )),
                 },
-                allow_insecure_tls: value.allow_insecure_tls,
-                protocol_child_port: value.protocol_child_port,
-                mutator_http_api_port: value.mutator_http_api_port,
-                mutator_attributes: value.mutator_attributes.try_into()?,
-                external_mutator_urls: value.external_mutator_urls.into_iter().map(|v| url::Url::from_str(&v).map_err(|parse_err|SemanticErrorExplanation(format!("mutation.external-mutator-urls member {v} could not be parsed. {parse_err}")))).collect::<Result<Vec<url::Url>, SemanticErrorExplanation>>()?,
+            }
+        };
+        let result = match ATTR_REF_RE.captures(s) {
+            Some(caps) if caps[0].len() == s.len() => lookup(&caps)?,
+            _ => {
+                let mut new = String::with_capacity(s.len());
+                let mut last_match = 0;
+                for caps in ATTR_REF_RE.captures_iter(s) {
+                    let m = caps.get(0).unwrap();
+                    new.push_str(&s[last_match..m.start()]);
+                    match lookup(&caps)? {
+                        AttrVal::String(v) => new.push_str(v.as_ref()),
+                        v => new.push_str(&v.to_string()),
+                    }
+                    last_match = m.end();
+                }
+                new.push_str(&s[last_match..]);
+                AttrVal::from(new)
+            }
+        };
+        visiting.pop();
+
+        resolved[i] = Some(result.clone());
+        Ok(result)
+    }
+
+    /// The result of refining part of a raw config. Independent parts are
+    /// refined independently, so that every problem with a config can be
+    /// reported at once; see [`super::check_file`].
+    pub(crate) type Refined<T> = Result<T, Vec<SemanticErrorExplanation>>;
+
+    /// Refine a raw config section into its public-facing counterpart.
+    pub(crate) trait Refine {
+        type Output;
+
+        fn refine(self) -> Refined<Self::Output>;
+    }
+
+    /// Gathers the errors from refining several parts of a section.
+    #[derive(Default)]
+    struct Errors(Vec<SemanticErrorExplanation>);
+
+    impl Errors {
+        /// The refined value, or, with its errors recorded, a placeholder
+        /// that [`Errors::into_result`] never lets out.
+        fn record<T: Default>(&mut self, refined: Refined<T>) -> T {
+            refined.unwrap_or_else(|errors| {
+                self.0.extend(errors);
+                T::default()
             })
         }
+
+        fn into_result<T>(self, value: T) -> Refined<T> {
+            if self.0.is_empty() {
+                Ok(value)
+            } else {
+                Err(self.0)
+            }
+        }
     }
-    impl TryFrom<raw_toml::TopLevelPlugins> for TopLevelPlugins {
-        type Error = SemanticErrorExplanation;
 
-        fn try_from(value: raw_toml::TopLevelPlugins) -> Result<Self, Self::Error> {
-            Ok(Self {
-                available_ports: if let Some(v) = value.available_ports {
-                    Some(v.try_into()?)
-                } else {
-                    None
-                },
-                plugins_dir: value.plugins_dir,
-                ingest: if let Some(v) = value.ingest {
-                    Some(v.try_into()?)
-                } else {
-                    None
-                },
-                mutation: if let Some(v) = value.mutation {
-                    Some(v.try_into()?)
-                } else {
-                    None
-                },
-            })
+    /// Refine each of `values`, gathering the errors from all of them.
+    fn refine_each<T>(values: impl IntoIterator<Item = Refined<T>>) -> Refined<Vec<T>> {
+        let mut errors = Vec::new();
+        let mut refined = Vec::new();
+        for v in values {
+            match v {
+                Ok(v) => refined.push(v),
+                Err(e) => errors.extend(e),
+            }
         }
+        Errors(errors).into_result(refined)
     }
 
-    impl TryFrom<raw_toml::AvailablePorts> for AvailablePorts {
-        type Error = SemanticErrorExplanation;
+    fn in_field<T>(refined: Refined<T>, name: &str) -> Refined<T> {
+        refined.map_err(|errors| errors.into_iter().map(|e| e.in_field(name)).collect())
+    }
 
-        fn try_from(value: raw_toml::AvailablePorts) -> Result<Self, Self::Error> {
-            Ok(Self {
-                any_local: value.any_local,
-                ranges: value
-                    .ranges
-                    .into_iter()
-                    .map(|v| InclusivePortRange::new(v[0], v[1]))
-                    .collect::<Result<Vec<InclusivePortRange>, SemanticErrorExplanation>>()?,
+    fn refine_opt<R: Refine>(value: Option<R>, field: &str) -> Refined<Option<R::Output>> {
+        value
+            .map(|v| in_field(v.refine(), field).map(Some))
+            .unwrap_or(Ok(None))
+    }
+
+    fn parse_url(url: Option<String>, section: &str) -> Refined<Option<Url>> {
+        url.map(|u| {
+            Url::from_str(&u).map(Some).map_err(|parse_err| {
+                vec![SemanticErrorExplanation::new(format!(
+                    "{section}.protocol-parent-url could not be parsed. {parse_err}"
+                ))
+                .in_field("protocol-parent-url")]
             })
-        }
+        })
+        .unwrap_or(Ok(None))
     }
-    impl TryFrom<raw_toml::PluginsIngest> for PluginsIngest {
-        type Error = SemanticErrorExplanation;
 
-        fn try_from(value: raw_toml::PluginsIngest) -> Result<Self, Self::Error> {
-            Ok(
-                Self {
-                    collectors:
-                        value
-                            .collectors
-                            .into_iter()
-                            .map(|(k, v)| v.try_into().map(|vv| (k, vv)))
-                            .collect::<Result<
-                                BTreeMap<String, PluginsIngestMember>,
-                                SemanticErrorExplanation,
-                            >>()?,
-                    importers:
-                        value
-                            .importers
-                            .into_iter()
-                            .map(|(k, v)| v.try_into().map(|vv| (k, vv)))
-                            .collect::<Result<
-                                BTreeMap<String, PluginsIngestMember>,
-                                SemanticErrorExplanation,
-                            >>()?,
-                },
-            )
-        }
+    fn members<R: Refine>(
+        field: &str,
+        values: BTreeMap<String, R>,
+    ) -> Refined<BTreeMap<String, R::Output>> {
+        let refined = refine_each(
+            values
+                .into_iter()
+                .map(|(k, v)| in_field(v.refine(), &k).map(|vv| (k, vv))),
+        );
+        in_field(refined, field).map(|members| members.into_iter().collect())
     }
-    impl TryFrom<raw_toml::PluginsIngestMember> for PluginsIngestMember {
+
+    use crate::reflector_config::raw_toml;
+    impl TryFrom<raw_toml::Config> for Config {
         type Error = SemanticErrorExplanation;
 
-        fn try_from(value: raw_toml::PluginsIngestMember) -> Result<Self, Self::Error> {
-            Ok(Self {
-                plugin: value.plugin,
-                timeline_attributes: value.timeline_attributes.try_into()?,
-                shutdown: value.shutdown.into(),
-                metadata: value.metadata,
-            })
+        /// Fails with the first of the config's problems; see [`Refine`] for
+        /// all of them.
+        fn try_from(value: raw_toml::Config) -> Result<Self, Self::Error> {
+            value.refine().map_err(|mut errors| errors.swap_remove(0))
         }
     }
-    impl TryFrom<raw_toml::PluginsMutation> for PluginsMutation {
-        type Error = SemanticErrorExplanation;
 
-        fn try_from(value: raw_toml::PluginsMutation) -> Result<Self, Self::Error> {
-            Ok(
-                Self {
-                    mutators:
-                        value
-                            .mutators
-                            .into_iter()
-                            .map(|(k, v)| v.try_into().map(|vv| (k, vv)))
-                            .collect::<Result<
-                                BTreeMap<String, PluginsMutationMember>,
-                                SemanticErrorExplanation,
-                            >>()?,
-                },
-            )
+    impl Refine for raw_toml::Config {
+        type Output = Config;
+
+        fn refine(self) -> Refined<Config> {
+            let mut errors = Errors::default();
+            let config = Config {
+                ingest: errors.record(refine_opt(self.ingest, "ingest")),
+                mutation: errors.record(refine_opt(self.mutation, "mutation")),
+                plugins: errors.record(refine_opt(self.plugins, "plugins")),
+                metadata: self.metadata,
+            };
+            errors.into_result(config)
         }
     }
-    impl TryFrom<raw_toml::PluginsMutationMember> for PluginsMutationMember {
-        type Error = SemanticErrorExplanation;
 
-        fn try_from(value: raw_toml::PluginsMutationMember) -> Result<Self, Self::Error> {
-            Ok(Self {
-                plugin: value.plugin,
-                mutator_attributes: value.mutator_attributes.try_into()?,
-                shutdown: value.shutdown.into(),
-                metadata: value.metadata,
+    impl Refine for raw_toml::TopLevelIngest {
+        type Output = TopLevelIngest;
+
+        fn refine(self) -> Refined<TopLevelIngest> {
+            let mut errors = Errors::default();
+            let ingest = TopLevelIngest {
+                protocol_parent_url: errors.record(parse_url(self.protocol_parent_url, "ingest")),
+                protocol_child_port: self.protocol_child_port,
+                timeline_attributes: errors.record(self.timeline_attributes.refine()),
+                allow_insecure_tls: self.allow_insecure_tls,
+                max_write_batch_staleness: self
+                    .max_write_batch_staleness_millis
+                    .map(Duration::from_millis),
+                max_events_per_second: self.max_events_per_second,
+                max_event_burst: self.max_event_burst,
+                protocol_version: self.protocol_version,
+            };
+            errors.into_result(ingest)
+        }
+    }
+    impl Refine for raw_toml::TimelineAttributes {
+        type Output = TimelineAttributes;
+
+        fn refine(self) -> Refined<TimelineAttributes> {
+            let mut errors = Errors::default();
+            let mut additional = errors.record(attr_kv_pairs(
+                "additional-timeline-attributes",
+                self.additional_timeline_attributes,
+            ));
+            let mut overrides = errors.record(attr_kv_pairs(
+                "override-timeline-attributes",
+                self.override_timeline_attributes,
+            ));
+            if errors.0.is_empty() {
+                errors.record(
+                    resolve_attr_refs([
+                        ("additional-timeline-attributes", &mut additional),
+                        ("override-timeline-attributes", &mut overrides),
+                    ])
+                    .map_err(|e| vec![e]),
+                );
+            }
+            errors.into_result(TimelineAttributes {
+                additional_timeline_attributes: additional,
+                override_timeline_attributes: overrides,
             })
         }
     }
-
-    impl From<raw_toml::PluginShutdown> for PluginShutdown {
-        fn from(value: raw_toml::PluginShutdown) -> Self {
-            Self {
-                shutdown_signal: value.shutdown_signal,
-                shutdown_timeout: value.shutdown_timeout_millis.map(Duration::from_millis),
+    impl Refine for raw_toml::MutatorAttributes {
+        type Output = MutatorAttributes;
+
+        fn refine(self) -> Refined<MutatorAttributes> {
+            let mut errors = Errors::default();
+            let mut additional = errors.record(attr_kv_pairs(
+                "additional-mutator-attributes",
+                self.additional_mutator_attributes,
+            ));
+            let mut overrides = errors.record(attr_kv_pairs(
+                "override-mutator-attributes",
+                self.override_mutator_attributes,
+            ));
+            if errors.0.is_empty() {
+                errors.record(
+                    resolve_attr_refs([
+                        ("additional-mutator-attributes", &mut additional),
+                        ("override-mutator-attributes", &mut overrides),
+                    ])
+                    .map_err(|e| vec![e]),
+                );
             }
+            errors.into_result(MutatorAttributes {
+                additional_mutator_attributes: additional,
+                override_mutator_attributes: overrides,
+            })
         }
     }
 
-    impl Config {
-        pub fn is_empty(&self) -> bool {
-            self.ingest.is_none()
-                && self.mutation.is_none()
-                && self.plugins.is_none()
+    impl Refine for raw_toml::TopLevelMutation {
+        type Output = TopLevelMutation;
+
+        fn refine(self) -> Refined<TopLevelMutation> {
+            let mut errors = Errors::default();
+            let mutator_http_api_key = errors.record(
+                self.mutator_http_api_key
+                    .map(|v| {
+                        ConfigSecret::from_toml(&v).map(Some).ok_or_else(|| {
+                            vec![SemanticErrorExplanation::new(
+                                "mutation.mutator-http-api-key must be a string or a { secret-ref = \"...\" } table",
+                            )
+                            .in_field("mutator-http-api-key")]
+                        })
+                    })
+                    .unwrap_or(Ok(None)),
+            );
+            let external_mutator_urls = errors.record(refine_each(
+                self.external_mutator_urls
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, v)| {
+                        Url::from_str(&v).map_err(|parse_err| {
+                            vec![SemanticErrorExplanation::new(format!(
+                                "mutation.external-mutator-urls member {v} could not be parsed. {parse_err}"
+                            ))
+                            .in_index(idx)
+                            .in_field("external-mutator-urls")]
+                        })
+                    }),
+            ));
+            let mutation = TopLevelMutation {
+                protocol_parent_url: errors.record(parse_url(self.protocol_parent_url, "mutation")),
+                allow_insecure_tls: self.allow_insecure_tls,
+                protocol_child_port: self.protocol_child_port,
+                mutator_http_api_port: self.mutator_http_api_port,
+                mutator_http_api_key,
+                mutator_attributes: errors.record(self.mutator_attributes.refine()),
+                external_mutator_urls,
+            };
+            errors.into_result(mutation)
+        }
+    }
+    impl Refine for raw_toml::TopLevelPlugins {
+        type Output = TopLevelPlugins;
+
+        fn refine(self) -> Refined<TopLevelPlugins> {
+            let mut errors = Errors::default();
+            let plugins = TopLevelPlugins {
+                available_ports: errors.record(refine_opt(self.available_ports, "available-ports")),
+                plugins_dir: self.plugins_dir,
+                ingest: errors.record(refine_opt(self.ingest, "ingest")),
+                mutation: errors.record(refine_opt(self.mutation, "mutation")),
+            };
+            errors.into_result(plugins)
+        }
+    }
+
+    impl Refine for raw_toml::AvailablePorts {
+        type Output = AvailablePorts;
+
+        fn refine(self) -> Refined<AvailablePorts> {
+            let ranges = refine_each(self.ranges.into_iter().enumerate().map(|(idx, v)| {
+                InclusivePortRange::new(v[0], v[1])
+                    .map_err(|e| vec![e.in_index(idx).in_field("ranges")])
+            }))?;
+            Ok(AvailablePorts {
+                any_local: self.any_local,
+                ranges,
+            })
+        }
+    }
+    impl Refine for raw_toml::PluginsIngest {
+        type Output = PluginsIngest;
+
+        fn refine(self) -> Refined<PluginsIngest> {
+            let mut errors = Errors::default();
+            let mut collectors = errors.record(members("collectors", self.collectors));
+            let mut importers = errors.record(members("importers", self.importers));
+            let collector_templates = apply_member_templates(&mut collectors);
+            let importer_templates = apply_member_templates(&mut importers);
+            errors.into_result(PluginsIngest {
+                collectors,
+                importers,
+                collector_templates,
+                importer_templates,
+            })
+        }
+    }
+    impl Refine for raw_toml::PluginsIngestMember {
+        type Output = PluginsIngestMember;
+
+        fn refine(self) -> Refined<PluginsIngestMember> {
+            Ok(PluginsIngestMember {
+                plugin: self.plugin,
+                timeline_attributes: self.timeline_attributes.refine()?,
+                shutdown: self.shutdown.into(),
+                metadata: self.metadata,
+            })
+        }
+    }
+    impl Refine for raw_toml::PluginsMutation {
+        type Output = PluginsMutation;
+
+        fn refine(self) -> Refined<PluginsMutation> {
+            let mut mutators = members("mutators", self.mutators)?;
+            let mutator_templates = apply_member_templates(&mut mutators);
+            Ok(PluginsMutation {
+                mutators,
+                mutator_templates,
+            })
+        }
+    }
+    impl Refine for raw_toml::PluginsMutationMember {
+        type Output = PluginsMutationMember;
+
+        fn refine(self) -> Refined<PluginsMutationMember> {
+            Ok(PluginsMutationMember {
+                plugin: self.plugin,
+                mutator_attributes: self.mutator_attributes.refine()?,
+                shutdown: self.shutdown.into(),
+                metadata: self.metadata,
+            })
+        }
+    }
+
+    impl From<raw_toml::PluginShutdown> for PluginShutdown {
+        fn from(value: raw_toml::PluginShutdown) -> Self {
+            Self {
+                shutdown_signal: value.shutdown_signal,
+                shutdown_timeout: value.shutdown_timeout_millis.map(Duration::from_millis),
+            }
+        }
+    }
+
+    impl Config {
+        /// A SHA-256 hash of the config's settings, for checking whether
+        /// the effective config has changed. Configs that are equal hash
+        /// equally, no matter how the files they came from were formatted
+        /// or ordered.
+        pub fn content_hash(&self) -> Result<[u8; 32], super::ConfigWriteError> {
+            use sha2::{Digest, Sha256};
+            let mut hasher = Sha256::new();
+            super::hash_toml(&super::diff::to_toml(self)?, &mut hasher);
+            Ok(hasher.finalize().into())
+        }
+
+        /// Advice about settings that are valid, but may not do what was
+        /// intended. Doesn't include unknown keys, which aren't kept in a
+        /// loaded config; see [`try_from_file_with_warnings`](super::try_from_file_with_warnings).
+        pub fn warnings(&self) -> Vec<super::ConfigWarning> {
+            use super::ConfigWarning;
+
+            let mut warnings = Vec::new();
+            if self.ingest.as_ref().is_some_and(|i| i.allow_insecure_tls) {
+                warnings.push(ConfigWarning::InsecureTlsEnabled {
+                    section: "ingest".to_owned(),
+                });
+            }
+            if self.mutation.as_ref().is_some_and(|m| m.allow_insecure_tls) {
+                warnings.push(ConfigWarning::InsecureTlsEnabled {
+                    section: "mutation".to_owned(),
+                });
+            }
+            let ports = self
+                .plugins
+                .as_ref()
+                .and_then(|p| p.available_ports.as_ref());
+            if ports.is_some_and(|p| p.ranges.is_empty() && p.any_local != Some(true)) {
+                warnings.push(ConfigWarning::EmptyPortRanges);
+            }
+            warnings
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.ingest.is_none()
+                && self.mutation.is_none()
+                && self.plugins.is_none()
                 && self.metadata.is_empty()
         }
+
+        /// A short, human readable description of where this config connects and which
+        /// plugins it defines, one labeled line per item. Intended for startup logging.
+        pub fn summary(&self) -> String {
+            use std::fmt::Write;
+
+            fn endpoint(url: Option<&Url>, allow_insecure_tls: bool) -> String {
+                let mut s = match url {
+                    Some(url) => url.to_string(),
+                    None => "default".to_owned(),
+                };
+                if allow_insecure_tls {
+                    s.push_str(" (insecure TLS allowed)");
+                }
+                s
+            }
+
+            let plugins = self.plugins.as_ref();
+            let plugins_ingest = plugins.and_then(|p| p.ingest.as_ref());
+            let collectors = plugins_ingest.map(|i| i.collectors.len()).unwrap_or(0);
+            let importers = plugins_ingest.map(|i| i.importers.len()).unwrap_or(0);
+            let mutators = plugins
+                .and_then(|p| p.mutation.as_ref())
+                .map(|m| m.mutators.len())
+                .unwrap_or(0);
+
+            let mut s = String::new();
+            let _ = writeln!(
+                s,
+                "ingest: {}",
+                endpoint(
+                    self.ingest
+                        .as_ref()
+                        .and_then(|i| i.protocol_parent_url.as_ref()),
+                    self.ingest.as_ref().is_some_and(|i| i.allow_insecure_tls)
+                )
+            );
+            let _ = writeln!(
+                s,
+                "mutation: {}",
+                endpoint(
+                    self.mutation
+                        .as_ref()
+                        .and_then(|m| m.protocol_parent_url.as_ref()),
+                    self.mutation.as_ref().is_some_and(|m| m.allow_insecure_tls)
+                )
+            );
+            let _ = write!(
+                s,
+                "plugins: {collectors} collector(s), {importers} importer(s), {mutators} mutator(s)"
+            );
+            s
+        }
     }
 
     impl PluginsIngest {
@@ -908,14 +1671,14 @@ mod refined {
             &self,
             plugin_name: S,
         ) -> Option<&PluginsIngestMember> {
-            find_member_by_plugin_name(&self.collectors, plugin_name)
+            find_member_by_plugin_name(&self.collectors, &self.collector_templates, plugin_name)
         }
 
         pub fn find_importer_member_by_plugin_name<S: AsRef<str>>(
             &self,
             plugin_name: S,
         ) -> Option<&PluginsIngestMember> {
-            find_member_by_plugin_name(&self.importers, plugin_name)
+            find_member_by_plugin_name(&self.importers, &self.importer_templates, plugin_name)
         }
     }
 
@@ -924,21 +1687,81 @@ mod refined {
             &self,
             plugin_name: S,
         ) -> Option<&PluginsMutationMember> {
-            find_member_by_plugin_name(&self.mutators, plugin_name)
+            find_member_by_plugin_name(&self.mutators, &self.mutator_templates, plugin_name)
         }
     }
 
-    pub(crate) fn find_member_by_plugin_name<T: PluginMemberExt, N: AsRef<str>>(
-        members: &BTreeMap<String, T>,
+    /// Find the member section for a plugin. The non-template `members` are
+    /// searched first by [member_matches_plugin_name]; failing that, the most
+    /// specific of the `templates` whose key matches `plugin_name` is used (see
+    /// [is_member_template]).
+    pub(crate) fn find_member_by_plugin_name<'a, T: PluginMemberExt, N: AsRef<str>>(
+        members: &'a BTreeMap<String, T>,
+        templates: &'a BTreeMap<String, T>,
         plugin_name: N,
-    ) -> Option<&T> {
-        members.iter().find_map(|(k, m)| {
-            if member_matches_plugin_name(plugin_name.as_ref(), k, m.plugin()) {
-                Some(m)
-            } else {
-                None
+    ) -> Option<&'a T> {
+        members
+            .iter()
+            .filter(|(k, _)| !is_member_template(k))
+            .find_map(|(k, m)| {
+                if member_matches_plugin_name(plugin_name.as_ref(), k, m.plugin()) {
+                    Some(m)
+                } else {
+                    None
+                }
+            })
+            .or_else(|| {
+                matching_member_templates(templates, plugin_name.as_ref())
+                    .first()
+                    .map(|(_, m)| *m)
+            })
+    }
+
+    /// A member section key containing a `*` or `?` wildcard, e.g. `"can*"`, is
+    /// a template: it applies to every plugin whose name matches it, and
+    /// supplies defaults for the other sections whose keys match it.
+    pub fn is_member_template(member_key: &str) -> bool {
+        member_key.contains(['*', '?'])
+    }
+
+    /// Match `name` against a glob `pattern`, where `*` matches any run of
+    /// characters and `?` matches any single one; see [AttrKeyPattern].
+    pub fn glob_matches(pattern: &str, name: &str) -> bool {
+        AttrKeyPattern::from(pattern).matches(name)
+    }
+
+    /// The `templates` matching `name`, most specific (most non-wildcard
+    /// characters) first.
+    fn matching_member_templates<'a, T>(
+        templates: &'a BTreeMap<String, T>,
+        name: &str,
+    ) -> Vec<(&'a String, &'a T)> {
+        let mut templates: Vec<_> = templates
+            .iter()
+            .filter(|(k, _)| is_member_template(k) && glob_matches(k, name))
+            .collect();
+        templates.sort_by_key(|(k, _)| {
+            std::cmp::Reverse(k.chars().filter(|c| !matches!(c, '*' | '?')).count())
+        });
+        templates
+    }
+
+    /// Take the templates out of `members`, then fill in the settings that each
+    /// remaining member leaves unset from the templates matching its key.
+    /// Returns the templates.
+    fn apply_member_templates<T: InheritFromTemplate>(
+        members: &mut BTreeMap<String, T>,
+    ) -> BTreeMap<String, T> {
+        let (templates, rest) = std::mem::take(members)
+            .into_iter()
+            .partition(|(k, _)| is_member_template(k));
+        *members = rest;
+        for (key, member) in members.iter_mut() {
+            for (_, template) in matching_member_templates(&templates, key) {
+                member.inherit(template);
             }
-        })
+        }
+        templates
     }
 
     pub(crate) fn member_matches_plugin_name<N: AsRef<str>, K: AsRef<str>, P: AsRef<str>>(
@@ -975,18 +1798,93 @@ mod refined {
         fn plugin(&self) -> Option<&str>;
     }
 
+    pub(crate) trait InheritFromTemplate {
+        /// Take each setting that isn't set here from `template`
+        fn inherit(&mut self, template: &Self);
+    }
+
     impl PluginMemberExt for PluginsIngestMember {
         fn plugin(&self) -> Option<&str> {
             self.plugin.as_deref()
         }
     }
 
+    impl InheritFromTemplate for PluginsIngestMember {
+        fn inherit(&mut self, template: &Self) {
+            let attrs = &mut self.timeline_attributes;
+            inherit_vec(
+                &mut attrs.additional_timeline_attributes,
+                &template.timeline_attributes.additional_timeline_attributes,
+            );
+            inherit_vec(
+                &mut attrs.override_timeline_attributes,
+                &template.timeline_attributes.override_timeline_attributes,
+            );
+            inherit_common(
+                (&mut self.plugin, &mut self.shutdown, &mut self.metadata),
+                (&template.plugin, &template.shutdown, &template.metadata),
+            );
+        }
+    }
+
     impl PluginMemberExt for PluginsMutationMember {
         fn plugin(&self) -> Option<&str> {
             self.plugin.as_deref()
         }
     }
 
+    impl InheritFromTemplate for PluginsMutationMember {
+        fn inherit(&mut self, template: &Self) {
+            let attrs = &mut self.mutator_attributes;
+            inherit_vec(
+                &mut attrs.additional_mutator_attributes,
+                &template.mutator_attributes.additional_mutator_attributes,
+            );
+            inherit_vec(
+                &mut attrs.override_mutator_attributes,
+                &template.mutator_attributes.override_mutator_attributes,
+            );
+            inherit_common(
+                (&mut self.plugin, &mut self.shutdown, &mut self.metadata),
+                (&template.plugin, &template.shutdown, &template.metadata),
+            );
+        }
+    }
+
+    fn inherit_vec<T: Clone>(v: &mut Vec<T>, template: &[T]) {
+        if v.is_empty() {
+            v.extend_from_slice(template);
+        }
+    }
+
+    fn inherit_common(
+        (plugin, shutdown, metadata): (
+            &mut Option<String>,
+            &mut PluginShutdown,
+            &mut BTreeMap<String, TomlValue>,
+        ),
+        (t_plugin, t_shutdown, t_metadata): (
+            &Option<String>,
+            &PluginShutdown,
+            &BTreeMap<String, TomlValue>,
+        ),
+    ) {
+        if plugin.is_none() {
+            plugin.clone_from(t_plugin);
+        }
+        if shutdown.shutdown_signal.is_none() {
+            shutdown
+                .shutdown_signal
+                .clone_from(&t_shutdown.shutdown_signal);
+        }
+        if shutdown.shutdown_timeout.is_none() {
+            shutdown.shutdown_timeout = t_shutdown.shutdown_timeout;
+        }
+        for (k, v) in t_metadata {
+            metadata.entry(k.clone()).or_insert_with(|| v.clone());
+        }
+    }
+
     #[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
     pub enum EnvSubError {
         #[error("The environment variable '{0}' contains invalid unicode")]
@@ -1001,53 +1899,103 @@ mod refined {
     /// * `${NAME}`
     /// * `${NAME-default}`
     /// * `${NAME:-default}`
-    fn envsub(input: &str) -> Result<String, EnvSubError> {
+    ///
+    /// A default runs to the `}` that balances the opening `${`, so it may
+    /// itself contain balanced braces, e.g. `${CFG:-{"a":1}}`. An unbalanced
+    /// brace can be written `\{` or `\}`. A default that never balances ends
+    /// at its first `}`.
+    ///
+    /// A literal `${` is written `$${`.
+    pub(crate) fn envsub(input: &str) -> Result<String, EnvSubError> {
         lazy_static! {
-            // Matches the following patterns with named capture groups:
-            // * '${NAME}' : var = 'NAME'
-            // * '${NAME-default}' : var = 'NAME', def = 'default'
-            // * '${NAME:-default}' : var = 'NAME', def = 'default'
-            static ref ENVSUB_RE: Regex =
-                Regex::new(r"\$\{(?P<var>[a-zA-Z_][a-zA-Z0-9_]*)(:?-(?P<def>.*?))?\}")
-                    .expect("Could not construct envsub Regex");
+            // Matches the start of an expression, with named capture groups:
+            // * '$${' : escape, for a literal '${'
+            // * '${NAME}' : var = 'NAME', close = '}'
+            // * '${NAME-' : var = 'NAME', and a default follows
+            // * '${NAME:-' : var = 'NAME', and a default follows
+            static ref ENVSUB_RE: Regex = Regex::new(
+                r"(?P<escape>\$\$\{)|\$\{(?P<var>[a-zA-Z_][a-zA-Z0-9_]*)(?:(?P<close>\})|:?-)"
+            )
+            .expect("Could not construct envsub Regex");
         }
 
-        replace_all(&ENVSUB_RE, input, |caps: &Captures| {
-            // SAFETY: the regex requires a match for capture group 'var'
+        let mut new = String::with_capacity(input.len());
+        let mut rest = input;
+        while let Some(caps) = ENVSUB_RE.captures(rest) {
+            let m = caps.get(0).unwrap();
+            if caps.name("escape").is_some() {
+                new.push_str(&rest[..m.start()]);
+                new.push_str("${");
+                rest = &rest[m.end()..];
+                continue;
+            }
+
+            // SAFETY: without the escape, the regex requires a match for capture group 'var'
             let env_var = &caps["var"];
+            let (def, end) = if caps.name("close").is_some() {
+                (None, m.end())
+            } else {
+                match scan_default(&rest[m.end()..]) {
+                    Some((def, len)) => (Some(def), m.end() + len),
+                    None => {
+                        // Not an expression after all
+                        new.push_str(&rest[..m.end()]);
+                        rest = &rest[m.end()..];
+                        continue;
+                    }
+                }
+            };
+
+            new.push_str(&rest[..m.start()]);
             match env::var(env_var) {
-                Ok(env_val_val) => Ok(env_val_val),
+                Ok(env_val_val) => new.push_str(&env_val_val),
                 Err(env::VarError::NotUnicode(_)) => {
-                    Err(EnvSubError::EnvVarNotUnicode(env_var.to_owned()))
+                    return Err(EnvSubError::EnvVarNotUnicode(env_var.to_owned()))
                 }
                 Err(env::VarError::NotPresent) => {
                     // Use the default value if one was provided
-                    if let Some(def) = caps.name("def") {
-                        Ok(def.as_str().to_string())
+                    if let Some(def) = def {
+                        new.push_str(&def);
                     } else {
-                        Err(EnvSubError::EnvVarNotPresent(env_var.to_owned()))
+                        return Err(EnvSubError::EnvVarNotPresent(env_var.to_owned()));
                     }
                 }
             }
-        })
+            rest = &rest[end..];
+        }
+        new.push_str(rest);
+        Ok(new)
     }
 
-    // This is essentially a fallible version of Regex::replace_all
-    fn replace_all(
-        re: &Regex,
-        input: &str,
-        replacement: impl Fn(&Captures) -> Result<String, EnvSubError>,
-    ) -> Result<String, EnvSubError> {
-        let mut new = String::with_capacity(input.len());
-        let mut last_match = 0;
-        for caps in re.captures_iter(input) {
-            let m = caps.get(0).unwrap();
-            new.push_str(&input[last_match..m.start()]);
-            new.push_str(&replacement(&caps)?);
-            last_match = m.end();
+    /// Read a default value from the start of `s`, up to the `}` closing its
+    /// expression. Returns the unescaped default, and the length of `s` used,
+    /// including that `}`.
+    fn scan_default(s: &str) -> Option<(String, usize)> {
+        let mut def = String::new();
+        let mut depth = 0usize;
+        let mut chars = s.char_indices().peekable();
+        while let Some((idx, c)) = chars.next() {
+            match c {
+                '\\' if matches!(chars.peek(), Some((_, '{' | '}'))) => {
+                    // SAFETY: just peeked
+                    def.push(chars.next().unwrap().1);
+                }
+                '{' => {
+                    depth += 1;
+                    def.push(c);
+                }
+                '}' if depth == 0 => return Some((def, idx + 1)),
+                '}' => {
+                    depth -= 1;
+                    def.push(c);
+                }
+                _ => def.push(c),
+            }
         }
-        new.push_str(&input[last_match..]);
-        Ok(new)
+
+        // Unbalanced; fall back to the first '}'
+        let end = s.find('}')?;
+        Some((s[..end].to_owned(), end + 1))
     }
 }
 
@@ -1060,7 +2008,42 @@ pub enum ConfigWriteError {
     Io(#[from] std::io::Error),
 }
 
+/// Advice about a config that loaded successfully, but may not do what was
+/// intended; see [`try_from_file_with_warnings`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigWarning {
+    /// TLS certificates aren't verified for the connections configured in
+    /// this section, `ingest` or `mutation`.
+    InsecureTlsEnabled { section: String },
+
+    /// A key that isn't part of the format, such as a misspelling, which is
+    /// ignored. Holds the key's dotted path.
+    UnknownKey { path: String },
+
+    /// `plugins.available-ports` has no port ranges, and doesn't allow any
+    /// local port, so there are no ports to give to plugins.
+    EmptyPortRanges,
+}
+
+impl std::fmt::Display for ConfigWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigWarning::InsecureTlsEnabled { section } => write!(
+                f,
+                "'{section}.allow-insecure-tls' is enabled, so TLS certificates won't be verified"
+            ),
+            ConfigWarning::UnknownKey { path } => write!(f, "Ignoring unknown config key '{path}'"),
+            ConfigWarning::EmptyPortRanges => f.write_str(
+                "'plugins.available-ports' has no ranges and doesn't allow any local port",
+            ),
+        }
+    }
+}
+
+/// Marked `non_exhaustive` since some variants, like `Fetch`, only exist with
+/// certain features enabled.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum ConfigLoadError {
     #[error("Error in config file {} relating to TOML parsing. {error}", .path.display())]
     ConfigFileToml {
@@ -1078,30 +2061,274 @@ pub enum ConfigLoadError {
     #[error("IO Error")]
     Io(#[from] std::io::Error),
 
+    #[error("Error reading timeline attributes file {}", .path.display())]
+    TimelineAttributesFile {
+        path: PathBuf,
+        #[source]
+        error: std::io::Error,
+    },
+
+    #[error("Error substituting environment variables in {path}. {error}")]
+    EnvSub {
+        /// Dotted path of the config entry being substituted
+        path: String,
+        #[source]
+        error: EnvSubError,
+    },
+
+    #[cfg(feature = "config_http")]
+    #[error("Error fetching config from {url}")]
+    Fetch {
+        url: String,
+        #[source]
+        error: reqwest::Error,
+    },
+
+    #[error("Unknown config keys: {}", .keys.join(", "))]
+    UnknownKeys {
+        /// Dotted paths of the unrecognized keys
+        keys: Vec<String>,
+    },
+
     #[error("Error in config content relating to semantics. {explanation}")]
-    DefinitionSemantics { explanation: String },
+    DefinitionSemantics {
+        /// Dotted path of the offending config entry, empty if not tied to one
+        path: String,
+        explanation: String,
+    },
 }
 
+impl From<SemanticErrorExplanation> for ConfigLoadError {
+    fn from(semantics: SemanticErrorExplanation) -> Self {
+        ConfigLoadError::DefinitionSemantics {
+            path: semantics.path,
+            explanation: semantics.message,
+        }
+    }
+}
+
+/// Load a config file. Keys that aren't part of the format are logged as warnings
+/// and otherwise ignored; see [`try_from_file_checked`] to inspect or reject them.
+///
+/// With the `config_http` feature, `path` may also be an `http://` or `https://`
+/// URL to fetch the config from; see [`read_config_source`].
 pub fn try_from_file(path: &Path) -> Result<refined::Config, ConfigLoadError> {
-    let content = &std::fs::read_to_string(path)?;
-    let partial: raw_toml::Config =
-        toml::from_str(content).map_err(|e| ConfigLoadError::ConfigFileToml {
+    let (cfg, unknown_keys) = try_from_file_checked(path, false)?;
+    warn_unknown_keys(&unknown_keys);
+    Ok(cfg)
+}
+
+/// Load a config file, also returning advisory warnings about it, such as insecure TLS
+/// being enabled, for the caller to report. Keys that aren't part of the format are
+/// returned as [`ConfigWarning::UnknownKey`], rather than logged.
+pub fn try_from_file_with_warnings(
+    path: &Path,
+) -> Result<(refined::Config, Vec<ConfigWarning>), ConfigLoadError> {
+    let (cfg, unknown_keys) = try_from_file_checked(path, false)?;
+    let warnings = with_unknown_key_warnings(&cfg, unknown_keys);
+    Ok((cfg, warnings))
+}
+
+/// Load config content, also returning advisory warnings about it; see
+/// [`try_from_file_with_warnings`].
+pub fn try_from_str_with_warnings(
+    content: &str,
+) -> Result<(refined::Config, Vec<ConfigWarning>), ConfigLoadError> {
+    let (cfg, unknown_keys) = try_from_str_checked(content, false)?;
+    let warnings = with_unknown_key_warnings(&cfg, unknown_keys);
+    Ok((cfg, warnings))
+}
+
+/// The result of checking a config file with [`check_file`].
+#[derive(Debug, Default)]
+pub struct CheckReport {
+    /// Problems that stop the config from loading. A `--check` command should
+    /// exit with failure if there are any.
+    pub errors: Vec<ConfigLoadError>,
+    /// Advice about a config that loads, but may not do what was intended.
+    pub warnings: Vec<ConfigWarning>,
+}
+
+impl CheckReport {
+    /// True if the config loads; there may still be warnings.
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// True if the config loads without any warnings.
+    pub fn is_clean(&self) -> bool {
+        self.errors.is_empty() && self.warnings.is_empty()
+    }
+}
+
+/// Load and check a config file, collecting everything wrong with it into a
+/// report instead of failing on the first problem, for a `--check` command or
+/// a CI step. Nothing is logged.
+///
+/// The config is only semantically checked once it parses, so a TOML or IO
+/// error is the only error reported. Otherwise every semantic error is
+/// reported, e.g. each bad port range, URL and plugin member, along with the
+/// unknown keys.
+pub fn check_file(path: &Path) -> CheckReport {
+    let partial = match read_raw_file(path) {
+        Ok(partial) => partial,
+        Err(e) => {
+            return CheckReport {
+                errors: vec![e],
+                warnings: Vec::new(),
+            }
+        }
+    };
+    let unknown_keys = partial.unknown_keys();
+    match partial.refine() {
+        Ok(cfg) => CheckReport {
+            errors: Vec::new(),
+            warnings: with_unknown_key_warnings(&cfg, unknown_keys),
+        },
+        Err(errors) => CheckReport {
+            errors: errors.into_iter().map(ConfigLoadError::from).collect(),
+            warnings: unknown_keys
+                .into_iter()
+                .map(|path| ConfigWarning::UnknownKey { path })
+                .collect(),
+        },
+    }
+}
+
+fn with_unknown_key_warnings(
+    cfg: &refined::Config,
+    unknown_keys: Vec<String>,
+) -> Vec<ConfigWarning> {
+    let mut warnings: Vec<ConfigWarning> = unknown_keys
+        .into_iter()
+        .map(|path| ConfigWarning::UnknownKey { path })
+        .collect();
+    warnings.extend(cfg.warnings());
+    warnings
+}
+
+/// Load config content. Keys that aren't part of the format are logged as warnings
+/// and otherwise ignored; see [`try_from_str_checked`] to inspect or reject them.
+pub fn try_from_str(content: &str) -> Result<refined::Config, ConfigLoadError> {
+    let (cfg, unknown_keys) = try_from_str_checked(content, false)?;
+    warn_unknown_keys(&unknown_keys);
+    Ok(cfg)
+}
+
+/// Load a config file, also returning the dotted paths of any keys that aren't part of
+/// the format, such as misspellings. In `strict` mode any such key is an error instead.
+pub fn try_from_file_checked(
+    path: &Path,
+    strict: bool,
+) -> Result<(refined::Config, Vec<String>), ConfigLoadError> {
+    refine_checked(read_raw_file(path)?, strict)
+}
+
+/// Read and parse a config file, with environment variables substituted and
+/// the timeline attributes file loaded, ready to refine.
+fn read_raw_file(path: &Path) -> Result<raw_toml::Config, ConfigLoadError> {
+    let (content, base_dir) = read_config_source(path)?;
+    let mut partial: raw_toml::Config =
+        toml::from_str(&content).map_err(|e| ConfigLoadError::ConfigFileToml {
             path: path.to_owned(),
             error: e,
         })?;
-    let r: Result<refined::Config, SemanticErrorExplanation> = partial.try_into();
-    r.map_err(|semantics| ConfigLoadError::DefinitionSemantics {
-        explanation: semantics.0,
-    })
+    partial.substitute_env_vars()?;
+    partial.load_timeline_attributes_file(base_dir)?;
+    Ok(partial)
 }
 
-pub fn try_from_str(content: &str) -> Result<refined::Config, ConfigLoadError> {
-    let partial: raw_toml::Config =
+/// Read config content from a file, or, with the `config_http` feature, from an
+/// `http://` or `https://` URL. Also returns the directory that relative paths in
+/// the content are resolved against, which URLs don't have.
+///
+/// URLs are fetched with a timeout of [`CONFIG_FETCH_TIMEOUT`], sending the value
+/// of the [`CONFIG_URL_AUTH_HEADER_ENV_VAR`] environment variable, if it's set, as
+/// the `Authorization` header.
+pub fn read_config_source(path: &Path) -> Result<(String, Option<&Path>), ConfigLoadError> {
+    #[cfg(feature = "config_http")]
+    if is_config_url(path) {
+        return Ok((fetch_config(&path.to_string_lossy())?, None));
+    }
+
+    Ok((std::fs::read_to_string(path)?, path.parent()))
+}
+
+/// `host` as it should appear in a URL: IPv6 addresses are put in brackets,
+/// e.g. `::1` becomes `[::1]`, and anything else is left alone.
+pub fn url_host(host: &str) -> std::borrow::Cow<'_, str> {
+    if host.parse::<std::net::Ipv6Addr>().is_ok() {
+        format!("[{host}]").into()
+    } else {
+        host.into()
+    }
+}
+
+/// Whether [`read_config_source`] fetches `path` as a URL, rather than reading it as a file.
+pub fn is_config_url(path: &Path) -> bool {
+    cfg!(feature = "config_http")
+        && path
+            .to_str()
+            .is_some_and(|s| s.starts_with("http://") || s.starts_with("https://"))
+}
+
+#[cfg(feature = "config_http")]
+fn fetch_config(url: &str) -> Result<String, ConfigLoadError> {
+    let fetch = {
+        let url = url.to_owned();
+        let auth_header = std::env::var(CONFIG_URL_AUTH_HEADER_ENV_VAR).ok();
+        move || -> Result<String, reqwest::Error> {
+            let client = reqwest::blocking::Client::builder()
+                .timeout(CONFIG_FETCH_TIMEOUT)
+                .build()?;
+            let mut req = client.get(url);
+            if let Some(auth_header) = auth_header {
+                req = req.header(reqwest::header::AUTHORIZATION, auth_header);
+            }
+            req.send()?.error_for_status()?.text()
+        }
+    };
+
+    // The blocking client can't be used from within an async runtime, which
+    // plugins commonly load their config from, so fetch on a separate thread.
+    std::thread::spawn(fetch)
+        .join()
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        .map_err(|error| ConfigLoadError::Fetch {
+            url: url.to_owned(),
+            error,
+        })
+}
+
+/// Load config content, also returning the dotted paths of any keys that aren't part of
+/// the format, such as misspellings. In `strict` mode any such key is an error instead.
+pub fn try_from_str_checked(
+    content: &str,
+    strict: bool,
+) -> Result<(refined::Config, Vec<String>), ConfigLoadError> {
+    let mut partial: raw_toml::Config =
         toml::from_str(content).map_err(|e| ConfigLoadError::ConfigToml { error: e })?;
+    partial.substitute_env_vars()?;
+    partial.load_timeline_attributes_file(None)?;
+    refine_checked(partial, strict)
+}
+
+fn refine_checked(
+    partial: raw_toml::Config,
+    strict: bool,
+) -> Result<(refined::Config, Vec<String>), ConfigLoadError> {
+    let unknown_keys = partial.unknown_keys();
+    if strict && !unknown_keys.is_empty() {
+        return Err(ConfigLoadError::UnknownKeys { keys: unknown_keys });
+    }
     let r: Result<refined::Config, SemanticErrorExplanation> = partial.try_into();
-    r.map_err(|semantics| ConfigLoadError::DefinitionSemantics {
-        explanation: semantics.0,
-    })
+    Ok((r?, unknown_keys))
+}
+
+pub(crate) fn warn_unknown_keys(unknown_keys: &[String]) {
+    for key in unknown_keys {
+        tracing::warn!("Ignoring unknown config key '{key}'");
+    }
 }
 
 pub fn try_to_file(config: &refined::Config, path: &Path) -> Result<(), ConfigWriteError> {
@@ -1120,10 +2347,62 @@ pub fn try_to_string(config: &refined::Config) -> Result<String, ConfigWriteErro
     Ok(content)
 }
 
+/// Feed `value` to `hasher` in a canonical, unambiguous encoding: each
+/// value is tagged with its type, strings and collections are prefixed with
+/// their lengths, and tables are in key order.
+fn hash_toml(value: &TomlValue, hasher: &mut sha2::Sha256) {
+    use sha2::Digest;
+
+    fn hash_str(s: &str, hasher: &mut sha2::Sha256) {
+        hasher.update((s.len() as u64).to_be_bytes());
+        hasher.update(s.as_bytes());
+    }
+
+    match value {
+        TomlValue::String(s) => {
+            hasher.update(b"s");
+            hash_str(s, hasher);
+        }
+        TomlValue::Integer(i) => {
+            hasher.update(b"i");
+            hasher.update(i.to_be_bytes());
+        }
+        TomlValue::Float(f) => {
+            hasher.update(b"f");
+            // -0.0 == 0.0
+            let f = if *f == 0.0 { 0.0 } else { *f };
+            hasher.update(f.to_bits().to_be_bytes());
+        }
+        TomlValue::Boolean(b) => hasher.update([b'b', *b as u8]),
+        TomlValue::Datetime(dt) => {
+            hasher.update(b"d");
+            hash_str(&dt.to_string(), hasher);
+        }
+        TomlValue::Array(items) => {
+            hasher.update(b"a");
+            hasher.update((items.len() as u64).to_be_bytes());
+            for item in items {
+                hash_toml(item, hasher);
+            }
+        }
+        TomlValue::Table(table) => {
+            hasher.update(b"t");
+            hasher.update((table.len() as u64).to_be_bytes());
+            let mut entries: Vec<_> = table.iter().collect();
+            entries.sort_by_key(|(k, _)| *k);
+            for (k, v) in entries {
+                hash_str(k, hasher);
+                hash_toml(v, hasher);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::api::AttrKey;
+    use crate::api::{AttrKey, AttrVal};
+    use proptest::prelude::*;
 
     /// Note that this toml example is not nearly as compact as it could be
     /// with shorthand choices that will still parse equivalently.
@@ -1137,6 +2416,7 @@ additional-timeline-attributes = [
 override-timeline-attributes = ['c = true']
 protocol-child-port = 9079
 protocol-parent-url = 'modality-ingest://auxon.io:9077'
+protocol-version = 1
 
 [metadata]
 bag = 42
@@ -1209,6 +2489,40 @@ moar-custom = [
 ]
 "#;
 
+    #[test]
+    fn template_loads_cleanly() {
+        let (cfg, warnings) = try_from_str_with_warnings(&template_toml()).unwrap();
+        assert_eq!(warnings, vec![]);
+        assert_eq!(
+            cfg.ingest.unwrap().protocol_parent_url,
+            Some(url::Url::parse("modality-ingest://localhost:14182").unwrap())
+        );
+        assert_eq!(
+            cfg.mutation.unwrap().protocol_parent_url,
+            Some(url::Url::parse("modality-mutation://localhost:14192").unwrap())
+        );
+        assert!(cfg.plugins.is_some());
+
+        for builder in [
+            TemplateBuilder::new(),
+            TemplateBuilder::new().with_ingest(),
+            TemplateBuilder::new().with_mutation(),
+            TemplateBuilder::new().with_plugins(),
+            TemplateBuilder::new().with_metadata(),
+        ] {
+            let (cfg, warnings) = try_from_str_with_warnings(&builder.build()).unwrap();
+            assert_eq!(warnings, vec![]);
+            assert_eq!(
+                cfg.ingest.is_some(),
+                builder == TemplateBuilder::new().with_ingest()
+            );
+            assert_eq!(
+                cfg.mutation.is_some(),
+                builder == TemplateBuilder::new().with_mutation()
+            );
+        }
+    }
+
     #[test]
     fn raw_representation_round_trip() {
         let raw: raw_toml::Config = toml::from_str(FULLY_FILLED_IN_TOML).unwrap();
@@ -1225,6 +2539,15 @@ moar-custom = [
         assert_eq!(FULLY_FILLED_IN_TOML, back_out.as_str());
     }
 
+    #[test]
+    fn arbitrary_refined_config_round_trip() {
+        proptest!(|(cfg in proptest_strategies::config())| {
+            let back_out = try_to_string(&cfg).unwrap();
+            let cfg_prime: refined::Config = try_from_str(&back_out).unwrap();
+            prop_assert_eq!(cfg, cfg_prime);
+        });
+    }
+
     #[test]
     fn everything_is_optional() {
         let empty = "";
@@ -1259,6 +2582,45 @@ additional-timeline-attributes = [
         );
     }
 
+    #[test]
+    fn envsub_defaults_with_braces_and_dashes() {
+        use refined::envsub;
+
+        assert_eq!(envsub(r#"${NOT_SET_CFG:-{"a":1}}"#).unwrap(), r#"{"a":1}"#);
+        assert_eq!(
+            envsub(r#"x=${NOT_SET_CFG-{"a":{"b":[1]}}}, y=${NOT_SET_Y:-2}"#).unwrap(),
+            r#"x={"a":{"b":[1]}}, y=2"#
+        );
+        assert_eq!(envsub(r"${NOT_SET_CFG:-\}x\{}").unwrap(), "}x{");
+        // Unbalanced, so the default ends at the first '}'
+        assert_eq!(envsub("${NOT_SET_CFG:-{a}").unwrap(), "{a");
+
+        assert_eq!(envsub("${NOT_SET_RANGE:-1-10}").unwrap(), "1-10");
+        assert_eq!(envsub("${NOT_SET_NEG--5}").unwrap(), "-5");
+        assert_eq!(envsub("${NOT_SET_NEG:--5}").unwrap(), "-5");
+        assert_eq!(envsub("${NOT_SET_EMPTY:-}").unwrap(), "");
+
+        assert_eq!(
+            envsub("${CARGO_PKG_NAME:-{x}}").unwrap(),
+            env!("CARGO_PKG_NAME")
+        );
+        assert_eq!(envsub("${NOT_CLOSED:-x").unwrap(), "${NOT_CLOSED:-x");
+
+        // Escaped, for a literal '${'
+        assert_eq!(envsub("$${NOT_SET_CFG}").unwrap(), "${NOT_SET_CFG}");
+        assert_eq!(
+            envsub("a$$${NOT_SET_CFG:-b}").unwrap(),
+            "a$${NOT_SET_CFG:-b}"
+        );
+        assert_eq!(envsub("$$ and $").unwrap(), "$$ and $");
+        assert_eq!(
+            envsub("${NOT_SET_CFG}"),
+            Err(refined::EnvSubError::EnvVarNotPresent(
+                "NOT_SET_CFG".to_owned()
+            ))
+        );
+    }
+
     #[test]
     fn attr_kv_envsub() {
         let toml = r#"
@@ -1298,13 +2660,501 @@ additional-timeline-attributes = [
     '${NOT_SET_KEY} = 1',
 ]"#;
         match try_from_str(toml).unwrap_err() {
-            ConfigLoadError::DefinitionSemantics { explanation } => {
+            ConfigLoadError::DefinitionSemantics { explanation, .. } => {
                 assert_eq!(explanation, "Error in additional-timeline-attributes member. The environment variable 'NOT_SET_KEY' is not set and no default value is specified".to_string())
             }
             _ => panic!(),
         }
     }
 
+    #[test]
+    #[serial_test::serial]
+    fn string_settings_envsub() {
+        std::env::set_var("HOST", "auxon.io:9077");
+        let toml = r#"
+metadata = { path = "/opt/${HOST}", nested = ["${NOT_SET_VAL:-x}"] }
+
+[ingest]
+protocol-parent-url = "modality-ingest://${HOST}"
+
+[plugins]
+plugins-dir = "${NOT_SET_DIR:-/usr/lib/modality}"
+"#;
+        let cfg = try_from_str(toml);
+        std::env::remove_var("HOST");
+        let cfg = cfg.unwrap();
+
+        assert_eq!(
+            cfg.ingest.unwrap().protocol_parent_url,
+            Some(url::Url::parse("modality-ingest://auxon.io:9077").unwrap())
+        );
+        assert_eq!(
+            cfg.plugins.unwrap().plugins_dir,
+            Some(PathBuf::from("/usr/lib/modality"))
+        );
+        assert_eq!(
+            cfg.metadata.get("path"),
+            Some(&TomlValue::String("/opt/auxon.io:9077".to_owned()))
+        );
+        assert_eq!(
+            cfg.metadata.get("nested"),
+            Some(&TomlValue::Array(vec![TomlValue::String("x".to_owned())]))
+        );
+
+        let cfg = try_from_str(r#"metadata = { template = "$${NOT_SET_VAL}" }"#).unwrap();
+        assert_eq!(
+            cfg.metadata.get("template"),
+            Some(&TomlValue::String("${NOT_SET_VAL}".to_owned()))
+        );
+
+        match try_from_str(r#"metadata = { path = "${NOT_SET_VAL}" }"#).unwrap_err() {
+            ConfigLoadError::EnvSub { path, .. } => assert_eq!(path, "metadata.path"),
+            e => panic!("unexpected error {e:?}"),
+        }
+    }
+
+    #[test]
+    fn config_summary() {
+        let cfg = try_from_str(FULLY_FILLED_IN_TOML).unwrap();
+        assert_eq!(
+            cfg.summary(),
+            "ingest: modality-ingest://auxon.io:9077\n\
+             mutation: modality-ingest://localhost:9078\n\
+             plugins: 3 collector(s), 1 importer(s), 1 mutator(s)"
+        );
+
+        assert_eq!(
+            Config::default().summary(),
+            "ingest: default\nmutation: default\nplugins: 0 collector(s), 0 importer(s), 0 mutator(s)"
+        );
+    }
+
+    #[test]
+    fn content_hash() {
+        let cfg = try_from_str(FULLY_FILLED_IN_TOML).unwrap();
+        let reformatted = try_from_str(&try_to_string(&cfg).unwrap()).unwrap();
+        assert_eq!(cfg, reformatted);
+        assert_eq!(
+            cfg.content_hash().unwrap(),
+            reformatted.content_hash().unwrap()
+        );
+
+        let a = try_from_str(
+            "[ingest]\nprotocol-parent-url = 'modality-ingest://localhost:14188'\nallow-insecure-tls = true\n",
+        )
+        .unwrap();
+        let b = try_from_str(
+            "[ingest]\n  allow-insecure-tls = true\n  protocol-parent-url = \"modality-ingest://localhost:14188\"\n",
+        )
+        .unwrap();
+        assert_eq!(a.content_hash().unwrap(), b.content_hash().unwrap());
+
+        let c = try_from_str(
+            "[ingest]\nprotocol-parent-url = 'modality-ingest://localhost:14189'\nallow-insecure-tls = true\n",
+        )
+        .unwrap();
+        assert_ne!(a.content_hash().unwrap(), c.content_hash().unwrap());
+        assert_ne!(
+            a.content_hash().unwrap(),
+            Config::default().content_hash().unwrap()
+        );
+    }
+
+    #[test]
+    fn config_warnings() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg_path = dir.path().join("config.toml");
+        std::fs::write(
+            &cfg_path,
+            r#"
+[ingest]
+protocol-parent-url = "modality-ingest-tls://auxon.io"
+allow-insecure-tls = true
+"#,
+        )
+        .unwrap();
+        let (_cfg, warnings) = try_from_file_with_warnings(&cfg_path).unwrap();
+        assert_eq!(
+            warnings,
+            vec![ConfigWarning::InsecureTlsEnabled {
+                section: "ingest".to_owned()
+            }]
+        );
+
+        let (_cfg, warnings) = try_from_str_with_warnings(
+            r#"
+[mutation]
+allow-insecure-tls = true
+protocol-parent-urll = "modality-mutation://localhost"
+
+[plugins.available-ports]
+ranges = []
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            warnings,
+            vec![
+                ConfigWarning::UnknownKey {
+                    path: "mutation.protocol-parent-urll".to_owned()
+                },
+                ConfigWarning::InsecureTlsEnabled {
+                    section: "mutation".to_owned()
+                },
+                ConfigWarning::EmptyPortRanges,
+            ]
+        );
+
+        let (_cfg, warnings) = try_from_str_with_warnings(FULLY_FILLED_IN_TOML).unwrap();
+        assert!(!warnings.contains(&ConfigWarning::EmptyPortRanges));
+    }
+
+    #[test]
+    fn check_file_reports() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg_path = dir.path().join("config.toml");
+
+        std::fs::write(
+            &cfg_path,
+            "[ingest]\nprotocol-parent-url = 'modality-ingest://localhost:14188'\n",
+        )
+        .unwrap();
+        let report = check_file(&cfg_path);
+        assert!(report.is_clean());
+
+        std::fs::write(&cfg_path, "[plugins.available-ports]\nranges = [[4, 3]]\n").unwrap();
+        let report = check_file(&cfg_path);
+        assert!(!report.is_ok());
+        assert!(matches!(
+            report.errors.as_slice(),
+            [ConfigLoadError::DefinitionSemantics { path, .. }] if path == "plugins.available-ports.ranges[0]"
+        ));
+
+        std::fs::write(&cfg_path, "[mutation]\nallow-insecure-tls = true\n").unwrap();
+        let report = check_file(&cfg_path);
+        assert!(report.is_ok());
+        assert!(!report.is_clean());
+        assert_eq!(
+            report.warnings,
+            vec![ConfigWarning::InsecureTlsEnabled {
+                section: "mutation".to_owned()
+            }]
+        );
+
+        // Independent problems are all reported
+        std::fs::write(
+            &cfg_path,
+            r#"
+[ingest]
+protocol-parent-url = 'not a url'
+
+[plugins.available-ports]
+ranges = [[4, 3], [1, 2], [9, 8]]
+
+[plugins.ingest.collectors.lttng-live]
+additional-timeline-attributes = ['bad']
+"#,
+        )
+        .unwrap();
+        let report = check_file(&cfg_path);
+        let paths: Vec<&str> = report
+            .errors
+            .iter()
+            .map(|e| match e {
+                ConfigLoadError::DefinitionSemantics { path, .. } => path.as_str(),
+                e => panic!("unexpected error {e:?}"),
+            })
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "ingest.protocol-parent-url",
+                "plugins.available-ports.ranges[0]",
+                "plugins.available-ports.ranges[2]",
+                "plugins.ingest.collectors.lttng-live.additional-timeline-attributes[0]",
+            ]
+        );
+
+        let report = check_file(&dir.path().join("missing.toml"));
+        assert!(matches!(report.errors.as_slice(), [ConfigLoadError::Io(_)]));
+    }
+
+    #[test]
+    fn unknown_keys_are_reported() {
+        let toml = r#"
+typo-at-top = 1
+
+[ingest]
+protocol-paret-url = 'modality-ingest://auxon.io'
+additional-timeline-attributes = ['a = 1']
+
+[plugins.ingest.collectors.lttng-live]
+plugin = 'modality-lttng'
+shutdown-signall = 'SIGINT'
+"#;
+        let (cfg, unknown) = try_from_str_checked(toml, false).unwrap();
+        assert_eq!(
+            unknown,
+            vec![
+                "typo-at-top".to_owned(),
+                "ingest.protocol-paret-url".to_owned(),
+                "plugins.ingest.collectors.lttng-live.shutdown-signall".to_owned(),
+            ]
+        );
+        let ingest = cfg.ingest.unwrap();
+        assert_eq!(ingest.protocol_parent_url, None);
+        assert_eq!(
+            ingest
+                .timeline_attributes
+                .additional_timeline_attributes
+                .len(),
+            1
+        );
+
+        match try_from_str_checked(toml, true).unwrap_err() {
+            ConfigLoadError::UnknownKeys { keys } => assert_eq!(keys, unknown),
+            e => panic!("unexpected error {e:?}"),
+        }
+
+        let (_, unknown) = try_from_str_checked(FULLY_FILLED_IN_TOML, true).unwrap();
+        assert!(unknown.is_empty());
+    }
+
+    #[test]
+    fn semantic_errors_carry_config_path() {
+        let toml = r#"
+[plugins.ingest.collectors.lttng-live]
+additional-timeline-attributes = [
+    'a = 1',
+    '.b = 2',
+]"#;
+        match try_from_str(toml).unwrap_err() {
+            ConfigLoadError::DefinitionSemantics { path, explanation } => {
+                assert_eq!(
+                    path,
+                    "plugins.ingest.collectors.lttng-live.additional-timeline-attributes[1]"
+                );
+                assert_eq!(
+                    explanation,
+                    "Error in additional-timeline-attributes member. The key '.b' starts with an invalid character."
+                );
+            }
+            _ => panic!(),
+        }
+
+        let toml = r#"
+[plugins.available-ports]
+ranges = [[1, 2], [4, 3]]"#;
+        match try_from_str(toml).unwrap_err() {
+            ConfigLoadError::DefinitionSemantics { path, .. } => {
+                assert_eq!(path, "plugins.available-ports.ranges[1]");
+            }
+            _ => panic!(),
+        }
+
+        let toml = r#"
+[mutation]
+external-mutator-urls = ['http://localhost:8080', 'not a url']"#;
+        let raw: raw_toml::Config = toml::from_str(toml).unwrap();
+        let err = refined::Config::try_from(raw).unwrap_err();
+        assert_eq!(err.path, "mutation.external-mutator-urls[1]");
+        assert_eq!(err.to_string(), err.message);
+    }
+
+    #[test]
+    fn timeline_attributes_file_is_merged() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("inventory.txt"),
+            "# site inventory\nserial = \"SN-1234\"\n\nrack = ${NOT_SET_RACK:-7}\n",
+        )
+        .unwrap();
+        let cfg_path = dir.path().join("config.toml");
+        std::fs::write(
+            &cfg_path,
+            r#"
+[ingest]
+additional-timeline-attributes = ['a = 1']
+timeline-attributes-file = 'inventory.txt'
+"#,
+        )
+        .unwrap();
+
+        let cfg = try_from_file(&cfg_path).unwrap();
+        let attrs = cfg
+            .ingest
+            .map(|i| i.timeline_attributes.additional_timeline_attributes)
+            .unwrap();
+        assert_eq!(
+            attrs,
+            vec![
+                AttrKeyEqValuePair(AttrKey::new("a".to_string()), 1_i64.into()),
+                AttrKeyEqValuePair(AttrKey::new("serial".to_string()), "SN-1234".into()),
+                AttrKeyEqValuePair(AttrKey::new("rack".to_string()), 7_i64.into()),
+            ]
+        );
+
+        std::fs::remove_file(dir.path().join("inventory.txt")).unwrap();
+        assert!(matches!(
+            try_from_file(&cfg_path),
+            Err(ConfigLoadError::TimelineAttributesFile { .. })
+        ));
+    }
+
+    #[test]
+    fn attr_references() {
+        let cfg = try_from_str(
+            r#"
+[ingest]
+additional-timeline-attributes = [
+    'timeline.full_name = "${attr:timeline.site}-${attr:timeline.host}"',
+    'timeline.site = "lab"',
+    'timeline.host = "${attr:timeline.node}"',
+    'timeline.node = "bench-1"',
+    'timeline.run = ${attr:timeline.run_id}',
+    'timeline.region = "${attr:timeline.region_override:-us-east}"',
+]
+override-timeline-attributes = ['timeline.run_id = 7']
+"#,
+        )
+        .unwrap();
+        let attrs = cfg.ingest.unwrap().timeline_attributes;
+        assert_eq!(
+            attrs.additional_timeline_attributes,
+            vec![
+                AttrKeyEqValuePair(
+                    AttrKey::from("timeline.full_name"),
+                    AttrVal::from("lab-bench-1")
+                ),
+                AttrKeyEqValuePair(AttrKey::from("timeline.site"), AttrVal::from("lab")),
+                AttrKeyEqValuePair(AttrKey::from("timeline.host"), AttrVal::from("bench-1")),
+                AttrKeyEqValuePair(AttrKey::from("timeline.node"), AttrVal::from("bench-1")),
+                AttrKeyEqValuePair(AttrKey::from("timeline.run"), AttrVal::from(7)),
+                AttrKeyEqValuePair(AttrKey::from("timeline.region"), AttrVal::from("us-east")),
+            ]
+        );
+
+        let toml = r#"
+[plugins.mutation.mutators.m]
+additional-mutator-attributes = [
+    'a = "${attr:b}"',
+    'b = "x-${attr:c}"',
+    'c = "${attr:a}"',
+]"#;
+        match try_from_str(toml).unwrap_err() {
+            ConfigLoadError::DefinitionSemantics { path, explanation } => {
+                assert_eq!(
+                    path,
+                    "plugins.mutation.mutators.m.additional-mutator-attributes[0]"
+                );
+                assert_eq!(
+                    explanation,
+                    "Error in additional-mutator-attributes member. Cyclic attribute reference: a -> b -> c -> a"
+                );
+            }
+            e => panic!("{e:?}"),
+        }
+
+        let toml = r#"
+[ingest]
+additional-timeline-attributes = ['a = "${attr:missing}"']"#;
+        match try_from_str(toml).unwrap_err() {
+            ConfigLoadError::DefinitionSemantics { path, .. } => {
+                assert_eq!(path, "ingest.additional-timeline-attributes[0]");
+            }
+            e => panic!("{e:?}"),
+        }
+    }
+
+    #[test]
+    fn attr_kv_numeric_looking_values() {
+        let kv: AttrKeyEqValuePair = "x = 12".parse().unwrap();
+        assert_eq!(kv.1, AttrVal::Integer(12));
+
+        let kv: AttrKeyEqValuePair = r#"x = "12ab""#.parse().unwrap();
+        assert_eq!(kv.1, AttrVal::from("12ab"));
+
+        let kv: AttrKeyEqValuePair = "x = -1.5".parse().unwrap();
+        assert_eq!(kv.1, AttrVal::Float((-1.5).into()));
+
+        assert_eq!(
+            "x = 12ab".parse::<AttrKeyEqValuePair>(),
+            Err(AttrKeyValuePairParseError::AmbiguousValue(
+                "12ab".to_string()
+            ))
+        );
+        assert_eq!(
+            "x = -.5e".parse::<AttrKeyEqValuePair>(),
+            Err(AttrKeyValuePairParseError::AmbiguousValue(
+                "-.5e".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn attr_kv_digit_led_strings() {
+        for val in [
+            "10.0.0.1",
+            "1.2.3",
+            "1.2.3-rc1",
+            "2024-01-31",
+            "2024-01-31T12:00:00Z",
+            "3f2a9c1",
+            "1234abcd",
+            "3f2a9c1e5b7d",
+        ] {
+            let kv: AttrKeyEqValuePair = format!("x = {val}").parse().unwrap();
+            assert_eq!(kv.1, AttrVal::from(val), "{val}");
+        }
+
+        // Typos in values substituted from the environment aren't the config's problem
+        std::env::set_var("ATTR_KV_DIGIT_LED_STRINGS", "12ab");
+        let kv: AttrKeyEqValuePair = "x = ${ATTR_KV_DIGIT_LED_STRINGS}".parse().unwrap();
+        std::env::remove_var("ATTR_KV_DIGIT_LED_STRINGS");
+        assert_eq!(kv.1, AttrVal::from("12ab"));
+    }
+
+    #[test]
+    fn attr_kv_nanoseconds_literals() {
+        let kv: AttrKeyEqValuePair = "x = 3000ns".parse().unwrap();
+        assert_eq!(kv.1, AttrVal::Duration(3000.into()));
+        assert_eq!(kv.to_string(), "x = 3000ns");
+
+        let kv: AttrKeyEqValuePair = "x = @1700000000000000000ns".parse().unwrap();
+        assert_eq!(kv.1, AttrVal::Timestamp(1700000000000000000.into()));
+        assert_eq!(kv.to_string(), "x = @1700000000000000000ns");
+    }
+
+    #[test]
+    fn attr_kv_display_round_trip() {
+        proptest!(|(kv in proptest_strategies::attr_kv_pair())| {
+            let kv_prime: AttrKeyEqValuePair = kv.to_string().parse().unwrap();
+            prop_assert_eq!(kv, kv_prime);
+        });
+    }
+
+    #[test]
+    fn attr_kv_parse_many() {
+        assert_eq!(
+            AttrKeyEqValuePair::parse_many("a = 1, b = true,\tc = \"x\",", ','),
+            Ok(vec![
+                AttrKeyEqValuePair("a".into(), AttrVal::Integer(1)),
+                AttrKeyEqValuePair("b".into(), AttrVal::Bool(true)),
+                AttrKeyEqValuePair("c".into(), AttrVal::from("x")),
+            ])
+        );
+        assert_eq!(AttrKeyEqValuePair::parse_many("", ','), Ok(vec![]));
+
+        assert_eq!(
+            AttrKeyEqValuePair::parse_many("a = 1\n\nb = 12ab\nc = oops = 2\n", '\n'),
+            Err((
+                2,
+                AttrKeyValuePairParseError::AmbiguousValue("12ab".to_string())
+            ))
+        );
+    }
+
     #[test]
     fn config_member_lookups() {
         let cfg: refined::Config = try_from_str(FULLY_FILLED_IN_TOML).unwrap();
@@ -1332,4 +3182,115 @@ additional-timeline-attributes = [
             .find_mutator_member_by_plugin_name("linux-network")
             .is_some());
     }
+
+    #[test]
+    fn member_templates() {
+        let cfg: refined::Config = try_from_str(
+            r#"
+[plugins.ingest.collectors."can*"]
+shutdown-signal = "SIGTERM"
+additional-timeline-attributes = ['bus = "can"']
+metadata.bitrate = 500000
+metadata.fd = false
+
+[plugins.ingest.collectors.can0]
+metadata.bitrate = 250000
+"#,
+        )
+        .unwrap();
+        let ingest = cfg.plugins.as_ref().unwrap().ingest.as_ref().unwrap();
+
+        let can0 = ingest.find_collector_member_by_plugin_name("can0").unwrap();
+        let can1 = ingest.find_collector_member_by_plugin_name("can1").unwrap();
+        for member in [can0, can1] {
+            assert_eq!(member.shutdown.shutdown_signal.as_deref(), Some("SIGTERM"));
+            assert_eq!(
+                member.timeline_attributes.additional_timeline_attributes,
+                vec![AttrKeyEqValuePair(
+                    AttrKey::new("bus".to_string()),
+                    "can".into()
+                )]
+            );
+            assert_eq!(member.metadata.get("fd"), Some(&TomlValue::Boolean(false)));
+        }
+        assert_eq!(
+            can0.metadata.get("bitrate"),
+            Some(&TomlValue::Integer(250000))
+        );
+        assert_eq!(
+            can1.metadata.get("bitrate"),
+            Some(&TomlValue::Integer(500000))
+        );
+        assert!(ingest
+            .find_collector_member_by_plugin_name("lin0")
+            .is_none());
+
+        // Templates aren't plugins in their own right
+        assert_eq!(ingest.collectors.keys().collect::<Vec<_>>(), vec!["can0"]);
+        assert!(ingest.collector_templates.contains_key("can*"));
+        assert!(cfg
+            .summary()
+            .contains("plugins: 1 collector(s), 0 importer(s), 0 mutator(s)"));
+    }
+
+    #[test]
+    fn url_hosts() {
+        assert_eq!(url_host("::1"), "[::1]");
+        assert_eq!(url_host("2001:db8::1"), "[2001:db8::1]");
+        assert_eq!(url_host("[::1]"), "[::1]");
+        assert_eq!(url_host("10.0.0.1"), "10.0.0.1");
+        assert_eq!(url_host("auxon.io"), "auxon.io");
+        assert_eq!(url_host("auxon.io:9077"), "auxon.io:9077");
+    }
+
+    #[test]
+    fn glob_matching() {
+        assert!(glob_matches("can*", "can"));
+        assert!(glob_matches("can*", "can12"));
+        assert!(glob_matches("c?n*-rx", "can0-rx"));
+        assert!(glob_matches("*-*", "a-b-c"));
+        assert!(!glob_matches("can?", "can"));
+        assert!(!glob_matches("can*", "lin0"));
+        assert!(!glob_matches("*-rx", "can0-tx"));
+    }
+
+    #[cfg(feature = "config_http")]
+    #[test]
+    #[serial_test::serial]
+    fn config_from_url() {
+        use crate::test_fixtures::serve_http;
+
+        let (url, requests) = serve_http(vec![
+            ("200 OK", FULLY_FILLED_IN_TOML),
+            ("404 Not Found", ""),
+        ]);
+        std::env::set_var(CONFIG_URL_AUTH_HEADER_ENV_VAR, "Bearer abc123");
+
+        let path = PathBuf::from(format!("{url}/config.toml"));
+        assert!(is_config_url(&path));
+        let cfg = try_from_file(&path);
+        std::env::remove_var(CONFIG_URL_AUTH_HEADER_ENV_VAR);
+        assert_eq!(cfg.unwrap(), try_from_str(FULLY_FILLED_IN_TOML).unwrap());
+
+        let request = requests.recv().unwrap().to_lowercase();
+        assert!(request.starts_with("get /config.toml http/1.1"));
+        assert!(request.contains("authorization: bearer abc123"));
+
+        let missing = PathBuf::from(format!("{url}/missing.toml"));
+        match try_from_file(&missing) {
+            Err(ConfigLoadError::Fetch {
+                url: fetched,
+                error,
+            }) => {
+                assert_eq!(fetched, missing.to_str().unwrap());
+                assert_eq!(error.status(), Some(reqwest::StatusCode::NOT_FOUND));
+            }
+            res => panic!("Unexpected result: {res:?}"),
+        }
+        assert!(!requests
+            .recv()
+            .unwrap()
+            .to_lowercase()
+            .contains("authorization"));
+    }
 }
@@ -0,0 +1,281 @@
+//! Strategies for generating arbitrary, semantically valid [`Config`] values.
+//!
+//! Every value produced here survives a [`try_to_string`](super::try_to_string) /
+//! [`try_from_str`](super::try_from_str) round trip, so the strategies are suitable
+//! for fuzzing config serialization.
+
+use super::refined::*;
+use super::TomlValue;
+use crate::api::{AttrKey, AttrVal};
+use proptest::collection::{btree_map, vec};
+use proptest::option;
+use proptest::prelude::*;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use url::Url;
+
+pub fn config() -> impl Strategy<Value = Config> {
+    (
+        option::of(top_level_ingest()),
+        option::of(top_level_mutation()),
+        option::of(top_level_plugins()),
+        metadata(),
+    )
+        .prop_map(|(ingest, mutation, plugins, metadata)| Config {
+            ingest,
+            mutation,
+            plugins,
+            metadata,
+        })
+}
+
+pub fn top_level_ingest() -> impl Strategy<Value = TopLevelIngest> {
+    (
+        option::of(url()),
+        any::<bool>(),
+        option::of(any::<u16>()),
+        timeline_attributes(),
+        option::of(millis_duration()),
+        option::of(any::<u32>()),
+        option::of(any::<u32>()),
+        option::of(any::<u32>()),
+    )
+        .prop_map(
+            |(
+                protocol_parent_url,
+                allow_insecure_tls,
+                protocol_child_port,
+                timeline_attributes,
+                max_write_batch_staleness,
+                max_events_per_second,
+                max_event_burst,
+                protocol_version,
+            )| TopLevelIngest {
+                protocol_parent_url,
+                allow_insecure_tls,
+                protocol_child_port,
+                timeline_attributes,
+                max_write_batch_staleness,
+                max_events_per_second,
+                max_event_burst,
+                protocol_version,
+            },
+        )
+}
+
+pub fn config_secret() -> impl Strategy<Value = ConfigSecret> {
+    prop_oneof![
+        any::<String>().prop_map(ConfigSecret::Plain),
+        "[a-z]+:[a-zA-Z0-9_/]+".prop_map(ConfigSecret::Ref),
+    ]
+}
+
+pub fn top_level_mutation() -> impl Strategy<Value = TopLevelMutation> {
+    (
+        option::of(url()),
+        any::<bool>(),
+        option::of(any::<u16>()),
+        option::of(any::<u16>()),
+        option::of(config_secret()),
+        mutator_attributes(),
+        vec(url(), 0..3),
+    )
+        .prop_map(
+            |(
+                protocol_parent_url,
+                allow_insecure_tls,
+                protocol_child_port,
+                mutator_http_api_port,
+                mutator_http_api_key,
+                mutator_attributes,
+                external_mutator_urls,
+            )| TopLevelMutation {
+                protocol_parent_url,
+                allow_insecure_tls,
+                protocol_child_port,
+                mutator_http_api_port,
+                mutator_http_api_key,
+                mutator_attributes,
+                external_mutator_urls,
+            },
+        )
+}
+
+pub fn top_level_plugins() -> impl Strategy<Value = TopLevelPlugins> {
+    (
+        option::of(available_ports()),
+        option::of(plugins_dir()),
+        option::of(plugins_ingest()),
+        option::of(plugins_mutation()),
+    )
+        .prop_map(
+            |(available_ports, plugins_dir, ingest, mutation)| TopLevelPlugins {
+                available_ports,
+                plugins_dir,
+                ingest,
+                mutation,
+            },
+        )
+}
+
+pub fn available_ports() -> impl Strategy<Value = AvailablePorts> {
+    (option::of(any::<bool>()), vec(inclusive_port_range(), 0..3))
+        .prop_map(|(any_local, ranges)| AvailablePorts { any_local, ranges })
+}
+
+pub fn inclusive_port_range() -> impl Strategy<Value = InclusivePortRange> {
+    (any::<u16>(), any::<u16>()).prop_map(|(a, b)| {
+        InclusivePortRange::new(a.min(b), a.max(b)).expect("start is never greater than end")
+    })
+}
+
+pub fn plugins_ingest() -> impl Strategy<Value = PluginsIngest> {
+    (
+        btree_map(member_name(), plugins_ingest_member(), 0..3),
+        btree_map(member_name(), plugins_ingest_member(), 0..3),
+    )
+        .prop_map(|(collectors, importers)| PluginsIngest {
+            collectors,
+            importers,
+            ..Default::default()
+        })
+}
+
+pub fn plugins_ingest_member() -> impl Strategy<Value = PluginsIngestMember> {
+    (
+        option::of(plugin_name()),
+        timeline_attributes(),
+        plugin_shutdown(),
+        metadata(),
+    )
+        .prop_map(
+            |(plugin, timeline_attributes, shutdown, metadata)| PluginsIngestMember {
+                plugin,
+                timeline_attributes,
+                shutdown,
+                metadata,
+            },
+        )
+}
+
+pub fn plugins_mutation() -> impl Strategy<Value = PluginsMutation> {
+    btree_map(member_name(), plugins_mutation_member(), 0..3).prop_map(|mutators| PluginsMutation {
+        mutators,
+        ..Default::default()
+    })
+}
+
+pub fn plugins_mutation_member() -> impl Strategy<Value = PluginsMutationMember> {
+    (
+        option::of(plugin_name()),
+        mutator_attributes(),
+        plugin_shutdown(),
+        metadata(),
+    )
+        .prop_map(
+            |(plugin, mutator_attributes, shutdown, metadata)| PluginsMutationMember {
+                plugin,
+                mutator_attributes,
+                shutdown,
+                metadata,
+            },
+        )
+}
+
+pub fn plugin_shutdown() -> impl Strategy<Value = PluginShutdown> {
+    (option::of("SIG[A-Z]{2,6}"), option::of(millis_duration())).prop_map(
+        |(shutdown_signal, shutdown_timeout)| PluginShutdown {
+            shutdown_signal,
+            shutdown_timeout,
+        },
+    )
+}
+
+pub fn timeline_attributes() -> impl Strategy<Value = TimelineAttributes> {
+    (vec(attr_kv_pair(), 0..4), vec(attr_kv_pair(), 0..4)).prop_map(
+        |(additional_timeline_attributes, override_timeline_attributes)| TimelineAttributes {
+            additional_timeline_attributes,
+            override_timeline_attributes,
+        },
+    )
+}
+
+pub fn mutator_attributes() -> impl Strategy<Value = MutatorAttributes> {
+    (vec(attr_kv_pair(), 0..4), vec(attr_kv_pair(), 0..4)).prop_map(
+        |(additional_mutator_attributes, override_mutator_attributes)| MutatorAttributes {
+            additional_mutator_attributes,
+            override_mutator_attributes,
+        },
+    )
+}
+
+/// Key/value pairs whose `Display` form parses back to the same pair.
+///
+/// Values are restricted to the literal notations that `AttrKeyEqValuePair`
+/// can currently express unambiguously: integers, finite floats, booleans,
+/// durations, timestamps and quoted strings free of `=`, quotes and envsub
+/// expressions.
+pub fn attr_kv_pair() -> impl Strategy<Value = AttrKeyEqValuePair> {
+    (attr_key(), attr_val()).prop_map(|(k, v)| AttrKeyEqValuePair(k, v))
+}
+
+pub fn attr_key() -> impl Strategy<Value = AttrKey> {
+    "[a-z][a-z0-9_]{0,8}(\\.[a-z][a-z0-9_]{0,8}){0,2}".prop_map(AttrKey::new)
+}
+
+pub fn attr_val() -> impl Strategy<Value = AttrVal> {
+    prop_oneof![
+        any::<i64>().prop_map_into(),
+        proptest::num::f64::NORMAL.prop_map_into(),
+        any::<bool>().prop_map_into(),
+        any::<u64>().prop_map(|ns| AttrVal::Duration(ns.into())),
+        any::<u64>().prop_map(|ns| AttrVal::Timestamp(ns.into())),
+        "[a-zA-Z0-9][a-zA-Z0-9_.-]{0,16}".prop_map(AttrVal::from),
+    ]
+}
+
+pub fn url() -> impl Strategy<Value = Url> {
+    (
+        prop_oneof!["http", "https", "modality-ingest", "modality-ingest-tls"],
+        "[a-z][a-z0-9-]{0,10}(\\.[a-z]{2,5}){0,2}",
+        option::of(1..=u16::MAX),
+    )
+        .prop_map(|(scheme, host, port)| {
+            let s = match port {
+                Some(port) => format!("{scheme}://{host}:{port}"),
+                None => format!("{scheme}://{host}"),
+            };
+            Url::parse(&s).expect("generated url is well-formed")
+        })
+}
+
+/// Durations are stored as whole milliseconds in the TOML representation
+pub fn millis_duration() -> impl Strategy<Value = Duration> {
+    any::<u32>().prop_map(|ms| Duration::from_millis(ms.into()))
+}
+
+pub fn plugins_dir() -> impl Strategy<Value = PathBuf> {
+    "[a-z][a-z0-9_-]{0,8}(/[a-z][a-z0-9_-]{0,8}){0,3}".prop_map(PathBuf::from)
+}
+
+pub fn member_name() -> impl Strategy<Value = String> {
+    "[a-z][a-z0-9-]{0,12}"
+}
+
+pub fn plugin_name() -> impl Strategy<Value = String> {
+    "modality-[a-z][a-z0-9-]{0,12}"
+}
+
+pub fn metadata() -> impl Strategy<Value = BTreeMap<String, TomlValue>> {
+    btree_map("[a-z][a-z0-9-]{0,12}", toml_value(), 0..4)
+}
+
+pub fn toml_value() -> impl Strategy<Value = TomlValue> {
+    let leaf = prop_oneof![
+        any::<i64>().prop_map(TomlValue::Integer),
+        any::<bool>().prop_map(TomlValue::Boolean),
+        "[a-zA-Z0-9 _.-]{0,16}".prop_map(TomlValue::String),
+    ];
+    prop_oneof![leaf.clone(), vec(leaf, 0..3).prop_map(TomlValue::Array)]
+}
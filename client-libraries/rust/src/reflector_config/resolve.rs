@@ -1,15 +1,28 @@
 use crate::auth_token::{
     decode_auth_token_hex, token_user_file::REFLECTOR_AUTH_TOKEN_DEFAULT_FILE_NAME, AuthToken,
 };
-use crate::reflector_config::{try_from_file, Config, ConfigLoadError, CONFIG_ENV_VAR};
+use crate::reflector_config::{
+    is_config_url, try_from_file, url_host, AvailablePorts, Config, ConfigLoadError,
+    PluginShutdown, CONFIG_ENV_VAR, MODALITY_MUTATION_CONNECT_PORT_DEFAULT,
+    MODALITY_MUTATION_CONNECT_TLS_PORT_DEFAULT, MODALITY_REFLECTOR_INGEST_CONNECT_PORT_DEFAULT,
+    MODALITY_REFLECTOR_MUTATION_CONNECT_PORT_DEFAULT, MODALITY_STORAGE_SERVICE_PORT_DEFAULT,
+    MODALITY_STORAGE_SERVICE_TLS_PORT_DEFAULT,
+};
 use std::{
+    collections::{BTreeMap, BTreeSet},
     env,
+    net::{Ipv4Addr, TcpListener},
     path::{Path, PathBuf},
 };
+use url::Url;
 
 const CONFIG_FILE_NAME: &str = "config.toml";
 const CONFIG_DIR: &str = "modality-reflector";
 const SYS_CONFIG_BASE_PATH: &str = "/etc";
+#[cfg(unix)]
+const SYS_PLUGINS_DIR: &str = "/usr/lib/modality-reflector-plugins";
+#[cfg(not(unix))]
+const PLUGINS_DIR: &str = "modality-reflector-plugins";
 
 pub const MODALITY_HOST_ENV_VAR: &str = "MODALITY_HOST";
 pub const MODALITY_REFLECTOR_PLUGINS_DIR_ENV_VAR: &str = "MODALITY_REFLECTOR_PLUGINS_DIR";
@@ -111,12 +124,11 @@ impl ConfigContext {
 
             let ingest = self.config.ingest.as_mut().unwrap();
             ingest.protocol_parent_url = Some(
-                url::Url::parse(&format!("modality-ingest://{modality_host}")).map_err(|_| {
-                    ExpandedConfigLoadError::InvalidHostNameFromEnv {
+                url::Url::parse(&format!("modality-ingest://{}", url_host(&modality_host)))
+                    .map_err(|_| ExpandedConfigLoadError::InvalidHostNameFromEnv {
                         var: MODALITY_HOST_ENV_VAR,
                         value: modality_host.clone(),
-                    }
-                })?,
+                    })?,
             );
 
             if self.config.mutation.is_none() {
@@ -125,12 +137,11 @@ impl ConfigContext {
 
             let mutation = self.config.mutation.as_mut().unwrap();
             mutation.protocol_parent_url = Some(
-                url::Url::parse(&format!("modality-mutation://{modality_host}")).map_err(|_| {
-                    ExpandedConfigLoadError::InvalidHostNameFromEnv {
+                url::Url::parse(&format!("modality-mutation://{}", url_host(&modality_host)))
+                    .map_err(|_| ExpandedConfigLoadError::InvalidHostNameFromEnv {
                         var: MODALITY_HOST_ENV_VAR,
                         value: modality_host,
-                    }
-                })?,
+                    })?,
             );
         }
 
@@ -146,6 +157,310 @@ impl ConfigContext {
     }
 }
 
+impl Config {
+    /// The directory plugins should be loaded from, taken from the first of:
+    /// - Environment variable `MODALITY_REFLECTOR_PLUGINS_DIR`, which overrides the
+    ///   config here as it does in `ConfigContext::apply_environment_variable_overrides`
+    /// - `plugins.plugins-dir` in the config
+    /// - The platform default (i.e. /usr/lib/modality-reflector-plugins on Linux, or
+    ///   `modality-reflector-plugins` in `dirs::data_dir()` elsewhere)
+    ///
+    /// Existing directories are canonicalized. If `required` is set, a directory that
+    /// doesn't exist is an error; otherwise the path is returned as-is.
+    pub fn plugins_dir_resolved(&self, required: bool) -> Result<PathBuf, ExpandedConfigLoadError> {
+        let dir = match env_str(MODALITY_REFLECTOR_PLUGINS_DIR_ENV_VAR)? {
+            Some(dir) => PathBuf::from(dir),
+            None => self
+                .plugins
+                .as_ref()
+                .and_then(|p| p.plugins_dir.clone())
+                .unwrap_or_else(default_plugins_dir),
+        };
+
+        match dir.canonicalize() {
+            Ok(canonical) => Ok(canonical),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && !required => Ok(dir),
+            Err(error) => Err(ExpandedConfigLoadError::PluginsDir { path: dir, error }),
+        }
+    }
+}
+
+/// A fully resolved, runtime view of a [Config]: everything a reflector needs to
+/// start its plugins, with defaults applied and nothing left optional.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedConfig {
+    /// Where ingest data is sent; defaults to `modality-ingest://localhost`,
+    /// always with an explicit port.
+    pub ingest_parent_url: Url,
+    pub ingest_allow_insecure_tls: bool,
+    /// The port the reflector accepts ingest connections from its plugins on
+    pub ingest_child_port: u16,
+    /// Where mutation connections are made; defaults to `modality-mutation://localhost`,
+    /// always with an explicit port.
+    pub mutation_parent_url: Url,
+    pub mutation_allow_insecure_tls: bool,
+    /// The port the reflector accepts mutation connections from its plugins on
+    pub mutation_child_port: u16,
+    pub plugins_dir: PathBuf,
+    pub collectors: BTreeMap<String, ResolvedPlugin>,
+    pub importers: BTreeMap<String, ResolvedPlugin>,
+    pub mutators: BTreeMap<String, ResolvedPlugin>,
+}
+
+/// A configured plugin instance, ready to be launched.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedPlugin {
+    /// The plugin executable
+    pub executable: PathBuf,
+    /// A port allocated to this instance from `plugins.available-ports`
+    pub port: u16,
+    pub shutdown: PluginShutdown,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PluginKind {
+    Collector,
+    Importer,
+    Mutator,
+}
+
+impl PluginKind {
+    /// The subdirectory of the plugins directory that holds plugins of this kind
+    pub fn dir_name(self) -> &'static str {
+        match self {
+            PluginKind::Collector => "collectors",
+            PluginKind::Importer => "importers",
+            PluginKind::Mutator => "mutators",
+        }
+    }
+}
+
+impl Config {
+    /// Resolve this configuration into the concrete values a reflector runs with:
+    ///
+    /// * Parent URLs default to `localhost`, and are given the default port for
+    ///   their scheme if they don't have one.
+    /// * Child ports default to the standard reflector ports.
+    /// * Each plugin's executable is found under the plugins directory (see
+    ///   [Config::plugins_dir_resolved]), in the `collectors`, `importers` or
+    ///   `mutators` subdirectory. The `plugin` setting names the executable, and
+    ///   defaults to the instance name; an absolute path is used as-is.
+    /// * Each plugin instance is allocated a distinct port from
+    ///   `plugins.available-ports`: first from the configured `ranges`, in order,
+    ///   then, if `any-local` is set, any free local port picked by the OS.
+    ///   `any-local` defaults to true when no ranges are given. A port is only
+    ///   considered free if it can be bound on localhost at the time of resolution.
+    pub fn resolve(&self) -> Result<ResolvedConfig, ResolveError> {
+        let ingest = self.ingest.clone().unwrap_or_default();
+        let mutation = self.mutation.clone().unwrap_or_default();
+        let plugins = self.plugins.clone().unwrap_or_default();
+
+        let ingest_parent_url = with_default_port(
+            ingest
+                .protocol_parent_url
+                .unwrap_or_else(|| Url::parse("modality-ingest://localhost").unwrap()),
+        )?;
+        let mutation_parent_url = with_default_port(
+            mutation
+                .protocol_parent_url
+                .unwrap_or_else(|| Url::parse("modality-mutation://localhost").unwrap()),
+        )?;
+
+        let ingest_members = plugins.ingest.unwrap_or_default();
+        let mutation_members = plugins.mutation.unwrap_or_default();
+        let has_plugins = !(ingest_members.collectors.is_empty()
+            && ingest_members.importers.is_empty()
+            && mutation_members.mutators.is_empty());
+        let plugins_dir = self.plugins_dir_resolved(has_plugins)?;
+
+        let mut ports = PortAllocator::new(plugins.available_ports.unwrap_or_default());
+        let mut resolve_members =
+            |kind: PluginKind, members: Vec<(String, Option<String>, PluginShutdown)>| {
+                members
+                    .into_iter()
+                    .map(|(name, plugin, shutdown)| {
+                        let executable = plugin_executable(&plugins_dir, kind, &name, plugin)?;
+                        let port = ports.allocate(&name)?;
+                        Ok((
+                            name,
+                            ResolvedPlugin {
+                                executable,
+                                port,
+                                shutdown,
+                            },
+                        ))
+                    })
+                    .collect::<Result<BTreeMap<_, _>, ResolveError>>()
+            };
+
+        let collectors = resolve_members(
+            PluginKind::Collector,
+            ingest_members
+                .collectors
+                .into_iter()
+                .map(|(n, m)| (n, m.plugin, m.shutdown))
+                .collect(),
+        )?;
+        let importers = resolve_members(
+            PluginKind::Importer,
+            ingest_members
+                .importers
+                .into_iter()
+                .map(|(n, m)| (n, m.plugin, m.shutdown))
+                .collect(),
+        )?;
+        let mutators = resolve_members(
+            PluginKind::Mutator,
+            mutation_members
+                .mutators
+                .into_iter()
+                .map(|(n, m)| (n, m.plugin, m.shutdown))
+                .collect(),
+        )?;
+
+        Ok(ResolvedConfig {
+            ingest_parent_url,
+            ingest_allow_insecure_tls: ingest.allow_insecure_tls,
+            ingest_child_port: ingest
+                .protocol_child_port
+                .unwrap_or(MODALITY_REFLECTOR_INGEST_CONNECT_PORT_DEFAULT),
+            mutation_parent_url,
+            mutation_allow_insecure_tls: mutation.allow_insecure_tls,
+            mutation_child_port: mutation
+                .protocol_child_port
+                .unwrap_or(MODALITY_REFLECTOR_MUTATION_CONNECT_PORT_DEFAULT),
+            plugins_dir,
+            collectors,
+            importers,
+            mutators,
+        })
+    }
+}
+
+fn with_default_port(mut url: Url) -> Result<Url, ResolveError> {
+    if url.port().is_none() {
+        let port = match url.scheme() {
+            "modality-ingest" => MODALITY_STORAGE_SERVICE_PORT_DEFAULT,
+            "modality-ingest-tls" => MODALITY_STORAGE_SERVICE_TLS_PORT_DEFAULT,
+            "modality-mutation" => MODALITY_MUTATION_CONNECT_PORT_DEFAULT,
+            "modality-mutation-tls" => MODALITY_MUTATION_CONNECT_TLS_PORT_DEFAULT,
+            _ => return Err(ResolveError::UnsupportedUrlScheme(url)),
+        };
+        url.set_port(Some(port))
+            .map_err(|_| ResolveError::UnsupportedUrlScheme(url.clone()))?;
+    }
+    Ok(url)
+}
+
+fn plugin_executable(
+    plugins_dir: &Path,
+    kind: PluginKind,
+    name: &str,
+    plugin: Option<String>,
+) -> Result<PathBuf, ResolveError> {
+    let plugin = PathBuf::from(plugin.unwrap_or_else(|| name.to_owned()));
+    let path = if plugin.is_absolute() {
+        plugin
+    } else {
+        plugins_dir.join(kind.dir_name()).join(plugin)
+    };
+    if path.is_file() {
+        Ok(path)
+    } else {
+        Err(ResolveError::PluginNotFound {
+            kind,
+            name: name.to_owned(),
+            path,
+        })
+    }
+}
+
+struct PortAllocator {
+    any_local: bool,
+    candidates: Box<dyn Iterator<Item = u16>>,
+    allocated: BTreeSet<u16>,
+}
+
+impl PortAllocator {
+    fn new(available: AvailablePorts) -> Self {
+        PortAllocator {
+            any_local: available.any_local.unwrap_or(available.ranges.is_empty()),
+            candidates: Box::new(
+                available
+                    .ranges
+                    .into_iter()
+                    .flat_map(|r| r.start()..=r.end()),
+            ),
+            allocated: Default::default(),
+        }
+    }
+
+    fn allocate(&mut self, plugin: &str) -> Result<u16, ResolveError> {
+        for port in self.candidates.by_ref() {
+            if !self.allocated.contains(&port)
+                && TcpListener::bind((Ipv4Addr::LOCALHOST, port)).is_ok()
+            {
+                self.allocated.insert(port);
+                return Ok(port);
+            }
+        }
+
+        if self.any_local {
+            // The OS won't hand out a port that's still bound, so hold on to
+            // each listener until a fresh one is found.
+            let mut held = Vec::new();
+            loop {
+                let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+                    .map_err(ResolveError::AnyLocalPort)?;
+                let port = listener
+                    .local_addr()
+                    .map_err(ResolveError::AnyLocalPort)?
+                    .port();
+                if self.allocated.insert(port) {
+                    return Ok(port);
+                }
+                held.push(listener);
+            }
+        }
+
+        Err(ResolveError::PortsExhausted(plugin.to_owned()))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ResolveError {
+    #[error("URL '{0}' has an unsupported scheme")]
+    UnsupportedUrlScheme(Url),
+
+    #[error("The {} plugin executable for '{name}' was not found at '{}'", .kind.dir_name(), .path.display())]
+    PluginNotFound {
+        kind: PluginKind,
+        name: String,
+        path: PathBuf,
+    },
+
+    #[error("No available port left to allocate to plugin '{0}'")]
+    PortsExhausted(String),
+
+    #[error("Could not allocate a local port")]
+    AnyLocalPort(#[source] std::io::Error),
+
+    #[error(transparent)]
+    ExpandedConfigLoad(#[from] ExpandedConfigLoadError),
+}
+
+#[cfg(unix)]
+fn default_plugins_dir() -> PathBuf {
+    PathBuf::from(SYS_PLUGINS_DIR)
+}
+
+#[cfg(not(unix))]
+fn default_plugins_dir() -> PathBuf {
+    dirs::data_dir()
+        .map(|d| d.join(PLUGINS_DIR))
+        .unwrap_or_else(|| PathBuf::from(PLUGINS_DIR))
+}
+
 fn load_system_config() -> Result<Option<ConfigContext>, ConfigLoadError> {
     let cfg_path = system_config_path();
     if cfg_path.exists() {
@@ -180,9 +495,14 @@ fn load_user_or_env_config(
         UserOrEnvPath::Env => env_config_path(),
     };
     match cfg_path {
-        Some(p) if p.exists() => {
+        Some(p) if p.exists() || is_config_url(&p) => {
             tracing::trace!("Load {} configuration file {}", loc, p.display());
-            if let Some(config_file_parent_dir) = p.as_path().parent().map(ToOwned::to_owned) {
+            let parent_dir = if is_config_url(&p) {
+                env::current_dir().ok()
+            } else {
+                p.as_path().parent().map(ToOwned::to_owned)
+            };
+            if let Some(config_file_parent_dir) = parent_dir {
                 Ok(Some(ConfigContext {
                     config: try_from_file(&p)?,
                     config_file: Some(p),
@@ -226,6 +546,13 @@ pub enum ExpandedConfigLoadError {
     #[error("Invalid hostname '{value}' specified in environment variable '{var}'")]
     InvalidHostNameFromEnv { var: &'static str, value: String },
 
+    #[error("Plugins directory '{}' could not be resolved", .path.display())]
+    PluginsDir {
+        path: PathBuf,
+        #[source]
+        error: std::io::Error,
+    },
+
     #[error("Config loading error.")]
     ConfigLoadError(
         #[source]
@@ -323,3 +650,242 @@ pub enum ReflectorAuthTokenLoadError {
     #[error("No auth token was specified.  Provide a path to a token file as a CLI argument or put the token hex contents into the MODALITY_AUTH_TOKEN environment path")]
     Underspecified,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reflector_config::{
+        InclusivePortRange, PluginsIngest, PluginsIngestMember, PluginsMutation,
+        PluginsMutationMember, TopLevelIngest, TopLevelPlugins,
+    };
+
+    fn config_with_plugins_dir(dir: &Path) -> Config {
+        Config {
+            plugins: Some(TopLevelPlugins {
+                plugins_dir: Some(dir.to_owned()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn plugins_dir_from_config() {
+        env::remove_var(MODALITY_REFLECTOR_PLUGINS_DIR_ENV_VAR);
+        let cfg_dir = tempfile::tempdir().unwrap();
+        let resolved = config_with_plugins_dir(cfg_dir.path()).plugins_dir_resolved(true);
+        assert_eq!(resolved.unwrap(), cfg_dir.path().canonicalize().unwrap());
+
+        let missing = cfg_dir.path().join("missing");
+        let cfg = config_with_plugins_dir(&missing);
+        assert_eq!(cfg.plugins_dir_resolved(false).unwrap(), missing);
+        assert!(matches!(
+            cfg.plugins_dir_resolved(true),
+            Err(ExpandedConfigLoadError::PluginsDir { .. })
+        ));
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn plugins_dir_from_env() {
+        let cfg_dir = tempfile::tempdir().unwrap();
+        let env_dir = tempfile::tempdir().unwrap();
+        env::set_var(MODALITY_REFLECTOR_PLUGINS_DIR_ENV_VAR, env_dir.path());
+
+        let resolved = Config::default().plugins_dir_resolved(true);
+        // The env var wins over the config, as with the other overrides
+        let overridden = config_with_plugins_dir(cfg_dir.path()).plugins_dir_resolved(true);
+        env::remove_var(MODALITY_REFLECTOR_PLUGINS_DIR_ENV_VAR);
+        assert_eq!(resolved.unwrap(), env_dir.path().canonicalize().unwrap());
+        assert_eq!(overridden.unwrap(), env_dir.path().canonicalize().unwrap());
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn plugins_dir_default() {
+        env::remove_var(MODALITY_REFLECTOR_PLUGINS_DIR_ENV_VAR);
+        let default = default_plugins_dir();
+        let expected = default.canonicalize().unwrap_or(default);
+        assert_eq!(
+            Config::default().plugins_dir_resolved(false).unwrap(),
+            expected
+        );
+    }
+
+    fn config_with_plugins(dir: &Path, available_ports: AvailablePorts) -> Config {
+        for (kind, name) in [("collectors", "a"), ("importers", "b"), ("mutators", "c")] {
+            std::fs::create_dir_all(dir.join(kind)).unwrap();
+            std::fs::write(dir.join(kind).join(name), "").unwrap();
+        }
+        let mut cfg = config_with_plugins_dir(dir);
+        let plugins = cfg.plugins.as_mut().unwrap();
+        plugins.available_ports = Some(available_ports);
+        plugins.ingest = Some(PluginsIngest {
+            collectors: [("a".to_owned(), PluginsIngestMember::default())].into(),
+            importers: [(
+                "other-b".to_owned(),
+                PluginsIngestMember {
+                    plugin: Some("b".to_owned()),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            ..Default::default()
+        });
+        plugins.mutation = Some(PluginsMutation {
+            mutators: [("c".to_owned(), PluginsMutationMember::default())].into(),
+            ..Default::default()
+        });
+        cfg
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn resolve_any_local_ports() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugins_dir = dir.path().canonicalize().unwrap();
+        let cfg = config_with_plugins(
+            &plugins_dir,
+            AvailablePorts {
+                any_local: Some(true),
+                ranges: vec![],
+            },
+        );
+
+        let resolved = cfg.resolve().unwrap();
+        assert_eq!(resolved.plugins_dir, plugins_dir);
+        assert_eq!(
+            resolved.collectors["a"].executable,
+            plugins_dir.join("collectors").join("a")
+        );
+        assert_eq!(
+            resolved.importers["other-b"].executable,
+            plugins_dir.join("importers").join("b")
+        );
+        assert_eq!(
+            resolved.mutators["c"].executable,
+            plugins_dir.join("mutators").join("c")
+        );
+
+        let ports: BTreeSet<u16> = [
+            resolved.collectors["a"].port,
+            resolved.importers["other-b"].port,
+            resolved.mutators["c"].port,
+        ]
+        .into();
+        assert_eq!(ports.len(), 3);
+        for port in ports {
+            assert_ne!(port, 0);
+            TcpListener::bind((Ipv4Addr::LOCALHOST, port)).unwrap();
+        }
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn resolve_skips_member_templates() {
+        env::remove_var(MODALITY_REFLECTOR_PLUGINS_DIR_ENV_VAR);
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("collectors")).unwrap();
+        std::fs::write(dir.path().join("collectors").join("can0"), "").unwrap();
+        let cfg = crate::reflector_config::try_from_str(&format!(
+            r#"
+[plugins]
+plugins-dir = '{}'
+available-ports = {{ any-local = true }}
+
+[plugins.ingest.collectors."can*"]
+shutdown-signal = "SIGTERM"
+
+[plugins.ingest.collectors.can0]
+"#,
+            dir.path().display()
+        ))
+        .unwrap();
+
+        let resolved = cfg.resolve().unwrap();
+        assert_eq!(resolved.collectors.keys().collect::<Vec<_>>(), vec!["can0"]);
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn resolve_port_ranges() {
+        let dir = tempfile::tempdir().unwrap();
+        let taken = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let taken_port = taken.local_addr().unwrap().port();
+        let free = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let free_port = free.local_addr().unwrap().port();
+        drop(free);
+
+        let range = |p| InclusivePortRange::new(p, p).unwrap();
+        let cfg = config_with_plugins(
+            dir.path(),
+            AvailablePorts {
+                any_local: Some(false),
+                ranges: vec![range(taken_port), range(free_port)],
+            },
+        );
+        assert!(matches!(
+            cfg.resolve(),
+            Err(ResolveError::PortsExhausted(_))
+        ));
+
+        let mut cfg = cfg;
+        cfg.plugins.as_mut().unwrap().available_ports = Some(AvailablePorts {
+            any_local: None,
+            ranges: vec![range(taken_port), range(free_port)],
+        });
+        cfg.plugins.as_mut().unwrap().mutation = None;
+        cfg.plugins
+            .as_mut()
+            .unwrap()
+            .ingest
+            .as_mut()
+            .unwrap()
+            .importers
+            .clear();
+        let resolved = cfg.resolve().unwrap();
+        assert_eq!(resolved.collectors["a"].port, free_port);
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn resolve_urls_and_missing_plugins() {
+        let dir = tempfile::tempdir().unwrap();
+        let resolved = config_with_plugins_dir(dir.path()).resolve().unwrap();
+        assert_eq!(
+            resolved.ingest_parent_url.as_str(),
+            "modality-ingest://localhost:14182"
+        );
+        assert_eq!(
+            resolved.mutation_parent_url.as_str(),
+            "modality-mutation://localhost:14192"
+        );
+        assert_eq!(
+            resolved.ingest_child_port,
+            MODALITY_REFLECTOR_INGEST_CONNECT_PORT_DEFAULT
+        );
+
+        let mut cfg = config_with_plugins(dir.path(), AvailablePorts::default());
+        cfg.ingest = Some(TopLevelIngest {
+            protocol_parent_url: Some(Url::parse("modality-ingest-tls://example.com").unwrap()),
+            protocol_child_port: Some(9000),
+            ..Default::default()
+        });
+        let resolved = cfg.resolve().unwrap();
+        assert_eq!(
+            resolved.ingest_parent_url.as_str(),
+            "modality-ingest-tls://example.com:14184"
+        );
+        assert_eq!(resolved.ingest_child_port, 9000);
+
+        std::fs::remove_file(dir.path().join("mutators").join("c")).unwrap();
+        assert!(matches!(
+            cfg.resolve(),
+            Err(ResolveError::PluginNotFound {
+                kind: PluginKind::Mutator,
+                ..
+            })
+        ));
+    }
+}
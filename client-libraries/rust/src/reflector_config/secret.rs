@@ -0,0 +1,121 @@
+//! Settings that may be given either in plaintext or as a reference to a
+//! secret kept elsewhere, written `{ secret-ref = "<scheme>:<reference>" }`.
+use super::TomlValue;
+use std::{env, path::PathBuf};
+
+/// The table key marking a value as a reference to a secret
+pub const SECRET_REF_KEY: &str = "secret-ref";
+
+/// Dotted paths of the settings that hold a [ConfigSecret]
+pub(crate) const SECRET_PATHS: &[&str] = &["mutation.mutator-http-api-key"];
+
+/// Shown in place of a plaintext secret
+pub const REDACTED: &str = "<redacted>";
+
+/// A config value that is either given directly, or refers to a secret.
+#[derive(Clone, PartialEq, Eq)]
+pub enum ConfigSecret {
+    Plain(String),
+    /// A `<scheme>:<reference>` secret reference, not yet resolved
+    Ref(String),
+}
+
+impl ConfigSecret {
+    /// Interpret a TOML value: a string is a plain value, and a table with a
+    /// single `secret-ref` string is a reference. Anything else is `None`.
+    pub fn from_toml(val: &TomlValue) -> Option<Self> {
+        match val {
+            TomlValue::String(s) => Some(ConfigSecret::Plain(s.clone())),
+            TomlValue::Table(table) => match (table.len(), table.get(SECRET_REF_KEY)) {
+                (1, Some(TomlValue::String(reference))) => {
+                    Some(ConfigSecret::Ref(reference.clone()))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn to_toml(&self) -> TomlValue {
+        match self {
+            ConfigSecret::Plain(s) => TomlValue::String(s.clone()),
+            ConfigSecret::Ref(reference) => TomlValue::Table(
+                [(
+                    SECRET_REF_KEY.to_owned(),
+                    TomlValue::String(reference.clone()),
+                )]
+                .into_iter()
+                .collect(),
+            ),
+        }
+    }
+
+    /// Get the secret value, resolving a reference with the built-in schemes.
+    /// See [resolve_secret_ref].
+    pub fn resolve(&self) -> Result<String, SecretRefError> {
+        match self {
+            ConfigSecret::Plain(s) => Ok(s.clone()),
+            ConfigSecret::Ref(reference) => resolve_secret_ref(reference),
+        }
+    }
+}
+
+/// Secrets are never printed; references are, since they aren't sensitive.
+impl std::fmt::Debug for ConfigSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSecret::Plain(_) => f.write_str("Plain(<redacted>)"),
+            ConfigSecret::Ref(reference) => f.debug_tuple("Ref").field(reference).finish(),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SecretRefError {
+    #[error("Secret reference '{0}' must be of the form '<scheme>:<reference>'")]
+    Malformed(String),
+
+    #[error("Secret reference '{0}' uses an unsupported scheme")]
+    UnsupportedScheme(String),
+
+    #[error("The environment variable '{0}' referenced by a secret is not set or not unicode")]
+    EnvVar(String),
+
+    #[error("Error reading secret file {}", .path.display())]
+    File {
+        path: PathBuf,
+        #[source]
+        error: std::io::Error,
+    },
+}
+
+/// Split a `<scheme>:<reference>` secret reference.
+pub fn split_secret_ref(reference: &str) -> Result<(&str, &str), SecretRefError> {
+    reference
+        .split_once(':')
+        .ok_or_else(|| SecretRefError::Malformed(reference.to_owned()))
+}
+
+/// Resolve a secret reference using the built-in schemes:
+///
+/// * `env:NAME` reads the `NAME` environment variable.
+///
+/// * `file:PATH` reads the file at `PATH`, without its trailing newline.
+pub fn resolve_secret_ref(reference: &str) -> Result<String, SecretRefError> {
+    let (scheme, rest) = split_secret_ref(reference)?;
+    match scheme {
+        "env" => env::var(rest).map_err(|_| SecretRefError::EnvVar(rest.to_owned())),
+        "file" => {
+            let content = std::fs::read_to_string(rest).map_err(|error| SecretRefError::File {
+                path: rest.into(),
+                error,
+            })?;
+            let secret = content
+                .strip_suffix('\n')
+                .map(|s| s.strip_suffix('\r').unwrap_or(s))
+                .unwrap_or(&content);
+            Ok(secret.to_owned())
+        }
+        _ => Err(SecretRefError::UnsupportedScheme(reference.to_owned())),
+    }
+}
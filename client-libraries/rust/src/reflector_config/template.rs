@@ -0,0 +1,191 @@
+//! A commented example config, as a starting point for writing one by hand,
+//! e.g. for a plugin's `--generate-config` flag:
+//!
+//! ```
+//! use auxon_sdk::reflector_config::TemplateBuilder;
+//!
+//! // Only the sections an ingest plugin cares about
+//! let toml = TemplateBuilder::new().with_ingest().with_metadata().build();
+//! assert!(auxon_sdk::reflector_config::try_from_str(&toml).is_ok());
+//! ```
+//!
+//! Settings with a sensible default are given; the rest are commented out.
+use super::{MODALITY_MUTATION_CONNECT_PORT_DEFAULT, MODALITY_STORAGE_SERVICE_PORT_DEFAULT};
+
+/// The commented example config, with every top-level section.
+pub fn template_toml() -> String {
+    TemplateBuilder::all().build()
+}
+
+/// Builds the example config from only the selected top-level sections.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TemplateBuilder {
+    ingest: bool,
+    mutation: bool,
+    plugins: bool,
+    metadata: bool,
+}
+
+impl TemplateBuilder {
+    /// No sections selected; the template is just its header.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every top-level section selected.
+    pub fn all() -> Self {
+        TemplateBuilder {
+            ingest: true,
+            mutation: true,
+            plugins: true,
+            metadata: true,
+        }
+    }
+
+    pub fn with_ingest(mut self) -> Self {
+        self.ingest = true;
+        self
+    }
+
+    pub fn with_mutation(mut self) -> Self {
+        self.mutation = true;
+        self
+    }
+
+    pub fn with_plugins(mut self) -> Self {
+        self.plugins = true;
+        self
+    }
+
+    pub fn with_metadata(mut self) -> Self {
+        self.metadata = true;
+        self
+    }
+
+    pub fn build(&self) -> String {
+        let mut sections = vec![HEADER.to_owned()];
+        if self.ingest {
+            sections.push(ingest_section());
+        }
+        if self.mutation {
+            sections.push(mutation_section());
+        }
+        if self.plugins {
+            sections.push(PLUGINS_SECTION.to_owned());
+        }
+        if self.metadata {
+            sections.push(METADATA_SECTION.to_owned());
+        }
+        sections.join("\n")
+    }
+}
+
+const HEADER: &str = "\
+# Modality reflector configuration.
+#
+# String values may refer to environment variables as ${NAME}, or
+# ${NAME:-default} to fall back to a default when NAME is unset.
+";
+
+fn ingest_section() -> String {
+    format!(
+        "\
+# Connection to Modality for sending events, shared by ingest plugins.
+[ingest]
+# Where to send ingested data: a modality-ingest:// URL, or
+# modality-ingest-tls:// for a TLS connection.
+protocol-parent-url = 'modality-ingest://localhost:{MODALITY_STORAGE_SERVICE_PORT_DEFAULT}'
+
+# Accept any TLS certificate from the server, e.g. a self-signed one.
+allow-insecure-tls = false
+
+# Port for the reflector to accept ingest connections from plugins on.
+# protocol-child-port = 14188
+
+# Require this ingest protocol version, rather than negotiating one.
+# protocol-version = 1
+
+# Longest that sent events may sit in a partially filled write batch.
+# max-write-batch-staleness-millis = 1000
+
+# Pace each plugin's events to a sustained rate, allowing short bursts.
+# max-events-per-second = 10000
+# max-event-burst = 1000
+
+# Attributes added to every timeline, as 'key = value' strings. Values may
+# use the placeholders {{pid}}, {{hostname}}, {{plugin}} and
+# {{run_id}}.
+additional-timeline-attributes = []
+
+# Attributes that replace any a plugin sets on its timelines.
+override-timeline-attributes = []
+
+# A file of further 'key = value' timeline attributes, one per line.
+# timeline-attributes-file = 'timeline-attributes.txt'
+"
+    )
+}
+
+fn mutation_section() -> String {
+    format!(
+        "\
+# Connection to Modality for mutators, shared by mutation plugins.
+[mutation]
+# Where to register mutators: a modality-mutation:// URL, or
+# modality-mutation-tls:// for a TLS connection.
+protocol-parent-url = 'modality-mutation://localhost:{MODALITY_MUTATION_CONNECT_PORT_DEFAULT}'
+
+# Accept any TLS certificate from the server, e.g. a self-signed one.
+allow-insecure-tls = false
+
+# Port for the reflector to accept mutation connections from plugins on.
+# protocol-child-port = 14198
+
+# Serve the mutator HTTP API on this port, requiring this key in the
+# mutator_apikey header. The key may be given inline, or as a reference
+# to a secret: {{ secret-ref = 'env:MUTATOR_API_KEY' }}.
+# mutator-http-api-port = 8080
+# mutator-http-api-key = {{ secret-ref = 'env:MUTATOR_API_KEY' }}
+
+# Attributes added to every mutator, as 'key = value' strings.
+additional-mutator-attributes = []
+
+# Attributes that replace any a plugin sets on its mutators.
+override-mutator-attributes = []
+
+# Mutator HTTP APIs served by other processes, to make available too.
+external-mutator-urls = []
+"
+    )
+}
+
+const PLUGINS_SECTION: &str = "\
+# Plugins hosted by the reflector.
+[plugins]
+# Where to look for plugin executables.
+# plugins-dir = '/usr/lib/modality-reflector-plugins'
+
+# Ports plugins may be given to listen on.
+# [plugins.available-ports]
+# any-local = false
+# ranges = [[9081, 9097]]
+
+# Settings for one collector; importers go under plugins.ingest.importers,
+# and mutators under plugins.mutation.mutators, in the same way.
+# [plugins.ingest.collectors.my-collector]
+# plugin = 'modality-example-collector'
+# additional-timeline-attributes = ['location = \"lab\"']
+# override-timeline-attributes = []
+# shutdown-signal = 'SIGINT'
+# shutdown-timeout-millis = 1000
+#
+# Plugin-specific settings for this collector.
+# [plugins.ingest.collectors.my-collector.metadata]
+# poll-interval-millis = 500
+";
+
+const METADATA_SECTION: &str = "\
+# Plugin-specific settings shared by all plugins.
+[metadata]
+# poll-interval-millis = 500
+";
@@ -0,0 +1,346 @@
+//! Shared test support: a [RecordedTransport] with the server's side of
+//! authentication done, helpers for reading back what a client sent, and
+//! fake servers for the ingest and mutation protocols and for HTTP.
+
+#[cfg(feature = "modality")]
+pub(crate) use ingest::*;
+#[cfg(feature = "modality")]
+mod ingest {
+    pub(crate) use crate::ingest_client::recorded::RecordedTransport;
+    use crate::{
+        api::{AttrVal, TimelineId},
+        ingest_client::IngestTransport,
+        ingest_protocol::{IngestMessage, IngestResponse, InternedAttrKey},
+    };
+    use std::collections::HashMap;
+    use tokio::sync::mpsc;
+    use url::Url;
+
+    /// A transport whose first response accepts the client's auth request
+    pub(crate) fn authed_transport() -> RecordedTransport {
+        let transport = RecordedTransport::new();
+        transport
+            .push_response(&IngestResponse::AuthResponse {
+                ok: true,
+                message: None,
+                protocol_version: None,
+            })
+            .unwrap();
+        transport
+    }
+
+    /// Every message the client wrote to `transport`
+    pub(crate) fn sent_messages(transport: &RecordedTransport) -> Vec<IngestMessage> {
+        transport
+            .frames()
+            .iter()
+            .map(|f| minicbor::decode(f).unwrap())
+            .collect()
+    }
+
+    /// An attribute with its key resolved to the name it was declared with
+    pub(crate) type NamedAttr = (String, AttrVal);
+
+    /// The attributes in a [TimelineMetadata](IngestMessage::TimelineMetadata)
+    /// or [Event](IngestMessage::Event) message, with their keys resolved to
+    /// the names they were declared with, and the timeline they were sent on
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) enum SentAttrs {
+        Timeline {
+            timeline: Option<TimelineId>,
+            attrs: Vec<NamedAttr>,
+        },
+        Event {
+            timeline: Option<TimelineId>,
+            be_ordering: Vec<u8>,
+            attrs: Vec<NamedAttr>,
+        },
+    }
+
+    /// The attributes sent in `messages`, in order; see [SentAttrs]
+    pub(crate) fn sent_attrs(messages: &[IngestMessage]) -> Vec<SentAttrs> {
+        let mut key_names: HashMap<InternedAttrKey, String> = HashMap::new();
+        let mut timeline = None;
+        let mut sent = Vec::new();
+        for msg in messages {
+            let name = |(k, v): &(InternedAttrKey, AttrVal)| (key_names[k].clone(), v.clone());
+            match msg {
+                IngestMessage::DeclareAttrKey { name, wire_id } => {
+                    key_names.insert(*wire_id, name.clone());
+                }
+                IngestMessage::OpenTimeline { id } => timeline = Some(*id),
+                IngestMessage::TimelineMetadata { attrs } => sent.push(SentAttrs::Timeline {
+                    timeline,
+                    attrs: attrs.0.iter().map(name).collect(),
+                }),
+                IngestMessage::Event { be_ordering, attrs } => sent.push(SentAttrs::Event {
+                    timeline,
+                    be_ordering: be_ordering.clone(),
+                    attrs: attrs.0.iter().map(name).collect(),
+                }),
+                _ => (),
+            }
+        }
+        sent
+    }
+
+    /// The attributes of each event sent in `messages`
+    pub(crate) fn sent_event_attrs(messages: &[IngestMessage]) -> Vec<Vec<NamedAttr>> {
+        sent_attrs(messages)
+            .into_iter()
+            .filter_map(|s| match s {
+                SentAttrs::Event { attrs, .. } => Some(attrs),
+                SentAttrs::Timeline { .. } => None,
+            })
+            .collect()
+    }
+
+    /// The timeline attributes sent in `messages`, with the timeline each
+    /// was sent on
+    pub(crate) fn sent_timeline_attrs(
+        messages: &[IngestMessage],
+    ) -> Vec<(Option<TimelineId>, Vec<NamedAttr>)> {
+        sent_attrs(messages)
+            .into_iter()
+            .filter_map(|s| match s {
+                SentAttrs::Timeline { timeline, attrs } => Some((timeline, attrs)),
+                SentAttrs::Event { .. } => None,
+            })
+            .collect()
+    }
+
+    /// The server's side of one ingest connection: answer each auth request,
+    /// accepting the tokens `accept` allows, and forward every message
+    /// received, auth requests included, then `None` once the client hangs up.
+    pub(crate) async fn serve_ingest_connection(
+        mut conn: impl IngestTransport,
+        accept: impl Fn(&[u8]) -> bool,
+        received: mpsc::UnboundedSender<Option<IngestMessage>>,
+    ) {
+        while let Ok(frame) = conn.read_frame().await {
+            let msg: IngestMessage = minicbor::decode(&frame).unwrap();
+            if let IngestMessage::AuthRequest { token, .. } = &msg {
+                let resp = minicbor::to_vec(IngestResponse::AuthResponse {
+                    ok: accept(token),
+                    message: None,
+                    protocol_version: None,
+                })
+                .unwrap();
+                conn.write_frame(&resp).await.unwrap();
+            }
+            if received.send(Some(msg)).is_err() {
+                return;
+            }
+        }
+        let _ = received.send(None);
+    }
+
+    /// The server's end of a TLS connection, for [FakeIngestServer]
+    impl IngestTransport for tokio_rustls::server::TlsStream<tokio::net::TcpStream> {}
+
+    /// An ingest server on localhost, serving each connection with
+    /// [serve_ingest_connection]
+    pub(crate) struct FakeIngestServer {
+        pub(crate) url: Url,
+        received: mpsc::UnboundedReceiver<Option<IngestMessage>>,
+    }
+
+    impl FakeIngestServer {
+        /// A plain TCP server accepting any token
+        pub(crate) async fn start() -> Self {
+            Self::start_with(false, |_| true).await
+        }
+
+        /// A server accepting the tokens `accept` allows, over TLS with the
+        /// [test certificates](crate::tls::test_certs) if `tls` is set
+        pub(crate) async fn start_with(
+            tls: bool,
+            accept: impl Fn(&[u8]) -> bool + Clone + Send + 'static,
+        ) -> Self {
+            use crate::ingest_client::{
+                MODALITY_INGEST_TLS_URL_SCHEME, MODALITY_INGEST_URL_SCHEME,
+            };
+            use crate::tls::test_certs;
+
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let url = if tls {
+                // The test certificate is for localhost
+                format!("{MODALITY_INGEST_TLS_URL_SCHEME}://localhost:{port}")
+            } else {
+                format!("{MODALITY_INGEST_URL_SCHEME}://127.0.0.1:{port}")
+            };
+            let acceptor =
+                tls.then(|| tokio_rustls::TlsAcceptor::from(test_certs::server_config()));
+            let (tx, received) = mpsc::unbounded_channel();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let (accept, tx, acceptor) = (accept.clone(), tx.clone(), acceptor.clone());
+                    tokio::spawn(async move {
+                        match acceptor {
+                            Some(acceptor) => {
+                                if let Ok(stream) = acceptor.accept(stream).await {
+                                    serve_ingest_connection(stream, accept, tx).await;
+                                }
+                            }
+                            None => serve_ingest_connection(stream, accept, tx).await,
+                        }
+                    });
+                }
+            });
+
+            FakeIngestServer {
+                url: Url::parse(&url).unwrap(),
+                received,
+            }
+        }
+
+        /// The next message received, on any connection
+        pub(crate) async fn next_message(&mut self) -> IngestMessage {
+            loop {
+                if let Some(msg) = self.received.recv().await.expect("server stopped") {
+                    return msg;
+                }
+            }
+        }
+
+        /// The token of the next auth request received
+        pub(crate) async fn next_token(&mut self) -> Vec<u8> {
+            loop {
+                if let IngestMessage::AuthRequest { token, .. } = self.next_message().await {
+                    return token;
+                }
+            }
+        }
+
+        /// Everything received on a connection, until the client hung up.
+        /// Connections are told apart by when they end, so this is only
+        /// meaningful for one connection at a time.
+        pub(crate) async fn next_connection(&mut self) -> Vec<IngestMessage> {
+            let mut messages = Vec::new();
+            while let Some(msg) = self.received.recv().await.expect("server stopped") {
+                messages.push(msg);
+            }
+            messages
+        }
+
+        /// A server on its own thread and runtime, for tests of blocking
+        /// code. Returns its address, and the messages received on the first
+        /// connection, which ends once the client hangs up.
+        #[cfg(feature = "modality_tracing")]
+        pub(crate) fn start_blocking() -> (
+            std::net::SocketAddr,
+            std::sync::mpsc::Receiver<IngestMessage>,
+        ) {
+            let (addr_tx, addr_rx) = std::sync::mpsc::channel();
+            let (tx, rx) = std::sync::mpsc::channel();
+            std::thread::spawn(move || {
+                let rt = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap();
+                rt.block_on(async move {
+                    let mut server = FakeIngestServer::start().await;
+                    let addr = server.url.socket_addrs(|| None).unwrap()[0];
+                    addr_tx.send(addr).unwrap();
+                    while let Some(Some(msg)) = server.received.recv().await {
+                        if tx.send(msg).is_err() {
+                            return;
+                        }
+                    }
+                });
+            });
+            (addr_rx.recv().unwrap(), rx)
+        }
+    }
+}
+
+#[cfg(all(feature = "modality", feature = "deviant"))]
+pub(crate) use mutation::*;
+#[cfg(all(feature = "modality", feature = "deviant"))]
+mod mutation {
+    use crate::ingest_client::IngestTransport;
+    use crate::mutation_plane::protocol::{
+        LeafwardsMessage, RootwardsMessage, MUTATION_PROTOCOL_VERSION,
+    };
+    use tokio::{net::TcpListener, sync::mpsc};
+    use url::Url;
+
+    /// A mutation plane that accepts a single child, authenticates it, and
+    /// forwards everything it sends afterwards. The mutation protocol is
+    /// framed like the ingest protocol, so the socket is read as an
+    /// [IngestTransport].
+    pub(crate) async fn fake_mutation_plane() -> (Url, mpsc::UnboundedReceiver<RootwardsMessage>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!(
+            "modality-mutation://{}",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            let (mut s, _) = listener.accept().await.unwrap();
+            let frame = s.read_frame().await.unwrap();
+            let RootwardsMessage::ChildAuthAttempt {
+                child_participant_id,
+                ..
+            } = minicbor::decode(&frame).unwrap()
+            else {
+                panic!("expected an auth attempt");
+            };
+            let outcome = minicbor::to_vec(LeafwardsMessage::ChildAuthOutcome {
+                child_participant_id,
+                version: MUTATION_PROTOCOL_VERSION,
+                ok: true,
+                message: None,
+            })
+            .unwrap();
+            s.write_frame(&outcome).await.unwrap();
+
+            while let Ok(frame) = s.read_frame().await {
+                if tx.send(minicbor::decode(&frame).unwrap()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        (url, rx)
+    }
+}
+
+/// Serve one canned HTTP response per expected request, returning the
+/// server's URL and a channel of the raw requests it received
+#[cfg(feature = "config_http")]
+pub(crate) fn serve_http(
+    responses: Vec<(&'static str, &'static str)>,
+) -> (String, std::sync::mpsc::Receiver<String>) {
+    use std::io::{BufRead, BufReader, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for (status, body) in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = String::new();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" || line.is_empty() {
+                    break;
+                }
+                request.push_str(&line);
+            }
+            tx.send(request).unwrap();
+            write!(
+                stream,
+                "HTTP/1.1 {status}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+        }
+    });
+    (url, rx)
+}
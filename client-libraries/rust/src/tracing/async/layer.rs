@@ -1,5 +1,5 @@
 use crate::tracing::{
-    common::layer::{LayerHandler, LocalMetadata},
+    common::layer::{LayerHandler, LocalMetadata, TimelineRouting},
     common::options::Options,
     ingest::{self, ModalityIngest, ModalityIngestTaskHandle, WrappedMessage},
     InitError,
@@ -19,6 +19,7 @@ use uuid::Uuid;
 /// Can be transformed into a `Subscriber` with [`ModalityLayer::into_subscriber()`].
 pub struct ModalityLayer {
     sender: UnboundedSender<WrappedMessage>,
+    routing: TimelineRouting,
 }
 
 impl ModalityLayer {
@@ -42,6 +43,7 @@ impl ModalityLayer {
     ) -> Result<(Self, ModalityIngestTaskHandle), InitError> {
         let run_id = Uuid::new_v4();
         opts.add_metadata("run_id", run_id.to_string());
+        let routing = TimelineRouting::new(&opts);
 
         let ingest = ModalityIngest::async_connect(opts)
             .await
@@ -49,7 +51,7 @@ impl ModalityLayer {
         let ingest_handle = ingest.spawn_task().await;
         let sender = ingest_handle.ingest_sender.clone();
 
-        Ok((ModalityLayer { sender }, ingest_handle))
+        Ok((ModalityLayer { sender, routing }, ingest_handle))
     }
 
     /// Convert this `Layer` into a `Subscriber`by by layering it on a new instace of `tracing`'s
@@ -71,4 +73,150 @@ impl LayerHandler for ModalityLayer {
     fn thread_timeline_initialized(&self) -> &'static LocalKey<Cell<bool>> {
         &Self::THREAD_TIMELINE_INITIALIZED
     }
+
+    fn timeline_routing(&self) -> &TimelineRouting {
+        &self.routing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::TimelineId;
+    use crate::tracing::ingest::Message;
+
+    #[test]
+    fn events_route_to_named_timelines() {
+        let (sender, mut recv) = mpsc::unbounded_channel();
+        let subscriber = ModalityLayer {
+            sender,
+            routing: Default::default(),
+        }
+        .into_subscriber();
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(modality.timeline = "first", "one");
+            tracing::info!(modality.timeline = "second", "two");
+            tracing::info!(modality.timeline = "first", "three");
+            tracing::info!("four");
+        });
+
+        let thread_timeline = ingest::current_timeline();
+        let mut new_timelines = Vec::new();
+        let mut event_timelines = Vec::new();
+        while let Ok(msg) = recv.try_recv() {
+            match msg.message {
+                Message::NewTimeline { name } => new_timelines.push((name, msg.timeline)),
+                Message::Event { records, .. } => {
+                    assert!(!records.contains_key("modality.timeline"));
+                    event_timelines.push(msg.timeline);
+                }
+                _ => (),
+            }
+        }
+
+        assert_eq!(event_timelines.len(), 4);
+        let (first, second) = (event_timelines[0], event_timelines[1]);
+        assert_ne!(first, second);
+        assert_ne!(first, thread_timeline);
+        assert_ne!(second, thread_timeline);
+        assert_eq!(event_timelines[2], first);
+        assert_eq!(event_timelines[3], thread_timeline);
+
+        assert!(new_timelines.contains(&("first".to_string(), first)));
+        assert!(new_timelines.contains(&("second".to_string(), second)));
+    }
+
+    #[test]
+    fn events_route_to_timeline_ids() {
+        let (sender, mut recv) = mpsc::unbounded_channel();
+        let subscriber = ModalityLayer {
+            sender,
+            routing: Default::default(),
+        }
+        .into_subscriber();
+
+        let external = Uuid::from_u128(0x1234);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(modality.timeline_id = %external, "one");
+            tracing::info!(modality.timeline_id = "not a uuid", "two");
+        });
+
+        let mut events = Vec::new();
+        while let Ok(msg) = recv.try_recv() {
+            if let Message::Event { records, .. } = msg.message {
+                events.push((msg.timeline, records));
+            }
+        }
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].0, external.into());
+        assert!(!events[0].1.contains_key("modality.timeline_id"));
+        assert_eq!(events[1].0, ingest::current_timeline());
+        assert!(events[1].1.contains_key("modality.timeline_id"));
+    }
+
+    fn event_timelines(recv: &mut mpsc::UnboundedReceiver<WrappedMessage>) -> Vec<TimelineId> {
+        let mut timelines = Vec::new();
+        while let Ok(msg) = recv.try_recv() {
+            if let Message::Event { .. } = msg.message {
+                timelines.push(msg.timeline);
+            }
+        }
+        timelines
+    }
+
+    #[test]
+    fn events_route_by_span_timeline_key() {
+        let (sender, mut recv) = mpsc::unbounded_channel();
+        let routing = TimelineRouting::new(&Options::new().with_timeline_key_field("task"));
+        let subscriber = ModalityLayer { sender, routing }.into_subscriber();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let outer = tracing::info_span!("outer", task = "a");
+            let _outer = outer.enter();
+            tracing::info!("one");
+            {
+                let inner = tracing::info_span!("inner", task = "b");
+                let _inner = inner.enter();
+                tracing::info!("two");
+                tracing::info!(modality.timeline = "c", "three");
+            }
+            tracing::info!("four");
+        });
+
+        let timelines = event_timelines(&mut recv);
+        assert_eq!(timelines.len(), 4);
+        let thread_timeline = ingest::current_timeline();
+        assert!(!timelines.contains(&thread_timeline));
+        assert_ne!(timelines[0], timelines[1]);
+        assert_ne!(timelines[1], timelines[2]);
+        assert_eq!(timelines[3], timelines[0]);
+    }
+
+    #[test]
+    fn named_timelines_are_per_layer_and_bounded() {
+        let (sender, mut recv) = mpsc::unbounded_channel();
+        let routing = TimelineRouting::new(&Options::new().with_max_named_timelines(1));
+        let subscriber = ModalityLayer { sender, routing }.into_subscriber();
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(modality.timeline = "first", "one");
+            tracing::info!(modality.timeline = "second", "two");
+            tracing::info!(modality.timeline = "first", "three");
+        });
+        let timelines = event_timelines(&mut recv);
+        assert_eq!(timelines[1], ingest::current_timeline());
+        assert_eq!(timelines[2], timelines[0]);
+
+        let (sender, mut recv) = mpsc::unbounded_channel();
+        let subscriber = ModalityLayer {
+            sender,
+            routing: Default::default(),
+        }
+        .into_subscriber();
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(modality.timeline = "first", "one");
+        });
+        assert_ne!(event_timelines(&mut recv)[0], timelines[0]);
+    }
 }
@@ -2,7 +2,9 @@ mod layer;
 
 use crate::tracing::InitError;
 pub use crate::tracing::{
-    ingest::ModalityIngestTaskHandle, options::Options, r#async::layer::ModalityLayer,
+    ingest::{ModalityIngestFlushHandle, ModalityIngestTaskHandle},
+    options::Options,
+    r#async::layer::ModalityLayer,
 };
 
 use anyhow::Context as _;
@@ -37,6 +39,11 @@ impl TracingModality {
         Ok(Self { ingest_handle })
     }
 
+    /// Get a handle for flushing the ingest task without finishing it.
+    pub fn flush_handle(&self) -> ModalityIngestFlushHandle {
+        self.ingest_handle.flush_handle()
+    }
+
     /// Stop accepting new trace events, flush all existing events, and stop ingest thread.
     pub async fn finish(self) {
         self.ingest_handle.finish().await;
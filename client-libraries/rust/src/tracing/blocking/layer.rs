@@ -1,5 +1,5 @@
 use crate::tracing::{
-    common::layer::{LayerHandler, LocalMetadata},
+    common::layer::{LayerHandler, LocalMetadata, TimelineRouting},
     common::options::Options,
     ingest::{self, ModalityIngest, ModalityIngestThreadHandle, WrappedMessage},
     InitError,
@@ -19,6 +19,7 @@ use uuid::Uuid;
 /// Can be transformed into a `Subscriber` with [`ModalityLayer::into_subscriber()`].
 pub struct ModalityLayer {
     sender: UnboundedSender<WrappedMessage>,
+    routing: TimelineRouting,
 }
 
 impl ModalityLayer {
@@ -42,12 +43,13 @@ impl ModalityLayer {
     ) -> Result<(Self, ModalityIngestThreadHandle), InitError> {
         let run_id = Uuid::new_v4();
         opts.add_metadata("run_id", run_id.to_string());
+        let routing = TimelineRouting::new(&opts);
 
         let ingest = ModalityIngest::connect(opts).context("connect to modality")?;
         let ingest_handle = ingest.spawn_thread();
         let sender = ingest_handle.ingest_sender.clone();
 
-        Ok((ModalityLayer { sender }, ingest_handle))
+        Ok((ModalityLayer { sender, routing }, ingest_handle))
     }
 
     /// Convert this `Layer` into a `Subscriber`by by layering it on a new instace of `tracing`'s
@@ -69,4 +71,8 @@ impl LayerHandler for ModalityLayer {
     fn thread_timeline_initialized(&self) -> &'static LocalKey<Cell<bool>> {
         &Self::THREAD_TIMELINE_INITIALIZED
     }
+
+    fn timeline_routing(&self) -> &TimelineRouting {
+        &self.routing
+    }
 }
@@ -2,11 +2,15 @@ mod layer;
 
 pub use crate::{
     api::TimelineId,
-    tracing::{ingest::ModalityIngestThreadHandle, timeline_id, InitError, Options},
+    tracing::{
+        ingest::{FlushTimeout, ModalityIngestFlushHandle, ModalityIngestThreadHandle},
+        timeline_id, InitError, Options,
+    },
 };
 pub use layer::ModalityLayer;
 
 use anyhow::Context as _;
+use std::time::Duration;
 use tracing::Dispatch;
 
 /// A global tracer instance for [tracing.rs](https://tracing.rs/) that sends traces via a network
@@ -37,8 +41,19 @@ impl TracingModality {
         Ok(Self { ingest_handle })
     }
 
+    /// Get a handle for flushing the ingest thread without finishing it.
+    pub fn flush_handle(&self) -> ModalityIngestFlushHandle {
+        self.ingest_handle.flush_handle()
+    }
+
     /// Stop accepting new trace events, flush all existing events, and stop ingest thread.
     pub fn finish(self) {
         self.ingest_handle.finish();
     }
+
+    /// Like [finish](Self::finish), but gives up waiting for the ingest thread after `timeout`.
+    /// See [`ModalityIngestThreadHandle::finish_timeout`].
+    pub fn finish_timeout(self, timeout: Duration) -> Result<(), FlushTimeout> {
+        self.ingest_handle.finish_timeout(timeout)
+    }
 }
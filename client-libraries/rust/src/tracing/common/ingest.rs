@@ -40,6 +40,11 @@ pub enum ConnectError {
     UnexpectedFailure(#[from] anyhow::Error),
 }
 
+/// The ingest thread didn't finish within the time allowed.
+#[derive(Debug, Error)]
+#[error("Timed out waiting for the ingest thread to finish")]
+pub struct FlushTimeout;
+
 #[derive(Debug, Error)]
 pub enum IngestError {
     /// Errors that it is assumed there is no way to handle without human intervention, meant for
@@ -99,14 +104,42 @@ pub(crate) enum Message {
     },
 }
 
+/// A cloneable handle for flushing the ingest thread or task from anywhere,
+/// such as a panic hook. See [`crate::plugin_utils::install_flush_on_panic`].
+#[derive(Clone)]
+pub struct ModalityIngestFlushHandle {
+    flush_sender: UnboundedSender<std::sync::mpsc::Sender<()>>,
+}
+
+impl ModalityIngestFlushHandle {
+    /// Have the ingest thread or task send all the trace events queued so far, and flush the
+    /// connection. Blocks the calling thread for up to `timeout` waiting for that to finish, and
+    /// returns whether it did.
+    ///
+    /// An ingest task can't make progress while this blocks the thread driving its runtime (e.g.
+    /// a current thread runtime), in which case this always waits out the timeout.
+    pub fn flush_blocking(&self, timeout: Duration) -> bool {
+        let (done_sender, done_receiver) = std::sync::mpsc::channel();
+        self.flush_sender.send(done_sender).is_ok() && done_receiver.recv_timeout(timeout).is_ok()
+    }
+}
+
 /// A handle to control the spawned ingest thread.
 pub struct ModalityIngestThreadHandle {
     pub(crate) ingest_sender: UnboundedSender<WrappedMessage>,
+    pub(crate) flush_handle: ModalityIngestFlushHandle,
     pub(crate) finish_sender: Option<oneshot::Sender<()>>,
     pub(crate) thread: Option<JoinHandle<()>>,
+    /// Signalled (or disconnected) when the ingest thread is done
+    pub(crate) done_receiver: std::sync::mpsc::Receiver<()>,
 }
 
 impl ModalityIngestThreadHandle {
+    /// Get a handle for flushing the ingest thread without finishing it.
+    pub fn flush_handle(&self) -> ModalityIngestFlushHandle {
+        self.flush_handle.clone()
+    }
+
     /// Stop accepting new trace events, flush all existing events, and stop ingest thread.
     ///
     /// This function must be called at the end of your main thread to give the ingest thread a
@@ -127,16 +160,42 @@ impl ModalityIngestThreadHandle {
             let _ = thread.join();
         }
     }
+
+    /// Like [finish](Self::finish), but gives up waiting for the ingest thread after `timeout`,
+    /// so a stuck connection can't hold up process exit. On timeout the thread is detached, and
+    /// any trace events it hadn't sent yet may be lost.
+    pub fn finish_timeout(mut self, timeout: Duration) -> Result<(), FlushTimeout> {
+        if let Some(finish) = self.finish_sender.take() {
+            let _ = finish.send(());
+        }
+
+        let Some(thread) = self.thread.take() else {
+            return Ok(());
+        };
+        if let Err(std::sync::mpsc::RecvTimeoutError::Timeout) =
+            self.done_receiver.recv_timeout(timeout)
+        {
+            return Err(FlushTimeout);
+        }
+        let _ = thread.join();
+        Ok(())
+    }
 }
 
 /// A handle to control the spawned ingest task.
 pub struct ModalityIngestTaskHandle {
     pub(crate) ingest_sender: UnboundedSender<WrappedMessage>,
+    pub(crate) flush_handle: ModalityIngestFlushHandle,
     pub(crate) finish_sender: Option<oneshot::Sender<()>>,
     pub(crate) task: Option<task::JoinHandle<()>>,
 }
 
 impl ModalityIngestTaskHandle {
+    /// Get a handle for flushing the ingest task without finishing it.
+    pub fn flush_handle(&self) -> ModalityIngestFlushHandle {
+        self.flush_handle.clone()
+    }
+
     /// Stop accepting new trace events, flush all existing events, and stop ingest thread.
     ///
     /// This function must be called at the end of your main thread to give the ingest thread a
@@ -155,6 +214,8 @@ impl ModalityIngestTaskHandle {
 pub(crate) struct ModalityIngest {
     client: IngestClient<BoundTimelineState>,
     global_metadata: Vec<(String, AttrVal)>,
+    global_event_attrs: Vec<(InternedAttrKey, AttrVal)>,
+    timestamp_fallback: bool,
     event_keys: HashMap<String, InternedAttrKey>,
     timeline_keys: HashMap<String, InternedAttrKey>,
     span_names: HashMap<NonZeroU64, String>,
@@ -197,19 +258,43 @@ impl ModalityIngest {
             .await
             .context("open new timeline")?;
 
-        Ok(Self {
+        let mut ingest = Self {
             client,
             global_metadata: options.metadata,
+            global_event_attrs: Vec::new(),
+            timestamp_fallback: options.timestamp_fallback,
             event_keys: HashMap::new(),
             timeline_keys: HashMap::new(),
             span_names: HashMap::new(),
             rt: None,
-        })
+        };
+        ingest
+            .set_global_event_attrs(options.global_event_attrs)
+            .await
+            .context("declare global event attr keys")?;
+
+        Ok(ingest)
+    }
+
+    /// Intern the global event attr keys up front, so packing an event doesn't have to look
+    /// them up again.
+    async fn set_global_event_attrs(
+        &mut self,
+        attrs: Vec<(String, AttrVal)>,
+    ) -> Result<(), IngestError> {
+        let mut global_event_attrs = Vec::with_capacity(attrs.len());
+        for (key, value) in attrs {
+            global_event_attrs.push((self.get_or_create_event_attr_key(key).await?, value));
+        }
+        self.global_event_attrs = global_event_attrs;
+        Ok(())
     }
 
     pub(crate) fn spawn_thread(mut self) -> ModalityIngestThreadHandle {
         let (sender, recv) = mpsc::unbounded_channel();
+        let (flush_sender, flush_receiver) = mpsc::unbounded_channel();
         let (finish_sender, finish_receiver) = oneshot::channel();
+        let (done_sender, done_receiver) = std::sync::mpsc::channel();
 
         let join_handle = thread::spawn(move || {
             // ensure this thread doesn't send trace events to the global dispatcher
@@ -221,24 +306,29 @@ impl ModalityIngest {
                     .expect("build local tokio current thread runtime")
             });
 
-            rt.block_on(self.handler_task(recv, finish_receiver))
+            rt.block_on(self.handler_task(recv, flush_receiver, finish_receiver));
+            let _ = done_sender.send(());
         });
 
         ModalityIngestThreadHandle {
             ingest_sender: sender,
+            flush_handle: ModalityIngestFlushHandle { flush_sender },
             finish_sender: Some(finish_sender),
             thread: Some(join_handle),
+            done_receiver,
         }
     }
 
     pub(crate) async fn spawn_task(self) -> ModalityIngestTaskHandle {
         let (ingest_sender, recv) = mpsc::unbounded_channel();
+        let (flush_sender, flush_receiver) = mpsc::unbounded_channel();
         let (finish_sender, finish_receiver) = oneshot::channel();
 
-        let task = tokio::spawn(self.handler_task(recv, finish_receiver));
+        let task = tokio::spawn(self.handler_task(recv, flush_receiver, finish_receiver));
 
         ModalityIngestTaskHandle {
             ingest_sender,
+            flush_handle: ModalityIngestFlushHandle { flush_sender },
             finish_sender: Some(finish_sender),
             task: Some(task),
         }
@@ -247,6 +337,7 @@ impl ModalityIngest {
     async fn handler_task(
         mut self,
         mut recv: UnboundedReceiver<WrappedMessage>,
+        mut flush: UnboundedReceiver<std::sync::mpsc::Sender<()>>,
         mut finish: oneshot::Receiver<()>,
     ) {
         loop {
@@ -254,6 +345,14 @@ impl ModalityIngest {
                 Some(message) = recv.recv() => {
                     let _ = self.handle_packet(message).await;
                 },
+                Some(done) = flush.recv() => {
+                    // send everything queued before the flush was requested
+                    while let Ok(message) = recv.try_recv() {
+                        let _ = self.handle_packet(message).await;
+                    }
+                    let _ = self.client.flush().await;
+                    let _ = done.send(());
+                },
                 _ = &mut finish => {
                     break
                 }
@@ -631,7 +730,7 @@ impl ModalityIngest {
             let remote_timeline_id = if let AttrVal::String(string) = attrval {
                 use std::str::FromStr;
                 if let Ok(uuid) = Uuid::from_str(&string) {
-                    AttrVal::TimelineId(Box::new(uuid.into()))
+                    AttrVal::timeline_id(uuid.into())
                 } else {
                     AttrVal::String(string)
                 }
@@ -667,28 +766,15 @@ impl ModalityIngest {
             ));
         }
 
-        // Manually retype the local timestamp
-        let local_timestamp = records.remove("timestamp").map(tracing_value_to_attr_val);
-        if let Some(attrval) = local_timestamp {
-            let remote_timestamp = match attrval {
-                AttrVal::Integer(i) if i >= 0 => AttrVal::Timestamp(Nanoseconds::from(i as u64)),
-                AttrVal::BigInt(i) if *i >= 0 && *i <= u64::MAX as i128 => {
-                    AttrVal::Timestamp(Nanoseconds::from(*i as u64))
-                }
-                AttrVal::Timestamp(t) => AttrVal::Timestamp(t),
-                x => x,
-            };
-
+        if let Some(timestamp) = event_timestamp(
+            &mut records,
+            maybe_nanos_since_unix_epoch,
+            self.timestamp_fallback,
+        ) {
             packed_attrs.push((
                 self.get_or_create_event_attr_key("event.timestamp".into())
                     .await?,
-                remote_timestamp,
-            ));
-        } else if let Some(nanos_since_unix_epoch) = maybe_nanos_since_unix_epoch {
-            packed_attrs.push((
-                self.get_or_create_event_attr_key("event.timestamp".into())
-                    .await?,
-                AttrVal::Timestamp(nanos_since_unix_epoch),
+                timestamp,
             ));
         }
 
@@ -705,10 +791,41 @@ impl ModalityIngest {
             packed_attrs.push((self.get_or_create_event_attr_key(key).await?, attrval));
         }
 
+        // global attrs fill in whatever the event didn't set itself
+        for (key, value) in &self.global_event_attrs {
+            if !packed_attrs.iter().any(|(k, _)| k == key) {
+                packed_attrs.push((*key, value.clone()));
+            }
+        }
+
         Ok(())
     }
 }
 
+/// The value for `event.timestamp`: an explicit `timestamp` field if present, otherwise the
+/// wall-clock time the message was recorded at, if `fallback` is enabled.
+fn event_timestamp(
+    records: &mut RecordMap,
+    maybe_nanos_since_unix_epoch: Option<Nanoseconds>,
+    fallback: bool,
+) -> Option<AttrVal> {
+    // Manually retype the local timestamp
+    if let Some(attrval) = records.remove("timestamp").map(tracing_value_to_attr_val) {
+        Some(match attrval {
+            AttrVal::Integer(i) if i >= 0 => AttrVal::Timestamp(Nanoseconds::from(i as u64)),
+            AttrVal::BigInt(i) if *i >= 0 && *i <= u64::MAX as i128 => {
+                AttrVal::Timestamp(Nanoseconds::from(*i as u64))
+            }
+            AttrVal::Timestamp(t) => AttrVal::Timestamp(t),
+            x => x,
+        })
+    } else if fallback {
+        maybe_nanos_since_unix_epoch.map(AttrVal::Timestamp)
+    } else {
+        None
+    }
+}
+
 fn tracing_value_to_attr_val(value: TracingValue) -> AttrVal {
     match value {
         TracingValue::String(s) => s.into(),
@@ -718,3 +835,142 @@ fn tracing_value_to_attr_val(value: TracingValue) -> AttrVal {
         TracingValue::Bool(b) => b.into(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{authed_transport, sent_event_attrs, sent_messages};
+    use tracing_core::{
+        callsite::{Callsite, Identifier},
+        field::FieldSet,
+        metadata::Kind,
+        subscriber::Interest,
+        Level,
+    };
+
+    struct TestCallsite;
+    static TEST_CALLSITE: TestCallsite = TestCallsite;
+    static TEST_METADATA: Metadata<'static> = Metadata::new(
+        "test event",
+        "test",
+        Level::INFO,
+        None,
+        None,
+        None,
+        FieldSet::new(&[], Identifier(&TEST_CALLSITE)),
+        Kind::EVENT,
+    );
+
+    impl Callsite for TestCallsite {
+        fn set_interest(&self, _: Interest) {}
+        fn metadata(&self) -> &Metadata<'_> {
+            &TEST_METADATA
+        }
+    }
+
+    #[test]
+    fn timestamp_fallback_can_be_disabled() {
+        let now = Some(Nanoseconds::from(1_000));
+
+        let mut records = RecordMap::new();
+        assert_eq!(
+            event_timestamp(&mut records, now, true),
+            Some(AttrVal::Timestamp(Nanoseconds::from(1_000)))
+        );
+        assert_eq!(event_timestamp(&mut records, now, false), None);
+
+        // an explicit timestamp field is honored either way
+        let mut records = RecordMap::new();
+        records.insert("timestamp".to_string(), TracingValue::U64(42));
+        assert_eq!(
+            event_timestamp(&mut records, now, false),
+            Some(AttrVal::Timestamp(Nanoseconds::from(42)))
+        );
+        assert!(records.is_empty());
+    }
+
+    #[tokio::test]
+    async fn global_event_attrs_fill_in_unset_fields() {
+        let transport = authed_transport();
+        let client = IngestClient::from_transport(transport.clone(), Duration::from_secs(1))
+            .authenticate(vec![])
+            .await
+            .unwrap()
+            .open_timeline(current_timeline())
+            .await
+            .unwrap();
+
+        let options = Options::new().with_global_event_attrs([
+            ("deployment".to_string(), "prod".into()),
+            ("event.region".to_string(), "eu".into()),
+        ]);
+        let mut ingest = ModalityIngest {
+            client,
+            global_metadata: options.metadata,
+            global_event_attrs: Vec::new(),
+            timestamp_fallback: false,
+            event_keys: HashMap::new(),
+            timeline_keys: HashMap::new(),
+            span_names: HashMap::new(),
+            rt: None,
+        };
+        ingest
+            .set_global_event_attrs(options.global_event_attrs)
+            .await
+            .unwrap();
+
+        let mut records = RecordMap::new();
+        records.insert("region".to_string(), TracingValue::String("us".to_string()));
+        ingest
+            .handle_packet(WrappedMessage {
+                message: Message::Event {
+                    metadata: &TEST_METADATA,
+                    records,
+                },
+                tick: Duration::ZERO,
+                nanos_since_unix_epoch: None,
+                timeline: current_timeline(),
+            })
+            .await
+            .unwrap();
+        ingest.client.flush().await.unwrap();
+
+        let attrs = sent_event_attrs(&sent_messages(&transport)).pop().unwrap();
+
+        assert!(attrs.contains(&("event.deployment".to_owned(), "prod".into())));
+        // the event's own field wins over the global one
+        let regions: Vec<_> = attrs.iter().filter(|(k, _)| k == "event.region").collect();
+        assert_eq!(
+            regions,
+            vec![&("event.region".to_owned(), AttrVal::from("us"))]
+        );
+    }
+
+    #[test]
+    fn finish_timeout_gives_up_on_a_stuck_thread() {
+        let (ingest_sender, _recv) = mpsc::unbounded_channel();
+        let (flush_sender, _flush_recv) = mpsc::unbounded_channel();
+        let (finish_sender, _finish_recv) = oneshot::channel();
+        let (unblock, blocked) = std::sync::mpsc::channel::<()>();
+        let (done_sender, done_receiver) = std::sync::mpsc::channel();
+        let handle = ModalityIngestThreadHandle {
+            ingest_sender,
+            flush_handle: ModalityIngestFlushHandle { flush_sender },
+            finish_sender: Some(finish_sender),
+            // stands in for an ingest thread stuck writing to its connection
+            thread: Some(thread::spawn(move || {
+                let _ = blocked.recv();
+                let _ = done_sender.send(());
+            })),
+            done_receiver,
+        };
+
+        let start = std::time::Instant::now();
+        assert!(matches!(
+            handle.finish_timeout(Duration::from_millis(50)),
+            Err(FlushTimeout)
+        ));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        drop(unblock);
+    }
+}
@@ -1,9 +1,11 @@
 use crate::{
-    api::{Nanoseconds, TimelineId},
+    api::{Nanoseconds, TimelineId, Uuid},
     tracing::ingest::{self, WrappedMessage},
+    tracing::Options,
 };
 use duplicate::duplicate_item;
 use once_cell::sync::Lazy;
+use parking_lot::RwLock;
 use std::time::SystemTime;
 use std::{
     cell::Cell,
@@ -30,6 +32,49 @@ use tracing_subscriber::{
 static START: Lazy<Instant> = Lazy::new(Instant::now);
 static NEXT_SPAN_ID: AtomicU64 = AtomicU64::new(1);
 
+/// The event field used to route an event to a named timeline instead of the current thread's
+/// timeline.
+pub(crate) const TIMELINE_FIELD: &str = "modality.timeline";
+
+/// The event field used to route an event to a specific timeline, by its id.
+pub(crate) const TIMELINE_ID_FIELD: &str = "modality.timeline_id";
+
+/// The span field used by default to route the events within a span to a named timeline.
+pub(crate) const DEFAULT_TIMELINE_KEY_FIELD: &str = "modality.timeline_key";
+
+/// The number of named timelines a layer keeps track of by default.
+pub(crate) const DEFAULT_MAX_NAMED_TIMELINES: usize = 1024;
+
+/// A layer's named timelines, and the settings for routing events to them.
+pub(crate) struct TimelineRouting {
+    named: RwLock<HashMap<String, TimelineId>>,
+    max_named: usize,
+    key_field: String,
+}
+
+impl TimelineRouting {
+    pub(crate) fn new(opts: &Options) -> Self {
+        TimelineRouting {
+            named: RwLock::new(HashMap::new()),
+            max_named: opts.max_named_timelines,
+            key_field: opts.timeline_key_field.clone(),
+        }
+    }
+}
+
+impl Default for TimelineRouting {
+    fn default() -> Self {
+        TimelineRouting {
+            named: RwLock::new(HashMap::new()),
+            max_named: DEFAULT_MAX_NAMED_TIMELINES,
+            key_field: DEFAULT_TIMELINE_KEY_FIELD.to_string(),
+        }
+    }
+}
+
+/// The timeline name given to a span by its timeline key field.
+struct TimelineKey(String);
+
 /// An ID for spans that we can use directly.
 #[derive(Copy, Clone, Debug)]
 pub(crate) struct LocalSpanId(NonZeroU64);
@@ -50,10 +95,16 @@ pub(crate) trait LayerHandler {
     fn send(&self, msg: WrappedMessage) -> Result<(), mpsc::error::SendError<WrappedMessage>>;
     fn local_metadata(&self) -> &'static LocalKey<Lazy<LocalMetadata>>;
     fn thread_timeline_initialized(&self) -> &'static LocalKey<Cell<bool>>;
+    fn timeline_routing(&self) -> &TimelineRouting;
 }
 
 trait LayerCommon: LayerHandler {
     fn handle_message(&self, message: ingest::Message) {
+        let timeline = self.local_metadata().with(|m| m.thread_timeline);
+        self.handle_message_on_timeline(message, timeline)
+    }
+
+    fn handle_message_on_timeline(&self, message: ingest::Message, timeline: TimelineId) {
         self.ensure_timeline_has_been_initialized();
         let wrapped_message = ingest::WrappedMessage {
             message,
@@ -65,7 +116,7 @@ trait LayerCommon: LayerHandler {
                     let n: Option<u64> = d.as_nanos().try_into().ok();
                     n.map(Nanoseconds::from)
                 }),
-            timeline: self.local_metadata().with(|m| m.thread_timeline),
+            timeline,
         };
 
         if let Err(_e) = self.send(wrapped_message) {
@@ -118,6 +169,75 @@ trait LayerCommon: LayerHandler {
             let _ = self.send(wrapped_message);
         }
     }
+
+    /// Look up the timeline for the given name, allocating it (and announcing it to the ingest
+    /// task) the first time the name is seen. Returns `None` once the layer is already tracking
+    /// as many named timelines as it's allowed to, and this is a new one.
+    fn named_timeline(&self, name: String) -> Option<TimelineId> {
+        let routing = self.timeline_routing();
+        if let Some(timeline) = routing.named.read().get(&name) {
+            return Some(*timeline);
+        }
+
+        let timeline = {
+            let mut named = routing.named.write();
+            if let Some(timeline) = named.get(&name) {
+                return Some(*timeline);
+            }
+            if named.len() >= routing.max_named {
+                static WARN_LATCH: Once = Once::new();
+                WARN_LATCH.call_once(|| {
+                    eprintln!(
+                        "warning: tracing modality reached its limit of {} named timelines, \
+                         further names are recorded on their thread's timeline",
+                        routing.max_named
+                    );
+                });
+                return None;
+            }
+            let timeline = TimelineId::allocate();
+            named.insert(name.clone(), timeline);
+            timeline
+        };
+
+        self.handle_message_on_timeline(ingest::Message::NewTimeline { name }, timeline);
+        Some(timeline)
+    }
+
+    /// Remember the timeline name given by the span's timeline key field, if it has one.
+    fn record_timeline_key<S>(&self, span: &Id, records: &RecordMap, ctx: &Context<'_, S>)
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        if let Some(value) = records.get(&self.timeline_routing().key_field) {
+            ctx.span(span)
+                .expect("get span tracing just told us about")
+                .extensions_mut()
+                .replace(TimelineKey(timeline_name(value)));
+        }
+    }
+}
+
+/// The timeline name given by a field's value
+fn timeline_name(value: &TracingValue) -> String {
+    match value {
+        TracingValue::String(s) => s.clone(),
+        TracingValue::F64(n) => n.to_string(),
+        TracingValue::I64(n) => n.to_string(),
+        TracingValue::U64(n) => n.to_string(),
+        TracingValue::Bool(b) => b.to_string(),
+    }
+}
+
+/// Take the timeline id given by the [TIMELINE_ID_FIELD] field out of `records`. A value that
+/// isn't a UUID is left in place, to be recorded like any other field.
+fn take_timeline_id(records: &mut RecordMap) -> Option<TimelineId> {
+    let TracingValue::String(value) = records.get(TIMELINE_ID_FIELD)? else {
+        return None;
+    };
+    let id = Uuid::parse_str(value).ok()?;
+    records.remove(TIMELINE_ID_FIELD);
+    Some(id.into())
 }
 
 fn get_local_span_id<S>(span: &Id, ctx: &Context<'_, S>) -> LocalSpanId
@@ -153,6 +273,7 @@ where
         attrs.record(&mut visitor);
         let records = visitor.values();
         let metadata = attrs.metadata();
+        self.record_timeline_key(id, &records, &ctx);
 
         let msg = ingest::Message::NewSpan {
             id: local_id.0,
@@ -168,10 +289,12 @@ where
 
         let mut visitor = RecordMapBuilder::new();
         values.record(&mut visitor);
+        let records = visitor.values();
+        self.record_timeline_key(span, &records, &ctx);
 
         let msg = ingest::Message::Record {
             span: local_id.0,
-            records: visitor.values(),
+            records,
         };
 
         self.handle_message(msg)
@@ -189,16 +312,34 @@ where
         self.handle_message(msg)
     }
 
-    fn on_event(&self, event: &tracing_core::Event<'_>, _ctx: Context<'_, S>) {
+    fn on_event(&self, event: &tracing_core::Event<'_>, ctx: Context<'_, S>) {
         let mut visitor = RecordMapBuilder::new();
         event.record(&mut visitor);
+        let mut records = visitor.values();
+
+        let timeline = if let Some(timeline) = take_timeline_id(&mut records) {
+            Some(timeline)
+        } else if let Some(value) = records.remove(TIMELINE_FIELD) {
+            self.named_timeline(timeline_name(&value))
+        } else {
+            // The innermost span with a timeline key decides
+            ctx.event_scope(event)
+                .and_then(|mut scope| {
+                    scope.find_map(|span| {
+                        let key = span.extensions().get::<TimelineKey>().map(|k| k.0.clone());
+                        key
+                    })
+                })
+                .and_then(|name| self.named_timeline(name))
+        }
+        .unwrap_or_else(|| self.local_metadata().with(|m| m.thread_timeline));
 
         let msg = ingest::Message::Event {
             metadata: event.metadata(),
-            records: visitor.values(),
+            records,
         };
 
-        self.handle_message(msg)
+        self.handle_message_on_timeline(msg, timeline)
     }
 
     fn on_enter(&self, span: &Id, ctx: Context<'_, S>) {
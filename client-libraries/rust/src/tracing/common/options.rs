@@ -1,4 +1,5 @@
 use crate::api::AttrVal;
+use crate::tracing::common::layer::{DEFAULT_MAX_NAMED_TIMELINES, DEFAULT_TIMELINE_KEY_FIELD};
 use std::net::SocketAddr;
 
 /// Initialization options.
@@ -6,7 +7,11 @@ use std::net::SocketAddr;
 pub struct Options {
     pub(crate) auth: Option<Vec<u8>>,
     pub(crate) metadata: Vec<(String, AttrVal)>,
+    pub(crate) global_event_attrs: Vec<(String, AttrVal)>,
     pub(crate) server_addr: SocketAddr,
+    pub(crate) timestamp_fallback: bool,
+    pub(crate) timeline_key_field: String,
+    pub(crate) max_named_timelines: usize,
 }
 
 impl Options {
@@ -16,7 +21,11 @@ impl Options {
         Options {
             auth,
             metadata: Vec::new(),
+            global_event_attrs: Vec::new(),
             server_addr,
+            timestamp_fallback: true,
+            timeline_key_field: DEFAULT_TIMELINE_KEY_FIELD.to_string(),
+            max_named_timelines: DEFAULT_MAX_NAMED_TIMELINES,
         }
     }
 
@@ -89,6 +98,24 @@ impl Options {
         self
     }
 
+    /// Add attributes to every event, e.g. `("deployment".to_string(), "prod".into())`.
+    ///
+    /// Keys are given the `event.` prefix if they don't already have it. A field set on an
+    /// individual event takes precedence over a global attribute with the same key.
+    ///
+    /// This can be called multiple times.
+    pub fn set_global_event_attrs(&mut self, attrs: impl IntoIterator<Item = (String, AttrVal)>) {
+        self.global_event_attrs.extend(attrs);
+    }
+    /// A chainable version of [set_global_event_attrs](Self::set_global_event_attrs).
+    pub fn with_global_event_attrs(
+        mut self,
+        attrs: impl IntoIterator<Item = (String, AttrVal)>,
+    ) -> Self {
+        self.global_event_attrs.extend(attrs);
+        self
+    }
+
     /// Set the address of modalityd or a modality reflector where trace data should be sent.
     ///
     /// Defaults to `localhost:default_port`
@@ -100,6 +127,52 @@ impl Options {
         self.server_addr = addr;
         self
     }
+
+    /// Set whether events without an explicit `timestamp` field are given an `event.timestamp`
+    /// taken from the system clock when they were recorded.
+    ///
+    /// Defaults to `true`. Disable this when events carry their own authoritative timestamps, so
+    /// that the wall-clock time isn't reported as if it were one of them. An explicit `timestamp`
+    /// field is always honored.
+    pub fn set_timestamp_fallback(&mut self, enabled: bool) {
+        self.timestamp_fallback = enabled;
+    }
+    /// A chainable version of [set_timestamp_fallback](Self::set_timestamp_fallback).
+    pub fn with_timestamp_fallback(mut self, enabled: bool) -> Self {
+        self.timestamp_fallback = enabled;
+        self
+    }
+
+    /// Set the span field that routes the events within a span to a named timeline, e.g. to give
+    /// each async task its own timeline rather than sharing its thread's.
+    ///
+    /// Defaults to `modality.timeline_key`. The innermost span with the field decides; an
+    /// event's own `modality.timeline` or `modality.timeline_id` field takes precedence. Only
+    /// events are routed, spans' own enter and exit events stay on their thread's timeline.
+    pub fn set_timeline_key_field<S: Into<String>>(&mut self, field: S) {
+        self.timeline_key_field = field.into();
+    }
+    /// A chainable version of [set_timeline_key_field](Self::set_timeline_key_field).
+    pub fn with_timeline_key_field<S: Into<String>>(mut self, field: S) -> Self {
+        self.timeline_key_field = field.into();
+        self
+    }
+
+    /// Set how many named timelines, from the `modality.timeline` field or the
+    /// [timeline key field](Self::set_timeline_key_field), the layer keeps track of.
+    ///
+    /// Defaults to 1024. Each distinct name is remembered for as long as the layer lives, so
+    /// names should come from a small set, like task names, not from unbounded values like
+    /// request ids. Once the limit is reached, events naming a new timeline are recorded on
+    /// their thread's timeline instead.
+    pub fn set_max_named_timelines(&mut self, max: usize) {
+        self.max_named_timelines = max;
+    }
+    /// A chainable version of [set_max_named_timelines](Self::set_max_named_timelines).
+    pub fn with_max_named_timelines(mut self, max: usize) -> Self {
+        self.max_named_timelines = max;
+        self
+    }
 }
 
 impl Default for Options {
@@ -68,6 +68,26 @@
 //! [`timeline_id()`] function and should send that ID along with the interaction
 //! for the remote thread to record the interaction on its own timeline.
 //!
+//! An individual event can be routed to a named timeline instead of its thread's
+//! timeline by giving it a `modality.timeline` field. All events using the same
+//! name, from any thread, are recorded on the same timeline, and the name is
+//! used as that timeline's `timeline.name`.
+//!
+//! To record an event on a timeline whose id is already known, e.g. one modeling
+//! an external entity, give it a `modality.timeline_id` field holding that id as
+//! a UUID string. This takes precedence over `modality.timeline`. No timeline
+//! metadata is sent for such timelines; a `modality.timeline_id` value that isn't
+//! a UUID is recorded as an ordinary field.
+//!
+//! The events within a span can be routed the same way, e.g. to give each async
+//! task its own timeline, by giving the span a `modality.timeline_key` field (see
+//! [`Options::set_timeline_key_field`]). The innermost such span decides.
+//!
+//! Named timelines belong to the layer that routed events to them, and each layer
+//! remembers a limited number of names (see [`Options::set_max_named_timelines`]),
+//! so names should come from a small set rather than unbounded values like
+//! request ids.
+//!
 //! ### `tracing` Metadata
 //!
 //! `tracing` implicitly generates some metadata for every event and span and
@@ -110,7 +130,7 @@ mod r#async;
 pub mod blocking;
 mod common;
 
-pub use common::ingest::ModalityIngestTaskHandle;
+pub use common::ingest::{ModalityIngestFlushHandle, ModalityIngestTaskHandle};
 pub use common::options::Options;
 pub use common::*;
 
@@ -10,8 +10,10 @@ use once_cell::sync::Lazy;
 use std::{
     borrow::Borrow,
     collections::HashMap,
-    ops::{Deref, DerefMut},
-    sync::RwLock,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        RwLock,
+    },
 };
 use thiserror::Error;
 use tracing_serde_structured::{
@@ -23,7 +25,104 @@ use tracing_serde_wire::{Packet, TWOther, TracingWire};
 pub use options::Options;
 
 // spans can be defined on any thread and then sent to another and entered/etc, track globally
-static SPAN_NAMES: Lazy<RwLock<HashMap<u64, String>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+static SPAN_NAMES: Lazy<RwLock<SpanNames>> =
+    Lazy::new(|| RwLock::new(SpanNames::new(DEFAULT_SPAN_NAME_CAPACITY)));
+
+/// The default for [set_span_name_capacity]
+pub const DEFAULT_SPAN_NAME_CAPACITY: usize = 10_000;
+
+/// Set how many span names are kept, so enter and exit events can be named after their span.
+///
+/// Span names are shared by every connection in the process, so this is a process-wide
+/// setting. Past the capacity, the least recently used names are evicted, and enter and exit
+/// events for those spans go unnamed. Defaults to [DEFAULT_SPAN_NAME_CAPACITY].
+pub fn set_span_name_capacity(capacity: usize) {
+    SPAN_NAMES
+        .write()
+        .expect("span name lock poisoned, this is a bug")
+        .set_capacity(capacity);
+}
+
+/// The number of span names currently stored, for use as a metric. Names are removed when their
+/// span closes, or when the least recently used ones are evicted to stay within the
+/// [capacity](set_span_name_capacity).
+pub fn span_name_count() -> usize {
+    SPAN_NAMES
+        .read()
+        .expect("span name lock poisoned, this is a bug")
+        .len()
+}
+
+/// Span names by span id, holding at most `capacity` names.
+///
+/// Looking a name up only needs a shared reference, so that entering and exiting spans on
+/// different threads don't contend; it just stamps the name with its use. Once over capacity,
+/// the least recently used names are evicted in a batch, leaving room for more, so that a full
+/// map isn't sorted for every new span.
+struct SpanNames {
+    capacity: usize,
+    /// id -> (name, last use)
+    names: HashMap<u64, (String, AtomicU64)>,
+    next_use: AtomicU64,
+}
+
+impl SpanNames {
+    fn new(capacity: usize) -> Self {
+        SpanNames {
+            capacity,
+            names: HashMap::new(),
+            next_use: AtomicU64::new(0),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict();
+    }
+
+    fn next_use(&self) -> u64 {
+        self.next_use.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn insert(&mut self, id: u64, name: String) {
+        let used = AtomicU64::new(self.next_use());
+        self.names.insert(id, (name, used));
+        self.evict();
+    }
+
+    fn get(&self, id: u64) -> Option<&str> {
+        let (name, last_use) = self.names.get(&id)?;
+        last_use.store(self.next_use(), Ordering::Relaxed);
+        Some(name)
+    }
+
+    fn remove(&mut self, id: u64) {
+        self.names.remove(&id);
+    }
+
+    fn evict(&mut self) {
+        if self.names.len() <= self.capacity {
+            return;
+        }
+
+        // Evict down to 90% of the capacity
+        let keep = self.capacity - self.capacity / 10;
+        let mut by_use: Vec<(u64, u64)> = self
+            .names
+            .iter()
+            .map(|(id, (_, used))| (used.load(Ordering::Relaxed), *id))
+            .collect();
+        by_use.sort_unstable();
+        let evict = by_use.len() - keep;
+        for (_, id) in &by_use[..evict] {
+            self.names.remove(id);
+        }
+    }
+}
 
 #[derive(Debug, Error)]
 pub enum ConnectError {
@@ -128,7 +227,6 @@ impl TracingModality {
                     SPAN_NAMES
                         .write()
                         .expect("span name lock poisoned, this is a bug")
-                        .deref_mut()
                         .insert(id.id.get(), name.clone());
 
                     name
@@ -214,8 +312,7 @@ impl TracingModality {
                     let name = SPAN_NAMES
                         .read()
                         .expect("span name lock poisoned, this is a bug")
-                        .deref()
-                        .get(&id.get())
+                        .get(id.get())
                         .map(|n| format!("enter: {}", n));
 
                     if let Some(name) = name {
@@ -258,8 +355,7 @@ impl TracingModality {
                     let name = SPAN_NAMES
                         .read()
                         .expect("span name lock poisoned, this is a bug")
-                        .deref()
-                        .get(&id.get())
+                        .get(id.get())
                         .map(|n| format!("exit: {}", n));
 
                     if let Some(name) = name {
@@ -298,17 +394,16 @@ impl TracingModality {
                 SPAN_NAMES
                     .write()
                     .expect("span name lock poisoned, this is a bug")
-                    .deref_mut()
-                    .remove(&id.get());
+                    .remove(id.get());
             }
             TracingWire::IdClone { old, new } => {
                 let mut span_names = SPAN_NAMES
                     .write()
                     .expect("span name lock poisoned, this is a bug");
 
-                let name = span_names.deref().get(&old.id.get()).cloned();
+                let name = span_names.get(old.id.get()).map(str::to_owned);
                 if let Some(name) = name {
-                    span_names.deref_mut().insert(new.id.get(), name);
+                    span_names.insert(new.id.get(), name);
                 }
             }
             TracingWire::Other(two) => {
@@ -489,7 +584,7 @@ impl TracingModality {
             let remote_timeline_id = if let AttrVal::String(string) = attrval {
                 use std::str::FromStr;
                 if let Ok(uuid) = Uuid::from_str(&string) {
-                    AttrVal::TimelineId(Box::new(uuid.into()))
+                    AttrVal::timeline_id(uuid.into())
                 } else {
                     AttrVal::String(string)
                 }
@@ -603,3 +698,49 @@ fn tracing_value_to_attr_val<'a, V: Borrow<SerializeValue<'a>>>(value: V) -> Opt
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn span_names_evict_least_recently_used() {
+        let mut names = SpanNames::new(2);
+        names.insert(1, "one".to_string());
+        names.insert(2, "two".to_string());
+        names.insert(3, "three".to_string());
+        assert_eq!(names.len(), 2);
+        assert_eq!(names.get(1), None);
+        assert_eq!(names.get(2), Some("two"));
+        assert_eq!(names.get(3), Some("three"));
+
+        // using 2 makes 3 the oldest
+        names.get(2);
+        names.insert(4, "four".to_string());
+        assert_eq!(names.get(3), None);
+        assert_eq!(names.get(2), Some("two"));
+        assert_eq!(names.get(4), Some("four"));
+
+        names.remove(2);
+        assert_eq!(names.len(), 1);
+        names.set_capacity(0);
+        assert_eq!(names.len(), 0);
+    }
+
+    #[test]
+    fn span_names_evict_in_batches() {
+        let mut names = SpanNames::new(10);
+        for id in 0..10 {
+            names.insert(id, id.to_string());
+        }
+        names.get(0);
+        names.insert(10, "10".to_string());
+
+        // Down to 9, keeping the most recently used
+        assert_eq!(names.len(), 9);
+        assert_eq!(names.get(0), Some("0"));
+        assert_eq!(names.get(1), None);
+        assert_eq!(names.get(2), None);
+        assert_eq!(names.get(10), Some("10"));
+    }
+}